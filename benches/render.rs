@@ -0,0 +1,93 @@
+#![cfg_attr(feature = "nightly-simd", feature(portable_simd))]
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use mandelbrot_set::*;
+use std::hint::black_box;
+
+const SIZES: [(u16, u16); 3] = [(80, 24), (160, 48), (320, 96)];
+
+fn default_position() -> Position {
+    Position::new(-1.0, 1.0, -2.0, 1.0)
+}
+
+fn kernels(c: &mut Criterion) {
+    let mut group = c.benchmark_group("kernels");
+    for (fractal_index, name) in FRACTAL_NAMES.iter().enumerate() {
+        let parameters = FractalParameters {
+            fractal_index,
+            ..DEFAULT_PARAMETERS
+        };
+        group.bench_function(*name, |b| {
+            b.iter(|| {
+                render_rows(
+                    black_box(32),
+                    black_box(8),
+                    &default_position(),
+                    u32x1::splat(500),
+                    &parameters,
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+fn colors(c: &mut Criterion) {
+    let max_iterations = u32x1::splat(1000);
+    let mut group = c.benchmark_group("palettes");
+    for (palette, name) in PALETTES.iter().zip(PALETTE_NAMES) {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for iteration in 0..max_iterations[0] {
+                    black_box(palette(u32x1::splat(iteration), max_iterations));
+                }
+            })
+        });
+    }
+    group.bench_function("Color table", |b| {
+        b.iter(|| ColorTable::new(black_box(max_iterations), &DEFAULT_PARAMETERS))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("shade");
+    for (width, height) in SIZES {
+        let (buffer, _) = render_buffer(
+            width,
+            height,
+            &default_position(),
+            u32x1::splat(200),
+            &DEFAULT_PARAMETERS,
+            DEFAULT_TILE_SIZE,
+            None,
+            &mut TileCache::new(0),
+        );
+        let id = BenchmarkId::from_parameter(format!("{}x{}", width, height));
+        group.bench_with_input(id, &buffer, |b, buffer| b.iter(|| buffer.text()));
+    }
+    group.finish();
+}
+
+fn frames(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame");
+    group.sample_size(20);
+    for (width, height) in SIZES {
+        let id = BenchmarkId::from_parameter(format!("{}x{}", width, height));
+        group.bench_with_input(id, &(width, height), |b, &(width, height)| {
+            b.iter(|| {
+                render_buffer(
+                    width,
+                    height,
+                    &default_position(),
+                    u32x1::splat(200),
+                    &DEFAULT_PARAMETERS,
+                    DEFAULT_TILE_SIZE,
+                    None,
+                    &mut TileCache::new(0),
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, kernels, colors, frames);
+criterion_main!(benches);
@@ -0,0 +1,3851 @@
+//! The interactive viewer: the state it keeps between events, the main loop, and the
+//! startup that puts the command line, config file and saved session together.
+
+use crate::bench::run_bench;
+use crate::cli::{apply_options, parse_args, start_position};
+use crate::commands::{parse_command, split_widths, Command, SplitSettings};
+use crate::config::{
+    load_config, load_keymap, load_plugins, load_scripts, write_default_config, Config,
+    WatchedFiles, WATCH_INTERVAL,
+};
+use crate::explore::{
+    boundary_distance, external_ray, find_boundary_point, iterations_for_zoom, nearest_boundary,
+    Dive, MorphPath, Random, Screensaver, DISTANCE_MIN_WIDTH, DIVES_PER_SWITCH, MORPH_PERIOD,
+    RANDOM_ZOOM_RANGE,
+};
+use crate::export::{
+    export_gif, export_progress, export_video, print_frame, run_batch, Export, GRID_SUBPIXELS,
+};
+use crate::keymap::{key_event_name, log_line, parse_keystrokes, Action, Keymap, Keystroke};
+use crate::menu::{
+    crosshair_lines, render_crosshair, toast_text, Aspect, Menu, MenuAction, MenuKind, Settings,
+    SETTINGS,
+};
+use crate::overlay::{
+    describe_view, help_lines, histogram_lines, render_axes, render_julia_preview,
+    render_letterbox, render_minimap, render_overlay, render_paths, render_period_tint,
+    render_selection, stats_lines, tone_frequency, Anchor, Tone, MINIMAP_ITERATIONS, MINIMAP_SIZE,
+};
+use crate::session::{
+    builtin_tour, export_path, keyframe_view, keyframes_seconds, load_session, load_view,
+    parse_tour, save_session, save_view, Keyframe, Location, Session, TourStop, View,
+    KEYFRAME_SECONDS,
+};
+use crate::telnet::serve;
+use crate::terminal::{
+    console_for, copy_to_clipboard, enter_terminal, install_terminal_guards, restore_terminal,
+    Cast, ConsoleOutput, Input, Recorder, TerminalInput,
+};
+use crate::tile_server::serve_tiles;
+use crate::{
+    box_zoom, cell_to_plane, fit_aspect, fixed_point, fractal_names, interpolate_position,
+    iterate_point, iteration_parameters, letterbox, orbit_period, palette_names,
+    precision_exhausted, render_rows, u32x1, Fixed, FractalParameters, FrameBuffer, Position,
+    Projection, Renderer, BRAILLE_INDEX, DEFAULT_PARAMETERS, DEFAULT_TILE_SIZE, FRACTAL_VIEWS,
+    JULIA_INDEX,
+};
+use std::io::Write;
+
+pub(crate) const TITLE: &str = "Mandelbrot Set";
+
+pub(crate) const KEYMAP_HELP: [(Action, Action, &str); 4] = [
+    (Action::PanUp, Action::PanDown, "Pan up / down"),
+    (Action::PanLeft, Action::PanRight, "Pan left / right"),
+    (Action::ZoomIn, Action::ZoomOut, "Zoom in / out"),
+    (
+        Action::MoreIterations,
+        Action::FewerIterations,
+        "Raise / lower max iterations by half",
+    ),
+];
+
+pub(crate) const KEYBINDINGS: [(&str, &str); 40] = [
+    ("i", "Toggle automatic iterations by zoom"),
+    ("[ / ]", "Previous / next fractal"),
+    ("f / p", "Choose fractal / palette"),
+    (". / ,", "Increase / decrease zoom step"),
+    ("> / <", "Increase / decrease pan step"),
+    ("r", "Reset view"),
+    ("u / Ctrl-R", "Undo / redo navigation"),
+    ("Click", "Center view on point"),
+    ("Drag", "Pan view"),
+    ("Scroll", "Zoom in / out at cursor"),
+    ("Right drag", "Zoom to selected box"),
+    ("( / ) / X", "Zoom x0.5 / x2 / x10 (:zoom <factor> to set it)"),
+    ("{ / }", "Rotate view (:rotate <degrees> to set the angle)"),
+    ("L", "Toggle log-polar view around the crosshair or center"),
+    ("Enter", "Redraw"),
+    (":", "Go to x y [zoom], iter <n>, load tour <file>, save / load / gif <file>"),
+    ("g", "Jump to random boundary location"),
+    ("B", "Move to the nearest boundary of the set"),
+    ("y", "Copy location (paste to jump)"),
+    ("e", "Export view to a JSON file"),
+    ("v", "Start screensaver"),
+    ("K / P", "Add keyframe / play keyframes (:clear removes them)"),
+    ("J", "Morph the Julia set (:morph for other paths)"),
+    ("D", "Dive toward the crosshair or center (:dive <rate>)"),
+    ("n / N", "Next / previous tour stop"),
+    ("1-9", "Switch tab (new tabs start from this view)"),
+    ("S", "Split to compare (:split for other settings)"),
+    ("c", "Toggle crosshair (arrows move it)"),
+    ("j", "Open Julia set at crosshair"),
+    ("m", "Toggle minimap"),
+    ("A", "Show axes, then axes and grid, then neither"),
+    ("H", "Toggle iteration histogram"),
+    ("T", "Tint the inside of the set by period"),
+    (":ray", "Draw the external ray at an angle, e.g. :ray 1/3"),
+    ("Z", "Play the iterations under the cursor as a tone"),
+    ("I", "Describe the view in words"),
+    ("Tab", "Open settings"),
+    ("F3", "Toggle render statistics"),
+    ("?", "Show this help"),
+    ("q", "Quit"),
+];
+
+fn hold_acceleration(hold_duration: std::time::Duration) -> f64 {
+    (1.0 + hold_duration.as_secs_f64() * HOLD_ACCELERATION_RATE).min(MAX_HOLD_ACCELERATION)
+}
+
+pub(crate) fn adjust_step(step: f64, increase: bool, range: (f64, f64)) -> f64 {
+    let step = if increase { step * 2.0 } else { step / 2.0 };
+    step.clamp(range.0, range.1)
+}
+
+pub(crate) fn step_iterations(max_iterations: u32x1, increase: bool) -> u32x1 {
+    let current = max_iterations[0];
+    let stepped = if increase {
+        ((current as f64 * ITERATION_STEP).ceil() as u32).max(current.saturating_add(1))
+    } else {
+        ((current as f64 / ITERATION_STEP) as u32).max(MIN_ITERATIONS.min(current))
+    };
+    u32x1::splat(stepped.min(MAX_ITERATIONS))
+}
+
+fn ease_in_out(t: f64) -> f64 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+pub(crate) const EASING_NAMES: [&str; 4] = ["Linear", "Ease In", "Ease Out", "Ease In Out"];
+
+pub(crate) fn ease(easing_index: usize, t: f64) -> f64 {
+    match easing_index {
+        1 => t * t,
+        2 => 1.0 - (1.0 - t) * (1.0 - t),
+        3 => ease_in_out(t),
+        _ => t,
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+struct ViewState {
+    position: Position,
+    max_iterations: u32x1,
+}
+
+const HISTORY_LIMIT: usize = 1000;
+
+// Where a fractal was left, to come back to when it is switched to again
+struct FractalMemory {
+    state: ViewState,
+    auto_iterations: bool,
+    palette_index: usize,
+}
+
+const TAB_COUNT: usize = 9;
+
+// A view waiting in the background while another tab is on screen, with the frame last drawn
+// of it so switching back shows it straight away
+pub(crate) struct Tab {
+    view: View,
+    history: History,
+    frame: String,
+    frame_buffer: Option<std::sync::Arc<FrameBuffer>>,
+    status_bar: String,
+}
+
+// The other pane of a split, which shows the same part of the plane as the view and has a
+// renderer of its own
+pub(crate) struct Split {
+    settings: SplitSettings,
+    renderer: Renderer,
+    width: u16,
+    frame: String,
+    status_bar: String,
+}
+
+// Frames rendered bigger than the view, so moves that stay inside the last one are cut out
+// of it instead of being rendered again
+struct Canvas {
+    size: (u16, u16),
+    // The size of the view in the frames being rendered
+    view_size: (u16, u16),
+    // The last frame rendered, and whether it was at reduced quality
+    frame: Option<(std::sync::Arc<FrameBuffer>, bool)>,
+    // A view cut out of the frame, to be shown next
+    cut: Option<FrameBuffer>,
+}
+
+impl Canvas {
+    fn new(size: (u16, u16)) -> Canvas {
+        Canvas {
+            size,
+            view_size: (0, 0),
+            frame: None,
+            cut: None,
+        }
+    }
+}
+
+// The view of a canvas `size` cells big around a `width` by `height` view, which it has in
+// its middle on whole cells
+fn canvas_position(size: (u16, u16), width: u16, height: u16, position: &Position) -> Position {
+    let cell_width = position.width() / width as f64;
+    let cell_height = position.height() / height as f64;
+    let offset = ((size.0 - width) / 2, (size.1 - height) / 2);
+    let center = position.point_at(
+        (size.0 as f64 / 2.0 - offset.0 as f64) * cell_width,
+        (size.1 as f64 / 2.0 - offset.1 as f64) * cell_height,
+    );
+    position
+        .centered_at(&center)
+        .resized(size.0 as f64 * cell_width, size.1 as f64 * cell_height)
+}
+
+// Terminals smaller than this get a message instead of a frame
+const MIN_TERMINAL_SIZE: (u16, u16) = (20, 5);
+// And frames are never wider or taller than this, so twice a column or row, which is where
+// its subpixels are, still fits in a u16
+pub(crate) const MAX_SIZE: u16 = 32767;
+const ANIMATION_DURATION: std::time::Duration = std::time::Duration::from_millis(150);
+const FRAME_INTERVAL: std::time::Duration = std::time::Duration::from_millis(16);
+// Low power mode draws animations and checks on background work less often, previews at no
+// better than this quality level, and renders on half the cores at a lower priority
+const LOW_POWER_FRAME_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+const LOW_POWER_QUALITY_LEVEL: u8 = 2;
+pub(crate) const LOW_POWER_NICE: i32 = 10;
+// Renders that finish quicker than this never show the spinner or their progress
+const SPINNER_DELAY: std::time::Duration = std::time::Duration::from_millis(200);
+const SPINNER_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+const SPINNER_FRAMES: [char; 4] = ['|', '/', '-', '\\'];
+
+// Target frame times in milliseconds, where 0 turns the budget off
+pub(crate) const FRAME_BUDGETS: [u64; 5] = [0, 25, 50, 100, 200];
+const MAX_QUALITY_LEVEL: u8 = 3;
+// Full quality comes back once navigation has paused for this long
+const QUALITY_RESTORE_DELAY: std::time::Duration = std::time::Duration::from_millis(300);
+
+// How long a toast about a changed setting stays in the corner
+const TOAST_DURATION: std::time::Duration = std::time::Duration::from_millis(1500);
+// Repeats of the same key arriving faster than this are treated as the key being held
+const HOLD_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(150);
+const HOLD_ACCELERATION_RATE: f64 = 2.0;
+const MAX_HOLD_ACCELERATION: f64 = 5.0;
+
+pub(crate) const ZOOM_STEP_RANGE: (f64, f64) = (0.01, 0.9);
+pub(crate) const TILE_SIZE_RANGE: (u16, u16) = (4, 64);
+pub(crate) const PAN_STEP_RANGE: (f64, f64) = (0.01, 0.5);
+// How many times a dive magnifies the view each second
+pub(crate) const DIVE_RATE: f64 = 2.0;
+pub(crate) const DIVE_RATE_RANGE: (f64, f64) = (1.1, 100.0);
+pub(crate) const SERIES_TOLERANCE_RANGE: (f64, f64) = (1e-12, 0.1);
+// Degrees the view turns for each press of `{` or `}`
+const ROTATION_STEP: f64 = 15.0;
+// The factor the iteration limit changes by for each press of `=` or `-`
+const ITERATION_STEP: f64 = 1.5;
+const MIN_ITERATIONS: u32 = 10;
+// The highest limit taken from `:iter`, the keys, `--iterations`, views and locations, which
+// keeps a mistyped digit from stalling every render
+pub(crate) const MAX_ITERATIONS: u32 = 100_000_000;
+
+struct History {
+    undo: Vec<ViewState>,
+    redo: Vec<ViewState>,
+    current: ViewState,
+}
+
+impl History {
+    fn new(state: ViewState) -> History {
+        History {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            current: state,
+        }
+    }
+
+    fn record(&mut self, state: ViewState) {
+        if state == self.current {
+            return;
+        }
+
+        if self.undo.len() == HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+        self.undo.push(self.current.clone());
+        self.redo.clear();
+        self.current = state;
+    }
+
+    fn undo(&mut self) -> Option<ViewState> {
+        let state = self.undo.pop()?;
+        self.redo
+            .push(std::mem::replace(&mut self.current, state.clone()));
+        Some(state)
+    }
+
+    fn redo(&mut self) -> Option<ViewState> {
+        let state = self.redo.pop()?;
+        self.undo
+            .push(std::mem::replace(&mut self.current, state.clone()));
+        Some(state)
+    }
+}
+
+fn snap_to_cells(distance: f64, extent: f64, cells: u16) -> f64 {
+    // Whole-cell pans let the next frame reuse most of the previous one
+    let cell = extent / cells.max(1) as f64;
+    let snapped = (distance / cell).round() * cell;
+    if snapped == 0.0 {
+        cell.copysign(distance)
+    } else {
+        snapped
+    }
+}
+
+pub(crate) fn fractal_label(parameters: &FractalParameters) -> String {
+    let name = fractal_names()[parameters.fractal_index];
+    if parameters.fractal_index == JULIA_INDEX {
+        let (x, y) = parameters.julia_constant;
+        format!("{} (c = {:.6} {:+.6}i)", name, x, y)
+    } else if parameters.fractal_index == 0 && parameters.initial_z != (0.0, 0.0) {
+        let (x, y) = parameters.initial_z;
+        format!("{} (z0 = {:.6} {:+.6}i)", name, x, y)
+    } else {
+        name.to_string()
+    }
+}
+
+pub(crate) fn format_zoom(zoom: f64) -> String {
+    if zoom < 1000.0 {
+        format!("{:.2}x", zoom)
+    } else {
+        format!("{:.2e}x", zoom)
+    }
+}
+
+// Where a fractal starts with fractal_views on; added fractals start from the default view
+fn fractal_view(default_position: &Position, fractal_index: usize) -> Position {
+    match FRACTAL_VIEWS.get(fractal_index) {
+        Some(&(center, zoom)) => default_position
+            .with_zoom(default_position, zoom)
+            .centered_at(&fixed_point(center)),
+        None => default_position.clone(),
+    }
+}
+
+fn window_title(
+    position: &Position,
+    default_position: &Position,
+    parameters: &FractalParameters,
+) -> String {
+    let zoom = default_position.width() / position.width();
+    format!("{} - {}", fractal_label(parameters), format_zoom(zoom))
+}
+
+// Enough decimal places to tell neighbouring cells apart at a zoom
+pub(crate) fn decimal_places(zoom: f64) -> usize {
+    (zoom.log10().max(0.0) as usize) + 6
+}
+
+pub(crate) fn trimmed_decimal(number: &Fixed, places: usize) -> String {
+    let number = number.to_decimal(places);
+    if number.contains('.') {
+        number
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    } else {
+        number
+    }
+}
+
+pub(crate) fn render_status_bar(
+    width: u16,
+    position: &Position,
+    default_position: &Position,
+    max_iterations: u32x1,
+    parameters: &FractalParameters,
+    frame_time: std::time::Duration,
+) -> String {
+    let center = position.center();
+    let zoom = default_position.width() / position.width();
+    let places = decimal_places(zoom);
+    // Lead with the warning so a narrow terminal doesn't cut it off
+    let warning = if precision_exhausted(width, position, parameters) {
+        " ! precision limit, image is approximate |"
+    } else {
+        ""
+    };
+
+    let boundary = match boundary_distance(position, max_iterations, parameters) {
+        Some(Some(distance)) => format!(" | boundary: {:.2e}", distance),
+        Some(None) => " | inside".to_string(),
+        None => String::new(),
+    };
+    let mut rotation = if position.rotation == 0.0 {
+        String::new()
+    } else {
+        format!(" | rotation: {:.0}°", position.rotation.to_degrees())
+    };
+    if position.projection == Projection::LogPolar {
+        rotation.push_str(" | log-polar");
+    }
+
+    let status = format!(
+        "{} {} | center: {}, {} | zoom: {}{}{} | iterations: {} | frame: {:.1} ms",
+        warning,
+        fractal_label(parameters),
+        center.0.to_decimal(places),
+        center.1.to_decimal(places),
+        format_zoom(zoom),
+        rotation,
+        boundary,
+        max_iterations[0],
+        frame_time.as_secs_f64() * 1000.0,
+    );
+    render_status_line(width, &status)
+}
+
+fn spinner_frame(elapsed: std::time::Duration) -> char {
+    let step = elapsed.as_millis() / SPINNER_INTERVAL.as_millis();
+    SPINNER_FRAMES[step as usize % SPINNER_FRAMES.len()]
+}
+
+// The spinner, after how much of the frame is done when that is known
+fn progress_indicator(elapsed: std::time::Duration, progress: Option<f64>) -> String {
+    let spinner = spinner_frame(elapsed);
+    match progress {
+        Some(progress) => format!("{:>3}% {}", (progress * 100.0) as u32, spinner),
+        None => spinner.to_string(),
+    }
+}
+
+// Steps the interactive quality down after a frame over budget, and back up
+// once frames come in well under it
+fn next_quality_level(level: u8, frame_time: std::time::Duration, budget: u64) -> u8 {
+    let budget = std::time::Duration::from_millis(budget);
+    if budget.is_zero() {
+        0
+    } else if frame_time > budget {
+        (level + 1).min(MAX_QUALITY_LEVEL)
+    } else if frame_time * 4 < budget {
+        level.saturating_sub(1)
+    } else {
+        level
+    }
+}
+
+// The first level drops supersampling, the others halve the resolution each time
+fn reduced_quality(level: u8, parameters: &FractalParameters) -> (u16, FractalParameters) {
+    if level == 0 {
+        return (1, *parameters);
+    }
+    let parameters = FractalParameters {
+        supersampling: 1,
+        ..*parameters
+    };
+    (1 << (level - 1), parameters)
+}
+
+fn render_status_line(width: u16, text: &str) -> String {
+    let text: String = text.chars().take(width as usize).collect();
+
+    format!(
+        "{}{}{:<width$}{}",
+        crossterm::style::SetForegroundColor(crossterm::style::Color::Black),
+        crossterm::style::SetBackgroundColor(crossterm::style::Color::White),
+        text,
+        crossterm::style::ResetColor,
+        width = width as usize
+    )
+}
+
+// Frames end their rows with a bare line feed, which only works from the left edge
+fn write_pane(writer: &mut impl Write, frame: &str, column: u16) -> std::io::Result<()> {
+    for (row, line) in frame.split('\n').enumerate() {
+        crossterm::execute!(writer, crossterm::cursor::MoveTo(column, row as u16))?;
+        writer.write_all(line.as_bytes())?;
+    }
+    write!(writer, "{}", crossterm::style::ResetColor)
+}
+
+/// Errors that end the program.
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    /// Reading from or writing to the terminal failed.
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    /// An option couldn't be applied, e.g. a thread count rayon rejected.
+    #[error("{0}")]
+    Options(String),
+    /// The default config couldn't be written.
+    #[error("config: {0}")]
+    Config(String),
+    /// A batch job file couldn't be read, or some of its images couldn't be written.
+    #[error("batch: {0}")]
+    Batch(String),
+    /// The benchmark results couldn't be written.
+    #[error("bench: {0}")]
+    Bench(String),
+    /// An animation couldn't be written.
+    #[error("export: {0}")]
+    Export(String),
+}
+
+// Everything the interactive viewer keeps between events
+pub(crate) struct App {
+    default_position: Position,
+    position: Position,
+    // Where the frame on screen is, which trails position while a transition runs
+    displayed_position: Position,
+    max_iterations: u32x1,
+    auto_iterations: bool,
+    parameters: FractalParameters,
+    animation: Option<(Position, std::time::Instant)>,
+    held_key: Option<(
+        crossterm::event::KeyCode,
+        std::time::Instant,
+        std::time::Instant,
+    )>,
+    zoom_step: f64,
+    pan_step: f64,
+    tile_size: u16,
+    frame_budget: u64,
+    quality_level: u8,
+    low_power: bool,
+    last_terminal_size: (u16, u16),
+    show_help: bool,
+    drag_start: Option<(u16, u16, Position)>,
+    dragged: bool,
+    selection: Option<((u16, u16), (u16, u16))>,
+    command_input: Option<String>,
+    message: Option<String>,
+    history: History,
+    show_stats: bool,
+    crosshair: Option<(u16, u16)>,
+    show_minimap: bool,
+    show_axes: bool,
+    show_grid: bool,
+    show_histogram: bool,
+    show_description: bool,
+    menu: Option<Menu>,
+    settings_selected: Option<usize>,
+    tour: Vec<TourStop>,
+    tour_stop: Option<usize>,
+    tour_shown: std::time::Instant,
+    screensaver: Option<Screensaver>,
+    keyframes: Vec<Keyframe>,
+    // The tabs in the background; the one on screen lives in the fields above and leaves its
+    // slot empty, as do tabs that haven't been opened
+    tabs: Vec<Option<Tab>>,
+    tab: usize,
+    // The views of the fractals switched away from, by fractal index, and whether fractals
+    // not among them start from their own views
+    fractal_memory: std::collections::HashMap<usize, FractalMemory>,
+    fractal_views: bool,
+    split: Option<Split>,
+    // When the keyframes started playing, while they are
+    playback: Option<std::time::Instant>,
+    // The path the Julia constant is morphing along and when it started
+    morph: Option<(MorphPath, std::time::Instant)>,
+    dive: Option<Dive>,
+    dive_rate: f64,
+    // Progress messages from an export running in the background, and the flag that cancels it
+    export: Option<(
+        std::sync::mpsc::Receiver<String>,
+        std::sync::Arc<std::sync::atomic::AtomicBool>,
+    )>,
+    toast: Option<(String, std::time::Instant)>,
+    window_title_text: String,
+    keymap: Keymap,
+    random: Random,
+    aspect: Aspect,
+    // The size of a terminal too small to draw in
+    too_small: Option<(u16, u16)>,
+    // When the window was last resized, and an event read while looking for more resizes
+    resized: Option<std::time::Instant>,
+    deferred_event: Option<crossterm::event::Event>,
+    minimap: Option<(FractalParameters, Vec<String>)>,
+    // The files --watch reloads, as they were when last checked, and when that was
+    watch: Option<(WatchedFiles, std::time::Instant)>,
+    show_periods: bool,
+    // The points of the external rays asked for
+    rays: Vec<Vec<(f64, f64)>>,
+    // The period tint of the frame buffer it was worked out for
+    period_tint: Option<(std::sync::Arc<FrameBuffer>, String)>,
+    // The tone playing the iterations under the crosshair or mouse, and where the mouse was
+    // last seen
+    sound: Option<Tone>,
+    pointer: Option<(u16, u16)>,
+    frame: String,
+    frame_buffer: Option<std::sync::Arc<FrameBuffer>>,
+    canvas: Option<Canvas>,
+    grid_index: usize,
+    renderer: Renderer,
+    render_start: Option<std::time::Instant>,
+    // When the last frame rendered for navigation was requested, and whether
+    // the frame on screen was rendered at reduced quality
+    last_navigation: std::time::Instant,
+    restoring: bool,
+    reduced_frame: bool,
+    frame_stats: (std::time::Duration, u64, u64),
+    // The series order and the iterations it skipped, for frames calculated by perturbation
+    series_stats: Option<(usize, usize)>,
+    status_bar: String,
+    should_redraw: bool,
+    should_repaint: bool,
+    should_animate: bool,
+    // Output that has to wait for the next draw
+    clipboard: Option<String>,
+    clear_screen: bool,
+    // Keys still to be replayed, and where pressed keys are being recorded, with the time
+    // each started from
+    replay: Option<(std::collections::VecDeque<Keystroke>, std::time::Instant)>,
+    key_recording: Option<(Box<dyn Write>, std::time::Instant)>,
+    // The --log-file log, and when the viewer started
+    log: Option<(Box<dyn Write>, std::time::Instant)>,
+    // Set for the clients of serve, who mustn't reach the server's files
+    restricted: bool,
+}
+
+impl App {
+    fn new(
+        default_position: Position,
+        position: Position,
+        max_iterations: u32x1,
+        parameters: FractalParameters,
+        keymap: Keymap,
+    ) -> App {
+        App {
+            displayed_position: position.clone(),
+            history: History::new(ViewState {
+                position: position.clone(),
+                max_iterations,
+            }),
+            default_position,
+            position,
+            max_iterations,
+            auto_iterations: true,
+            parameters,
+            animation: None,
+            held_key: None,
+            zoom_step: 0.1,
+            pan_step: 0.05,
+            tile_size: DEFAULT_TILE_SIZE,
+            frame_budget: FRAME_BUDGETS[2],
+            quality_level: 0,
+            low_power: false,
+            last_terminal_size: (0, 0),
+            show_help: false,
+            drag_start: None,
+            dragged: false,
+            selection: None,
+            command_input: None,
+            message: None,
+            show_stats: false,
+            crosshair: None,
+            show_minimap: false,
+            show_axes: false,
+            show_grid: false,
+            show_histogram: false,
+            show_description: false,
+            menu: None,
+            settings_selected: None,
+            tour: builtin_tour(),
+            tour_stop: None,
+            tour_shown: std::time::Instant::now(),
+            export: None,
+            screensaver: None,
+            keyframes: Vec::new(),
+            tabs: (0..TAB_COUNT).map(|_| None).collect(),
+            tab: 0,
+            fractal_memory: std::collections::HashMap::new(),
+            fractal_views: false,
+            split: None,
+            playback: None,
+            morph: None,
+            dive: None,
+            dive_rate: DIVE_RATE,
+            toast: None,
+            window_title_text: TITLE.to_string(),
+            keymap,
+            random: Random::new(
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |duration| duration.as_nanos() as u64),
+            ),
+            aspect: Aspect::Stretch,
+            too_small: None,
+            resized: None,
+            deferred_event: None,
+            minimap: None,
+            watch: None,
+            show_periods: false,
+            rays: Vec::new(),
+            period_tint: None,
+            sound: None,
+            pointer: None,
+            frame: String::new(),
+            frame_buffer: None,
+            canvas: None,
+            grid_index: 0,
+            renderer: Renderer::spawn(),
+            render_start: None,
+            last_navigation: std::time::Instant::now(),
+            restoring: false,
+            reduced_frame: false,
+            frame_stats: (std::time::Duration::ZERO, 0, 0),
+            series_stats: None,
+            status_bar: String::new(),
+            // Draw the first frame straight away instead of waiting for input
+            should_redraw: true,
+            should_repaint: false,
+            should_animate: false,
+            clipboard: None,
+            clear_screen: false,
+            replay: None,
+            key_recording: None,
+            log: None,
+            restricted: false,
+        }
+    }
+
+    pub(crate) fn run(
+        &mut self,
+        input: &mut impl Input,
+        writer: &mut impl Write,
+    ) -> Result<(), AppError> {
+        loop {
+            self.should_animate = false;
+            let previous = (self.max_iterations, self.parameters);
+            let event = match self.replayed_key() {
+                Some(event) => Some(crossterm::event::Event::Key(event)),
+                None => self.next_event(input)?,
+            };
+            self.expire();
+            self.check_watched();
+            if let Some(event) = event {
+                if let crossterm::event::Event::Key(event) = event {
+                    self.record_key(event)?;
+                }
+                if !self.handle_event(event) {
+                    return Ok(());
+                }
+            }
+            self.update(previous, input.size()?);
+            self.receive_frame(writer)?;
+            self.draw(writer)?;
+        }
+    }
+
+    // The next event from the input, with a burst of resizes, as dragging a window's edge
+    // sends, taken as the last of them
+    fn next_event(
+        &mut self,
+        input: &mut impl Input,
+    ) -> std::io::Result<Option<crossterm::event::Event>> {
+        let mut event = match self.deferred_event.take() {
+            Some(event) => Some(event),
+            None => input.next_event(self.timeout())?,
+        };
+        while let Some(crossterm::event::Event::Resize(..)) = event {
+            match input.next_event(Some(std::time::Duration::ZERO))? {
+                Some(next @ crossterm::event::Event::Resize(..)) => event = Some(next),
+                Some(next) => {
+                    self.deferred_event = Some(next);
+                    break;
+                }
+                None => break,
+            }
+        }
+        Ok(event)
+    }
+
+    fn record_key(&mut self, event: crossterm::event::KeyEvent) -> std::io::Result<()> {
+        match &mut self.key_recording {
+            Some((output, started)) if event.kind != crossterm::event::KeyEventKind::Release => {
+                let keystroke = Keystroke {
+                    time: started.elapsed(),
+                    event,
+                };
+                // Flushed straight away so a crash keeps the keys that led up to it
+                writeln!(output, "{}", keystroke.line())?;
+                output.flush()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn log(&mut self, event: &str, fields: &[(&str, String)]) {
+        if let Some((output, started)) = &mut self.log {
+            // The log is only there to help with debugging, so failing to write it doesn't
+            // stop the viewer
+            let line = log_line(started.elapsed(), event, fields);
+            let _ = writeln!(output, "{}", line).and_then(|()| output.flush());
+        }
+    }
+
+    // The next replayed key, once it is time to press it
+    fn replayed_key(&mut self) -> Option<crossterm::event::KeyEvent> {
+        let (keystrokes, started) = self.replay.as_mut()?;
+        let event = match keystrokes.front() {
+            Some(keystroke) if keystroke.time <= started.elapsed() => keystrokes.pop_front()?.event,
+            _ => return None,
+        };
+        if keystrokes.is_empty() {
+            self.replay = None;
+        }
+        Some(event)
+    }
+
+    fn frame_interval(&self) -> std::time::Duration {
+        if self.low_power {
+            LOW_POWER_FRAME_INTERVAL
+        } else {
+            FRAME_INTERVAL
+        }
+    }
+
+    // How long to wait for input before there is something to do anyway
+    fn timeout(&self) -> Option<std::time::Duration> {
+        // Keep drawing frames while a transition is running, and stop
+        // accelerating once a held key hasn't repeated for a while
+        let timeout = if self.should_redraw || self.should_repaint {
+            Some(std::time::Duration::ZERO)
+        } else if self.animation.is_some() || self.animating() {
+            Some(self.frame_interval())
+        } else if self.held_key.is_some() {
+            Some(HOLD_TIMEOUT)
+        } else {
+            None
+        };
+        // Check back regularly for a frame or an export that is rendering in the background
+        let split_pending = self
+            .split
+            .as_ref()
+            .is_some_and(|split| split.renderer.pending());
+        let timeout = if self.renderer.pending() || split_pending || self.export.is_some() {
+            let interval = self.frame_interval();
+            Some(timeout.map_or(interval, |timeout| timeout.min(interval)))
+        } else {
+            timeout
+        };
+        // Wake up in time to bring a reduced frame back to full quality
+        let timeout = if self.reduced_frame {
+            let remaining = QUALITY_RESTORE_DELAY.saturating_sub(self.last_navigation.elapsed());
+            Some(timeout.map_or(remaining, |timeout| timeout.min(remaining)))
+        } else {
+            timeout
+        };
+        // Wake up in time to take an expired toast down
+        let timeout = match &self.toast {
+            Some((_, shown)) => {
+                let remaining = TOAST_DURATION.saturating_sub(shown.elapsed());
+                Some(timeout.map_or(remaining, |timeout| timeout.min(remaining)))
+            }
+            None => timeout,
+        };
+        // Wake up in time to look for changes to watched files
+        let timeout = match &self.watch {
+            Some((_, checked)) => {
+                let remaining = WATCH_INTERVAL.saturating_sub(checked.elapsed());
+                Some(timeout.map_or(remaining, |timeout| timeout.min(remaining)))
+            }
+            None => timeout,
+        };
+        // Wake up in time to move a timed tour on
+        let timeout = match self.tour_remaining() {
+            Some(remaining) => Some(timeout.map_or(remaining, |timeout| timeout.min(remaining))),
+            None => timeout,
+        };
+        // Wake up in time to press the next replayed key
+        let replay_remaining = self.replay.as_ref().and_then(|(keystrokes, started)| {
+            Some(keystrokes.front()?.time.saturating_sub(started.elapsed()))
+        });
+        match replay_remaining {
+            Some(remaining) => Some(timeout.map_or(remaining, |timeout| timeout.min(remaining))),
+            None => timeout,
+        }
+    }
+
+    // Reloads the config file and scripts once either changes on disk, with --watch
+    fn check_watched(&mut self) {
+        let Some((files, checked)) = &mut self.watch else {
+            return;
+        };
+        if checked.elapsed() < WATCH_INTERVAL {
+            return;
+        }
+        *checked = std::time::Instant::now();
+        let current = WatchedFiles::now();
+        let config_changed = current.config != files.config;
+        let scripts_changed = current.scripts != files.scripts;
+        *files = current;
+
+        let mut reloaded = Vec::new();
+        let mut error = None;
+        if config_changed {
+            match load_config() {
+                // Only settings that don't describe the view are taken, so it stays put
+                Ok(mut config) => {
+                    let (keymap, keymap_error) = load_keymap(config.keymap.take());
+                    self.keymap = keymap;
+                    self.zoom_step = config.zoom_step.unwrap_or(self.zoom_step);
+                    self.pan_step = config.pan_step.unwrap_or(self.pan_step);
+                    self.dive_rate = config.dive_rate.unwrap_or(self.dive_rate);
+                    self.fractal_views = config.fractal_views.unwrap_or(self.fractal_views);
+                    let canvas_size = self.canvas.as_ref().map(|canvas| canvas.size);
+                    if config.canvas_size.is_some() && config.canvas_size != canvas_size {
+                        self.canvas = config.canvas_size.map(Canvas::new);
+                    }
+                    if let Some(grid_index) = config.grid_index {
+                        if grid_index != self.grid_index {
+                            self.set_grid(grid_index);
+                        }
+                    }
+                    error = keymap_error;
+                    reloaded.push("config");
+                }
+                Err(config_error) => error = Some(format!("config: {}", config_error)),
+            }
+        }
+        if scripts_changed {
+            let (tour, errors) = load_scripts();
+            self.tour = if tour.is_empty() {
+                builtin_tour()
+            } else {
+                tour
+            };
+            let stops = self.tour.len();
+            self.tour_stop = self.tour_stop.filter(|index| *index < stops);
+            error = errors.into_iter().next().or(error);
+            reloaded.push("scripts");
+
+            // Frames of the old formulas and palettes can't be reused
+            self.renderer.reset();
+            if let Some(split) = &mut self.split {
+                split.renderer.reset();
+            }
+            self.minimap = None;
+            self.should_redraw = true;
+        }
+        if config_changed || scripts_changed {
+            self.message = error.or(Some(format!("reloaded {}", reloaded.join(" and "))));
+            self.should_repaint = true;
+        }
+    }
+
+    fn expire(&mut self) {
+        if matches!(&self.toast, Some((_, shown)) if shown.elapsed() >= TOAST_DURATION) {
+            self.toast = None;
+            self.should_repaint = true;
+        }
+
+        if let Some((_, _, last_repeat)) = self.held_key {
+            if last_repeat.elapsed() >= HOLD_TIMEOUT {
+                self.held_key = None;
+            }
+        }
+    }
+
+    // Returns false once the viewer should quit
+    pub(crate) fn handle_event(&mut self, event: crossterm::event::Event) -> bool {
+        match event {
+            crossterm::event::Event::Key(event) => return self.handle_key(event),
+            crossterm::event::Event::Mouse(event) => self.handle_mouse(event),
+            crossterm::event::Event::Paste(text) => self.handle_paste(text),
+            crossterm::event::Event::Resize(width, height)
+                if width != self.last_terminal_size.0 || height != self.last_terminal_size.1 =>
+            {
+                self.log(
+                    "resize",
+                    &[("width", width.to_string()), ("height", height.to_string())],
+                );
+                if let Some((column, row)) = &mut self.crosshair {
+                    *column = (*column).min(width.saturating_sub(1));
+                    *row = (*row).min(height.saturating_sub(2));
+                }
+                self.clear_screen = true;
+                self.should_redraw = true;
+                self.resized = Some(std::time::Instant::now());
+            }
+            _ => (),
+        }
+        true
+    }
+
+    fn handle_key(&mut self, event: crossterm::event::KeyEvent) -> bool {
+        if event.kind == crossterm::event::KeyEventKind::Release {
+            return true;
+        }
+        self.log("key", &[("key", key_event_name(&event))]);
+        // Raw mode turns Ctrl-C into a key instead of a SIGINT, so it quits from here, whatever
+        // is open
+        if event.code == crossterm::event::KeyCode::Char('c')
+            && event.modifiers.contains(crossterm::event::KeyModifiers::CONTROL)
+        {
+            return false;
+        }
+
+        let now = std::time::Instant::now();
+        let hold_start = match self.held_key {
+            Some((code, hold_start, _)) if code == event.code => hold_start,
+            _ => now,
+        };
+        self.held_key = Some((event.code, hold_start, now));
+        let acceleration = hold_acceleration(now - hold_start);
+
+        if self.message.take().is_some() {
+            self.should_repaint = true;
+        }
+
+        if self.animating() {
+            self.screensaver = None;
+            self.playback = None;
+            self.morph = None;
+            self.dive = None;
+            self.should_repaint = true;
+        } else if self.show_help {
+            self.show_help = false;
+            self.should_repaint = true;
+        } else if let Some(selected) = self.settings_selected.as_mut() {
+            let mut settings = Settings {
+                max_iterations: &mut self.max_iterations,
+                auto_iterations: &mut self.auto_iterations,
+                parameters: &mut self.parameters,
+                aspect: &mut self.aspect,
+                zoom_step: &mut self.zoom_step,
+                tile_size: &mut self.tile_size,
+                frame_budget: &mut self.frame_budget,
+            };
+            match event.code {
+                crossterm::event::KeyCode::Up => {
+                    *selected = (*selected + SETTINGS.len() - 1) % SETTINGS.len();
+                }
+                crossterm::event::KeyCode::Down => {
+                    *selected = (*selected + 1) % SETTINGS.len();
+                }
+                crossterm::event::KeyCode::Left | crossterm::event::KeyCode::Right => {
+                    let increase = event.code == crossterm::event::KeyCode::Right;
+                    settings.adjust(*selected, increase);
+                    self.should_redraw = true;
+                }
+                crossterm::event::KeyCode::Tab | crossterm::event::KeyCode::Esc => {
+                    self.settings_selected = None;
+                }
+                _ => (),
+            }
+            self.should_repaint = true;
+        } else if let Some(open_menu) = self.menu.as_mut() {
+            match open_menu.handle_key(event.code) {
+                MenuAction::Select(index) => {
+                    match open_menu.kind {
+                        MenuKind::Fractal => self.switch_fractal(index),
+                        MenuKind::Palette => self.parameters.palette_index = index,
+                    }
+                    self.menu = None;
+                    self.should_redraw = true;
+                }
+                MenuAction::Close => self.menu = None,
+                MenuAction::None => (),
+            }
+            self.should_repaint = true;
+        } else if let Some(input) = self.command_input.as_mut() {
+            match event.code {
+                crossterm::event::KeyCode::Esc => self.command_input = None,
+                crossterm::event::KeyCode::Backspace if input.pop().is_none() => {
+                    self.command_input = None
+                }
+                crossterm::event::KeyCode::Char(character) => input.push(character),
+                crossterm::event::KeyCode::Enter => {
+                    match parse_command(input) {
+                        Ok(
+                            Command::Save(_)
+                            | Command::Gif(_)
+                            | Command::Load(_)
+                            | Command::Tour(_),
+                        ) if self.restricted => {
+                            self.message = Some("files aren't available here".to_string());
+                        }
+                        Ok(Command::Goto { x, y, zoom }) => {
+                            if let Some(zoom) = zoom {
+                                self.position =
+                                    self.position.with_zoom(&self.default_position, zoom);
+                            }
+                            self.position = self.position.centered_at(&(x, y));
+                            self.should_redraw = true;
+                            self.should_animate = true;
+                        }
+                        Ok(Command::Save(path)) => {
+                            self.message = Some(
+                                match save_view(path.as_ref(), &self.view(), &self.default_position)
+                                {
+                                    Ok(()) => format!("saved view to {}", path),
+                                    Err(error) => error,
+                                },
+                            );
+                        }
+                        Ok(Command::Dive(rate)) => {
+                            self.dive_rate = rate;
+                            self.start_dive();
+                        }
+                        Ok(Command::Zoom(zoom)) => {
+                            let scale = self.default_position.width() / self.position.width();
+                            self.magnify(zoom / scale);
+                        }
+                        Ok(Command::Iterations(iterations)) => {
+                            self.max_iterations = u32x1::splat(iterations);
+                            self.auto_iterations = false;
+                            self.message = Some(if iterations == MAX_ITERATIONS {
+                                format!("iteration limit set to the highest, {}", iterations)
+                            } else {
+                                format!("iteration limit set to {}", iterations)
+                            });
+                            self.should_redraw = true;
+                        }
+                        Ok(Command::InitialZ(point)) => {
+                            self.parameters.initial_z = point;
+                            self.message = Some(format!(
+                                "Mandelbrot orbits start at {}, {}",
+                                point.0, point.1
+                            ));
+                            self.should_redraw = true;
+                        }
+                        Ok(Command::Rotate(degrees)) => {
+                            let angle = degrees.to_radians() - self.position.rotation;
+                            self.position = self.position.rotated(angle);
+                            self.should_redraw = true;
+                            self.should_animate = true;
+                        }
+                        Ok(Command::Split(settings)) => self.toggle_split(settings),
+                        Ok(Command::Morph(path)) => {
+                            let path = path.unwrap_or_else(|| {
+                                MorphPath::circle_through(self.parameters.julia_constant)
+                            });
+                            self.start_morph(path);
+                        }
+                        Ok(Command::Ray(None)) => {
+                            self.rays.clear();
+                            self.message = Some("removed the rays".to_string());
+                        }
+                        Ok(Command::Ray(Some(_))) if self.parameters.fractal_index != 0 => {
+                            self.message = Some(
+                                "external rays are only traced for the Mandelbrot set".to_string(),
+                            );
+                        }
+                        Ok(Command::Ray(Some((numerator, denominator)))) => {
+                            let ray = external_ray(numerator, denominator);
+                            let end = ray.last().copied().unwrap_or_default();
+                            self.message = Some(format!(
+                                "ray {}/{} lands near {:.6}, {:.6}",
+                                numerator, denominator, end.0, end.1
+                            ));
+                            self.rays.push(ray);
+                        }
+                        Ok(Command::ClearKeyframes) => {
+                            self.keyframes.clear();
+                            self.message = Some("cleared keyframes".to_string());
+                        }
+                        Ok(Command::Gif(_)) if self.export.is_some() => {
+                            self.message = Some("an export is already running".to_string());
+                        }
+                        Ok(Command::Gif(path)) => {
+                            let export = Export {
+                                output: path.into(),
+                                ..Export::default()
+                            };
+                            let view = self.view();
+                            let default_position = self.default_position.clone();
+                            let (sender, receiver) = std::sync::mpsc::channel();
+                            let cancelled =
+                                std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+                            let cancel = cancelled.clone();
+                            std::thread::spawn(move || {
+                                let start = std::time::Instant::now();
+                                let result = export_gif(
+                                    &export,
+                                    &view,
+                                    &default_position,
+                                    |frame, frames| {
+                                        let _ = sender.send(format!(
+                                            "exporting {} (Esc cancels)",
+                                            export_progress(frame, frames, start.elapsed())
+                                        ));
+                                        !cancel.load(std::sync::atomic::Ordering::Relaxed)
+                                    },
+                                );
+                                let _ = sender.send(match result {
+                                    Ok(()) => format!("wrote {}", export.output.display()),
+                                    Err(error) => error,
+                                });
+                            });
+                            self.export = Some((receiver, cancelled));
+                        }
+                        Ok(Command::Load(path)) => {
+                            match load_view(path.as_ref(), &self.default_position) {
+                                // Palette files only change the palette of the view on screen
+                                Ok(view) if path.to_lowercase().ends_with(".kfp") => {
+                                    self.parameters.palette_index = view.parameters.palette_index;
+                                    self.should_redraw = true;
+                                }
+                                Ok(view) => self.apply_view(view),
+                                Err(error) => self.message = Some(error),
+                            }
+                        }
+                        Ok(Command::Tour(path)) => {
+                            match std::fs::read_to_string(&path)
+                                .map_err(|error| format!("{}: {}", path, error))
+                                .and_then(|text| parse_tour(&text))
+                            {
+                                Ok(stops) => {
+                                    self.message =
+                                        Some(format!("loaded tour with {} stops", stops.len()));
+                                    self.tour = stops;
+                                    self.tour_stop = None;
+                                }
+                                Err(error) => self.message = Some(error),
+                            }
+                        }
+                        Err(error) => self.message = Some(error),
+                    }
+                    self.command_input = None;
+                }
+                _ => (),
+            }
+            self.should_repaint = true;
+        } else {
+            let action = self.keymap.action(event.code);
+            match event.code {
+                crossterm::event::KeyCode::Char('q') => return false,
+                crossterm::event::KeyCode::Left if self.crosshair.is_some() => {
+                    if let Some((column, _)) = &mut self.crosshair {
+                        *column = column.saturating_sub(1);
+                    }
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Right if self.crosshair.is_some() => {
+                    if let Some((column, _)) = &mut self.crosshair {
+                        *column = (*column + 1).min(self.last_terminal_size.0.saturating_sub(1));
+                    }
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Up if self.crosshair.is_some() => {
+                    if let Some((_, row)) = &mut self.crosshair {
+                        *row = row.saturating_sub(1);
+                    }
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Down if self.crosshair.is_some() => {
+                    if let Some((_, row)) = &mut self.crosshair {
+                        *row = (*row + 1).min(self.last_terminal_size.1.saturating_sub(2));
+                    }
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Esc if self.crosshair.is_some() => {
+                    self.crosshair = None;
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Esc if self.export.is_some() => {
+                    if let Some((_, cancelled)) = &self.export {
+                        cancelled.store(true, std::sync::atomic::Ordering::Relaxed);
+                    }
+                    self.message = Some("cancelling the export".to_string());
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Char('j')
+                    if self.parameters.fractal_index == 0 && self.crosshair.is_some() =>
+                {
+                    // Promote the point under the crosshair to the active Julia constant
+                    if let Some((column, row)) = self.crosshair.take() {
+                        let (width, height) = self.last_terminal_size;
+                        self.parameters = FractalParameters {
+                            fractal_index: JULIA_INDEX,
+                            julia_constant: {
+                                let point = cell_to_plane(
+                                    column,
+                                    row,
+                                    width,
+                                    height.saturating_sub(1),
+                                    &self.displayed_position,
+                                );
+                                (point.0.to_f64(), point.1.to_f64())
+                            },
+                            ..self.parameters
+                        };
+                        self.should_redraw = true;
+                    }
+                }
+                crossterm::event::KeyCode::Char('c') => {
+                    self.crosshair = match self.crosshair {
+                        Some(_) => None,
+                        None => Some((
+                            self.last_terminal_size.0 / 2,
+                            self.last_terminal_size.1.saturating_sub(1) / 2,
+                        )),
+                    };
+                    self.should_repaint = true;
+                }
+                _ if action == Some(Action::PanUp) => {
+                    let height = self.position.height();
+                    let distance = snap_to_cells(
+                        -height * self.pan_step * acceleration,
+                        height,
+                        self.last_terminal_size.1.saturating_sub(1),
+                    );
+                    self.position = self.position.moved(0.0, distance);
+                    self.should_redraw = true;
+                    self.should_animate = true;
+                }
+                _ if action == Some(Action::PanDown) => {
+                    let height = self.position.height();
+                    let distance = snap_to_cells(
+                        height * self.pan_step * acceleration,
+                        height,
+                        self.last_terminal_size.1.saturating_sub(1),
+                    );
+                    self.position = self.position.moved(0.0, distance);
+                    self.should_redraw = true;
+                    self.should_animate = true;
+                }
+                _ if action == Some(Action::PanLeft) => {
+                    let width = self.position.width();
+                    let distance = snap_to_cells(
+                        -width * self.pan_step * acceleration,
+                        width,
+                        self.last_terminal_size.0,
+                    );
+                    self.position = self.position.moved(distance, 0.0);
+                    self.should_redraw = true;
+                    self.should_animate = true;
+                }
+                _ if action == Some(Action::PanRight) => {
+                    let width = self.position.width();
+                    let distance = snap_to_cells(
+                        width * self.pan_step * acceleration,
+                        width,
+                        self.last_terminal_size.0,
+                    );
+                    self.position = self.position.moved(distance, 0.0);
+                    self.should_redraw = true;
+                    self.should_animate = true;
+                }
+                _ if action == Some(Action::ZoomIn) => {
+                    let center = self.position.center();
+                    let factor = (1.0 - self.zoom_step).powf(acceleration);
+                    self.position = self.position.zoomed_at(&center, factor);
+                    self.should_redraw = true;
+                    self.should_animate = true;
+                }
+                _ if action == Some(Action::ZoomOut) => {
+                    let center = self.position.center();
+                    let factor = (1.0 + self.zoom_step).powf(acceleration);
+                    self.position = self.position.zoomed_at(&center, factor);
+                    self.should_redraw = true;
+                    self.should_animate = true;
+                }
+                crossterm::event::KeyCode::Char('L') => self.toggle_log_polar(),
+                crossterm::event::KeyCode::Char('(') => self.magnify(0.5),
+                crossterm::event::KeyCode::Char(')') => self.magnify(2.0),
+                crossterm::event::KeyCode::Char('X') => self.magnify(10.0),
+                crossterm::event::KeyCode::Char(key @ ('{' | '}')) => {
+                    let step = if key == '{' {
+                        -ROTATION_STEP
+                    } else {
+                        ROTATION_STEP
+                    };
+                    self.position = self.position.rotated(step.to_radians());
+                    self.should_redraw = true;
+                    self.should_animate = true;
+                }
+                _ if action == Some(Action::MoreIterations)
+                    || action == Some(Action::FewerIterations) =>
+                {
+                    let increase = action == Some(Action::MoreIterations);
+                    self.max_iterations = step_iterations(self.max_iterations, increase);
+                    self.auto_iterations = false;
+                    if increase && self.max_iterations[0] == MAX_ITERATIONS {
+                        self.message =
+                            Some(format!("iteration limit is at the highest, {}", MAX_ITERATIONS));
+                    }
+                    self.should_redraw = true;
+                }
+                crossterm::event::KeyCode::Enter => {
+                    self.should_redraw = true;
+                }
+                crossterm::event::KeyCode::Char(digit @ '1'..='9') => {
+                    self.switch_tab(digit as usize - '1' as usize);
+                }
+                crossterm::event::KeyCode::Char('i') => {
+                    self.auto_iterations = !self.auto_iterations;
+                    let text = if self.auto_iterations {
+                        "auto iterations on"
+                    } else {
+                        "auto iterations off"
+                    };
+                    self.toast = Some((text.to_string(), std::time::Instant::now()));
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Char('[') => {
+                    let count = fractal_names().len();
+                    self.switch_fractal((self.parameters.fractal_index + count - 1) % count);
+                }
+                crossterm::event::KeyCode::Char(']') => {
+                    let count = fractal_names().len();
+                    self.switch_fractal((self.parameters.fractal_index + 1) % count);
+                }
+                crossterm::event::KeyCode::Char('u') => {
+                    if let Some(state) = self.history.undo() {
+                        self.position = state.position;
+                        self.max_iterations = state.max_iterations;
+                        self.should_redraw = true;
+                        self.should_animate = true;
+                    }
+                }
+                crossterm::event::KeyCode::Char('r')
+                    if event
+                        .modifiers
+                        .contains(crossterm::event::KeyModifiers::CONTROL) =>
+                {
+                    if let Some(state) = self.history.redo() {
+                        self.position = state.position;
+                        self.max_iterations = state.max_iterations;
+                        self.should_redraw = true;
+                        self.should_animate = true;
+                    }
+                }
+                crossterm::event::KeyCode::Char('r') if self.position != self.default_position => {
+                    self.position = self.default_position.clone();
+                    self.should_redraw = true;
+                    self.should_animate = true;
+                }
+                crossterm::event::KeyCode::Char(character @ ('.' | ',')) => {
+                    self.zoom_step = adjust_step(self.zoom_step, character == '.', ZOOM_STEP_RANGE);
+                    self.message = Some(format!("zoom step: {:.1}%", self.zoom_step * 100.0));
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Char(character @ ('>' | '<')) => {
+                    self.pan_step = adjust_step(self.pan_step, character == '>', PAN_STEP_RANGE);
+                    self.message = Some(format!("pan step: {:.1}%", self.pan_step * 100.0));
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Char(character @ ('n' | 'N')) => {
+                    let index = match (self.tour_stop, character) {
+                        (None, _) => 0,
+                        (Some(index), 'n') => (index + 1) % self.tour.len(),
+                        (Some(index), _) => (index + self.tour.len() - 1) % self.tour.len(),
+                    };
+                    self.show_tour_stop(index);
+                }
+                crossterm::event::KeyCode::Char('g') => {
+                    let zoom =
+                        10f64.powf(self.random.range(RANDOM_ZOOM_RANGE.0, RANDOM_ZOOM_RANGE.1));
+                    match find_boundary_point(
+                        &mut self.random,
+                        &self.default_position,
+                        zoom,
+                        &self.parameters,
+                    ) {
+                        Some(center) => {
+                            let location = Location {
+                                fractal_index: self.parameters.fractal_index,
+                                center: fixed_point(center),
+                                zoom,
+                                max_iterations: iterations_for_zoom(zoom),
+                            };
+                            self.max_iterations = u32x1::splat(location.max_iterations);
+                            self.position =
+                                location.position(&self.position, &self.default_position);
+                            self.should_redraw = true;
+                            self.should_animate = true;
+                        }
+                        None => self.message = Some("no boundary found".to_string()),
+                    }
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Char('K') => {
+                    self.keyframes.push(Keyframe {
+                        view: View {
+                            keyframes: Vec::new(),
+                            ..self.view()
+                        },
+                        seconds: if self.keyframes.is_empty() {
+                            0.0
+                        } else {
+                            KEYFRAME_SECONDS
+                        },
+                    });
+                    self.message = Some(format!("added keyframe {}", self.keyframes.len()));
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Char('P') => {
+                    if self.keyframes.len() < 2 {
+                        self.message = Some("add two or more keyframes with K first".to_string());
+                    } else {
+                        self.playback = Some(std::time::Instant::now());
+                        self.message = Some("playing keyframes: press any key to stop".to_string());
+                    }
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Char('D') => self.start_dive(),
+                crossterm::event::KeyCode::Char('S') => self.toggle_split(None),
+                crossterm::event::KeyCode::Char('J') => {
+                    self.start_morph(MorphPath::circle_through(self.parameters.julia_constant));
+                }
+                crossterm::event::KeyCode::Char('v') => {
+                    let dive = Screensaver::dive(
+                        &mut self.random,
+                        &self.default_position,
+                        &self.parameters,
+                        0,
+                    );
+                    self.position = dive.start.clone();
+                    self.screensaver = Some(dive);
+                    self.message = Some("screensaver: press any key to stop".to_string());
+                    self.should_redraw = true;
+                    self.should_animate = true;
+                }
+                crossterm::event::KeyCode::Char('y') => {
+                    let location = Location {
+                        fractal_index: self.parameters.fractal_index,
+                        center: self.position.center(),
+                        zoom: self.default_position.width() / self.position.width(),
+                        max_iterations: self.max_iterations[0],
+                    };
+                    self.clipboard = Some(copy_to_clipboard(&location.encode()));
+                    self.message = Some("copied location to clipboard".to_string());
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Char('e') if self.restricted => {
+                    self.message = Some("files aren't available here".to_string());
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Char('e') => {
+                    self.message = Some(
+                        match export_path().ok_or("no home directory to export the view to") {
+                            Ok(path) => {
+                                match save_view(&path, &self.view(), &self.default_position) {
+                                    Ok(()) => format!("saved view to {}", path.display()),
+                                    Err(error) => error,
+                                }
+                            }
+                            Err(error) => error.to_string(),
+                        },
+                    );
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Char(':') => {
+                    self.command_input = Some(String::new());
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Char('f') => {
+                    self.menu = Some(Menu::new(
+                        MenuKind::Fractal,
+                        "Fractal",
+                        &fractal_names(),
+                        self.parameters.fractal_index,
+                    ));
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Char('p') => {
+                    self.menu = Some(Menu::new(
+                        MenuKind::Palette,
+                        "Palette",
+                        &palette_names(),
+                        self.parameters.palette_index,
+                    ));
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Tab => {
+                    self.settings_selected = Some(0);
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Char('m') => {
+                    self.show_minimap = !self.show_minimap;
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Char('B') => self.move_to_boundary(),
+                crossterm::event::KeyCode::Char('T') => {
+                    self.show_periods = !self.show_periods;
+                    self.should_repaint = true;
+                }
+                // The server's speakers aren't the client's
+                crossterm::event::KeyCode::Char('Z') if self.restricted => {
+                    self.message = Some("sound isn't available here".to_string());
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Char('Z') => {
+                    self.message = Some(match self.sound.take() {
+                        Some(_) => "sound off".to_string(),
+                        None => match Tone::start() {
+                            Ok(tone) => {
+                                self.sound = Some(tone);
+                                "sound on".to_string()
+                            }
+                            Err(error) => error,
+                        },
+                    });
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Char('H') => {
+                    self.show_histogram = !self.show_histogram;
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Char('I') => {
+                    self.show_description = !self.show_description;
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Char('A') => {
+                    (self.show_axes, self.show_grid) = match (self.show_axes, self.show_grid) {
+                        (false, _) => (true, false),
+                        (true, false) => (true, true),
+                        (true, true) => (false, false),
+                    };
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::F(3) => {
+                    self.show_stats = !self.show_stats;
+                    self.should_repaint = true;
+                }
+                crossterm::event::KeyCode::Char('?') => {
+                    self.show_help = true;
+                    self.should_repaint = true;
+                }
+                _ => (),
+            }
+        }
+        true
+    }
+
+    // Switches between the flat view and a log-polar one around the crosshair, or the middle
+    fn toggle_log_polar(&mut self) {
+        let (width, height) = self.last_terminal_size;
+        let projection = match self.position.projection {
+            Projection::Flat => Projection::LogPolar,
+            Projection::LogPolar => Projection::Flat,
+        };
+        let center = match self.crosshair.take() {
+            Some((column, row)) => cell_to_plane(
+                column,
+                row,
+                width,
+                height.saturating_sub(1),
+                &self.displayed_position,
+            ),
+            None => self.position.center(),
+        };
+        self.position = Position {
+            projection,
+            ..self.position.centered_at(&center)
+        };
+        let places = decimal_places(self.default_position.width() / self.position.width());
+        self.message = Some(match projection {
+            Projection::Flat => "flat view".to_string(),
+            Projection::LogPolar => format!(
+                "log-polar around {}, {}",
+                trimmed_decimal(&center.0, places),
+                trimmed_decimal(&center.1, places)
+            ),
+        });
+        self.should_redraw = true;
+        self.should_repaint = true;
+    }
+
+    // Multiplies the zoom by `factor` exactly, rather than by compounding zoom steps
+    fn magnify(&mut self, factor: f64) {
+        let zoom = self.default_position.width() / self.position.width() * factor;
+        self.position = self.position.with_zoom(&self.default_position, zoom);
+        self.message = Some(format!("zoom: {}", format_zoom(zoom)));
+        self.should_redraw = true;
+        self.should_animate = true;
+    }
+
+    // Dives toward the point under the crosshair, or the middle of the view without one
+    fn start_dive(&mut self) {
+        let (width, height) = self.last_terminal_size;
+        let target = match self.crosshair {
+            Some((column, row)) => cell_to_plane(
+                column,
+                row,
+                width,
+                height.saturating_sub(1),
+                &self.displayed_position,
+            ),
+            None => self.position.center(),
+        };
+        self.dive = Some(Dive {
+            target,
+            start: self.position.clone(),
+            started: std::time::Instant::now(),
+            rate: self.dive_rate,
+        });
+        self.message = Some(format!(
+            "diving at {}x a second: press any key to stop",
+            self.dive_rate
+        ));
+        self.should_repaint = true;
+    }
+
+    fn start_morph(&mut self, path: MorphPath) {
+        self.parameters.fractal_index = JULIA_INDEX;
+        self.morph = Some((path, std::time::Instant::now()));
+        self.message = Some("morphing the Julia set: press any key to stop".to_string());
+        self.should_redraw = true;
+    }
+
+    // Whether the view is being moved along by itself, which isn't worth recording or
+    // announcing step by step
+    fn animating(&self) -> bool {
+        self.screensaver.is_some()
+            || self.playback.is_some()
+            || self.morph.is_some()
+            || self.dive.is_some()
+    }
+
+    fn show_tour_stop(&mut self, index: usize) {
+        let stop = &self.tour[index];
+        self.parameters.fractal_index = stop.location.fractal_index;
+        self.max_iterations = u32x1::splat(stop.location.max_iterations);
+        self.auto_iterations = false;
+        self.position = stop
+            .location
+            .position(&self.position, &self.default_position);
+        self.message = Some(format!(
+            "Tour {}/{}: {}",
+            index + 1,
+            self.tour.len(),
+            stop.caption
+        ));
+        self.tour_stop = Some(index);
+        self.tour_shown = std::time::Instant::now();
+        self.should_redraw = true;
+        self.should_animate = true;
+    }
+
+    // How long until the stop on screen moves on by itself, if it does
+    fn tour_remaining(&self) -> Option<std::time::Duration> {
+        let index = self.tour_stop?;
+        // The last stop stays up
+        let stop = self
+            .tour
+            .get(index)
+            .filter(|_| index + 1 < self.tour.len())?;
+        Some(stop.duration?.saturating_sub(self.tour_shown.elapsed()))
+    }
+
+    fn handle_mouse(&mut self, mut event: crossterm::event::MouseEvent) {
+        let (width, height) = self.last_terminal_size;
+        // Both panes of a split show the same part of the plane
+        if self.split.is_some() && event.column > width {
+            event.column -= width + 1;
+        }
+        let frame_height = height.saturating_sub(1);
+        self.pointer = (event.row < frame_height).then_some((event.column, event.row));
+
+        match event.kind {
+            crossterm::event::MouseEventKind::Down(crossterm::event::MouseButton::Left)
+                if event.row < frame_height =>
+            {
+                self.drag_start = Some((event.column, event.row, self.position.clone()));
+                self.dragged = false;
+            }
+            crossterm::event::MouseEventKind::Drag(crossterm::event::MouseButton::Left) => {
+                if let Some((start_column, start_row, start_position)) = &self.drag_start {
+                    // Move the plane with the cursor so the grabbed point stays under it
+                    let start = cell_to_plane(
+                        *start_column,
+                        *start_row,
+                        width,
+                        frame_height,
+                        start_position,
+                    );
+                    let current =
+                        cell_to_plane(event.column, event.row, width, frame_height, start_position);
+
+                    self.position = start_position.translated(
+                        (&start.0 - &current.0).to_f64(),
+                        (&start.1 - &current.1).to_f64(),
+                    );
+                    self.dragged = true;
+                    self.should_redraw = true;
+                }
+            }
+            crossterm::event::MouseEventKind::ScrollUp
+            | crossterm::event::MouseEventKind::ScrollDown
+                if event.row < frame_height && self.drag_start.is_none() =>
+            {
+                let factor = if event.kind == crossterm::event::MouseEventKind::ScrollUp {
+                    1.0 - self.zoom_step
+                } else {
+                    1.0 + self.zoom_step
+                };
+                let point =
+                    cell_to_plane(event.column, event.row, width, frame_height, &self.position);
+                self.position = self.position.zoomed_at(&point, factor);
+                self.should_redraw = true;
+            }
+            crossterm::event::MouseEventKind::Down(crossterm::event::MouseButton::Right)
+                if event.row < frame_height =>
+            {
+                self.selection = Some(((event.column, event.row), (event.column, event.row)));
+                self.should_repaint = true;
+            }
+            crossterm::event::MouseEventKind::Drag(crossterm::event::MouseButton::Right) => {
+                if let Some((start, _)) = self.selection {
+                    let end = (
+                        event.column.min(width.saturating_sub(1)),
+                        event.row.min(frame_height.saturating_sub(1)),
+                    );
+                    self.selection = Some((start, end));
+                    self.should_repaint = true;
+                }
+            }
+            crossterm::event::MouseEventKind::Up(crossterm::event::MouseButton::Right) => {
+                if let Some((start, end)) = self.selection.take() {
+                    // Use the outer edges of the selected cells rather than their centers
+                    let cell_width = self.position.width() / width as f64;
+                    let cell_height = self.position.height() / frame_height as f64;
+                    let top_left = cell_to_plane(
+                        start.0.min(end.0),
+                        start.1.min(end.1),
+                        width,
+                        frame_height,
+                        &self.position,
+                    );
+                    let bottom_right = cell_to_plane(
+                        start.0.max(end.0),
+                        start.1.max(end.1),
+                        width,
+                        frame_height,
+                        &self.position,
+                    );
+
+                    if let Some(zoomed) = box_zoom(
+                        &self.position,
+                        &(
+                            &top_left.0 - cell_width / 2.0,
+                            &top_left.1 - cell_height / 2.0,
+                        ),
+                        &(
+                            &bottom_right.0 + cell_width / 2.0,
+                            &bottom_right.1 + cell_height / 2.0,
+                        ),
+                    ) {
+                        self.position = zoomed;
+                    }
+                    self.should_redraw = true;
+                }
+            }
+            crossterm::event::MouseEventKind::Up(crossterm::event::MouseButton::Left) => {
+                if let Some((start_column, start_row, start_position)) = self.drag_start.take() {
+                    if !self.dragged {
+                        let point = cell_to_plane(
+                            start_column,
+                            start_row,
+                            width,
+                            frame_height,
+                            &start_position,
+                        );
+                        self.position = start_position.centered_at(&point);
+                        self.should_redraw = true;
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn handle_paste(&mut self, text: String) {
+        if let Some(input) = self.command_input.as_mut() {
+            input.push_str(&text);
+        } else {
+            match Location::decode(&text) {
+                Ok(location) => {
+                    self.parameters.fractal_index = location.fractal_index;
+                    self.max_iterations = u32x1::splat(location.max_iterations);
+                    self.auto_iterations = false;
+                    self.position = location.position(&self.position, &self.default_position);
+                    self.should_redraw = true;
+                    self.should_animate = true;
+                }
+                Err(error) => self.message = Some(error),
+            }
+        }
+        self.should_repaint = true;
+    }
+
+    // Follow the view to wherever input, transitions and settings have moved it,
+    // and ask for a new frame if it changed
+    fn update(&mut self, previous: (u32x1, FractalParameters), terminal_size: (u16, u16)) {
+        let (previous_iterations, previous_parameters) = previous;
+        let too_small = (terminal_size.0 < MIN_TERMINAL_SIZE.0
+            || terminal_size.1 < MIN_TERMINAL_SIZE.1)
+            .then_some(terminal_size);
+        if too_small != self.too_small {
+            self.too_small = too_small;
+            self.clear_screen = true;
+            self.should_repaint = true;
+            self.should_redraw = true;
+        }
+        // The view takes the left pane of a split
+        let (terminal_size, pane_width) = match &self.split {
+            Some(_) => {
+                let (left, right) = split_widths(terminal_size.0);
+                ((left, terminal_size.1), right)
+            }
+            None => (terminal_size, 0),
+        };
+
+        if let Some((export, _)) = &self.export {
+            loop {
+                match export.try_recv() {
+                    Ok(message) => {
+                        self.message = Some(message);
+                        self.should_repaint = true;
+                    }
+                    Err(std::sync::mpsc::TryRecvError::Empty) => break,
+                    Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                        self.export = None;
+                        break;
+                    }
+                }
+            }
+        }
+
+        if let Some(index) = self.tour_stop {
+            if self.tour_remaining() == Some(std::time::Duration::ZERO) {
+                self.show_tour_stop(index + 1);
+            }
+        }
+
+        if self.aspect != Aspect::Stretch {
+            let (width, height) = terminal_size;
+            let fitted = match self.aspect {
+                Aspect::Letterbox => letterbox(
+                    &self.position,
+                    &self.default_position,
+                    width,
+                    height.saturating_sub(1),
+                ),
+                _ => fit_aspect(&self.position, width, height.saturating_sub(1)),
+            };
+            if fitted != self.position {
+                self.position = fitted;
+                self.should_redraw = true;
+            }
+        }
+
+        if let Some(dive) = self.screensaver.as_ref() {
+            match dive.position() {
+                Some(dive_position) => self.position = dive_position,
+                None => {
+                    let dives = dive.dives + 1;
+                    if dives % DIVES_PER_SWITCH == 0 {
+                        self.parameters.fractal_index =
+                            self.random.next_u64() as usize % fractal_names().len();
+                        self.parameters.palette_index =
+                            self.random.next_u64() as usize % palette_names().len();
+                    }
+                    let dive = Screensaver::dive(
+                        &mut self.random,
+                        &self.default_position,
+                        &self.parameters,
+                        dives,
+                    );
+                    self.position = dive.start.clone();
+                    self.screensaver = Some(dive);
+                    self.should_animate = true;
+                }
+            }
+            self.max_iterations = u32x1::splat(iterations_for_zoom(
+                self.default_position.width() / self.position.width(),
+            ));
+            self.should_redraw = true;
+        }
+
+        if let Some(start) = self.playback {
+            let seconds = start.elapsed().as_secs_f64();
+            let view = keyframe_view(&self.keyframes, &self.default_position, seconds, 0);
+            self.position = view.position;
+            self.max_iterations = view.max_iterations;
+            self.auto_iterations = view.auto_iterations;
+            self.parameters = view.parameters;
+            if seconds >= keyframes_seconds(&self.keyframes) {
+                self.playback = None;
+            }
+            self.should_redraw = true;
+        }
+
+        // The constant only moves on once the last one has been drawn, so slow frames are
+        // skipped rather than queued up
+        if let Some((path, start)) = &self.morph {
+            if !self.renderer.pending() {
+                let t = start.elapsed().as_secs_f64() / MORPH_PERIOD.as_secs_f64();
+                self.parameters.julia_constant = path.constant(t);
+                self.should_redraw = true;
+            }
+        }
+
+        // Dives are paced the same way, with the reduced quality frames of navigation
+        // keeping them moving
+        if let Some(dive) = &self.dive {
+            if !self.renderer.pending() {
+                let position = dive.position();
+                if precision_exhausted(terminal_size.0, &position, &self.parameters) {
+                    self.dive = None;
+                    self.message = Some("dive stopped: out of precision".to_string());
+                    self.should_repaint = true;
+                } else {
+                    self.position = position;
+                    self.should_redraw = true;
+                }
+            }
+        }
+
+        if self.auto_iterations {
+            let zoom = self.default_position.width() / self.position.width();
+            let tuned = u32x1::splat(iterations_for_zoom(zoom));
+            if tuned != self.max_iterations {
+                self.max_iterations = tuned;
+                self.should_redraw = true;
+            }
+        }
+
+        if !self.animating() {
+            let text = toast_text(
+                previous_iterations,
+                &previous_parameters,
+                self.max_iterations,
+                &self.parameters,
+            );
+            if let Some(text) = text {
+                self.toast = Some((text, std::time::Instant::now()));
+                self.should_repaint = true;
+            }
+        }
+
+        // A drag is recorded as a single step once the button is released
+        if self.drag_start.is_none() && !self.animating() {
+            self.history.record(ViewState {
+                position: self.position.clone(),
+                max_iterations: self.max_iterations,
+            });
+        }
+
+        if self.should_animate {
+            self.animation = Some((self.displayed_position.clone(), std::time::Instant::now()));
+        } else if self.should_redraw && self.animation.is_none() {
+            self.displayed_position = self.position.clone();
+        }
+
+        if let Some((start_position, start_time)) = &self.animation {
+            self.should_redraw = true;
+            let t = start_time.elapsed().as_secs_f64() / ANIMATION_DURATION.as_secs_f64();
+            if t >= 1.0 {
+                self.displayed_position = self.position.clone();
+                self.animation = None;
+            } else {
+                self.displayed_position =
+                    interpolate_position(start_position, &self.position, ease_in_out(t));
+                if let Some(buffer) = &self.frame_buffer {
+                    self.displayed_position = buffer.snap(&self.displayed_position);
+                }
+            }
+        }
+
+        let restore = !self.should_redraw
+            && self.reduced_frame
+            && self.animation.is_none()
+            && !self.renderer.pending()
+            && self.last_navigation.elapsed() >= QUALITY_RESTORE_DELAY;
+        if (self.should_redraw || restore) && self.too_small.is_none() {
+            // The bottom row is reserved for the status bar
+            let frame_height = terminal_size.1.saturating_sub(1);
+
+            self.restoring = restore;
+            // Frames while the window is being resized are only there to follow it, so they
+            // are rendered as fast as they can be until it settles
+            let resizing = self
+                .resized
+                .is_some_and(|resized| resized.elapsed() < QUALITY_RESTORE_DELAY);
+            let (scale, frame_parameters) = if self.restoring {
+                (1, self.parameters)
+            } else {
+                self.last_navigation = std::time::Instant::now();
+                let level = if resizing {
+                    MAX_QUALITY_LEVEL
+                } else if self.low_power {
+                    self.quality_level.max(LOW_POWER_QUALITY_LEVEL)
+                } else {
+                    self.quality_level
+                };
+                reduced_quality(level, &self.parameters)
+            };
+
+            let canvas_active = self.canvas.as_ref().is_some_and(|canvas| {
+                self.split.is_none()
+                    && canvas.size.0 >= terminal_size.0
+                    && canvas.size.1 >= frame_height
+                    && self.displayed_position.projection == Projection::Flat
+                    // Cuts are shaded from one cell per terminal cell, which loses Braille dots
+                    && self.grid_index != BRAILLE_INDEX
+            });
+            let cut = if canvas_active {
+                self.cut_from_canvas(terminal_size.0, frame_height, &frame_parameters)
+            } else {
+                None
+            };
+            if let Some((view, reduced)) = cut {
+                // Views inside the canvas are cut out of it rather than rendered again
+                self.reduced_frame = reduced;
+                if let Some(canvas) = &mut self.canvas {
+                    canvas.cut = Some(view);
+                }
+            } else {
+                // Show the last frame stretched over the new view while the new one renders
+                if let Some(buffer) = &self.frame_buffer {
+                    if !self.restoring
+                        && buffer.cell_shift(&self.displayed_position).is_none()
+                        && iteration_parameters(&buffer.parameters)
+                            == iteration_parameters(&frame_parameters)
+                    {
+                        self.frame = buffer
+                            .resampled(
+                                terminal_size.0,
+                                frame_height,
+                                &self.displayed_position,
+                                &self.parameters,
+                            )
+                            .text();
+                        self.last_terminal_size = terminal_size;
+                        self.should_repaint = true;
+                    }
+                }
+
+                // A canvas is rendered around the view instead of it
+                let (width, height, position) = match &mut self.canvas {
+                    Some(canvas) if canvas_active => {
+                        canvas.view_size = (terminal_size.0, frame_height);
+                        let position = canvas_position(
+                            canvas.size,
+                            terminal_size.0,
+                            frame_height,
+                            &self.displayed_position,
+                        );
+                        (canvas.size.0, canvas.size.1, position)
+                    }
+                    _ => (
+                        terminal_size.0,
+                        frame_height,
+                        self.displayed_position.clone(),
+                    ),
+                };
+                if !self.renderer.pending() {
+                    self.render_start = Some(std::time::Instant::now());
+                }
+                if self.log.is_some() {
+                    let zoom = self.default_position.width() / self.displayed_position.width();
+                    let places = decimal_places(zoom);
+                    let center = self.displayed_position.center();
+                    let fields = [
+                        ("fractal", fractal_label(&frame_parameters)),
+                        ("x", center.0.to_decimal(places)),
+                        ("y", center.1.to_decimal(places)),
+                        ("zoom", format!("{:e}", zoom)),
+                        ("iterations", self.max_iterations[0].to_string()),
+                        ("scale", scale.to_string()),
+                        ("supersampling", frame_parameters.supersampling.to_string()),
+                    ];
+                    self.log("view", &fields);
+                }
+                self.renderer.request(
+                    width,
+                    height,
+                    &position,
+                    self.max_iterations,
+                    &frame_parameters,
+                    self.tile_size,
+                    scale,
+                );
+                if let Some(split) = &mut self.split {
+                    let (max_iterations, parameters) =
+                        split.settings.apply(self.max_iterations, &frame_parameters);
+                    split.width = pane_width;
+                    split.renderer.request(
+                        pane_width,
+                        frame_height,
+                        &self.displayed_position,
+                        max_iterations,
+                        &parameters,
+                        self.tile_size,
+                        scale,
+                    );
+                }
+            }
+        }
+
+        if let Some(tone) = &self.sound {
+            let (width, frame_height) = (terminal_size.0, terminal_size.1.saturating_sub(1));
+            let (column, row) = self
+                .crosshair
+                .or(self.pointer)
+                .unwrap_or((width / 2, frame_height / 2));
+            let point = cell_to_plane(column, row, width, frame_height, &self.displayed_position);
+            let iteration = iterate_point(
+                point.0.to_f64(),
+                point.1.to_f64(),
+                self.max_iterations,
+                &self.parameters,
+            );
+            tone.set(tone_frequency(iteration, self.max_iterations));
+        }
+    }
+
+    fn receive_frame(&mut self, writer: &mut impl Write) -> std::io::Result<()> {
+        if let Some(split) = &mut self.split {
+            if let Some(rendered) = split.renderer.latest() {
+                let buffer = &rendered.buffer;
+                split.status_bar = render_status_bar(
+                    buffer.width,
+                    &buffer.position,
+                    &self.default_position,
+                    buffer.max_iterations,
+                    &buffer.parameters,
+                    rendered.frame_time,
+                );
+                split.frame = rendered.text;
+                self.should_repaint = true;
+            }
+        }
+        if let Some(rendered) = self.renderer.latest() {
+            let buffer = &rendered.buffer;
+            let pixels =
+                buffer.width as u64 * buffer.height as u64 * GRID_SUBPIXELS[self.grid_index];
+            self.log(
+                "render",
+                &[
+                    ("width", buffer.width.to_string()),
+                    ("height", buffer.height.to_string()),
+                    (
+                        "ms",
+                        format!("{:.1}", rendered.frame_time.as_secs_f64() * 1000.0),
+                    ),
+                    ("iterations", rendered.iterations.to_string()),
+                    ("pixels", pixels.to_string()),
+                    ("scale", rendered.scale.to_string()),
+                ],
+            );
+            self.frame_stats = (rendered.frame_time, rendered.iterations, pixels);
+            self.series_stats = rendered
+                .skipped_iterations
+                .map(|skipped| (buffer.parameters.series_order, skipped));
+            self.reduced_frame = rendered.scale > 1
+                || buffer.parameters.supersampling < self.parameters.supersampling;
+            if !self.restoring {
+                self.quality_level =
+                    next_quality_level(self.quality_level, rendered.frame_time, self.frame_budget);
+            }
+
+            match &mut self.canvas {
+                Some(canvas) if (buffer.width, buffer.height) == canvas.size => {
+                    // The view moves on while a canvas renders, so the part cut out of it is
+                    // for the view as it is now, or stretched over it if it's off the grid
+                    let (width, height) = canvas.view_size;
+                    let view = buffer
+                        .cropped(width, height, &self.displayed_position)
+                        .unwrap_or_else(|| {
+                            buffer.resampled(
+                                width,
+                                height,
+                                &self.displayed_position,
+                                &buffer.parameters,
+                            )
+                        });
+                    canvas.frame = Some((rendered.buffer.clone(), self.reduced_frame));
+                    canvas.cut = Some(view);
+                }
+                canvas => {
+                    if let Some(canvas) = canvas {
+                        canvas.frame = None;
+                    }
+                    self.show_frame(writer, rendered.buffer, rendered.text)?;
+                }
+            }
+        }
+        if let Some(view) = self.canvas.as_mut().and_then(|canvas| canvas.cut.take()) {
+            let text = view.text();
+            self.show_frame(writer, std::sync::Arc::new(view), text)?;
+        }
+        Ok(())
+    }
+
+    // Puts a frame on screen, with the status bar and window title for its view
+    fn show_frame(
+        &mut self,
+        writer: &mut impl Write,
+        buffer: std::sync::Arc<FrameBuffer>,
+        text: String,
+    ) -> std::io::Result<()> {
+        self.frame = text;
+        self.status_bar = render_status_bar(
+            buffer.width,
+            &buffer.position,
+            &self.default_position,
+            buffer.max_iterations,
+            &buffer.parameters,
+            self.frame_stats.0,
+        );
+
+        let title = window_title(&buffer.position, &self.default_position, &buffer.parameters);
+        if title != self.window_title_text {
+            crossterm::execute!(writer, crossterm::terminal::SetTitle(&title))?;
+            self.window_title_text = title;
+        }
+
+        self.last_terminal_size = (buffer.width, buffer.height + 1);
+        self.frame_buffer = Some(buffer);
+        if !self.renderer.pending() {
+            self.render_start = None;
+        }
+        self.should_repaint = true;
+        Ok(())
+    }
+
+    // The part of the last canvas the view covers, if it is inside it and the canvas was
+    // rendered with the settings the view needs, with whether it is at reduced quality
+    fn cut_from_canvas(
+        &self,
+        width: u16,
+        height: u16,
+        frame_parameters: &FractalParameters,
+    ) -> Option<(FrameBuffer, bool)> {
+        let (canvas, reduced) = self.canvas.as_ref()?.frame.as_ref()?;
+        // A canvas at full quality does for any frame, but one at reduced quality only for
+        // another preview
+        let parameters = match reduced {
+            true if self.restoring => return None,
+            true => frame_parameters,
+            false => &self.parameters,
+        };
+        if canvas.max_iterations != self.max_iterations
+            || iteration_parameters(&canvas.parameters) != iteration_parameters(parameters)
+        {
+            return None;
+        }
+        let mut view = canvas.cropped(width, height, &self.displayed_position)?;
+        // Palettes and colorings changed since are taken from the view
+        view.parameters = *parameters;
+        Some((view, *reduced))
+    }
+
+    fn draw(&mut self, writer: &mut impl Write) -> std::io::Result<()> {
+        if self.clear_screen {
+            crossterm::execute!(
+                writer,
+                crossterm::terminal::Clear(crossterm::terminal::ClearType::All)
+            )?;
+            self.clear_screen = false;
+        }
+        if let Some(text) = self.clipboard.take() {
+            writer.write_all(text.as_bytes())?;
+        }
+
+        if let Some((width, height)) = self.too_small {
+            if self.should_repaint {
+                let lines = [
+                    "Terminal too small".to_string(),
+                    format!("needs {}x{}", MIN_TERMINAL_SIZE.0, MIN_TERMINAL_SIZE.1),
+                ];
+                crossterm::execute!(writer, crossterm::style::ResetColor)?;
+                for (row, line) in lines.iter().take(height as usize).enumerate() {
+                    let line: String = line.chars().take(width as usize).collect();
+                    crossterm::execute!(writer, crossterm::cursor::MoveTo(0, row as u16))?;
+                    writer.write_all(line.as_bytes())?;
+                }
+                writer.flush()?;
+            }
+            self.should_redraw = false;
+            self.should_repaint = false;
+            return Ok(());
+        }
+
+        if self.should_repaint {
+            // Overlays are painted on top of the cached frame so they can be
+            // opened and closed without recalculating the fractal
+            let (width, height) = self.last_terminal_size;
+            // Lines across the screen, like the command line, span both panes of a split
+            let screen_width = match &self.split {
+                Some(split) => width + 1 + split.width,
+                None => width,
+            };
+            match &self.split {
+                Some(split) => {
+                    write_pane(writer, &self.frame, 0)?;
+                    write_pane(writer, &split.frame, width + 1)?;
+                    for row in 0..height.saturating_sub(1) {
+                        crossterm::execute!(writer, crossterm::cursor::MoveTo(width, row))?;
+                        write!(writer, "{}│", crossterm::style::ResetColor)?;
+                    }
+                }
+                None => {
+                    crossterm::execute!(writer, crossterm::cursor::MoveTo(0, 0))?;
+                    writer.write_all(self.frame.as_bytes())?;
+                }
+            }
+            if self.aspect == Aspect::Letterbox {
+                let frame_height = height.saturating_sub(1);
+                let position = &self.displayed_position;
+                let mut bars =
+                    render_letterbox(width, frame_height, position, &self.default_position, 0);
+                if let Some(split) = &self.split {
+                    bars.push_str(&render_letterbox(
+                        split.width,
+                        frame_height,
+                        position,
+                        &self.default_position,
+                        width + 1,
+                    ));
+                }
+                writer.write_all(bars.as_bytes())?;
+            }
+            crossterm::execute!(
+                writer,
+                crossterm::cursor::MoveTo(0, height.saturating_sub(1))
+            )?;
+            if let Some(input) = &self.command_input {
+                let line = render_status_line(screen_width, &format!(":{}", input));
+                writer.write_all(line.as_bytes())?;
+            } else if let Some(message) = &self.message {
+                let line = render_status_line(screen_width, &format!(" {}", message));
+                writer.write_all(line.as_bytes())?;
+            } else {
+                writer.write_all(self.status_bar.as_bytes())?;
+                if let Some(split) = &self.split {
+                    write!(writer, " {}", split.status_bar)?;
+                }
+            }
+
+            if let Some(buffer) = self.frame_buffer.as_ref().filter(|_| self.show_periods) {
+                // Orbits are only followed again once a new frame comes in
+                if !matches!(
+                    &self.period_tint,
+                    Some((cached, _)) if std::sync::Arc::ptr_eq(cached, buffer)
+                ) {
+                    self.period_tint = Some((buffer.clone(), render_period_tint(buffer)));
+                }
+                if let Some((_, tint)) = &self.period_tint {
+                    writer.write_all(tint.as_bytes())?;
+                }
+            }
+
+            if !self.rays.is_empty() && self.parameters.fractal_index == 0 {
+                let paths: Vec<&[(f64, f64)]> = self.rays.iter().map(Vec::as_slice).collect();
+                let frame_height = height.saturating_sub(1);
+                let rays = render_paths(&paths, width, frame_height, &self.displayed_position);
+                writer.write_all(rays.as_bytes())?;
+            }
+
+            if self.show_axes {
+                let axes = render_axes(
+                    width,
+                    height.saturating_sub(1),
+                    &self.displayed_position,
+                    self.show_grid,
+                );
+                writer.write_all(axes.as_bytes())?;
+            }
+
+            if let Some((start, end)) = self.selection {
+                writer.write_all(render_selection(start, end).as_bytes())?;
+            }
+
+            if self.show_minimap {
+                // The full view only depends on the fractal, so only re-render it when that changes
+                if !matches!(&self.minimap, Some((cached, _)) if *cached == self.parameters) {
+                    let (rows, _) = render_rows(
+                        MINIMAP_SIZE.0,
+                        MINIMAP_SIZE.1,
+                        &self.default_position,
+                        u32x1::splat(MINIMAP_ITERATIONS),
+                        &self.parameters,
+                    );
+                    self.minimap = Some((self.parameters, rows));
+                }
+
+                if let Some((_, rows)) = &self.minimap {
+                    let inset = render_minimap(
+                        rows,
+                        &self.displayed_position,
+                        &self.default_position,
+                        width,
+                        height.saturating_sub(1),
+                    );
+                    writer.write_all(inset.as_bytes())?;
+                }
+            }
+
+            if let Some(buffer) = self.frame_buffer.as_ref().filter(|_| self.show_histogram) {
+                // One bin a row, leaving room for the border, title and totals
+                let frame_height = height.saturating_sub(1);
+                let bins = (frame_height as usize).saturating_sub(5);
+                let lines = histogram_lines(buffer.cells(), buffer.max_iterations, bins);
+                let overlay = render_overlay(&lines, Anchor::Right, width, frame_height);
+                writer.write_all(overlay.as_bytes())?;
+            }
+
+            if let Some((column, row)) = self.crosshair {
+                let frame_height = height.saturating_sub(1);
+                let point =
+                    cell_to_plane(column, row, width, frame_height, &self.displayed_position);
+                let approximate = (point.0.to_f64(), point.1.to_f64());
+                let iteration = iterate_point(
+                    approximate.0,
+                    approximate.1,
+                    self.max_iterations,
+                    &self.parameters,
+                );
+
+                if self.parameters.fractal_index == 0 {
+                    let preview = render_julia_preview(
+                        approximate,
+                        &self.parameters,
+                        self.max_iterations,
+                        width,
+                        frame_height,
+                    );
+                    writer.write_all(preview.as_bytes())?;
+                }
+
+                writer.write_all(render_crosshair(column, row).as_bytes())?;
+                let period = (iteration == self.max_iterations)
+                    .then(|| {
+                        orbit_period(
+                            approximate.0,
+                            approximate.1,
+                            self.max_iterations,
+                            &self.parameters,
+                        )
+                    })
+                    .flatten();
+                let lines = crosshair_lines(&point, iteration, self.max_iterations, period);
+                let overlay = render_overlay(&lines, Anchor::TopRight, width, frame_height);
+                writer.write_all(overlay.as_bytes())?;
+            }
+
+            if self.show_stats {
+                let (frame_time, iterations, pixels) = self.frame_stats;
+                let lines = stats_lines(frame_time, iterations, pixels, self.series_stats);
+                let overlay = render_overlay(&lines, Anchor::TopLeft, width, height);
+                writer.write_all(overlay.as_bytes())?;
+            }
+
+            if let Some(buffer) = self.frame_buffer.as_ref().filter(|_| self.show_description) {
+                let lines = describe_view(buffer, &self.default_position);
+                let overlay =
+                    render_overlay(&lines, Anchor::Bottom, width, height.saturating_sub(1));
+                writer.write_all(overlay.as_bytes())?;
+            }
+
+            if let Some((text, _)) = &self.toast {
+                let lines = [text.clone()];
+                let overlay =
+                    render_overlay(&lines, Anchor::Bottom, width, height.saturating_sub(1));
+                writer.write_all(overlay.as_bytes())?;
+            }
+
+            if let Some(menu) = &self.menu {
+                let overlay = render_overlay(&menu.lines(), Anchor::Center, screen_width, height);
+                writer.write_all(overlay.as_bytes())?;
+            }
+
+            if let Some(selected) = self.settings_selected {
+                let settings = Settings {
+                    max_iterations: &mut self.max_iterations,
+                    auto_iterations: &mut self.auto_iterations,
+                    parameters: &mut self.parameters,
+                    aspect: &mut self.aspect,
+                    zoom_step: &mut self.zoom_step,
+                    tile_size: &mut self.tile_size,
+                    frame_budget: &mut self.frame_budget,
+                };
+                let overlay = render_overlay(
+                    &settings.lines(selected),
+                    Anchor::Center,
+                    screen_width,
+                    height,
+                );
+                writer.write_all(overlay.as_bytes())?;
+            }
+
+            if self.show_help {
+                let lines = help_lines(
+                    &self.keymap,
+                    self.max_iterations,
+                    &self.parameters,
+                    self.zoom_step,
+                    self.pan_step,
+                );
+                let overlay = render_overlay(&lines, Anchor::Center, screen_width, height);
+                writer.write_all(overlay.as_bytes())?;
+            }
+
+            writer.flush()?;
+        }
+
+        if let Some(start) = self.render_start {
+            if start.elapsed() >= SPINNER_DELAY && self.command_input.is_none() {
+                let (width, height) = self.last_terminal_size;
+                let indicator = progress_indicator(start.elapsed(), self.renderer.progress());
+                let length = indicator.chars().count() as u16;
+                crossterm::execute!(
+                    writer,
+                    crossterm::cursor::MoveTo(
+                        width.saturating_sub(length),
+                        height.saturating_sub(1)
+                    )
+                )?;
+                writer.write_all(render_status_line(length, &indicator).as_bytes())?;
+                writer.flush()?;
+            }
+        }
+
+        self.should_redraw = false;
+        self.should_repaint = false;
+        Ok(())
+    }
+
+    fn view(&self) -> View {
+        View {
+            position: self.position.clone(),
+            max_iterations: self.max_iterations,
+            auto_iterations: self.auto_iterations,
+            parameters: self.parameters,
+            keyframes: self.keyframes.clone(),
+        }
+    }
+
+    fn move_to_boundary(&mut self) {
+        let width = self.position.width();
+        if width < DISTANCE_MIN_WIDTH {
+            self.message = Some("too deep to look for the boundary".to_string());
+            self.should_repaint = true;
+            return;
+        }
+        let center = self.position.center();
+        let center = (center.0.to_f64(), center.1.to_f64());
+        match nearest_boundary(center, width, self.max_iterations, &self.parameters) {
+            // Moving rather than jumping keeps the digits beyond f64 in the center
+            Some(point) => {
+                self.position = self
+                    .position
+                    .translated(point.0 - center.0, point.1 - center.1);
+                self.should_redraw = true;
+                self.should_animate = true;
+            }
+            None => {
+                self.message = Some("no boundary nearby".to_string());
+                self.should_repaint = true;
+            }
+        }
+    }
+
+    // Splits cells into subpixels as `grid_index` of GRID_NAMES says from the next frame on
+    fn set_grid(&mut self, grid_index: usize) {
+        self.grid_index = grid_index;
+        self.renderer.set_grid(grid_index);
+        if let Some(split) = &mut self.split {
+            split.renderer.set_grid(grid_index);
+        }
+        self.should_redraw = true;
+    }
+
+    fn toggle_split(&mut self, settings: Option<SplitSettings>) {
+        self.split = match (settings, self.split.take()) {
+            (None, Some(_)) => None,
+            (settings, split) => {
+                let next_fractal = (self.parameters.fractal_index + 1) % fractal_names().len();
+                let settings = settings.unwrap_or(SplitSettings {
+                    fractal_index: Some(next_fractal),
+                    ..SplitSettings::default()
+                });
+                Some(Split {
+                    settings,
+                    renderer: split.map_or_else(Renderer::spawn, |split| split.renderer),
+                    width: 0,
+                    frame: String::new(),
+                    status_bar: String::new(),
+                })
+            }
+        };
+        self.set_grid(self.grid_index);
+        self.clear_screen = true;
+        self.should_redraw = true;
+    }
+
+    fn switch_tab(&mut self, index: usize) {
+        if index == self.tab {
+            self.message = Some(format!("already on tab {}", index + 1));
+            self.should_repaint = true;
+            return;
+        }
+
+        // New tabs start from the view on screen, with nothing to undo
+        let history = History::new(ViewState {
+            position: self.position.clone(),
+            max_iterations: self.max_iterations,
+        });
+        let current = Tab {
+            view: self.view(),
+            history: std::mem::replace(&mut self.history, history),
+            frame: self.frame.clone(),
+            frame_buffer: self.frame_buffer.clone(),
+            status_bar: self.status_bar.clone(),
+        };
+        self.message = Some(match self.tabs[index].take() {
+            Some(tab) => {
+                self.apply_view(tab.view);
+                self.history = tab.history;
+                self.frame = tab.frame;
+                self.frame_buffer = tab.frame_buffer;
+                self.status_bar = tab.status_bar;
+                format!("tab {}", index + 1)
+            }
+            None => format!("tab {}: new, from tab {}", index + 1, self.tab + 1),
+        });
+        self.tabs[self.tab] = Some(current);
+        self.tab = index;
+
+        // Tabs are switched to, not flown between
+        self.should_animate = false;
+        self.animation = None;
+        self.displayed_position = self.position.clone();
+        self.should_redraw = true;
+        self.should_repaint = true;
+    }
+
+    // Switches to another fractal, coming back to where it was left if it was on screen before.
+    // Otherwise the view stays, or moves to the fractal's own with fractal_views on
+    fn switch_fractal(&mut self, index: usize) {
+        self.should_redraw = true;
+        if index == self.parameters.fractal_index {
+            return;
+        }
+
+        let left = FractalMemory {
+            state: ViewState {
+                position: self.position.clone(),
+                max_iterations: self.max_iterations,
+            },
+            auto_iterations: self.auto_iterations,
+            palette_index: self.parameters.palette_index,
+        };
+        self.fractal_memory
+            .insert(self.parameters.fractal_index, left);
+        self.parameters.fractal_index = index;
+        if let Some(memory) = self.fractal_memory.remove(&index) {
+            self.position = memory.state.position;
+            self.max_iterations = memory.state.max_iterations;
+            self.auto_iterations = memory.auto_iterations;
+            self.parameters.palette_index = memory.palette_index;
+        } else if self.fractal_views {
+            self.position = fractal_view(&self.default_position, index);
+            if self.auto_iterations {
+                self.max_iterations = u32x1::splat(iterations_for_zoom(
+                    self.default_position.width() / self.position.width(),
+                ));
+            }
+        } else {
+            return;
+        }
+        // Fractals are switched to, not flown between
+        self.animation = None;
+        self.displayed_position = self.position.clone();
+    }
+
+    fn apply_view(&mut self, view: View) {
+        self.position = view.position;
+        self.max_iterations = view.max_iterations;
+        self.auto_iterations = view.auto_iterations;
+        self.parameters = view.parameters;
+        self.keyframes = view.keyframes;
+        self.should_redraw = true;
+        self.should_animate = true;
+    }
+
+    fn session(&self) -> Session {
+        Session {
+            location: Location {
+                fractal_index: self.parameters.fractal_index,
+                center: self.position.center(),
+                zoom: self.default_position.width() / self.position.width(),
+                max_iterations: self.max_iterations[0],
+            },
+            palette_index: self.parameters.palette_index,
+            julia_constant: self.parameters.julia_constant,
+            auto_iterations: self.auto_iterations,
+            rotation: self.position.rotation.to_degrees(),
+            projection: self.position.projection,
+        }
+    }
+}
+
+/// Runs the viewer, or one of its subcommands, with the command-line arguments after the
+/// program name. Help and usage errors are printed by clap, which exits with the right status.
+pub fn run(args: impl IntoIterator<Item = String>) -> Result<(), AppError> {
+    // Scripts and plugins come first so their fractals and palettes can be named in options
+    // and the config
+    let (script_tour, mut script_errors) = load_scripts();
+    script_errors.extend(load_plugins());
+    let options = parse_args(args).unwrap_or_else(|error| error.exit());
+    if options.write_default_config {
+        let path = write_default_config().map_err(AppError::Config)?;
+        println!("wrote {}", path.display());
+        return Ok(());
+    }
+    apply_options(&options).map_err(AppError::Options)?;
+    let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+    if let Some(path) = &options.batch {
+        for error in &script_errors {
+            eprintln!("{}", error);
+        }
+        return run_batch(path, &default_position).map_err(AppError::Batch);
+    }
+    if let Some(json) = &options.bench {
+        let backend_index = options
+            .backend_index
+            .unwrap_or(DEFAULT_PARAMETERS.backend_index);
+        return run_bench(json.as_deref(), &default_position, backend_index)
+            .map_err(AppError::Bench);
+    }
+    let (mut config, config_error) = match load_config() {
+        Ok(config) => (config, None),
+        Err(error) => (Config::default(), Some(format!("config: {}", error))),
+    };
+
+    // Command-line options win over the config file, which wins over the defaults
+    let mut parameters = FractalParameters {
+        fractal_index: options
+            .fractal_index
+            .or(config.fractal_index)
+            .unwrap_or(DEFAULT_PARAMETERS.fractal_index),
+        palette_index: options
+            .palette_index
+            .or(config.palette_index)
+            .unwrap_or(DEFAULT_PARAMETERS.palette_index),
+        coloring_index: config
+            .coloring_index
+            .unwrap_or(DEFAULT_PARAMETERS.coloring_index),
+        supersampling: config
+            .supersampling
+            .unwrap_or(DEFAULT_PARAMETERS.supersampling),
+        backend_index: options
+            .backend_index
+            .or(config.backend_index)
+            .unwrap_or(DEFAULT_PARAMETERS.backend_index),
+        series_order: config
+            .series_order
+            .unwrap_or(DEFAULT_PARAMETERS.series_order),
+        series_tolerance: config
+            .series_tolerance
+            .unwrap_or(DEFAULT_PARAMETERS.series_tolerance),
+        tile_limits: config
+            .tile_limits
+            .unwrap_or(DEFAULT_PARAMETERS.tile_limits),
+        ..DEFAULT_PARAMETERS
+    };
+    let console = console_for(options.console_index.or(config.console_index).unwrap_or(0));
+    let grid_index = config.grid_index.unwrap_or(0);
+    let mut position = start_position(&options, &default_position);
+    let zoom = default_position.width() / position.width();
+    let mut max_iterations = u32x1::splat(
+        options
+            .iterations
+            .unwrap_or_else(|| iterations_for_zoom(zoom)),
+    );
+    let mut auto_iterations = options.iterations.is_none();
+    let mut keyframes = Vec::new();
+    if let Some(path) = &options.view {
+        let view = load_view(path, &default_position).map_err(AppError::Options)?;
+        position = view.position;
+        max_iterations = view.max_iterations;
+        auto_iterations = view.auto_iterations;
+        keyframes = view.keyframes;
+        // A palette given alongside the file still wins
+        parameters = FractalParameters {
+            palette_index: options
+                .palette_index
+                .unwrap_or(view.parameters.palette_index),
+            ..view.parameters
+        };
+    }
+
+    if let Some(path) = &options.export_view {
+        let view = View {
+            position,
+            max_iterations,
+            auto_iterations,
+            parameters,
+            keyframes,
+        };
+        save_view(path, &view, &default_position).map_err(AppError::Options)?;
+        println!("wrote {}", path.display());
+        return Ok(());
+    }
+    if let Some(export) = options.gif.as_ref().or(options.video.as_ref()) {
+        let view = View {
+            position,
+            max_iterations,
+            auto_iterations,
+            parameters,
+            keyframes,
+        };
+        let start = std::time::Instant::now();
+        let progress = |frame, frames| {
+            // Padded to cover a longer line before it
+            eprint!("\rrendered {}  ", export_progress(frame, frames, start.elapsed()));
+            true
+        };
+        let result = if options.gif.is_some() {
+            export_gif(export, &view, &default_position, progress)
+        } else {
+            export_video(export, &view, &default_position, progress)
+        };
+        eprintln!();
+        result.map_err(AppError::Export)?;
+        println!("wrote {}", export.output.display());
+        return Ok(());
+    }
+
+    if let Some(address) = &options.tiles {
+        for error in script_errors.iter().chain(&config_error) {
+            eprintln!("{}", error);
+        }
+        return serve_tiles(address, parameters, default_position);
+    }
+
+    if options.once {
+        for error in script_errors.iter().chain(&config_error) {
+            eprintln!("{}", error);
+        }
+        // Leave a row for the shell prompt when filling the terminal
+        let size = options
+            .size
+            .unwrap_or_else(|| match crossterm::terminal::size() {
+                Ok((width, height)) => (width, height.saturating_sub(1).max(1)),
+                Err(_) => (80, 24),
+            });
+        let describe = options.describe.then_some(&default_position);
+        let mut output = ConsoleOutput::new(std::io::stdout().lock(), console);
+        print_frame(
+            &mut output,
+            size,
+            &position,
+            max_iterations,
+            &parameters,
+            grid_index,
+            describe,
+        )?;
+        return Ok(());
+    }
+
+    // Pick up where the last run left off, unless a view was asked for on the command line
+    let view_given = options.fractal_index.is_some()
+        || options.view.is_some()
+        || options.center.is_some()
+        || options.zoom.is_some()
+        || options.iterations.is_some();
+    let mut session_error = None;
+    // Clients of serve all start from the same view, not the last one on this terminal
+    if !options.fresh && !view_given && options.serve.is_none() {
+        match load_session() {
+            Ok(Some(session)) => {
+                parameters.fractal_index = session.location.fractal_index;
+                parameters.julia_constant = session.julia_constant;
+                if options.palette_index.is_none() {
+                    parameters.palette_index = session.palette_index;
+                }
+                position = Position {
+                    projection: session.projection,
+                    ..session
+                        .location
+                        .position(&default_position, &default_position)
+                        .rotated(session.rotation.to_radians())
+                };
+                max_iterations = u32x1::splat(session.location.max_iterations);
+                auto_iterations = session.auto_iterations;
+            }
+            Ok(None) => (),
+            Err(error) => session_error = Some(format!("session: {}", error)),
+        }
+    }
+
+    let mut message = script_errors
+        .into_iter()
+        .next()
+        .or(config_error)
+        .or(session_error);
+    let (keymap, keymap_error) = load_keymap(config.keymap.take());
+    message = keymap_error.or(message);
+    let low_power = options.low_power;
+    let canvas_size = options.canvas_size.or(config.canvas_size);
+    let new_app = move || {
+        let mut app = App::new(
+            default_position.clone(),
+            position.clone(),
+            max_iterations,
+            parameters,
+            keymap.clone(),
+        );
+        app.auto_iterations = auto_iterations;
+        app.keyframes = keyframes.clone();
+        app.zoom_step = config.zoom_step.unwrap_or(app.zoom_step);
+        app.pan_step = config.pan_step.unwrap_or(app.pan_step);
+        app.dive_rate = config.dive_rate.unwrap_or(app.dive_rate);
+        app.fractal_views = config.fractal_views.unwrap_or(app.fractal_views);
+        app.canvas = canvas_size.map(Canvas::new);
+        app.set_grid(grid_index);
+        app.low_power = low_power;
+        // Scripted tours take the place of the built-in one
+        if !script_tour.is_empty() {
+            app.tour = script_tour.clone();
+        }
+        app
+    };
+    if let Some(address) = &options.serve {
+        if let Some(message) = message {
+            eprintln!("{}", message);
+        }
+        return serve(address, move || App {
+            restricted: true,
+            ..new_app()
+        });
+    }
+    let mut app = new_app();
+    app.message = message;
+    if options.watch {
+        app.watch = Some((WatchedFiles::now(), std::time::Instant::now()));
+    }
+
+    if let Some(path) = &options.replay {
+        let context = |error: String| AppError::Options(format!("{}: {}", path.display(), error));
+        let text = std::fs::read_to_string(path).map_err(|error| context(error.to_string()))?;
+        let keystrokes = parse_keystrokes(&text).map_err(context)?;
+        app.replay = (!keystrokes.is_empty()).then(|| (keystrokes, std::time::Instant::now()));
+    }
+    if let Some(path) = &options.record_keys {
+        let file = std::fs::File::create(path)
+            .map_err(|error| AppError::Options(format!("{}: {}", path.display(), error)))?;
+        app.key_recording = Some((
+            Box::new(std::io::BufWriter::new(file)),
+            std::time::Instant::now(),
+        ));
+    }
+    if let Some(path) = &options.log_file {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|error| AppError::Options(format!("{}: {}", path.display(), error)))?;
+        app.log = Some((
+            Box::new(std::io::BufWriter::new(file)),
+            std::time::Instant::now(),
+        ));
+        // What the terminal says it is, for reports from terminals that misbehave
+        let variable = |name| std::env::var(name).unwrap_or_default();
+        let (width, height) = crossterm::terminal::size()?;
+        app.log(
+            "start",
+            &[
+                ("version", env!("CARGO_PKG_VERSION").to_string()),
+                ("term", variable("TERM")),
+                ("colorterm", variable("COLORTERM")),
+                ("term_program", variable("TERM_PROGRAM")),
+                ("console", format!("{:?}", console)),
+                ("width", width.to_string()),
+                ("height", height.to_string()),
+                ("threads", rayon::current_num_threads().to_string()),
+            ],
+        );
+    }
+    let cast = match &options.record {
+        Some(path) => Some(
+            Cast::create(path, crossterm::terminal::size()?)
+                .map_err(|error| AppError::Options(format!("{}: {}", path.display(), error)))?,
+        ),
+        None => None,
+    };
+    let mut writer = Recorder {
+        terminal: ConsoleOutput::new(std::io::BufWriter::new(std::io::stdout()), console),
+        cast,
+    };
+    install_terminal_guards();
+    crossterm::terminal::enable_raw_mode()?;
+    enter_terminal(&mut writer)?;
+
+    // Put the terminal back before reporting an error from the viewer
+    let result = app.run(&mut TerminalInput, &mut writer);
+    restore_terminal(&mut writer)?;
+    drop(writer);
+    result?;
+
+    if let Err(error) = save_session(&app.session()) {
+        eprintln!("session: {}", error);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::session::TOUR;
+    use crate::telnet::ClientInput;
+
+    #[test]
+    fn test_history() {
+        let state = |iterations: u32| ViewState {
+            position: Position::new(-1.0, 1.0, -2.0, 1.0),
+            max_iterations: u32x1::splat(iterations),
+        };
+
+        let mut history = History::new(state(100));
+        assert_eq!(history.undo(), None);
+
+        history.record(state(110));
+        history.record(state(110));
+        history.record(state(120));
+        assert_eq!(history.undo(), Some(state(110)));
+        assert_eq!(history.undo(), Some(state(100)));
+        assert_eq!(history.undo(), None);
+        assert_eq!(history.redo(), Some(state(110)));
+
+        // Navigating after an undo discards the redo branch
+        history.record(state(130));
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.undo(), Some(state(110)));
+    }
+
+    #[test]
+    fn test_hold_acceleration() {
+        assert_eq!(hold_acceleration(std::time::Duration::ZERO), 1.0);
+        assert_eq!(hold_acceleration(std::time::Duration::from_millis(500)), 2.0);
+        assert_eq!(
+            hold_acceleration(std::time::Duration::from_secs(60)),
+            MAX_HOLD_ACCELERATION
+        );
+    }
+
+    #[test]
+    fn test_adjust_step() {
+        assert_eq!(adjust_step(0.1, true, ZOOM_STEP_RANGE), 0.2);
+        assert_eq!(adjust_step(0.1, false, ZOOM_STEP_RANGE), 0.05);
+        assert_eq!(adjust_step(0.8, true, ZOOM_STEP_RANGE), 0.9);
+        assert_eq!(adjust_step(0.015, false, ZOOM_STEP_RANGE), 0.01);
+    }
+
+    #[test]
+    fn test_step_iterations() {
+        assert_eq!(step_iterations(u32x1::splat(100), true), u32x1::splat(150));
+        assert_eq!(step_iterations(u32x1::splat(101), true), u32x1::splat(152));
+        assert_eq!(step_iterations(u32x1::splat(150), false), u32x1::splat(100));
+        // Small limits still grow, and shrinking stops at the minimum
+        assert_eq!(step_iterations(u32x1::splat(1), true), u32x1::splat(2));
+        assert_eq!(step_iterations(u32x1::splat(12), false), u32x1::splat(10));
+        assert_eq!(step_iterations(u32x1::splat(5), false), u32x1::splat(5));
+        // And growing stops at the maximum
+        assert_eq!(
+            step_iterations(u32x1::splat(u32::MAX), true),
+            u32x1::splat(MAX_ITERATIONS)
+        );
+        assert_eq!(
+            step_iterations(u32x1::splat(MAX_ITERATIONS - 1), true),
+            u32x1::splat(MAX_ITERATIONS)
+        );
+    }
+
+    #[test]
+    fn test_ease() {
+        for easing_index in 0..EASING_NAMES.len() {
+            assert_eq!(ease(easing_index, 0.0), 0.0);
+            assert_eq!(ease(easing_index, 1.0), 1.0);
+        }
+        assert_eq!(ease(0, 0.25), 0.25);
+        assert_eq!(ease(1, 0.5), 0.25);
+        assert_eq!(ease(2, 0.5), 0.75);
+        assert_eq!(ease(3, 0.5), 0.5);
+        assert!(ease(3, 0.25) < 0.25);
+    }
+
+    #[test]
+    fn test_format_zoom() {
+        assert_eq!(format_zoom(1.0), "1.00x");
+        assert_eq!(format_zoom(123.456), "123.46x");
+        assert_eq!(format_zoom(32000000.0), "3.20e7x");
+    }
+
+    #[test]
+    fn test_fractal_label() {
+        let mut parameters = DEFAULT_PARAMETERS;
+        assert_eq!(fractal_label(&parameters), "Mandelbrot Set");
+
+        parameters.fractal_index = JULIA_INDEX;
+        parameters.julia_constant = (-0.8, -0.156);
+        assert_eq!(fractal_label(&parameters), "Julia Set (c = -0.800000 -0.156000i)");
+    }
+
+    #[test]
+    fn test_window_title() {
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        assert_eq!(
+            window_title(&default_position, &default_position, &DEFAULT_PARAMETERS),
+            "Mandelbrot Set - 1.00x"
+        );
+
+        let position = default_position.zoomed_at(&fixed_point((-0.5, 0.0)), 0.0001);
+        let parameters = FractalParameters {
+            fractal_index: 1,
+            ..DEFAULT_PARAMETERS
+        };
+        assert_eq!(
+            window_title(&position, &default_position, &parameters),
+            "Sinking Ship - 1.00e4x"
+        );
+    }
+
+    #[test]
+    fn test_canvas() {
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let mut app = App::new(
+            default_position.clone(),
+            default_position,
+            u32x1::splat(100),
+            DEFAULT_PARAMETERS,
+            Keymap::preset("default").unwrap(),
+        );
+        app.canvas = Some(Canvas::new((60, 30)));
+        let settle = |app: &mut App| {
+            app.update((app.max_iterations, app.parameters), (40, 20));
+            while app.renderer.pending() {
+                app.receive_frame(&mut Vec::new()).unwrap();
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+            app.receive_frame(&mut Vec::new()).unwrap();
+        };
+        settle(&mut app);
+
+        // The view is cut out of the middle of a bigger frame
+        let canvas = app.canvas.as_ref().unwrap().frame.clone().unwrap().0;
+        assert_eq!((canvas.width, canvas.height), (60, 30));
+        let view = app.frame_buffer.clone().unwrap();
+        assert_eq!((view.width, view.height), (40, 19));
+        assert_eq!(view.position, app.position);
+        assert_eq!(view.cells()[0], canvas.cells()[5 * 60 + 10]);
+
+        // Moves inside it are shown without rendering again
+        let cell_width = app.position.width() / 40.0;
+        app.position = app.position.moved(8.0 * cell_width, 0.0);
+        app.should_redraw = true;
+        app.update((app.max_iterations, app.parameters), (40, 20));
+        assert!(!app.renderer.pending());
+        app.receive_frame(&mut Vec::new()).unwrap();
+        let view = app.frame_buffer.clone().unwrap();
+        assert_eq!(view.position, app.position);
+        assert_eq!(view.cells()[0], canvas.cells()[5 * 60 + 18]);
+
+        // but moves out of it render a new canvas around the view
+        app.position = app.position.moved(8.0 * cell_width, 0.0);
+        app.should_redraw = true;
+        settle(&mut app);
+        assert_eq!(app.frame_buffer.as_ref().unwrap().position, app.position);
+        let moved = app.canvas.as_ref().unwrap().frame.clone().unwrap().0;
+        assert_eq!(
+            moved.cells()[15 * 60..][..44],
+            canvas.cells()[15 * 60 + 16..][..44]
+        );
+    }
+
+    #[test]
+    fn test_grid() {
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let mut app = App::new(
+            default_position.clone(),
+            default_position,
+            u32x1::splat(100),
+            DEFAULT_PARAMETERS,
+            Keymap::preset("default").unwrap(),
+        );
+        app.set_grid(BRAILLE_INDEX);
+        app.canvas = Some(Canvas::new((60, 30)));
+        app.update((app.max_iterations, app.parameters), (40, 20));
+        while app.renderer.pending() {
+            app.receive_frame(&mut Vec::new()).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        // Frames still have a cell for each terminal cell, but are shown as Braille dots
+        let buffer = app.frame_buffer.clone().unwrap();
+        assert_eq!((buffer.width, buffer.height), (40, 19));
+        assert_eq!(app.frame.lines().count(), 19);
+        assert!(app.frame.chars().any(|c| ('\u{2801}'..'\u{28ff}').contains(&c)));
+        assert_eq!(app.frame_stats.2, 40 * 19 * 8);
+        // which a canvas would lose
+        assert!(app.canvas.as_ref().unwrap().frame.is_none());
+    }
+
+    #[test]
+    fn test_low_power() {
+        use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let mut app = App::new(
+            default_position.clone(),
+            default_position,
+            u32x1::splat(100),
+            DEFAULT_PARAMETERS,
+            Keymap::preset("default").unwrap(),
+        );
+        app.low_power = true;
+        app.update((app.max_iterations, app.parameters), (80, 24));
+        while app.renderer.pending() {
+            app.receive_frame(&mut Vec::new()).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        // Moving previews at reduced quality even when frames are quick
+        let key = KeyEvent::new(KeyCode::Char('d'), KeyModifiers::NONE);
+        app.handle_event(Event::Key(key));
+        app.update((app.max_iterations, app.parameters), (80, 24));
+        while app.renderer.pending() {
+            app.receive_frame(&mut Vec::new()).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert!(app.reduced_frame);
+
+        // Background work is checked on less often
+        let (_sender, receiver) = std::sync::mpsc::channel();
+        app.export = Some((receiver, Default::default()));
+        app.should_redraw = false;
+        app.should_repaint = false;
+        app.toast = None;
+        assert_eq!(app.timeout(), Some(LOW_POWER_FRAME_INTERVAL));
+    }
+
+    #[test]
+    fn test_watch() {
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let mut app = App::new(
+            default_position.clone(),
+            default_position,
+            u32x1::splat(100),
+            DEFAULT_PARAMETERS,
+            Keymap::preset("default").unwrap(),
+        );
+        let stale = || WatchedFiles {
+            config: vec![Some(std::time::UNIX_EPOCH)],
+            scripts: vec![("gone.rhai".into(), None)],
+        };
+
+        // Files are only looked at every so often
+        app.watch = Some((stale(), std::time::Instant::now()));
+        app.check_watched();
+        assert_eq!(app.watch.as_ref().unwrap().0, stale());
+        assert!(app.timeout().unwrap() <= WATCH_INTERVAL);
+
+        app.watch.as_mut().unwrap().1 -= WATCH_INTERVAL;
+        app.should_redraw = false;
+        app.check_watched();
+        assert_eq!(app.watch.as_ref().unwrap().0, WatchedFiles::now());
+        assert!(app.message.is_some());
+        assert!(app.should_redraw);
+    }
+
+    #[test]
+    fn test_replay() {
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let mut app = App::new(
+            default_position.clone(),
+            default_position,
+            u32x1::splat(100),
+            DEFAULT_PARAMETERS,
+            Keymap::preset("default").unwrap(),
+        );
+        let started = std::time::Instant::now();
+        app.replay = Some((parse_keystrokes("0 z\n60 ctrl+x").unwrap(), started));
+        app.should_redraw = false;
+        app.renderer.latest();
+
+        // Only keys that are due are pressed, and the wait ends in time for the next one
+        let z = KeyEvent::new(KeyCode::Char('z'), KeyModifiers::NONE);
+        assert_eq!(app.replayed_key(), Some(z));
+        assert_eq!(app.replayed_key(), None);
+        let timeout = app.timeout().unwrap();
+        assert!(timeout > std::time::Duration::from_secs(59));
+        assert!(timeout <= std::time::Duration::from_secs(60));
+        app.replay.as_mut().unwrap().1 = started - std::time::Duration::from_secs(60);
+        assert_eq!(app.replayed_key().unwrap().code, KeyCode::Char('x'));
+        assert!(app.replay.is_none());
+
+        let path = std::env::temp_dir().join(format!("keys-{}.txt", std::process::id()));
+        let file = std::fs::File::create(&path).unwrap();
+        app.key_recording = Some((Box::new(file), std::time::Instant::now()));
+        app.record_key(z).unwrap();
+        let mut release = z;
+        release.kind = crossterm::event::KeyEventKind::Release;
+        app.record_key(release).unwrap();
+        app.key_recording = None;
+        let recorded = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            parse_keystrokes(&recorded)
+                .unwrap()
+                .iter()
+                .map(|keystroke| keystroke.event)
+                .collect::<Vec<_>>(),
+            [z]
+        );
+    }
+
+    #[test]
+    fn test_log() {
+        use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+        assert_eq!(
+            log_line(
+                std::time::Duration::from_millis(1500),
+                "start",
+                &[
+                    ("term", "xterm-256color".to_string()),
+                    ("colorterm", String::new()),
+                    ("fractal", "Julia (c = 0 +1i)".to_string()),
+                ],
+            ),
+            r#"t=1.500 event=start term=xterm-256color colorterm="" fractal="Julia (c = 0 +1i)""#
+        );
+
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let mut app = App::new(
+            default_position.clone(),
+            default_position,
+            u32x1::splat(100),
+            DEFAULT_PARAMETERS,
+            Keymap::preset("default").unwrap(),
+        );
+        let path = std::env::temp_dir().join(format!("log-{}.txt", std::process::id()));
+        let file = std::fs::File::create(&path).unwrap();
+        app.log = Some((Box::new(file), std::time::Instant::now()));
+        app.handle_event(Event::Key(KeyEvent::new(
+            KeyCode::Char('z'),
+            KeyModifiers::CONTROL,
+        )));
+        app.handle_event(Event::Resize(100, 30));
+        app.log = None;
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let events: Vec<_> = log
+            .lines()
+            .map(|line| line.split_once(' ').unwrap().1)
+            .collect();
+        assert_eq!(
+            events,
+            ["event=key key=ctrl+z", "event=resize width=100 height=30"]
+        );
+    }
+
+    #[test]
+    fn test_app() {
+        use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+        let key = |code| Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let mut app = App::new(
+            default_position.clone(),
+            default_position.clone(),
+            u32x1::splat(100),
+            DEFAULT_PARAMETERS,
+            Keymap::preset("default").unwrap(),
+        );
+        app.last_terminal_size = (80, 24);
+
+        assert!(app.handle_event(key(KeyCode::Char('d'))));
+        assert!(app.position.center().0.to_f64() > -0.5);
+        assert!(app.should_redraw);
+
+        // An open overlay takes the next key instead of it quitting
+        assert!(app.handle_event(key(KeyCode::Char('?'))));
+        assert!(app.show_help);
+        assert!(app.handle_event(key(KeyCode::Char('q'))));
+        assert!(!app.show_help);
+        assert!(!app.handle_event(key(KeyCode::Char('q'))));
+        let control_c = Event::Key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        app.show_help = true;
+        assert!(!app.handle_event(control_c));
+        assert!(app.crosshair.is_none());
+        app.show_help = false;
+
+        // Clients of serve can't save views on the server
+        app.restricted = true;
+        app.handle_event(key(KeyCode::Char('e')));
+        assert_eq!(app.message.as_deref(), Some("files aren't available here"));
+        app.handle_event(key(KeyCode::Char(':')));
+        app.handle_event(Event::Paste("save /tmp/view.json".to_string()));
+        app.handle_event(key(KeyCode::Enter));
+        assert_eq!(app.message.as_deref(), Some("files aren't available here"));
+        app.restricted = false;
+
+        // The view turns in steps either way
+        app.handle_event(key(KeyCode::Char('}')));
+        assert_eq!(app.position.rotation, ROTATION_STEP.to_radians());
+        app.handle_event(key(KeyCode::Char('{')));
+        assert_eq!(app.position.rotation, 0.0);
+
+        // Precise zoom steps land on exact magnifications
+        let zoom = |app: &App| default_position.width() / app.position.width();
+        app.handle_event(key(KeyCode::Char('X')));
+        app.handle_event(key(KeyCode::Char(')')));
+        assert!((zoom(&app) - 20.0).abs() < 1e-9);
+        app.handle_event(key(KeyCode::Char('(')));
+        assert!((zoom(&app) - 10.0).abs() < 1e-9);
+        assert_eq!(app.message.as_deref(), Some("zoom: 10.00x"));
+
+        // Each fractal comes back where it was left, and a new one starts from the view
+        app.handle_event(key(KeyCode::Char(']')));
+        assert_eq!(app.parameters.fractal_index, 1);
+        assert!((zoom(&app) - 10.0).abs() < 1e-9);
+        app.position = app.position.with_zoom(&default_position, 1.0);
+        app.parameters.palette_index = 2;
+        app.handle_event(key(KeyCode::Char('[')));
+        assert_eq!(app.parameters.fractal_index, 0);
+        assert!((zoom(&app) - 10.0).abs() < 1e-9);
+        assert_eq!(app.parameters.palette_index, 0);
+        app.handle_event(key(KeyCode::Char(']')));
+        assert_eq!(zoom(&app), 1.0);
+        assert_eq!(app.parameters.palette_index, 2);
+        app.switch_fractal(0);
+        app.parameters.palette_index = 0;
+
+        // With fractal_views, a fractal not seen yet starts from its own view
+        app.fractal_memory.clear();
+        app.fractal_views = true;
+        app.handle_event(key(KeyCode::Char(']')));
+        assert_eq!(app.position, fractal_view(&default_position, 1));
+        assert_eq!(app.position.center().0.to_f64(), FRACTAL_VIEWS[1].0 .0);
+        app.switch_fractal(0);
+        assert!((zoom(&app) - 10.0).abs() < 1e-9);
+        app.fractal_memory.clear();
+        app.fractal_views = false;
+        app.position = app.position.with_zoom(&default_position, 1.0);
+
+        // Log-polar views are centered on the crosshair
+        app.displayed_position = app.position.clone();
+        let center = app.position.center();
+        app.handle_event(key(KeyCode::Char('c')));
+        app.handle_event(key(KeyCode::Right));
+        app.handle_event(key(KeyCode::Char('L')));
+        assert_eq!(app.position.projection, Projection::LogPolar);
+        assert!(app.position.center().0.to_f64() > center.0.to_f64());
+        assert!(app.crosshair.is_none());
+        app.handle_event(key(KeyCode::Char('L')));
+        assert_eq!(app.position.projection, Projection::Flat);
+        app.position = app.position.centered_at(&center);
+
+        // Resizes in a burst are taken together, and the last size is rendered roughly until
+        // resizing stops
+        let (sender, events) = std::sync::mpsc::channel();
+        let mut input = ClientInput {
+            events,
+            size: (80, 24),
+        };
+        for event in [
+            Event::Resize(90, 30),
+            Event::Resize(100, 30),
+            key(KeyCode::Char('m')),
+        ] {
+            sender.send(event).unwrap();
+        }
+        let event = app.next_event(&mut input).unwrap();
+        assert_eq!(event, Some(Event::Resize(100, 30)));
+        app.handle_event(event.unwrap());
+        assert_eq!(app.deferred_event, Some(key(KeyCode::Char('m'))));
+        assert_eq!(
+            app.next_event(&mut input).unwrap(),
+            Some(key(KeyCode::Char('m')))
+        );
+        app.update((app.max_iterations, app.parameters), (100, 30));
+        while app.renderer.pending() {
+            app.receive_frame(&mut Vec::new()).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert!(app.reduced_frame);
+        app.resized = None;
+        app.last_navigation -= QUALITY_RESTORE_DELAY;
+        app.update((app.max_iterations, app.parameters), (100, 30));
+        while app.renderer.pending() {
+            app.receive_frame(&mut Vec::new()).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert!(!app.reduced_frame);
+
+        let location = Location {
+            fractal_index: 1,
+            center: fixed_point((0.25, -0.5)),
+            zoom: 4.0,
+            max_iterations: 300,
+        };
+        app.handle_event(Event::Paste(location.encode()));
+        let session = app.session();
+        assert_eq!(session.location.encode(), location.encode());
+        assert!(!session.auto_iterations);
+
+        // A saved view comes back after wandering off
+        let path = std::env::temp_dir().join(format!("view-{}.json", std::process::id()));
+        let command = |app: &mut App, text: String| {
+            app.handle_event(key(KeyCode::Char(':')));
+            for character in text.chars() {
+                app.handle_event(key(KeyCode::Char(character)));
+            }
+            app.handle_event(key(KeyCode::Enter));
+        };
+        command(&mut app, format!("save {}", path.display()));
+        assert_eq!(
+            app.message,
+            Some(format!("saved view to {}", path.display()))
+        );
+        app.handle_event(key(KeyCode::Char('r')));
+        app.handle_event(key(KeyCode::Char(']')));
+        command(&mut app, format!("load {}", path.display()));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(app.session().location.encode(), location.encode());
+
+        // Keyframes play back to the last one, then leave it on screen
+        app.handle_event(key(KeyCode::Char('P')));
+        assert!(app.playback.is_none());
+        app.handle_event(key(KeyCode::Char('K')));
+        app.handle_event(key(KeyCode::Char('r')));
+        app.handle_event(key(KeyCode::Char('K')));
+        assert_eq!(app.message, Some("added keyframe 2".to_string()));
+        app.handle_event(key(KeyCode::Char('P')));
+        assert!(app.playback.is_some());
+        app.playback = Some(std::time::Instant::now() - std::time::Duration::from_secs(10));
+        app.update((app.max_iterations, app.parameters), (80, 24));
+        assert!(app.playback.is_none());
+        assert_eq!(app.position, app.keyframes[1].view.position);
+        command(&mut app, "clear".to_string());
+        assert!(app.keyframes.is_empty());
+
+        // Morphing switches to the Julia set and moves its constant until a key is pressed
+        let constant = app.parameters.julia_constant;
+        app.handle_event(key(KeyCode::Char('J')));
+        assert_eq!(app.parameters.fractal_index, JULIA_INDEX);
+        if let Some((_, start)) = &mut app.morph {
+            *start -= MORPH_PERIOD / 4;
+        }
+        // It waits for the frame before to be drawn
+        while app.renderer.pending() {
+            app.renderer.latest();
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        app.update((app.max_iterations, app.parameters), (80, 24));
+        assert_ne!(app.parameters.julia_constant, constant);
+        app.handle_event(key(KeyCode::Char('x')));
+        assert!(app.morph.is_none());
+
+        // Diving closes in on the middle of the view, which stays put
+        let center = app.position.center();
+        let width = app.position.width();
+        app.handle_event(key(KeyCode::Char('D')));
+        if let Some(dive) = &mut app.dive {
+            dive.started -= std::time::Duration::from_secs(3);
+        }
+        while app.renderer.pending() {
+            app.renderer.latest();
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        app.update((app.max_iterations, app.parameters), (80, 24));
+        // At least three seconds in, give or take waiting for the renderer
+        let zoom = width / app.position.width();
+        assert!((8.0..32.0).contains(&zoom), "{}", zoom);
+        assert!((&app.position.center().0 - &center.0).to_f64().abs() < 1e-12);
+        app.handle_event(key(KeyCode::Char('x')));
+        assert!(app.dive.is_none());
+
+        // Timed tour stops move on by themselves, and the last one stays up
+        app.tour = builtin_tour();
+        for stop in &mut app.tour {
+            stop.duration = Some(std::time::Duration::ZERO);
+        }
+        app.handle_event(key(KeyCode::Char('n')));
+        assert_eq!(app.tour_stop, Some(0));
+        app.update((app.max_iterations, app.parameters), (80, 24));
+        assert_eq!(app.tour_stop, Some(1));
+        for _ in 0..TOUR.len() {
+            app.update((app.max_iterations, app.parameters), (80, 24));
+        }
+        assert_eq!(app.tour_stop, Some(TOUR.len() - 1));
+
+        // Each tab keeps its own view
+        app.tour_stop = None;
+        let position = app.position.clone();
+        app.handle_event(key(KeyCode::Char('2')));
+        assert_eq!(app.message.as_deref(), Some("tab 2: new, from tab 1"));
+        assert_eq!(app.position, position);
+        app.handle_event(key(KeyCode::Char('d')));
+        app.update((app.max_iterations, app.parameters), (80, 24));
+        let moved = app.position.clone();
+        app.handle_event(key(KeyCode::Char('1')));
+        assert_eq!(app.position, position);
+        assert_eq!(app.displayed_position, position);
+        app.handle_event(key(KeyCode::Char('2')));
+        assert_eq!(app.message.as_deref(), Some("tab 2"));
+        assert_eq!(app.position, moved);
+        // Undoing doesn't reach back past where the tab started
+        app.handle_event(key(KeyCode::Char('u')));
+        assert_eq!(app.position, position);
+        assert_eq!(app.history.undo(), None);
+
+        // A split shows the view again next to it, on the next fractal unless told otherwise
+        app.handle_event(key(KeyCode::Char('S')));
+        app.update((app.max_iterations, app.parameters), (81, 24));
+        let mut output = Vec::new();
+        while app.renderer.pending() || app.split.as_ref().unwrap().renderer.pending() {
+            app.receive_frame(&mut output).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(app.last_terminal_size, (40, 24));
+        let split = app.split.as_ref().unwrap();
+        assert_eq!(split.width, 40);
+        let next_fractal = (app.parameters.fractal_index + 1) % fractal_names().len();
+        assert_eq!(split.settings.fractal_index, Some(next_fractal));
+        assert_eq!(split.frame.split('\n').count(), 23);
+        app.handle_event(key(KeyCode::Char('S')));
+        assert!(app.split.is_none());
+
+        // A terminal too small to draw in says so instead of rendering
+        app.update((app.max_iterations, app.parameters), (12, 3));
+        assert!(!app.renderer.pending());
+        let mut output = Vec::new();
+        app.draw(&mut output).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.contains("Terminal too"));
+        assert!(text.contains("needs 20x5"));
+        app.update((app.max_iterations, app.parameters), (80, 24));
+        assert!(app.too_small.is_none() && app.renderer.pending());
+        while app.renderer.pending() {
+            app.receive_frame(&mut Vec::new()).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        // Letterboxing keeps the default view's proportions with bars at the sides
+        app.aspect = Aspect::Letterbox;
+        app.position = default_position.clone();
+        app.update((app.max_iterations, app.parameters), (80, 24));
+        assert_eq!(app.position.center(), default_position.center());
+        assert_eq!(app.position.height(), default_position.height());
+        assert!(app.position.width() > default_position.width());
+    }
+
+    #[test]
+    fn test_spinner_frame() {
+        assert_eq!(spinner_frame(std::time::Duration::ZERO), '|');
+        assert_eq!(spinner_frame(SPINNER_INTERVAL * 5), '/');
+        assert_eq!(progress_indicator(SPINNER_INTERVAL * 5, None), "/");
+        assert_eq!(progress_indicator(std::time::Duration::ZERO, Some(0.425)), " 42% |");
+        assert_eq!(progress_indicator(std::time::Duration::ZERO, Some(1.0)), "100% |");
+    }
+
+    #[test]
+    fn test_quality_level() {
+        let millis = std::time::Duration::from_millis;
+        assert_eq!(next_quality_level(0, millis(80), 50), 1);
+        assert_eq!(
+            next_quality_level(MAX_QUALITY_LEVEL, millis(80), 50),
+            MAX_QUALITY_LEVEL
+        );
+        assert_eq!(next_quality_level(2, millis(30), 50), 2);
+        assert_eq!(next_quality_level(2, millis(10), 50), 1);
+        assert_eq!(next_quality_level(2, millis(80), 0), 0);
+
+        let parameters = FractalParameters {
+            supersampling: 3,
+            ..DEFAULT_PARAMETERS
+        };
+        assert_eq!(reduced_quality(0, &parameters), (1, parameters));
+        let (scale, reduced) = reduced_quality(1, &parameters);
+        assert_eq!((scale, reduced.supersampling), (1, 1));
+        assert_eq!(reduced_quality(3, &parameters).0, 4);
+    }
+
+    #[test]
+    fn test_snap_to_cells() {
+        assert_eq!(snap_to_cells(0.26, 3.0, 30), 0.30000000000000004);
+        assert_eq!(snap_to_cells(-0.01, 3.0, 30), -0.1);
+        assert_eq!(snap_to_cells(0.0, 3.0, 30), 0.1);
+    }
+
+    #[test]
+    fn test_deep_position() {
+        // Far past f64 precision, moving and bookmarking the view must stay exact
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let center = (
+            Fixed::parse("-1.76890882500000000000000000000123").unwrap(),
+            Fixed::parse("0.00200833840000000000000000000456").unwrap(),
+        );
+        let position = default_position
+            .with_zoom(&default_position, 1e30)
+            .centered_at(&center);
+        let decimal = |point: (Fixed, Fixed)| (point.0.to_decimal(35), point.1.to_decimal(35));
+        assert_eq!(decimal(position.center()), decimal(center.clone()));
+        assert!((position.width() - 3e-30).abs() < 1e-40);
+
+        let moved = position.translated(position.width(), 0.0);
+        assert_eq!(
+            moved.center().0.to_decimal(35),
+            "-1.76890882499999999999999999999823000"
+        );
+
+        let location = Location {
+            fractal_index: 0,
+            center: position.center(),
+            zoom: 1e30,
+            max_iterations: 1000,
+        };
+        let decoded = Location::decode(&location.encode()).unwrap();
+        assert_eq!(
+            decimal(decoded.position(&position, &default_position).center()),
+            decimal(center)
+        );
+    }
+
+    #[test]
+    fn test_precision_exhausted() {
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let deep = default_position.with_zoom(&default_position, 1e17);
+        let sinking_ship = FractalParameters {
+            fractal_index: 1,
+            ..DEFAULT_PARAMETERS
+        };
+
+        assert!(!precision_exhausted(80, &default_position, &sinking_ship));
+        assert!(precision_exhausted(80, &deep, &sinking_ship));
+        // The Mandelbrot set switches to perturbation instead
+        assert!(!precision_exhausted(80, &deep, &DEFAULT_PARAMETERS));
+
+        let status = |parameters| {
+            render_status_bar(
+                200,
+                &deep,
+                &default_position,
+                u32x1::splat(100),
+                parameters,
+                std::time::Duration::ZERO,
+            )
+        };
+        assert!(status(&sinking_ship).contains("precision limit"));
+        assert!(!status(&DEFAULT_PARAMETERS).contains("precision limit"));
+    }
+}
@@ -0,0 +1,174 @@
+// The bench subcommand, which times a fixed set of views so runs can be compared.
+
+use crate::cli::slug;
+use crate::export::write_atomically;
+use crate::{
+    render_buffer, u32x1, Fixed, FractalParameters, Position, TileCache, BACKEND_NAMES,
+    DEFAULT_PARAMETERS, DEFAULT_TILE_SIZE,
+};
+
+// The views bench renders: a name, the fractal, the center, the zoom and the iteration
+// limit. Centers are text so the deep view keeps all of its digits.
+const BENCH_VIEWS: [(&str, usize, &str, &str, f64, u32); 6] = [
+    ("Whole set", 0, "-0.5", "0", 1.0, 500),
+    ("Seahorse Valley", 0, "-0.75", "0.1", 25.0, 1000),
+    ("Minibrot on the antenna", 0, "-1.985424253", "0", 20000.0, 2000),
+    ("Feigenbaum point", 0, "-1.4011551890920506004", "0", 1e15, 5000),
+    ("Sinking Ship", 1, "-1.76", "-0.03", 12.0, 500),
+    ("Julia Set", 2, "0", "0", 0.75, 500),
+];
+// In cells, which are 2x2 pixels each
+const BENCH_SIZE: (u16, u16) = (320, 96);
+// Each view is rendered this many times and the fastest kept, which is the least disturbed
+// by whatever else the machine is doing
+const BENCH_RUNS: usize = 3;
+
+struct BenchResult {
+    name: &'static str,
+    frame_time: std::time::Duration,
+    iterations: u64,
+}
+
+fn bench_view(
+    view: &(&'static str, usize, &str, &str, f64, u32),
+    default_position: &Position,
+    backend_index: usize,
+) -> BenchResult {
+    let (name, fractal_index, x, y, zoom, max_iterations) = *view;
+    let center = (Fixed::parse(x).unwrap(), Fixed::parse(y).unwrap());
+    let position = default_position
+        .with_zoom(default_position, zoom)
+        .centered_at(&center);
+    let parameters = FractalParameters {
+        fractal_index,
+        backend_index,
+        ..DEFAULT_PARAMETERS
+    };
+
+    let mut best = None;
+    for _ in 0..BENCH_RUNS {
+        let start = std::time::Instant::now();
+        // A fresh cache each time, so no run reuses the work of the one before
+        let (_, iterations) = render_buffer(
+            BENCH_SIZE.0,
+            BENCH_SIZE.1,
+            &position,
+            u32x1::splat(max_iterations),
+            &parameters,
+            DEFAULT_TILE_SIZE,
+            None,
+            &mut TileCache::new(0),
+        );
+        let frame_time = start.elapsed();
+        if best.is_none_or(|(best, _)| frame_time < best) {
+            best = Some((frame_time, iterations));
+        }
+    }
+    let (frame_time, iterations) = best.unwrap();
+    BenchResult {
+        name,
+        frame_time,
+        iterations,
+    }
+}
+
+// The geometric mean of the frames a second each view was rendered at, so no one view
+// outweighs the others. Iteration counts aren't used because shortcuts like filling in tiles
+// count iterations that were never done.
+fn bench_score(results: &[BenchResult]) -> f64 {
+    let log_sum: f64 = results
+        .iter()
+        .map(|result| -result.frame_time.as_secs_f64().max(f64::EPSILON).ln())
+        .sum();
+    (log_sum / results.len() as f64).exp()
+}
+
+fn bench_json(results: &[BenchResult], backend_index: usize) -> serde_json::Value {
+    let views: Vec<serde_json::Value> = results
+        .iter()
+        .map(|result| {
+            serde_json::json!({
+                "name": result.name,
+                "ms": result.frame_time.as_secs_f64() * 1000.0,
+                "iterations": result.iterations,
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "backend": slug(BACKEND_NAMES[backend_index]),
+        "threads": rayon::current_num_threads(),
+        "size": format!("{}x{}", BENCH_SIZE.0, BENCH_SIZE.1),
+        "runs": BENCH_RUNS,
+        "views": views,
+        "score": bench_score(results),
+    })
+}
+
+// Renders the reference views and prints how long each took. The config file is left out,
+// like in batch jobs, so scores from different machines can be compared.
+pub(crate) fn run_bench(
+    json: Option<&std::path::Path>,
+    default_position: &Position,
+    backend_index: usize,
+) -> Result<(), String> {
+    println!(
+        "{}x{} cells, {} backend, {} threads, best of {} runs",
+        BENCH_SIZE.0,
+        BENCH_SIZE.1,
+        BACKEND_NAMES[backend_index],
+        rayon::current_num_threads(),
+        BENCH_RUNS
+    );
+    let mut results = Vec::new();
+    for view in &BENCH_VIEWS {
+        let result = bench_view(view, default_position, backend_index);
+        println!(
+            "{:<26}{:>10.1} ms{:>10.1} M iterations",
+            result.name,
+            result.frame_time.as_secs_f64() * 1000.0,
+            result.iterations as f64 / 1e6
+        );
+        results.push(result);
+    }
+    println!("score: {:.1}", bench_score(&results));
+
+    if let Some(path) = json {
+        let text = serde_json::to_string_pretty(&bench_json(&results, backend_index)).unwrap();
+        write_atomically(path, |partial| {
+            std::fs::write(partial, text + "\n").map_err(|error| error.to_string())
+        })?;
+        println!("wrote {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FRACTAL_NAMES;
+
+    #[test]
+    fn test_bench() {
+        for (_, fractal_index, x, y, _, _) in BENCH_VIEWS {
+            assert!(fractal_index < FRACTAL_NAMES.len());
+            assert!(Fixed::parse(x).is_ok() && Fixed::parse(y).is_ok());
+        }
+
+        let result = |name, ms| BenchResult {
+            name,
+            frame_time: std::time::Duration::from_millis(ms),
+            iterations: 1000,
+        };
+        // 100 and 25 frames a second
+        let results = [result("a", 10), result("b", 40)];
+        assert!((bench_score(&results) - 50.0).abs() < 1e-9);
+
+        let json = bench_json(&results, 2);
+        assert_eq!(json["backend"], "perturbation");
+        assert_eq!(json["size"], "320x96");
+        assert_eq!(json["views"][1]["name"], "b");
+        assert_eq!(json["views"][1]["ms"], 40.0);
+        assert_eq!(json["views"][1]["iterations"], 1000);
+    }
+}
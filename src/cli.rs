@@ -0,0 +1,761 @@
+// The command line: its options, parsing them, and applying the ones that act at once.
+
+use crate::app::{EASING_NAMES, LOW_POWER_NICE, MAX_ITERATIONS, MAX_SIZE};
+use crate::commands::parse_number;
+use crate::export::{
+    Export, EXPORT_FPS, EXPORT_FRAMES, EXPORT_SIZE, VIDEO_FPS, VIDEO_SECONDS, VIDEO_SIZE,
+};
+use crate::telnet::SERVE_ADDRESS;
+use crate::terminal::CONSOLE_NAMES;
+use crate::tile_server::TILES_ADDRESS;
+use crate::{fractal_names, palette_names, Fixed, Position, BACKEND_NAMES};
+
+#[derive(PartialEq, Debug, Default)]
+pub(crate) struct Options {
+    threads: Option<usize>,
+    nice: Option<i32>,
+    pub(crate) low_power: bool,
+    pub(crate) fractal_index: Option<usize>,
+    pub(crate) center: Option<(Fixed, Fixed)>,
+    pub(crate) zoom: Option<f64>,
+    pub(crate) iterations: Option<u32>,
+    pub(crate) palette_index: Option<usize>,
+    pub(crate) size: Option<(u16, u16)>,
+    pub(crate) canvas_size: Option<(u16, u16)>,
+    pub(crate) backend_index: Option<usize>,
+    pub(crate) console_index: Option<usize>,
+    pub(crate) once: bool,
+    pub(crate) describe: bool,
+    pub(crate) fresh: bool,
+    pub(crate) watch: bool,
+    pub(crate) write_default_config: bool,
+    pub(crate) view: Option<std::path::PathBuf>,
+    pub(crate) export_view: Option<std::path::PathBuf>,
+    pub(crate) record: Option<std::path::PathBuf>,
+    pub(crate) record_keys: Option<std::path::PathBuf>,
+    pub(crate) log_file: Option<std::path::PathBuf>,
+    pub(crate) replay: Option<std::path::PathBuf>,
+    // The animations the gif and video subcommands write
+    pub(crate) gif: Option<Export>,
+    pub(crate) video: Option<Export>,
+    // The job file of the batch subcommand
+    pub(crate) batch: Option<std::path::PathBuf>,
+    // The bench subcommand, with the file to write its results to as JSON
+    pub(crate) bench: Option<Option<std::path::PathBuf>>,
+    // The addresses the serve and tiles subcommands listen on
+    pub(crate) serve: Option<String>,
+    pub(crate) tiles: Option<String>,
+}
+
+// Names are matched case-insensitively with dashes for spaces, and the first word is enough
+pub(crate) fn parse_name(names: &[&str], kind: &str, value: &str) -> Result<usize, String> {
+    let lowercase = value.to_lowercase();
+    names
+        .iter()
+        .map(|name| slug(name))
+        .position(|name| name == lowercase || name.split('-').next() == Some(&lowercase))
+        .ok_or(format!("unknown {}: {}", kind, value))
+}
+
+pub(crate) fn slug(name: &str) -> String {
+    name.to_lowercase().replace(' ', "-")
+}
+
+fn name_list(names: &[&str]) -> String {
+    let names: Vec<String> = names.iter().map(|name| slug(name)).collect();
+    names.join(", ")
+}
+
+pub(crate) fn parse_center(value: &str) -> Result<(Fixed, Fixed), String> {
+    match value.split_once(',') {
+        Some((x, y)) => Ok((Fixed::parse(x.trim())?, Fixed::parse(y.trim())?)),
+        None => Err(format!("invalid center: {}", value)),
+    }
+}
+
+pub(crate) fn parse_size(value: &str) -> Result<(u16, u16), String> {
+    let size = value.split_once('x').and_then(|(width, height)| {
+        let width = width.parse::<u16>().ok().filter(|width| *width > 0)?;
+        let height = height.parse::<u16>().ok().filter(|height| *height > 0)?;
+        Some((width, height))
+    });
+    match size {
+        Some((width, height)) if width > MAX_SIZE || height > MAX_SIZE => Err(format!(
+            "size above {}x{}: {}",
+            MAX_SIZE, MAX_SIZE, value
+        )),
+        Some(size) => Ok(size),
+        None => Err(format!("invalid size: {}", value)),
+    }
+}
+
+pub(crate) fn command() -> clap::Command {
+    let arg = |name: &'static str, value_name: &'static str, help: String| {
+        clap::Arg::new(name)
+            .long(name)
+            .value_name(value_name)
+            .help(help)
+            .allow_hyphen_values(true)
+    };
+    // Options shared by the animation subcommands
+    let output = || {
+        clap::Arg::new("output")
+            .value_name("FILE")
+            .required(true)
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+    };
+    let size = |default: (u16, u16)| {
+        let help = format!(
+            "Size of the frames in pixels (default {}x{})",
+            default.0, default.1
+        );
+        arg("size", "WIDTHxHEIGHT", help).value_parser(parse_size)
+    };
+    let fps = |default: u32| {
+        arg(
+            "fps",
+            "RATE",
+            format!("Frames per second (default {})", default),
+        )
+        .value_parser(|value: &str| {
+            let fps = value
+                .parse::<u32>()
+                .ok()
+                .filter(|fps| (1..=100).contains(fps));
+            fps.ok_or(format!("invalid frame rate: {}", value))
+        })
+    };
+    let easing = || {
+        let help = format!(
+            "How the zoom speeds up and slows down: {} (default linear)",
+            name_list(&EASING_NAMES)
+        );
+        arg("easing", "CURVE", help)
+            .value_parser(|value: &str| parse_name(&EASING_NAMES, "easing", value))
+    };
+
+    clap::Command::new("mandelbrot_set")
+        .about("A terminal viewer for the Mandelbrot Set")
+        .arg(
+            arg(
+                "fractal",
+                "NAME",
+                format!("Start on a fractal: {}", name_list(&fractal_names())),
+            )
+            .value_parser(|value: &str| parse_name(&fractal_names(), "fractal", value)),
+        )
+        .arg(
+            arg("center", "X,Y", "Center the view on a point".to_string())
+                .value_parser(parse_center),
+        )
+        .arg(
+            arg(
+                "zoom",
+                "FACTOR",
+                "Magnify the starting view by FACTOR".to_string(),
+            )
+            .value_parser(|value: &str| match parse_number(value) {
+                Ok(zoom) if zoom > 0.0 => Ok(zoom),
+                _ => Err(format!("invalid zoom: {}", value)),
+            }),
+        )
+        .arg(
+            arg(
+                "iterations",
+                "COUNT",
+                "Use a fixed iteration limit instead of tuning it to the zoom".to_string(),
+            )
+            .value_parser(|value: &str| {
+                let iterations = value.parse::<u32>().ok();
+                match iterations.filter(|count| *count > 0) {
+                    Some(count) if count > MAX_ITERATIONS => Err(format!(
+                        "iteration count above {}: {}",
+                        MAX_ITERATIONS, value
+                    )),
+                    Some(count) => Ok(count),
+                    None => Err(format!("invalid iteration count: {}", value)),
+                }
+            }),
+        )
+        .arg(
+            arg(
+                "palette",
+                "NAME",
+                format!("Color with a palette: {}", name_list(&palette_names())),
+            )
+            .value_parser(|value: &str| parse_name(&palette_names(), "palette", value)),
+        )
+        .arg(
+            arg(
+                "size",
+                "WIDTHxHEIGHT",
+                "Size in cells of the frame --once prints, instead of the terminal's".to_string(),
+            )
+            .value_parser(parse_size)
+            .requires("once"),
+        )
+        .arg(
+            arg(
+                "canvas",
+                "WIDTHxHEIGHT",
+                "Render this many cells around the view, so moves inside them are instant"
+                    .to_string(),
+            )
+            .value_parser(parse_size)
+            .conflicts_with("once"),
+        )
+        .arg(
+            arg(
+                "backend",
+                "NAME",
+                format!("Calculate deep views with: {}", name_list(&BACKEND_NAMES)),
+            )
+            .value_parser(|value: &str| parse_name(&BACKEND_NAMES, "backend", value)),
+        )
+        .arg(
+            arg(
+                "console",
+                "NAME",
+                format!("Draw for a terminal that is: {}", name_list(&CONSOLE_NAMES)),
+            )
+            .value_parser(|value: &str| parse_name(&CONSOLE_NAMES, "console", value)),
+        )
+        .arg(
+            arg(
+                "view",
+                "FILE",
+                "Start from a view file: JSON, .kfr, .kfp or .upr".to_string(),
+            )
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+            .conflicts_with_all(["fractal", "center", "zoom", "iterations"]),
+        )
+        .arg(
+            arg(
+                "export-view",
+                "FILE",
+                "Write the starting view to a JSON file and exit".to_string(),
+            )
+            .value_parser(clap::value_parser!(std::path::PathBuf)),
+        )
+        .arg(
+            arg(
+                "record",
+                "FILE",
+                "Record the session to an asciinema cast file".to_string(),
+            )
+            .conflicts_with("once")
+            .value_parser(clap::value_parser!(std::path::PathBuf)),
+        )
+        .arg(
+            arg(
+                "record-keys",
+                "FILE",
+                "Record the keys pressed, with their times, for --replay".to_string(),
+            )
+            .conflicts_with("once")
+            .value_parser(clap::value_parser!(std::path::PathBuf)),
+        )
+        .arg(
+            arg(
+                "log-file",
+                "FILE",
+                "Log renders, their timings and the keys pressed to a file".to_string(),
+            )
+            .conflicts_with("once")
+            .value_parser(clap::value_parser!(std::path::PathBuf)),
+        )
+        .arg(
+            arg(
+                "replay",
+                "FILE",
+                "Press the keys recorded in a file at the times they were".to_string(),
+            )
+            .conflicts_with("once")
+            .value_parser(clap::value_parser!(std::path::PathBuf)),
+        )
+        .arg(
+            clap::Arg::new("once")
+                .long("once")
+                .action(clap::ArgAction::SetTrue)
+                .help("Print a single frame to stdout and exit"),
+        )
+        .arg(
+            clap::Arg::new("describe")
+                .long("describe")
+                .action(clap::ArgAction::SetTrue)
+                .requires("once")
+                .help("Print a description of the frame in words instead of the frame"),
+        )
+        .arg(
+            clap::Arg::new("fresh")
+                .long("fresh")
+                .action(clap::ArgAction::SetTrue)
+                .help("Start from the default view instead of where the last run left off"),
+        )
+        .arg(
+            clap::Arg::new("write-default-config")
+                .long("write-default-config")
+                .action(clap::ArgAction::SetTrue)
+                .help("Write a config file with the default settings and exit"),
+        )
+        .arg(
+            clap::Arg::new("watch")
+                .long("watch")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("once")
+                .help("Reload the config file and scripts whenever they change"),
+        )
+        .arg(
+            clap::Arg::new("low-power")
+                .long("low-power")
+                .action(clap::ArgAction::SetTrue)
+                .help("Save power with half the threads, a lower priority and rougher previews"),
+        )
+        .arg(
+            arg(
+                "threads",
+                "COUNT",
+                "Render on COUNT threads instead of one per core".to_string(),
+            )
+            .value_parser(|value: &str| {
+                let threads = value.parse::<usize>().ok().filter(|threads| *threads > 0);
+                threads.ok_or(format!("invalid thread count: {}", value))
+            }),
+        )
+        .arg(
+            arg(
+                "nice",
+                "INCREMENT",
+                "Lower the scheduling priority by INCREMENT (0-19)".to_string(),
+            )
+            .value_parser(|value: &str| {
+                let nice = value
+                    .parse::<i32>()
+                    .ok()
+                    .filter(|nice| (0..=19).contains(nice));
+                nice.ok_or(format!("invalid niceness: {}", value))
+            }),
+        )
+        .subcommand(
+            clap::Command::new("gif")
+                .about("Render a zoom into the starting view as an animated GIF")
+                .arg(output())
+                .arg(size(EXPORT_SIZE))
+                .arg(
+                    arg(
+                        "frames",
+                        "COUNT",
+                        format!("Frames in the zoom (default {})", EXPORT_FRAMES),
+                    )
+                    .value_parser(|value: &str| {
+                        let frames = value.parse::<u32>().ok().filter(|frames| *frames > 0);
+                        frames.ok_or(format!("invalid frame count: {}", value))
+                    }),
+                )
+                .arg(fps(EXPORT_FPS))
+                .arg(easing()),
+        )
+        .subcommand(
+            clap::Command::new("video")
+                .about("Render a zoom into the starting view as an MP4 or WebM video with ffmpeg")
+                .arg(output())
+                .arg(size(VIDEO_SIZE))
+                .arg(
+                    arg(
+                        "duration",
+                        "SECONDS",
+                        format!("Length of the video (default {})", VIDEO_SECONDS),
+                    )
+                    .value_parser(|value: &str| {
+                        let seconds = value.parse::<f64>().ok();
+                        let seconds =
+                            seconds.filter(|seconds| *seconds > 0.0 && *seconds <= 3600.0);
+                        seconds.ok_or(format!("invalid duration: {}", value))
+                    }),
+                )
+                .arg(fps(VIDEO_FPS))
+                .arg(easing()),
+        )
+        .subcommand(
+            clap::Command::new("batch")
+                .about("Render the views in a JSON job file to images, without the viewer")
+                .arg(
+                    clap::Arg::new("jobs")
+                        .value_name("JOBS")
+                        .required(true)
+                        .value_parser(clap::value_parser!(std::path::PathBuf)),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("bench")
+                .about("Time renders of a fixed set of views, to compare machines and backends")
+                .arg(
+                    arg(
+                        "json",
+                        "FILE",
+                        "Write the timings and the score to a JSON file".to_string(),
+                    )
+                    .value_parser(clap::value_parser!(std::path::PathBuf)),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("serve")
+                .about("Give everyone who connects with telnet a viewer of their own")
+                .arg(
+                    clap::Arg::new("address")
+                        .value_name("ADDRESS")
+                        .default_value(SERVE_ADDRESS)
+                        .help("The address and port to listen on"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("tiles")
+                .about("Serve map tiles of the fractal over HTTP, without the viewer")
+                .arg(
+                    clap::Arg::new("address")
+                        .value_name("ADDRESS")
+                        .default_value(TILES_ADDRESS)
+                        .help("The address and port to listen on"),
+                ),
+        )
+}
+
+pub(crate) fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, clap::Error> {
+    let program = std::iter::once("mandelbrot_set".to_string());
+    let matches = command().try_get_matches_from(program.chain(args))?;
+    Ok(Options {
+        threads: matches.get_one("threads").copied(),
+        nice: matches.get_one("nice").copied(),
+        low_power: matches.get_flag("low-power"),
+        watch: matches.get_flag("watch"),
+        fractal_index: matches.get_one("fractal").copied(),
+        center: matches.get_one("center").cloned(),
+        zoom: matches.get_one("zoom").copied(),
+        iterations: matches.get_one("iterations").copied(),
+        palette_index: matches.get_one("palette").copied(),
+        size: matches.get_one("size").copied(),
+        canvas_size: matches.get_one("canvas").copied(),
+        backend_index: matches.get_one("backend").copied(),
+        console_index: matches.get_one("console").copied(),
+        once: matches.get_flag("once"),
+        describe: matches.get_flag("describe"),
+        fresh: matches.get_flag("fresh"),
+        write_default_config: matches.get_flag("write-default-config"),
+        view: matches.get_one("view").cloned(),
+        export_view: matches.get_one("export-view").cloned(),
+        record: matches.get_one("record").cloned(),
+        record_keys: matches.get_one("record-keys").cloned(),
+        log_file: matches.get_one("log-file").cloned(),
+        replay: matches.get_one("replay").cloned(),
+        gif: matches.subcommand_matches("gif").map(|gif| Export {
+            output: gif.get_one("output").cloned().unwrap(),
+            size: gif.get_one("size").copied().unwrap_or(EXPORT_SIZE),
+            frames: gif.get_one("frames").copied().unwrap_or(EXPORT_FRAMES),
+            fps: gif.get_one("fps").copied().unwrap_or(EXPORT_FPS),
+            easing_index: gif.get_one("easing").copied().unwrap_or(0),
+        }),
+        video: matches.subcommand_matches("video").map(|video| {
+            let fps = video.get_one("fps").copied().unwrap_or(VIDEO_FPS);
+            let seconds: f64 = video.get_one("duration").copied().unwrap_or(VIDEO_SECONDS);
+            Export {
+                output: video.get_one("output").cloned().unwrap(),
+                size: video.get_one("size").copied().unwrap_or(VIDEO_SIZE),
+                frames: ((seconds * fps as f64).round() as u32).max(1),
+                fps,
+                easing_index: video.get_one("easing").copied().unwrap_or(0),
+            }
+        }),
+        batch: matches
+            .subcommand_matches("batch")
+            .and_then(|batch| batch.get_one("jobs").cloned()),
+        bench: matches
+            .subcommand_matches("bench")
+            .map(|bench| bench.get_one("json").cloned()),
+        serve: matches
+            .subcommand_matches("serve")
+            .and_then(|serve| serve.get_one("address").cloned()),
+        tiles: matches
+            .subcommand_matches("tiles")
+            .and_then(|tiles| tiles.get_one("address").cloned()),
+    })
+}
+
+pub(crate) fn apply_options(options: &Options) -> Result<(), String> {
+    // Low power mode only suggests a priority where it can be set
+    let low_power_nice = (options.low_power && cfg!(unix)).then_some(LOW_POWER_NICE);
+    if let Some(nice) = options.nice.or(low_power_nice) {
+        // Threads start with the priority of the thread that spawns them, so lowering it
+        // before anything else starts covers the render threads too
+        #[cfg(unix)]
+        // Safety: nice only changes the scheduling priority of the calling thread
+        unsafe {
+            libc::nice(nice);
+        }
+        #[cfg(not(unix))]
+        if nice != 0 {
+            return Err("--nice is only supported on Unix".to_string());
+        }
+    }
+
+    let low_power_threads = options.low_power.then(|| {
+        let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get());
+        cores.div_ceil(2)
+    });
+    if let Some(threads) = options.threads.or(low_power_threads) {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .map_err(|error| error.to_string())?;
+    }
+    Ok(())
+}
+
+pub(crate) fn start_position(options: &Options, default_position: &Position) -> Position {
+    let position = default_position.with_zoom(default_position, options.zoom.unwrap_or(1.0));
+    match &options.center {
+        Some(center) => position.centered_at(center),
+        None => position,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FRACTAL_NAMES, JULIA_INDEX, NEWTON_INDEX, PALETTE_NAMES};
+
+    #[test]
+    fn test_parse_args() {
+        let args = |args: &[&str]| parse_args(args.iter().map(|arg| arg.to_string()));
+        let error = |args: &[&str]| parse_args(args.iter().map(|arg| arg.to_string())).unwrap_err();
+        assert_eq!(args(&[]).unwrap(), Options::default());
+        assert_eq!(
+            args(&["--threads", "2", "--nice=10"]).unwrap(),
+            Options {
+                threads: Some(2),
+                nice: Some(10),
+                ..Options::default()
+            }
+        );
+        assert_eq!(args(&["--threads=4"]).unwrap().threads, Some(4));
+        assert!(args(&["--low-power"]).unwrap().low_power);
+        assert!(args(&["--watch"]).unwrap().watch);
+        assert_eq!(
+            args(&["--canvas", "400x200"]).unwrap().canvas_size,
+            Some((400, 200))
+        );
+        assert_eq!(
+            args(&[
+                "--fractal",
+                "julia",
+                "--center=-0.75,0.1",
+                "--zoom",
+                "1e3",
+                "--iterations",
+                "500",
+                "--palette",
+                "Fire",
+                "--backend",
+                "perturbation",
+                "--once",
+                "--size",
+                "40x12",
+            ])
+            .unwrap(),
+            Options {
+                fractal_index: Some(JULIA_INDEX),
+                center: Some(parse_center("-0.75,0.1").unwrap()),
+                zoom: Some(1000.0),
+                iterations: Some(500),
+                palette_index: Some(2),
+                size: Some((40, 12)),
+                backend_index: Some(2),
+                once: true,
+                ..Options::default()
+            }
+        );
+
+        assert_eq!(
+            args(&["--threads=2", "batch", "jobs.json"]).unwrap(),
+            Options {
+                threads: Some(2),
+                batch: Some("jobs.json".into()),
+                ..Options::default()
+            }
+        );
+        assert_eq!(args(&["serve"]).unwrap().serve, Some(SERVE_ADDRESS.to_string()));
+        assert_eq!(
+            args(&["serve", "0.0.0.0:23"]).unwrap().serve,
+            Some("0.0.0.0:23".to_string())
+        );
+        assert_eq!(args(&["tiles"]).unwrap().tiles, Some(TILES_ADDRESS.to_string()));
+
+        assert_eq!(
+            args(&[
+                "--zoom=100",
+                "gif",
+                "zoom.gif",
+                "--size",
+                "64x48",
+                "--fps=25"
+            ])
+            .unwrap(),
+            Options {
+                zoom: Some(100.0),
+                gif: Some(Export {
+                    output: "zoom.gif".into(),
+                    size: (64, 48),
+                    fps: 25,
+                    ..Export::default()
+                }),
+                ..Options::default()
+            }
+        );
+
+        assert_eq!(
+            args(&[
+                "video",
+                "zoom.mp4",
+                "--duration=2.5",
+                "--easing",
+                "ease-in-out"
+            ])
+            .unwrap(),
+            Options {
+                video: Some(Export {
+                    output: "zoom.mp4".into(),
+                    size: VIDEO_SIZE,
+                    frames: 75,
+                    fps: VIDEO_FPS,
+                    easing_index: 3,
+                }),
+                ..Options::default()
+            }
+        );
+
+        assert_eq!(
+            args(&["--view=seahorse.json", "--export-view", "copy.json"]).unwrap(),
+            Options {
+                view: Some("seahorse.json".into()),
+                export_view: Some("copy.json".into()),
+                ..Options::default()
+            }
+        );
+        assert_eq!(
+            args(&["--record", "session.cast"]).unwrap().record,
+            Some("session.cast".into())
+        );
+        assert_eq!(
+            args(&["--log-file", "debug.log"]).unwrap().log_file,
+            Some("debug.log".into())
+        );
+        assert_eq!(
+            args(&["--record-keys", "keys.txt", "--replay=demo.txt"]).unwrap(),
+            Options {
+                record_keys: Some("keys.txt".into()),
+                replay: Some("demo.txt".into()),
+                ..Options::default()
+            }
+        );
+
+        assert_eq!(error(&["-h"]).kind(), clap::error::ErrorKind::DisplayHelp);
+        assert_eq!(
+            error(&["--view", "a.json", "--zoom", "2"]).kind(),
+            clap::error::ErrorKind::ArgumentConflict
+        );
+        assert_eq!(
+            error(&["--record=a.cast", "--once"]).kind(),
+            clap::error::ErrorKind::ArgumentConflict
+        );
+        assert_eq!(
+            error(&["--replay=demo.txt", "--once"]).kind(),
+            clap::error::ErrorKind::ArgumentConflict
+        );
+        assert_eq!(
+            error(&["batch"]).kind(),
+            clap::error::ErrorKind::MissingRequiredArgument
+        );
+        assert!(error(&["gif", "zoom.gif", "--frames", "0"])
+            .to_string()
+            .contains("invalid frame count: 0"));
+        assert!(error(&["video", "zoom.mp4", "--easing", "bounce"])
+            .to_string()
+            .contains("unknown easing: bounce"));
+        assert_eq!(
+            error(&["--threads"]).kind(),
+            clap::error::ErrorKind::InvalidValue
+        );
+        assert!(error(&["--threads", "0"])
+            .to_string()
+            .contains("invalid thread count: 0"));
+        assert!(error(&["--nice", "20"])
+            .to_string()
+            .contains("invalid niceness: 20"));
+        assert!(error(&["--zoom", "-2"])
+            .to_string()
+            .contains("invalid zoom: -2"));
+        assert!(error(&["--fractal", "koch"])
+            .to_string()
+            .contains("unknown fractal: koch"));
+        assert_eq!(
+            error(&["--size", "40x12"]).kind(),
+            clap::error::ErrorKind::MissingRequiredArgument
+        );
+        assert_eq!(
+            error(&["--describe"]).kind(),
+            clap::error::ErrorKind::MissingRequiredArgument
+        );
+        assert_eq!(
+            error(&["--fast"]).kind(),
+            clap::error::ErrorKind::UnknownArgument
+        );
+        assert!(error(&["--iterations", "4000000000"])
+            .to_string()
+            .contains("iteration count above 100000000"));
+    }
+
+    #[test]
+    fn test_parse_name() {
+        assert_eq!(parse_name(&FRACTAL_NAMES, "fractal", "sinking-ship"), Ok(1));
+        assert_eq!(parse_name(&FRACTAL_NAMES, "fractal", "Mandelbrot"), Ok(0));
+        assert_eq!(parse_name(&PALETTE_NAMES, "palette", "ocean"), Ok(3));
+        assert_eq!(
+            parse_name(&PALETTE_NAMES, "palette", "Teal"),
+            Err("unknown palette: Teal".to_string())
+        );
+        assert_eq!(parse_name(&FRACTAL_NAMES, "fractal", "newton"), Ok(NEWTON_INDEX));
+        assert_eq!(
+            name_list(&FRACTAL_NAMES),
+            "mandelbrot-set, sinking-ship, julia-set, newton-fractal"
+        );
+
+        assert_eq!(parse_size("80x24"), Ok((80, 24)));
+        assert!(parse_size("80x0").is_err());
+        assert!(parse_size("80").is_err());
+        assert_eq!(parse_size("32767x1"), Ok((32767, 1)));
+        assert_eq!(
+            parse_size("32768x1"),
+            Err("size above 32767x32767: 32768x1".to_string())
+        );
+        assert!(parse_size("1x40000").is_err());
+        assert!(parse_center("1, 2").is_ok());
+        assert_eq!(parse_center("1"), Err("invalid center: 1".to_string()));
+    }
+
+    #[test]
+    fn test_start_position() {
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        assert_eq!(
+            start_position(&Options::default(), &default_position),
+            default_position
+        );
+
+        let options = Options {
+            center: Some(parse_center("-0.75,0.1").unwrap()),
+            zoom: Some(10.0),
+            ..Options::default()
+        };
+        let position = start_position(&options, &default_position);
+        assert_eq!(position.center(), options.center.unwrap());
+        assert!((position.width() - 0.3).abs() < 1e-12);
+    }
+}
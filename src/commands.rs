@@ -0,0 +1,286 @@
+// The commands typed after `:`.
+
+use crate::app::{DIVE_RATE_RANGE, MAX_ITERATIONS};
+use crate::cli::{parse_center, parse_name};
+use crate::explore::{parse_angle, MorphPath};
+use crate::{fractal_names, palette_names, u32x1, Fixed, FractalParameters};
+
+#[derive(PartialEq, Debug)]
+pub(crate) enum Command {
+    Goto {
+        x: Fixed,
+        y: Fixed,
+        zoom: Option<f64>,
+    },
+    Tour(String),
+    Save(String),
+    Load(String),
+    Gif(String),
+    ClearKeyframes,
+    // Morphing with no path goes round the circle through the current constant
+    Morph(Option<MorphPath>),
+    Dive(f64),
+    // The angle to turn the view to, in degrees
+    Rotate(f64),
+    // Where Mandelbrot orbits start
+    InitialZ((f64, f64)),
+    Iterations(u32),
+    // A magnification to zoom to, keeping the center
+    Zoom(f64),
+    // Splitting with no settings closes the split, or opens it on the next fractal
+    Split(Option<SplitSettings>),
+    // A ray with no angle removes the rays drawn so far
+    Ray(Option<(u64, u64)>),
+}
+
+// What the other pane of a split changes from the view; everything else follows it
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub(crate) struct SplitSettings {
+    pub(crate) fractal_index: Option<usize>,
+    pub(crate) palette_index: Option<usize>,
+    pub(crate) max_iterations: Option<u32>,
+}
+
+impl SplitSettings {
+    // Numbers are iteration limits, and names are tried as fractals before palettes
+    fn parse(arguments: &[&str]) -> Result<SplitSettings, String> {
+        let mut settings = SplitSettings::default();
+        for argument in arguments {
+            if let Ok(iterations) = argument.parse::<u64>() {
+                if iterations == 0 {
+                    return Err("the iteration limit must be positive".to_string());
+                }
+                settings.max_iterations = Some(iterations.min(MAX_ITERATIONS as u64) as u32);
+            } else if let Ok(index) = parse_name(&fractal_names(), "fractal", argument) {
+                settings.fractal_index = Some(index);
+            } else {
+                let index = parse_name(&palette_names(), "palette", argument)
+                    .map_err(|_| format!("unknown fractal or palette: {}", argument))?;
+                settings.palette_index = Some(index);
+            }
+        }
+        Ok(settings)
+    }
+
+    pub(crate) fn apply(
+        &self,
+        max_iterations: u32x1,
+        parameters: &FractalParameters,
+    ) -> (u32x1, FractalParameters) {
+        let parameters = FractalParameters {
+            fractal_index: self.fractal_index.unwrap_or(parameters.fractal_index),
+            palette_index: self.palette_index.unwrap_or(parameters.palette_index),
+            ..*parameters
+        };
+        (
+            self.max_iterations.map_or(max_iterations, u32x1::splat),
+            parameters,
+        )
+    }
+}
+
+// The left and right pane widths of a split, either side of a one column divider
+pub(crate) fn split_widths(width: u16) -> (u16, u16) {
+    let panes = width.saturating_sub(1);
+    (panes / 2, panes - panes / 2)
+}
+
+pub(crate) fn parse_number(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(number) if number.is_finite() => Ok(number),
+        _ => Err(format!("invalid number: {}", value)),
+    }
+}
+
+pub(crate) fn parse_command(input: &str) -> Result<Command, String> {
+    let arguments: Vec<&str> = input.split_whitespace().collect();
+
+    match arguments[..] {
+        ["tour", path] => Ok(Command::Tour(path.to_string())),
+        ["save", path] => Ok(Command::Save(path.to_string())),
+        ["load", path] => Ok(Command::Load(path.to_string())),
+        ["gif", path] => Ok(Command::Gif(path.to_string())),
+        ["clear"] => Ok(Command::ClearKeyframes),
+        ["morph"] => Ok(Command::Morph(None)),
+        ["split"] => Ok(Command::Split(None)),
+        ["ray"] => Ok(Command::Ray(None)),
+        ["ray", angle] => Ok(Command::Ray(Some(parse_angle(angle)?))),
+        ["rotate", degrees] => Ok(Command::Rotate(parse_number(degrees)?)),
+        ["zoom", zoom] => match parse_number(zoom)? {
+            zoom if zoom > 0.0 => Ok(Command::Zoom(zoom)),
+            _ => Err("zoom must be positive".to_string()),
+        },
+        ["iter", iterations] => match iterations.parse::<u64>() {
+            Ok(iterations) if iterations > 0 => Ok(Command::Iterations(
+                iterations.min(MAX_ITERATIONS as u64) as u32,
+            )),
+            _ => Err(format!("invalid iteration limit: {}", iterations)),
+        },
+        ["z0"] => Ok(Command::InitialZ((0.0, 0.0))),
+        ["z0", point] => {
+            let (x, y) = parse_center(point)?;
+            Ok(Command::InitialZ((x.to_f64(), y.to_f64())))
+        }
+        ["split", ref arguments @ ..] => Ok(Command::Split(Some(SplitSettings::parse(arguments)?))),
+        ["dive", rate] => match parse_number(rate)? {
+            rate if (DIVE_RATE_RANGE.0..=DIVE_RATE_RANGE.1).contains(&rate) => {
+                Ok(Command::Dive(rate))
+            }
+            _ => Err(format!(
+                "the dive rate must be between {} and {}",
+                DIVE_RATE_RANGE.0, DIVE_RATE_RANGE.1
+            )),
+        },
+        ["morph", "circle", radius] => {
+            let radius = parse_number(radius)?;
+            if radius <= 0.0 {
+                return Err("the radius must be positive".to_string());
+            }
+            Ok(Command::Morph(Some(MorphPath::Circle {
+                radius,
+                start_angle: 0.0,
+            })))
+        }
+        ["morph", ref points @ ..] => {
+            let points = points
+                .iter()
+                .map(|point| parse_center(point).map(|(x, y)| (x.to_f64(), y.to_f64())))
+                .collect::<Result<Vec<(f64, f64)>, String>>()?;
+            let moves = points.windows(2).any(|pair| pair[0] != pair[1]);
+            if !moves {
+                return Err("a morph path needs two or more different points".to_string());
+            }
+            Ok(Command::Morph(Some(MorphPath::Points(points))))
+        }
+        [x, y] => Ok(Command::Goto {
+            x: Fixed::parse(x)?,
+            y: Fixed::parse(y)?,
+            zoom: None,
+        }),
+        [x, y, zoom] => {
+            let zoom = parse_number(zoom)?;
+            if zoom <= 0.0 {
+                return Err("zoom must be positive".to_string());
+            }
+
+            Ok(Command::Goto {
+                x: Fixed::parse(x)?,
+                y: Fixed::parse(y)?,
+                zoom: Some(zoom),
+            })
+        }
+        _ => Err(
+            "usage: <x> <y> [zoom] | tour <file> | save <file> | load <file> | gif <file> | clear \
+             | morph [circle <radius> | <x,y> <x,y>...] | dive <rate> | rotate <degrees> \
+             | zoom <factor> | iter <iterations> | z0 [<x,y>] \
+             | split [<fractal>] [<palette>] [<iterations>] | ray [<p/q>]"
+                .to_string(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::JULIA_INDEX;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            parse_command("-0.743643887 0.131825904 1e9"),
+            Ok(Command::Goto {
+                x: Fixed::parse("-0.743643887").unwrap(),
+                y: Fixed::parse("0.131825904").unwrap(),
+                zoom: Some(1e9),
+            })
+        );
+        assert_eq!(
+            parse_command("  1   2 "),
+            Ok(Command::Goto {
+                x: Fixed::parse("1").unwrap(),
+                y: Fixed::parse("2").unwrap(),
+                zoom: None,
+            })
+        );
+        assert_eq!(
+            parse_command("tour stops.txt"),
+            Ok(Command::Tour("stops.txt".to_string()))
+        );
+        assert_eq!(
+            parse_command("save view.json"),
+            Ok(Command::Save("view.json".to_string()))
+        );
+        assert_eq!(
+            parse_command("load view.json"),
+            Ok(Command::Load("view.json".to_string()))
+        );
+        assert_eq!(
+            parse_command("gif zoom.gif"),
+            Ok(Command::Gif("zoom.gif".to_string()))
+        );
+        assert_eq!(parse_command("clear"), Ok(Command::ClearKeyframes));
+        assert_eq!(parse_command("morph"), Ok(Command::Morph(None)));
+        assert_eq!(
+            parse_command("morph circle 0.7"),
+            Ok(Command::Morph(Some(MorphPath::Circle {
+                radius: 0.7,
+                start_angle: 0.0
+            })))
+        );
+        assert_eq!(
+            parse_command("morph -0.8,0.156 0.285,0.01"),
+            Ok(Command::Morph(Some(MorphPath::Points(vec![
+                (-0.8, 0.156),
+                (0.285, 0.01)
+            ]))))
+        );
+        assert!(parse_command("morph 1,1 1,1").is_err());
+        assert!(parse_command("morph circle -1").is_err());
+        assert_eq!(parse_command("dive 4"), Ok(Command::Dive(4.0)));
+        assert_eq!(parse_command("iter 5000"), Ok(Command::Iterations(5000)));
+        assert_eq!(
+            parse_command("iter 4000000000"),
+            Ok(Command::Iterations(MAX_ITERATIONS))
+        );
+        assert_eq!(
+            parse_command("split 4000000000"),
+            Ok(Command::Split(Some(SplitSettings {
+                max_iterations: Some(MAX_ITERATIONS),
+                ..SplitSettings::default()
+            })))
+        );
+        assert_eq!(parse_command("zoom 3.2e8"), Ok(Command::Zoom(3.2e8)));
+        assert!(parse_command("zoom -2").is_err());
+        assert!(parse_command("iter 0").is_err());
+        assert!(parse_command("iter many").is_err());
+        assert_eq!(parse_command("rotate -30"), Ok(Command::Rotate(-30.0)));
+        assert_eq!(parse_command("z0 0.5,-1"), Ok(Command::InitialZ((0.5, -1.0))));
+        assert_eq!(parse_command("z0"), Ok(Command::InitialZ((0.0, 0.0))));
+        assert_eq!(
+            parse_command("dive 1"),
+            Err("the dive rate must be between 1.1 and 100".to_string())
+        );
+        assert_eq!(parse_command("split"), Ok(Command::Split(None)));
+        assert_eq!(
+            parse_command("split julia fire 500"),
+            Ok(Command::Split(Some(SplitSettings {
+                fractal_index: Some(JULIA_INDEX),
+                palette_index: parse_name(&palette_names(), "palette", "fire").ok(),
+                max_iterations: Some(500),
+            })))
+        );
+        assert_eq!(
+            parse_command("split sky"),
+            Err("unknown fractal or palette: sky".to_string())
+        );
+        assert!(parse_command("split 0").is_err());
+        assert_eq!(parse_command("ray"), Ok(Command::Ray(None)));
+        assert_eq!(parse_command("ray 1/3"), Ok(Command::Ray(Some((1, 3)))));
+        assert!(parse_command("ray 1/0").is_err());
+        assert!(parse_command("ray 0.5").is_err());
+        assert!(parse_command("").is_err());
+        assert!(parse_command("1 abc").is_err());
+        assert!(parse_command("1 2 0").is_err());
+        assert!(parse_command("1 2 inf").is_err());
+    }
+}
@@ -0,0 +1,675 @@
+// The config file, keymap file, scripts and plugins, and reloading them as they change.
+
+use crate::app::{DIVE_RATE_RANGE, PAN_STEP_RANGE, SERIES_TOLERANCE_RANGE, ZOOM_STEP_RANGE};
+use crate::cli::{parse_name, parse_size};
+use crate::keymap::Keymap;
+use crate::session::{Location, TourStop, LOCATION_PREFIX};
+use crate::terminal::CONSOLE_NAMES;
+use crate::{
+    fractal_names, palette_names, plugin, register_fractal, register_palette, script,
+    CustomPalette, BACKEND_NAMES, COLORING_NAMES, GRID_NAMES, MAX_SERIES_ORDER,
+};
+
+fn config_dir() -> Option<std::path::PathBuf> {
+    // An empty XDG_CONFIG_HOME counts as unset
+    let config = match std::env::var_os("XDG_CONFIG_HOME").filter(|path| !path.is_empty()) {
+        Some(path) => std::path::PathBuf::from(path),
+        None => std::path::PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config.join("mandelbrot-term"))
+}
+
+fn keymap_path() -> Option<std::path::PathBuf> {
+    Some(config_dir()?.join("keymap.toml"))
+}
+
+fn config_path() -> Option<std::path::PathBuf> {
+    Some(config_dir()?.join("config.toml"))
+}
+
+const DEFAULT_CONFIG: &str = "# Defaults for mandelbrot-term. Command-line options override them.
+
+fractal = \"mandelbrot-set\"
+palette = \"rainbow\"
+coloring = \"linear\"
+supersampling = 1
+backend = \"auto\"
+# Terms of the series approximation deep views skip their first iterations with (0 to 16, 0
+# turns it off), and how small its highest term must stay. More terms and a larger
+# tolerance skip more iterations, at the cost of accuracy.
+series_order = 3
+series_tolerance = 1e-6
+# Lower each tile's iteration limit to a few times the slowest escape a coarse sample of it
+# finds, when none of the sample is inside. Deep views with high limits render faster, but
+# the odd filament that escapes past a tile's limit is drawn as inside.
+tile_limits = false
+# What the terminal can show: modern, legacy (16 colors and the characters of code page 437)
+# or ascii; auto picks legacy for the Windows console host and the Linux console
+console = \"auto\"
+# How each cell is split into subpixels: quadrants (2x2), half-blocks (1x2), which render
+# faster, or braille (2x4), which shows more detail with large fonts
+grid = \"quadrants\"
+# How much of the view each zoom and pan step covers
+zoom_step = 0.1
+pan_step = 0.05
+# How many times diving with D magnifies the view each second
+dive_rate = 2.0
+# Whether a fractal switched to for the first time starts from its own view, such as the
+# Sinking Ship's ship, rather than the one on screen
+fractal_views = false
+# Render this many cells across and down around the view, so moves that stay inside them
+# are shown without rendering again
+# canvas = \"400x200\"
+
+# Key bindings, in the same format as keymap.toml
+[keymap]
+preset = \"default\"
+
+# [keymap.keys]
+# zoom_in = [\"Up\", \"+\"]
+";
+
+// Settings left out of the file keep their built-in defaults
+#[derive(Default)]
+pub(crate) struct Config {
+    pub(crate) fractal_index: Option<usize>,
+    pub(crate) palette_index: Option<usize>,
+    pub(crate) coloring_index: Option<usize>,
+    pub(crate) supersampling: Option<u32>,
+    pub(crate) backend_index: Option<usize>,
+    pub(crate) series_order: Option<usize>,
+    pub(crate) series_tolerance: Option<f64>,
+    pub(crate) tile_limits: Option<bool>,
+    pub(crate) console_index: Option<usize>,
+    pub(crate) grid_index: Option<usize>,
+    pub(crate) zoom_step: Option<f64>,
+    pub(crate) pan_step: Option<f64>,
+    pub(crate) dive_rate: Option<f64>,
+    pub(crate) fractal_views: Option<bool>,
+    pub(crate) canvas_size: Option<(u16, u16)>,
+    pub(crate) keymap: Option<Keymap>,
+}
+
+impl Config {
+    fn parse(text: &str) -> Result<Config, String> {
+        let table = text
+            .parse::<toml::Table>()
+            .map_err(|error| error.message().to_string())?;
+
+        let mut config = Config::default();
+        for (key, value) in &table {
+            let name = |names: &[&str], kind: &str| match value {
+                toml::Value::String(name) => parse_name(names, kind, name),
+                _ => Err(format!("{} must be a string", key)),
+            };
+            let step = |range: (f64, f64)| {
+                value
+                    .as_float()
+                    .filter(|step| (range.0..=range.1).contains(step))
+                    .ok_or(format!(
+                        "{} must be between {} and {}",
+                        key, range.0, range.1
+                    ))
+            };
+
+            match key.as_str() {
+                "fractal" => config.fractal_index = Some(name(&fractal_names(), "fractal")?),
+                "palette" => config.palette_index = Some(name(&palette_names(), "palette")?),
+                "coloring" => config.coloring_index = Some(name(&COLORING_NAMES, "coloring")?),
+                "backend" => config.backend_index = Some(name(&BACKEND_NAMES, "backend")?),
+                "console" => config.console_index = Some(name(&CONSOLE_NAMES, "console")?),
+                "grid" => config.grid_index = Some(name(&GRID_NAMES, "grid")?),
+                "supersampling" => {
+                    let samples = value
+                        .as_integer()
+                        .filter(|samples| (1..=4).contains(samples))
+                        .ok_or("supersampling must be between 1 and 4")?;
+                    config.supersampling = Some(samples as u32);
+                }
+                "series_order" => {
+                    let order = value
+                        .as_integer()
+                        .filter(|order| (0..=MAX_SERIES_ORDER as i64).contains(order))
+                        .ok_or(format!(
+                            "series_order must be between 0 and {}",
+                            MAX_SERIES_ORDER
+                        ))?;
+                    config.series_order = Some(order as usize);
+                }
+                "series_tolerance" => config.series_tolerance = Some(step(SERIES_TOLERANCE_RANGE)?),
+                "zoom_step" => config.zoom_step = Some(step(ZOOM_STEP_RANGE)?),
+                "pan_step" => config.pan_step = Some(step(PAN_STEP_RANGE)?),
+                "dive_rate" => config.dive_rate = Some(step(DIVE_RATE_RANGE)?),
+                "tile_limits" => {
+                    let enabled = value.as_bool().ok_or("tile_limits must be true or false")?;
+                    config.tile_limits = Some(enabled);
+                }
+                "fractal_views" => {
+                    let enabled = value.as_bool().ok_or("fractal_views must be true or false")?;
+                    config.fractal_views = Some(enabled);
+                }
+                "canvas" => match value {
+                    toml::Value::String(size) => config.canvas_size = Some(parse_size(size)?),
+                    _ => return Err("canvas must be a string".to_string()),
+                },
+                "keymap" => {
+                    let keymap = match value {
+                        toml::Value::Table(table) => Keymap::from_table(table)
+                            .map_err(|error| format!("keymap: {}", error))?,
+                        _ => return Err("keymap must be a table".to_string()),
+                    };
+                    config.keymap = Some(keymap);
+                }
+                _ => return Err(format!("unknown setting: {}", key)),
+            }
+        }
+        Ok(config)
+    }
+}
+
+// Bindings in the config file take the place of keymap.toml, returning an error from that
+pub(crate) fn load_keymap(config_keymap: Option<Keymap>) -> (Keymap, Option<String>) {
+    let keymap_text = keymap_path().and_then(|path| std::fs::read_to_string(path).ok());
+    match (config_keymap, keymap_text) {
+        (Some(keymap), _) => (keymap, None),
+        (None, Some(text)) => match Keymap::parse(&text) {
+            Ok(keymap) => (keymap, None),
+            Err(error) => (
+                Keymap::preset("default").unwrap(),
+                Some(format!("keymap: {}", error)),
+            ),
+        },
+        (None, None) => (Keymap::preset("default").unwrap(), None),
+    }
+}
+
+pub(crate) const WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+// When the files --watch reloads were last modified, to tell when they change
+#[derive(PartialEq, Debug)]
+pub(crate) struct WatchedFiles {
+    pub(crate) config: Vec<Option<std::time::SystemTime>>,
+    pub(crate) scripts: Vec<(std::path::PathBuf, Option<std::time::SystemTime>)>,
+}
+
+impl WatchedFiles {
+    pub(crate) fn now() -> WatchedFiles {
+        let modified = |path: &std::path::Path| std::fs::metadata(path).ok()?.modified().ok();
+        WatchedFiles {
+            config: [config_path(), keymap_path()]
+                .iter()
+                .map(|path| modified(path.as_deref()?))
+                .collect(),
+            scripts: script_paths()
+                .into_iter()
+                .map(|path| {
+                    let time = modified(&path);
+                    (path, time)
+                })
+                .collect(),
+        }
+    }
+}
+
+pub(crate) fn load_config() -> Result<Config, String> {
+    match config_path().and_then(|path| std::fs::read_to_string(path).ok()) {
+        Some(text) => Config::parse(&text),
+        None => Ok(Config::default()),
+    }
+}
+
+fn scripts_dir() -> Option<std::path::PathBuf> {
+    Some(config_dir()?.join("scripts"))
+}
+
+fn plugins_dir() -> Option<std::path::PathBuf> {
+    Some(config_dir()?.join("plugins"))
+}
+
+// Script files are Rhai programs, so a tour can be worked out with loops and arithmetic
+// rather than listed stop by stop. They add to the viewer through these functions:
+//
+//   fractal(name, step), fractal(name, step, julia), fractal(name, start, step, julia)
+//   palette(name, red, green, blue), palette(name, function)
+//   location(fractal, x, y, zoom, iterations)
+//   stop(location, caption), stop(location, caption, seconds)
+//
+// Formulas and palette channels are given as expressions for the script module, which are
+// compiled and so quick enough to run for every iteration of every pixel. A palette can also
+// be a Rhai function of t returning [red, green, blue], which is only called once for each
+// color in the table.
+
+// Enough for any sensible script, while an endless loop can't hold up startup for long
+const MAX_SCRIPT_OPERATIONS: u64 = 10_000_000;
+
+fn script_engine() -> rhai::Engine {
+    let mut engine = rhai::Engine::new();
+    engine.set_max_operations(MAX_SCRIPT_OPERATIONS);
+    // Printing would draw over the viewer
+    engine.on_print(|_| {}).on_debug(|_, _, _| {});
+    engine
+}
+
+// A Rhai error as "line 3: ..." like the other config errors
+fn script_error(mut error: rhai::EvalAltResult) -> String {
+    let position = error.take_position();
+    let message = match error {
+        rhai::EvalAltResult::ErrorRuntime(message, _) => message.to_string(),
+        error => error.to_string(),
+    };
+    match position.line() {
+        Some(line) => format!("line {}: {}", line, message),
+        None => message,
+    }
+}
+
+// A color returned by a palette function, with each channel from 0 to 255
+fn script_color(channels: &rhai::Array) -> Option<[u8; 3]> {
+    let channel = |value: &rhai::Dynamic| {
+        let value = value
+            .as_float()
+            .or(value.as_int().map(|value| value as f64));
+        // Casting saturates, and sends NaN to 0
+        value.ok().map(|value| value.clamp(0.0, 255.0) as u8)
+    };
+    match channels.as_slice() {
+        [red, green, blue] => Some([channel(red)?, channel(green)?, channel(blue)?]),
+        _ => None,
+    }
+}
+
+// The fractals, palettes and tour stops defined in one script file
+struct Script {
+    fractals: Vec<(String, script::Formula)>,
+    palettes: Vec<(String, CustomPalette)>,
+    // Tour stops are decoded once the fractals they may show have been registered
+    tour: Vec<(String, String, Option<std::time::Duration>)>,
+}
+
+impl Script {
+    fn parse(text: &str) -> Result<Script, String> {
+        type Outcome = Result<(), Box<rhai::EvalAltResult>>;
+        let script = std::sync::Arc::new(std::sync::Mutex::new(Script {
+            fractals: Vec::new(),
+            palettes: Vec::new(),
+            tour: Vec::new(),
+        }));
+        let mut engine = script_engine();
+        let ast = std::sync::Arc::new(engine.compile(text).map_err(|error| {
+            script_error(rhai::EvalAltResult::ErrorParsing(
+                error.err_type().clone(),
+                error.position(),
+            ))
+        })?);
+
+        let fractal = {
+            let script = script.clone();
+            move |name: &str, start: &str, step: &str, julia: bool| -> Outcome {
+                let formula = script::Formula::new(start, step, julia)?;
+                let mut script = script.lock().unwrap();
+                script.fractals.push((name.to_string(), formula));
+                Ok(())
+            }
+        };
+        // Julia sets start from the pixel, and everything else from zero
+        let julia_fractal = fractal.clone();
+        engine.register_fn("fractal", move |name: &str, step: &str, julia: bool| {
+            julia_fractal(name, if julia { "p" } else { "0" }, step, julia)
+        });
+        let plain_fractal = fractal.clone();
+        engine.register_fn("fractal", move |name: &str, step: &str| {
+            plain_fractal(name, "0", step, false)
+        });
+        engine.register_fn("fractal", fractal);
+
+        let palettes = script.clone();
+        engine.register_fn(
+            "palette",
+            move |name: &str, red: &str, green: &str, blue: &str| -> Outcome {
+                let palette = script::ExpressionPalette::new(red, green, blue)?;
+                palettes.lock().unwrap().palettes.push((
+                    name.to_string(),
+                    std::sync::Arc::new(move |iteration, max_iterations| {
+                        palette.color(iteration, max_iterations)
+                    }),
+                ));
+                Ok(())
+            },
+        );
+        let palettes = script.clone();
+        let palette_ast = ast.clone();
+        engine.register_fn(
+            "palette",
+            move |name: &str, function: rhai::FnPtr| -> Outcome {
+                // Palettes are called with an engine of their own, so coloring can't add to
+                // the script
+                let (engine, ast) = (script_engine(), palette_ast.clone());
+                let color = move |t: f64| -> Result<[u8; 3], Box<rhai::EvalAltResult>> {
+                    let channels = function.call::<rhai::Dynamic>(&engine, &ast, (t,))?;
+                    let channels = channels.try_cast::<rhai::Array>().unwrap_or_default();
+                    script_color(&channels)
+                        .ok_or("a palette function must give [red, green, blue] numbers".into())
+                };
+                // Trying the function out here reports a broken palette with its script
+                color(0.5)?;
+                let palette: CustomPalette =
+                    std::sync::Arc::new(move |iteration, max_iterations| {
+                        if iteration == max_iterations {
+                            return [0, 0, 0];
+                        }
+                        color(iteration as f64 / max_iterations.max(1) as f64).unwrap_or([0, 0, 0])
+                    });
+                palettes
+                    .lock()
+                    .unwrap()
+                    .palettes
+                    .push((name.to_string(), palette));
+                Ok(())
+            },
+        );
+
+        engine.register_fn(
+            "location",
+            |fractal_index: rhai::INT, x: f64, y: f64, zoom: f64, iterations: rhai::INT| {
+                format!(
+                    "{}:{}:{}:{}:{}:{}",
+                    LOCATION_PREFIX, fractal_index, x, y, zoom, iterations
+                )
+            },
+        );
+        let stop = {
+            let script = script.clone();
+            move |location: &str, caption: &str, seconds: Option<f64>| -> Outcome {
+                let duration = match seconds {
+                    Some(seconds) if seconds > 0.0 && seconds.is_finite() => {
+                        Some(std::time::Duration::from_secs_f64(seconds))
+                    }
+                    Some(_) => return Err("seconds must be a positive number".into()),
+                    None => None,
+                };
+                let mut script = script.lock().unwrap();
+                script
+                    .tour
+                    .push((location.to_string(), caption.to_string(), duration));
+                Ok(())
+            }
+        };
+        let timed_stop = stop.clone();
+        engine.register_fn(
+            "stop",
+            move |location: &str, caption: &str, seconds: rhai::Dynamic| {
+                let seconds = seconds
+                    .as_float()
+                    .or(seconds.as_int().map(|seconds| seconds as f64));
+                timed_stop(location, caption, Some(seconds.unwrap_or(f64::NAN)))
+            },
+        );
+        engine.register_fn("stop", move |location: &str, caption: &str| {
+            stop(location, caption, None)
+        });
+
+        engine.run_ast(&ast).map_err(|error| script_error(*error))?;
+        // The engine holds the functions above, and with them the other handles on the script
+        drop(engine);
+        let script = std::sync::Arc::into_inner(script).unwrap();
+        Ok(script.into_inner().unwrap())
+    }
+}
+
+// The scripts in the scripts directory, in the order they're loaded
+fn script_paths() -> Vec<std::path::PathBuf> {
+    let mut paths: Vec<std::path::PathBuf> =
+        match scripts_dir().and_then(|directory| std::fs::read_dir(directory).ok()) {
+            Some(entries) => entries
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|extension| extension == "rhai")
+                })
+                .collect(),
+            None => return Vec::new(),
+        };
+    // Loading in a fixed order keeps each fractal's index, and so copied locations, the same
+    // from run to run
+    paths.sort();
+    paths
+}
+
+// Registers the fractals and palettes from every script, returning the tour the scripts make
+// up and any errors. A broken script is skipped without holding up the others. Loading the
+// scripts again replaces the fractals and palettes they registered before.
+pub(crate) fn load_scripts() -> (Vec<TourStop>, Vec<String>) {
+    let mut tour = Vec::new();
+    let mut errors = Vec::new();
+    for path in script_paths() {
+        let file = path.file_name().unwrap_or_default().to_string_lossy();
+        let script = std::fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| Script::parse(&text));
+        let script = match script {
+            Ok(script) => script,
+            Err(error) => {
+                errors.push(format!("script {}: {}", file, error));
+                continue;
+            }
+        };
+
+        for (name, formula) in script.fractals {
+            register_fractal(&name, std::sync::Arc::new(formula));
+        }
+        for (name, palette) in script.palettes {
+            register_palette(&name, palette);
+        }
+        for (number, (location, caption, duration)) in script.tour.into_iter().enumerate() {
+            match Location::decode(&location) {
+                Ok(location) => tour.push(TourStop {
+                    caption,
+                    location,
+                    duration,
+                }),
+                Err(error) => {
+                    errors.push(format!("script {}: tour {}: {}", file, number + 1, error))
+                }
+            }
+        }
+    }
+    (tour, errors)
+}
+
+// Registers the fractal from every shared library in the plugins directory, returning any
+// errors
+pub(crate) fn load_plugins() -> Vec<String> {
+    let mut paths: Vec<std::path::PathBuf> =
+        match plugins_dir().and_then(|directory| std::fs::read_dir(directory).ok()) {
+            Some(entries) => entries
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|extension| extension == std::env::consts::DLL_EXTENSION)
+                })
+                .collect(),
+            None => return Vec::new(),
+        };
+    // Sorted for the same reason as scripts
+    paths.sort();
+
+    let mut errors = Vec::new();
+    for path in paths {
+        // Safety: the plugins directory is the user's own, and whatever is put there is
+        // trusted to be a plugin, as the README warns
+        match unsafe { plugin::Plugin::load(&path) } {
+            Ok(plugin) => {
+                let name = plugin.name.clone();
+                register_fractal(&name, std::sync::Arc::new(plugin));
+            }
+            Err(error) => errors.push(format!(
+                "plugin {}: {}",
+                path.file_name().unwrap_or_default().to_string_lossy(),
+                error
+            )),
+        }
+    }
+    errors
+}
+
+pub(crate) fn write_default_config() -> Result<std::path::PathBuf, String> {
+    let path = config_path().ok_or("no home directory to write the config to")?;
+    if path.exists() {
+        return Err(format!("{} already exists", path.display()));
+    }
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)
+            .map_err(|error| format!("{}: {}", directory.display(), error))?;
+    }
+    std::fs::write(&path, DEFAULT_CONFIG)
+        .map_err(|error| format!("{}: {}", path.display(), error))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::DIVE_RATE;
+    use crate::DEFAULT_PARAMETERS;
+
+    use crate::keymap::Action;
+
+    #[test]
+    fn test_config() {
+        let config = Config::parse(DEFAULT_CONFIG).unwrap();
+        assert_eq!(config.fractal_index, Some(DEFAULT_PARAMETERS.fractal_index));
+        assert_eq!(config.palette_index, Some(DEFAULT_PARAMETERS.palette_index));
+        assert_eq!(
+            config.coloring_index,
+            Some(DEFAULT_PARAMETERS.coloring_index)
+        );
+        assert_eq!(config.supersampling, Some(DEFAULT_PARAMETERS.supersampling));
+        assert_eq!(config.backend_index, Some(DEFAULT_PARAMETERS.backend_index));
+        assert_eq!(config.series_order, Some(DEFAULT_PARAMETERS.series_order));
+        assert_eq!(
+            config.series_tolerance,
+            Some(DEFAULT_PARAMETERS.series_tolerance)
+        );
+        assert_eq!(config.console_index, Some(0));
+        assert_eq!(config.grid_index, Some(0));
+        assert_eq!((config.zoom_step, config.pan_step), (Some(0.1), Some(0.05)));
+        assert_eq!(config.dive_rate, Some(DIVE_RATE));
+        assert_eq!(config.tile_limits, Some(false));
+        assert_eq!(config.fractal_views, Some(false));
+        assert_eq!(config.canvas_size, None);
+        let keymap = config.keymap.unwrap();
+        assert_eq!(keymap.keys(Action::ZoomIn), "Up");
+
+        let config = Config::parse("palette = \"ocean\"\n[keymap]\npreset = \"vim\"\n").unwrap();
+        assert_eq!(config.palette_index, Some(3));
+        assert_eq!(config.fractal_index, None);
+        assert_eq!(config.keymap.unwrap().keys(Action::PanLeft), "h");
+
+        assert!(Config::parse("").unwrap().keymap.is_none());
+        assert_eq!(
+            Config::parse("backend = \"double-double\"").unwrap().backend_index,
+            Some(3)
+        );
+        assert!(Config::parse("series_order = 17").is_err());
+        assert!(Config::parse("series_tolerance = 1.0").is_err());
+        assert_eq!(
+            Config::parse("canvas = \"400x200\"").unwrap().canvas_size,
+            Some((400, 200))
+        );
+        assert_eq!(
+            Config::parse("grid = \"half\"").unwrap().grid_index,
+            Some(1)
+        );
+        assert_eq!(
+            Config::parse("zoom_step = 2.0").err(),
+            Some("zoom_step must be between 0.01 and 0.9".to_string())
+        );
+        assert_eq!(
+            Config::parse("supersampling = 9").err(),
+            Some("supersampling must be between 1 and 4".to_string())
+        );
+        assert_eq!(
+            Config::parse("fractal = 1").err(),
+            Some("fractal must be a string".to_string())
+        );
+        assert_eq!(
+            Config::parse("speed = 1").err(),
+            Some("unknown setting: speed".to_string())
+        );
+        assert_eq!(
+            Config::parse("[keymap]\npreset = \"emacs\"").err(),
+            Some("keymap: unknown preset: emacs".to_string())
+        );
+    }
+
+    #[test]
+    fn test_script() {
+        let script = Script::parse(
+            r#"
+fractal("Burning Ship", "(abs(re(z)) + i*abs(im(z)))^2 + c");
+fractal("Cubic Julia", "z^3 + c", true);
+
+palette("Sunset", "255 * t", "128 * t^2", "64");
+palette("Gray", |t| [255 * t, 255 * t, 255 * t]);
+
+stop("mandelbrot-term:0:-0.75:0.1:25:300", "Seahorse Valley", 5);
+// A dive that doubles the zoom at each stop
+for step in 0..3 {
+    stop(location(0, -0.75, 0.1, 2.0 ** step, 300), `Dive ${step}`);
+}
+"#,
+        )
+        .unwrap();
+        assert_eq!(script.fractals.len(), 2);
+        assert_eq!(script.fractals[1].0, "Cubic Julia");
+        assert_eq!(
+            Some(&script.fractals[1].1),
+            script::Formula::new("p", "z^3 + c", true).ok().as_ref()
+        );
+        assert_eq!((script.palettes[0].1)(50, 100), [127, 32, 64]);
+        assert_eq!((script.palettes[1].1)(50, 100), [127, 127, 127]);
+        assert_eq!((script.palettes[1].1)(100, 100), [0, 0, 0]);
+        assert_eq!(
+            script.tour[0],
+            (
+                "mandelbrot-term:0:-0.75:0.1:25:300".to_string(),
+                "Seahorse Valley".to_string(),
+                Some(std::time::Duration::from_secs(5))
+            )
+        );
+        assert_eq!(script.tour.len(), 4);
+        assert_eq!(
+            script.tour[3],
+            (
+                "mandelbrot-term:0:-0.75:0.1:4:300".to_string(),
+                "Dive 2".to_string(),
+                None
+            )
+        );
+        assert!(Location::decode(&script.tour[3].0).is_ok());
+
+        let error = |text: &str| Script::parse(text).err().unwrap();
+        assert_eq!(
+            error("fractal(\"x\", \"z^2 + q\");"),
+            "line 1: step: unknown name: q"
+        );
+        assert_eq!(
+            error("\nfractal(\"x\");"),
+            "line 2: Function not found: fractal (&str | ImmutableString | String)"
+        );
+        assert_eq!(
+            error("palette(\"x\", |t| t);"),
+            "line 1: a palette function must give [red, green, blue] numbers"
+        );
+        assert_eq!(
+            error("stop(\"x\", \"\", -1);"),
+            "line 1: seconds must be a positive number"
+        );
+        assert_eq!(error("loop {}"), "line 1: Too many operations");
+        assert_eq!(
+            error("fractal(\"x\""),
+            "line 1: Syntax error: Expecting ')' to close the arguments list of this function call \
+             'fractal'"
+        );
+    }
+}
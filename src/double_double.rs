@@ -0,0 +1,138 @@
+// Numbers held as the unevaluated sum of two f64s, the low one below the last bit of the
+// high one. That gives about 106 bits of precision, twice f64's, for a few times its cost,
+// which is far cheaper than arbitrary precision.
+
+use std::ops::{Add, Mul, Neg, Sub};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct DoubleDouble {
+    pub hi: f64,
+    pub lo: f64,
+}
+
+// The sum and its rounding error, exactly
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    let b_part = sum - a;
+    (sum, (a - (sum - b_part)) + (b - b_part))
+}
+
+// The same, in fewer steps, when |a| >= |b|
+fn quick_two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    (sum, b - (sum - a))
+}
+
+// Halves with 26 bits each, whose products are exact (Dekker's split), so products don't
+// need a fused multiply-add, which is slow where the CPU doesn't have one
+fn split(a: f64) -> (f64, f64) {
+    let scaled = 134217729.0 * a;
+    let hi = scaled - (scaled - a);
+    (hi, a - hi)
+}
+
+// The product and its rounding error, exactly
+fn two_product(a: f64, b: f64) -> (f64, f64) {
+    let product = a * b;
+    let ((a_hi, a_lo), (b_hi, b_lo)) = (split(a), split(b));
+    let error = ((a_hi * b_hi - product) + a_hi * b_lo + a_lo * b_hi) + a_lo * b_lo;
+    (product, error)
+}
+
+impl DoubleDouble {
+    pub fn abs(self) -> DoubleDouble {
+        if self.hi < 0.0 {
+            -self
+        } else {
+            self
+        }
+    }
+
+    // Exact, since doubling only changes the exponents
+    pub fn doubled(self) -> DoubleDouble {
+        DoubleDouble {
+            hi: self.hi * 2.0,
+            lo: self.lo * 2.0,
+        }
+    }
+}
+
+impl From<f64> for DoubleDouble {
+    fn from(number: f64) -> DoubleDouble {
+        DoubleDouble {
+            hi: number,
+            lo: 0.0,
+        }
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn add(self, other: DoubleDouble) -> DoubleDouble {
+        let (sum, error) = two_sum(self.hi, other.hi);
+        let (low_sum, low_error) = two_sum(self.lo, other.lo);
+        let (hi, lo) = quick_two_sum(sum, error + low_sum);
+        let (hi, lo) = quick_two_sum(hi, lo + low_error);
+        DoubleDouble { hi, lo }
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn neg(self) -> DoubleDouble {
+        DoubleDouble {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn sub(self, other: DoubleDouble) -> DoubleDouble {
+        self + -other
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn mul(self, other: DoubleDouble) -> DoubleDouble {
+        // The product of the low halves is below the precision kept
+        let (product, error) = two_product(self.hi, other.hi);
+        let error = error + (self.hi * other.lo + self.lo * other.hi);
+        let (hi, lo) = quick_two_sum(product, error);
+        DoubleDouble { hi, lo }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arithmetic() {
+        // (1 + 2^-40)(1 - 2^-40) = 1 - 2^-80, which an f64 rounds to 1
+        let tiny = 2f64.powi(-40);
+        let one = DoubleDouble::from(1.0);
+        let product = (one + tiny.into()) * (one - tiny.into());
+        assert_eq!(
+            product,
+            DoubleDouble {
+                hi: 1.0,
+                lo: -tiny * tiny
+            }
+        );
+
+        // Sums keep what a single f64 rounds off
+        let sum = DoubleDouble::from(1e16) + DoubleDouble::from(1.0);
+        assert_eq!((sum - DoubleDouble::from(1e16)).hi, 1.0);
+        assert_eq!(
+            DoubleDouble::from(-3.0).abs().doubled(),
+            DoubleDouble::from(6.0)
+        );
+    }
+}
@@ -0,0 +1,506 @@
+// Finding interesting places: random views, the boundary of the set, external rays, and
+// the screensaver, dives and Julia morphs that move through them by themselves.
+
+use crate::{
+    distance_estimate, fixed_point, interpolate_position, iterate_point, u32x1, Fixed,
+    FractalParameters, Position, JULIA_INDEX,
+};
+
+pub(crate) const RANDOM_ZOOM_RANGE: (f64, f64) = (1.0, 6.0);
+const RANDOM_ATTEMPTS: usize = 2000;
+
+pub(crate) struct Random(u64);
+
+impl Random {
+    pub(crate) fn new(seed: u64) -> Random {
+        // xorshift gets stuck on zero
+        Random(seed | 1)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub(crate) fn range(&mut self, min: f64, max: f64) -> f64 {
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        min + unit * (max - min)
+    }
+}
+
+pub(crate) fn iterations_for_zoom(zoom: f64) -> u32 {
+    (100.0 + 100.0 * zoom.max(1.0).log10().powf(1.5)) as u32
+}
+
+fn is_boundary(
+    x: f64,
+    y: f64,
+    radius: f64,
+    max_iterations: u32x1,
+    parameters: &FractalParameters,
+) -> bool {
+    let inside = [
+        (0.0, 0.0),
+        (radius, 0.0),
+        (-radius, 0.0),
+        (0.0, radius),
+        (0.0, -radius),
+    ]
+    .iter()
+    .filter(|(offset_x, offset_y)| {
+        iterate_point(x + offset_x, y + offset_y, max_iterations, parameters) == max_iterations
+    })
+    .count();
+
+    inside > 0 && inside < 5
+}
+
+pub(crate) fn find_boundary_point(
+    random: &mut Random,
+    default_position: &Position,
+    target_zoom: f64,
+    parameters: &FractalParameters,
+) -> Option<(f64, f64)> {
+    // Narrow in a decade at a time, so deep targets stay near the boundary found so far
+    let center = default_position.center();
+    let mut center = (center.0.to_f64(), center.1.to_f64());
+    let mut zoom: f64 = 1.0;
+
+    while zoom < target_zoom {
+        let width = default_position.width() / zoom;
+        let height = default_position.height() / zoom;
+        zoom = (zoom * 10.0).min(target_zoom);
+        let radius = default_position.width() / zoom * 0.05;
+        let max_iterations = u32x1::splat(iterations_for_zoom(zoom));
+
+        center = (0..RANDOM_ATTEMPTS)
+            .map(|_| {
+                (
+                    center.0 + random.range(-0.5, 0.5) * width,
+                    center.1 + random.range(-0.5, 0.5) * height,
+                )
+            })
+            .find(|(x, y)| is_boundary(*x, *y, radius, max_iterations, parameters))?;
+    }
+
+    Some(center)
+}
+
+// Below this view width the center is too rounded in f64 to estimate distances from
+pub(crate) const DISTANCE_MIN_WIDTH: f64 = 1e-12;
+const BOUNDARY_STEPS: usize = 100;
+const BOUNDARY_DIRECTIONS: usize = 16;
+
+// The estimated distance from the middle of the view to the set, or None inside it, for the
+// fractals with an estimate
+pub(crate) fn boundary_distance(
+    position: &Position,
+    max_iterations: u32x1,
+    parameters: &FractalParameters,
+) -> Option<Option<f64>> {
+    if parameters.fractal_index != 0 && parameters.fractal_index != JULIA_INDEX
+        || position.width() < DISTANCE_MIN_WIDTH
+    {
+        return None;
+    }
+    let center = position.center();
+    let estimate = distance_estimate(
+        center.0.to_f64(),
+        center.1.to_f64(),
+        max_iterations,
+        parameters,
+    );
+    Some(estimate.map(|(distance, _)| distance))
+}
+
+// Finds a point on the boundary of the set near a point, to within about a thousandth of
+// scale. Outside the Mandelbrot and Julia sets it walks down the distance estimate, in steps
+// short enough not to cross a filament; otherwise it looks round rings of growing radius for
+// a point on the other side and halves the way there
+pub(crate) fn nearest_boundary(
+    point: (f64, f64),
+    scale: f64,
+    max_iterations: u32x1,
+    parameters: &FractalParameters,
+) -> Option<(f64, f64)> {
+    let inside =
+        |(x, y): (f64, f64)| iterate_point(x, y, max_iterations, parameters) == max_iterations;
+
+    let mut point = point;
+    for _ in 0..BOUNDARY_STEPS {
+        match distance_estimate(point.0, point.1, max_iterations, parameters) {
+            Some((distance, _)) if distance < scale / 1000.0 => return Some(point),
+            Some((distance, direction)) => {
+                point = (
+                    point.0 + direction.0 * distance / 4.0,
+                    point.1 + direction.1 * distance / 4.0,
+                );
+            }
+            None => break,
+        }
+    }
+
+    let start_inside = inside(point);
+    let mut radius = scale / 16.0;
+    // Everything is outside the escape radius
+    while radius < parameters.escape_radius * 4.0 {
+        for direction in 0..BOUNDARY_DIRECTIONS {
+            let angle = direction as f64 / BOUNDARY_DIRECTIONS as f64 * std::f64::consts::TAU;
+            let mut other = (
+                point.0 + radius * angle.cos(),
+                point.1 + radius * angle.sin(),
+            );
+            if inside(other) == start_inside {
+                continue;
+            }
+            let mut near = point;
+            // Halving 64 times reaches the limits of f64 from any radius
+            for _ in 0..64 {
+                if (other.0 - near.0).abs() + (other.1 - near.1).abs() <= scale / 1000.0 {
+                    break;
+                }
+                let middle = ((near.0 + other.0) / 2.0, (near.1 + other.1) / 2.0);
+                if inside(middle) == start_inside {
+                    near = middle;
+                } else {
+                    other = middle;
+                }
+            }
+            return Some(near);
+        }
+        radius *= 2.0;
+    }
+    None
+}
+
+const RAY_ESCAPE_RADIUS: f64 = 65536.0;
+// Points traced for each halving of the potential, and how many halvings to follow the ray for
+const RAY_SHARPNESS: u32 = 4;
+const RAY_LEVELS: u32 = 80;
+const RAY_NEWTON_STEPS: usize = 32;
+
+// Traces the external ray of the Mandelbrot set at the angle numerator / denominator turns in
+// from far away, stepping down the potential and finding each point by Newton's method from
+// the one before, so the points end near where the ray lands
+pub(crate) fn external_ray(numerator: u64, denominator: u64) -> Vec<(f64, f64)> {
+    let mut numerator = numerator % denominator;
+    let angle = |numerator: u64| numerator as f64 / denominator as f64 * std::f64::consts::TAU;
+    let mut c = (
+        RAY_ESCAPE_RADIUS * angle(numerator).cos(),
+        RAY_ESCAPE_RADIUS * angle(numerator).sin(),
+    );
+    let mut points = vec![c];
+
+    for level in 0..RAY_LEVELS {
+        for step in 0..RAY_SHARPNESS {
+            // After level + 1 iterations the ray is at this radius and the angle doubled
+            // level times
+            let radius =
+                RAY_ESCAPE_RADIUS.powf(0.5f64.powf((step as f64 + 0.5) / RAY_SHARPNESS as f64));
+            let target = (
+                radius * angle(numerator).cos(),
+                radius * angle(numerator).sin(),
+            );
+            for _ in 0..RAY_NEWTON_STEPS {
+                let (mut z, mut dz) = ((0.0, 0.0), (0.0, 0.0));
+                for _ in 0..=level {
+                    dz = (
+                        2.0 * (z.0 * dz.0 - z.1 * dz.1) + 1.0,
+                        2.0 * (z.0 * dz.1 + z.1 * dz.0),
+                    );
+                    z = (z.0 * z.0 - z.1 * z.1 + c.0, 2.0 * z.0 * z.1 + c.1);
+                }
+                // c -= (z - target) / dz
+                let error = (z.0 - target.0, z.1 - target.1);
+                let norm = dz.0 * dz.0 + dz.1 * dz.1;
+                let next = (
+                    c.0 - (error.0 * dz.0 + error.1 * dz.1) / norm,
+                    c.1 - (error.1 * dz.0 - error.0 * dz.1) / norm,
+                );
+                if !(next.0.is_finite() && next.1.is_finite()) {
+                    return points;
+                }
+                let moved = (next.0 - c.0).abs() + (next.1 - c.1).abs();
+                c = next;
+                if moved < 1e-15 {
+                    break;
+                }
+            }
+            points.push(c);
+        }
+        // Doubling the angle exactly keeps the ray on course however far it is followed
+        numerator = (numerator * 2) % denominator;
+    }
+    points
+}
+
+pub(crate) fn parse_angle(value: &str) -> Result<(u64, u64), String> {
+    let angle = value.split_once('/').and_then(|(numerator, denominator)| {
+        let numerator = numerator.parse::<u64>().ok()?;
+        let denominator = denominator.parse::<u64>().ok()?;
+        // Doubling the numerator mustn't overflow
+        (denominator > 0 && denominator < 1 << 62).then_some((numerator, denominator))
+    });
+    angle.ok_or(format!(
+        "invalid angle: {} (expected a fraction like 1/3)",
+        value
+    ))
+}
+
+const DIVE_DURATION: std::time::Duration = std::time::Duration::from_secs(20);
+const DIVE_ZOOM_RANGE: (f64, f64) = (3.0, 6.0);
+pub(crate) const DIVES_PER_SWITCH: u32 = 3;
+
+pub(crate) struct Screensaver {
+    pub(crate) start: Position,
+    target: Position,
+    started: std::time::Instant,
+    pub(crate) dives: u32,
+}
+
+impl Screensaver {
+    pub(crate) fn dive(
+        random: &mut Random,
+        default_position: &Position,
+        parameters: &FractalParameters,
+        dives: u32,
+    ) -> Screensaver {
+        let zoom = 10f64.powf(random.range(DIVE_ZOOM_RANGE.0, DIVE_ZOOM_RANGE.1));
+        let center = find_boundary_point(random, default_position, zoom, parameters)
+            .map(fixed_point)
+            .unwrap_or(default_position.center());
+        let start = default_position.centered_at(&center);
+
+        Screensaver {
+            target: start.with_zoom(default_position, zoom),
+            start,
+            started: std::time::Instant::now(),
+            dives,
+        }
+    }
+
+    pub(crate) fn position(&self) -> Option<Position> {
+        let t = self.started.elapsed().as_secs_f64() / DIVE_DURATION.as_secs_f64();
+        if t < 1.0 {
+            Some(interpolate_position(&self.start, &self.target, t))
+        } else {
+            None
+        }
+    }
+}
+
+// A dive toward a target point, which stays where it is on screen as the view closes in
+pub(crate) struct Dive {
+    pub(crate) target: (Fixed, Fixed),
+    pub(crate) start: Position,
+    pub(crate) started: std::time::Instant,
+    pub(crate) rate: f64,
+}
+
+impl Dive {
+    pub(crate) fn position(&self) -> Position {
+        let seconds = self.started.elapsed().as_secs_f64();
+        self.start.zoomed_at(&self.target, self.rate.powf(-seconds))
+    }
+}
+
+// One time round a morph path, however long it is
+pub(crate) const MORPH_PERIOD: std::time::Duration = std::time::Duration::from_secs(20);
+// The circle the classic morphing Julia sets are drawn along
+const MORPH_RADIUS: f64 = 0.7885;
+
+// The route a morph takes the Julia constant around, over and over
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum MorphPath {
+    Circle { radius: f64, start_angle: f64 },
+    // Points joined by straight lines, back to the first one
+    Points(Vec<(f64, f64)>),
+}
+
+impl MorphPath {
+    // Goes round the circle from the constant's angle, or the classic one from zero
+    pub(crate) fn circle_through(constant: (f64, f64)) -> MorphPath {
+        let radius = constant.0.hypot(constant.1);
+        MorphPath::Circle {
+            radius: if radius > 0.0 { radius } else { MORPH_RADIUS },
+            start_angle: constant.1.atan2(constant.0),
+        }
+    }
+
+    // The constant `t` of the way round, at a steady speed
+    pub(crate) fn constant(&self, t: f64) -> (f64, f64) {
+        let t = t.rem_euclid(1.0);
+        match self {
+            MorphPath::Circle {
+                radius,
+                start_angle,
+            } => {
+                let angle = start_angle + t * std::f64::consts::TAU;
+                (radius * angle.cos(), radius * angle.sin())
+            }
+            MorphPath::Points(points) => {
+                let segments: Vec<((f64, f64), (f64, f64))> = (0..points.len())
+                    .map(|index| (points[index], points[(index + 1) % points.len()]))
+                    .collect();
+                let length =
+                    |(from, to): &((f64, f64), (f64, f64))| (to.0 - from.0).hypot(to.1 - from.1);
+                let total: f64 = segments.iter().map(length).sum();
+                let mut distance = t * total;
+                for segment in &segments {
+                    let (from, to) = *segment;
+                    let segment_length = length(segment);
+                    if distance < segment_length {
+                        let fraction = distance / segment_length;
+                        return (
+                            from.0 + (to.0 - from.0) * fraction,
+                            from.1 + (to.1 - from.1) * fraction,
+                        );
+                    }
+                    distance -= segment_length;
+                }
+                points[0]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::render_status_bar;
+    use crate::DEFAULT_PARAMETERS;
+
+    #[test]
+    fn test_random() {
+        let mut random = Random::new(0);
+        assert_ne!(random.next_u64(), 0);
+        for _ in 0..100 {
+            let value = random.range(-1.0, 3.0);
+            assert!((-1.0..3.0).contains(&value));
+        }
+        assert_eq!(Random::new(42).next_u64(), Random::new(42).next_u64());
+    }
+
+    #[test]
+    fn test_iterations_for_zoom() {
+        assert_eq!(iterations_for_zoom(0.5), 100);
+        assert_eq!(iterations_for_zoom(1.0), 100);
+        assert_eq!(iterations_for_zoom(1e4), 900);
+        assert!(iterations_for_zoom(1e12) > iterations_for_zoom(1e6));
+    }
+
+    #[test]
+    fn test_find_boundary_point() {
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let mut random = Random::new(7);
+        let zoom = 1e4;
+        let (x, y) =
+            find_boundary_point(&mut random, &default_position, zoom, &DEFAULT_PARAMETERS).unwrap();
+        let radius = default_position.width() / zoom * 0.05;
+        assert!(is_boundary(
+            x,
+            y,
+            radius,
+            u32x1::splat(iterations_for_zoom(zoom)),
+            &DEFAULT_PARAMETERS
+        ));
+    }
+
+    #[test]
+    fn test_nearest_boundary() {
+        let max_iterations = u32x1::splat(500);
+        let boundary = |point| nearest_boundary(point, 0.1, max_iterations, &DEFAULT_PARAMETERS);
+        // From outside it walks down to the cusp, and from inside it finds a way out
+        let walked = boundary((1.0, 0.0)).unwrap();
+        assert!(
+            (walked.0 - 0.25).abs() < 0.01 && walked.1.abs() < 0.01,
+            "{:?}",
+            walked
+        );
+        let found = boundary((-0.5, 0.0)).unwrap();
+        let escapes = |(x, y): (f64, f64)| {
+            iterate_point(x, y, max_iterations, &DEFAULT_PARAMETERS) != max_iterations
+        };
+        assert!(!escapes(found));
+        assert!((0..8).any(|step| {
+            let angle = step as f64 * std::f64::consts::FRAC_PI_4;
+            escapes((found.0 + 1e-3 * angle.cos(), found.1 + 1e-3 * angle.sin()))
+        }));
+
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let status = |position: &Position| {
+            render_status_bar(
+                200,
+                position,
+                &default_position,
+                max_iterations,
+                &DEFAULT_PARAMETERS,
+                std::time::Duration::ZERO,
+            )
+        };
+        assert!(status(&default_position).contains("| inside |"));
+        let outside = default_position.centered_at(&fixed_point((1.0, 0.0)));
+        assert!(status(&outside).contains("| boundary: "));
+    }
+
+    #[test]
+    fn test_external_ray() {
+        let lands = |numerator, denominator, point: (f64, f64)| {
+            let ray = external_ray(numerator, denominator);
+            let end = ray.last().unwrap();
+            // Rays close in slowly on the roots of bulbs
+            ((end.0 - point.0).abs() + (end.1 - point.1).abs()) < 0.05
+        };
+        // The ray at angle 0 runs along the real axis to the cusp of the cardioid
+        assert!(external_ray(0, 1).iter().all(|(_, y)| *y == 0.0));
+        assert!(lands(0, 1, (0.25, 0.0)));
+        assert!(lands(1, 2, (-2.0, 0.0)));
+        // 1/3 goes to the root of the period 2 bulb, and 1/4 to a tip
+        assert!(lands(1, 3, (-0.75, 0.0)));
+        assert!(lands(4, 3, (-0.75, 0.0)));
+        assert!(lands(1, 4, (-0.228155493653962, 1.115142508039937)));
+    }
+
+    #[test]
+    fn test_screensaver_dive() {
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let mut random = Random::new(3);
+        let dive = Screensaver::dive(&mut random, &default_position, &DEFAULT_PARAMETERS, 2);
+
+        assert_eq!(dive.dives, 2);
+        assert_eq!(dive.start.width(), default_position.width());
+        assert_eq!(dive.target.center(), dive.start.center());
+        let zoom = dive.start.width() / dive.target.width();
+        assert!(zoom >= 10f64.powf(DIVE_ZOOM_RANGE.0) * 0.999);
+        assert!(zoom <= 10f64.powf(DIVE_ZOOM_RANGE.1) * 1.001);
+        assert!(dive.position().unwrap().width() <= dive.start.width());
+    }
+
+    #[test]
+    fn test_morph_path() {
+        let close =
+            |a: (f64, f64), b: (f64, f64)| (a.0 - b.0).abs() < 1e-12 && (a.1 - b.1).abs() < 1e-12;
+        // A circle through a constant starts there
+        let circle = MorphPath::circle_through((0.0, 0.5));
+        assert!(close(circle.constant(0.0), (0.0, 0.5)));
+        assert!(close(circle.constant(0.25), (-0.5, 0.0)));
+        assert!(close(circle.constant(1.5), (0.0, -0.5)));
+        assert_eq!(
+            MorphPath::circle_through((0.0, 0.0)),
+            MorphPath::Circle {
+                radius: MORPH_RADIUS,
+                start_angle: 0.0
+            }
+        );
+
+        // Paths go round at a steady speed, however long their sides are
+        let path = MorphPath::Points(vec![(0.0, 0.0), (3.0, 0.0), (3.0, 1.0), (0.0, 1.0)]);
+        assert!(close(path.constant(0.0), (0.0, 0.0)));
+        assert!(close(path.constant(0.125), (1.0, 0.0)));
+        assert!(close(path.constant(0.4), (3.0, 0.2)));
+        assert!(close(path.constant(0.95), (0.0, 0.4)));
+        assert!(close(path.constant(1.0), (0.0, 0.0)));
+    }
+}
@@ -0,0 +1,531 @@
+// Printing frames and exporting animations, images and batches of them.
+
+use crate::app::ease;
+use crate::cli::parse_size;
+use crate::explore::iterations_for_zoom;
+use crate::overlay::describe_view;
+use crate::session::{keyframe_frames, View};
+use crate::{
+    grid_frame, image, render_buffer, u32x1, zoom_position, FractalParameters, Position, TileCache,
+    DEFAULT_TILE_SIZE,
+};
+
+use std::io::Write;
+
+// Subpixels calculated for each terminal cell with each of GRID_NAMES
+pub(crate) const GRID_SUBPIXELS: [u64; 3] = [4, 2, 8];
+
+pub(crate) fn print_frame(
+    writer: &mut impl Write,
+    size: (u16, u16),
+    position: &Position,
+    max_iterations: u32x1,
+    parameters: &FractalParameters,
+    grid_index: usize,
+    // Given to describe the frame in words, against this default view's zoom
+    describe: Option<&Position>,
+) -> std::io::Result<()> {
+    let (width, height, grid_position) = grid_frame(grid_index, size.0, size.1, position);
+    let (buffer, _) = render_buffer(
+        width,
+        height,
+        &grid_position,
+        max_iterations,
+        parameters,
+        DEFAULT_TILE_SIZE,
+        None,
+        &mut TileCache::new(0),
+    );
+    let (text, stretched) = buffer.grid_text(grid_index, size.0, size.1, position);
+    let text = match describe {
+        Some(default_position) => {
+            describe_view(stretched.as_ref().unwrap_or(&buffer), default_position).join("\n")
+        }
+        None => text,
+    };
+    writeln!(writer, "{}", text)?;
+    writer.flush()
+}
+
+pub(crate) const EXPORT_SIZE: (u16, u16) = (320, 240);
+pub(crate) const EXPORT_FRAMES: u32 = 60;
+pub(crate) const EXPORT_FPS: u32 = 15;
+pub(crate) const VIDEO_SIZE: (u16, u16) = (1280, 720);
+pub(crate) const VIDEO_SECONDS: f64 = 10.0;
+pub(crate) const VIDEO_FPS: u32 = 30;
+
+// Where and how to render an animation
+#[derive(PartialEq, Debug)]
+pub(crate) struct Export {
+    pub(crate) output: std::path::PathBuf,
+    pub(crate) size: (u16, u16),
+    pub(crate) frames: u32,
+    pub(crate) fps: u32,
+    pub(crate) easing_index: usize,
+}
+
+impl Default for Export {
+    fn default() -> Export {
+        Export {
+            output: std::path::PathBuf::new(),
+            size: EXPORT_SIZE,
+            frames: EXPORT_FRAMES,
+            fps: EXPORT_FPS,
+            easing_index: 0,
+        }
+    }
+}
+
+// The view and iteration limit of each frame of a zoom from the default view into `view`
+fn zoom_frames(
+    view: &View,
+    default_position: &Position,
+    frames: u32,
+    easing_index: usize,
+) -> Vec<(Position, u32x1)> {
+    (0..frames)
+        .map(|frame| {
+            let t = match frames {
+                1 => 1.0,
+                _ => frame as f64 / (frames - 1) as f64,
+            };
+            let position = zoom_position(default_position, &view.position, ease(easing_index, t));
+            let max_iterations = if view.auto_iterations {
+                u32x1::splat(iterations_for_zoom(
+                    default_position.width() / position.width(),
+                ))
+            } else {
+                view.max_iterations
+            };
+            (position, max_iterations)
+        })
+        .collect()
+}
+
+// How far an export has got, with the time left at the rate it has gone so far
+pub(crate) fn export_progress(frame: usize, frames: usize, elapsed: std::time::Duration) -> String {
+    let left = elapsed.as_secs_f64() / frame.max(1) as f64 * frames.saturating_sub(frame) as f64;
+    let left = left.round() as u64;
+    format!(
+        "frame {} of {}, {}:{:02} left",
+        frame,
+        frames,
+        left / 60,
+        left % 60
+    )
+}
+
+// Writes to a hidden file beside `path` and only moves it into place once `write` succeeds,
+// so a failed or cancelled export never leaves a broken file under the name asked for. The
+// extension is kept for writers that go by it.
+pub(crate) fn write_atomically(
+    path: &std::path::Path,
+    write: impl FnOnce(&std::path::Path) -> Result<(), String>,
+) -> Result<(), String> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let partial = path.with_file_name(format!(".partial-{}", name));
+    match write(&partial) {
+        Ok(()) => std::fs::rename(&partial, path)
+            .map_err(|error| format!("{}: {}", path.display(), error)),
+        Err(error) => {
+            let _ = std::fs::remove_file(&partial);
+            Err(error)
+        }
+    }
+}
+
+// Renders each frame of the zoom into `view`, or of its keyframes, and hands it to `write`,
+// reporting each frame that is done to `progress`, which returns false to stop
+fn render_animation(
+    export: &Export,
+    view: &View,
+    default_position: &Position,
+    mut progress: impl FnMut(usize, usize) -> bool,
+    mut write: impl FnMut(&image::Image) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    // A view with keyframes animates through them instead, taking as long as they do
+    let frames: Vec<(Position, u32x1, FractalParameters)> = if view.keyframes.len() > 1 {
+        keyframe_frames(
+            &view.keyframes,
+            default_position,
+            export.fps,
+            export.easing_index,
+        )
+        .into_iter()
+        .map(|frame| (frame.position, frame.max_iterations, frame.parameters))
+        .collect()
+    } else {
+        zoom_frames(view, default_position, export.frames, export.easing_index)
+            .into_iter()
+            .map(|(position, max_iterations)| (position, max_iterations, view.parameters))
+            .collect()
+    };
+    for (number, (position, max_iterations, parameters)) in frames.iter().enumerate() {
+        let frame = image::Image::render(
+            export.size.0,
+            export.size.1,
+            position,
+            *max_iterations,
+            parameters,
+        );
+        write(&frame)?;
+        if !progress(number + 1, frames.len()) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                "export cancelled",
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Renders the zoom into `view` as a GIF
+pub(crate) fn export_gif(
+    export: &Export,
+    view: &View,
+    default_position: &Position,
+    progress: impl FnMut(usize, usize) -> bool,
+) -> Result<(), String> {
+    let error = |error: std::io::Error| format!("{}: {}", export.output.display(), error);
+    write_atomically(&export.output, |path| {
+        let file = std::fs::File::create(path).map_err(error)?;
+        // GIF delays are in hundredths of a second
+        let delay = (100 / export.fps).max(1) as u16;
+        let mut gif = image::GifWriter::new(
+            std::io::BufWriter::new(file),
+            export.size.0,
+            export.size.1,
+            delay,
+        )
+        .map_err(error)?;
+        render_animation(export, view, default_position, progress, |frame| {
+            gif.write_frame(frame)
+        })
+        .map_err(error)?;
+        gif.finish().map_err(error)?;
+        Ok(())
+    })
+}
+
+// The ffmpeg arguments that encode raw frames from stdin into `output`, in the format of
+// `export.output`
+fn ffmpeg_args(export: &Export, output: &std::path::Path) -> Result<Vec<String>, String> {
+    let extension = export
+        .output
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let codec = match extension.to_lowercase().as_str() {
+        "mp4" => "libx264",
+        "webm" => "libvpx-vp9",
+        _ => return Err(format!("unsupported video format: {}", extension)),
+    };
+    // Both codecs subsample color in 2x2 blocks
+    if !export.size.0.is_multiple_of(2) || !export.size.1.is_multiple_of(2) {
+        return Err("video sizes must be even".to_string());
+    }
+    // The output path goes on its own, since it may contain spaces
+    let args = format!(
+        "-y -v error -f rawvideo -pix_fmt rgb24 -s {}x{} -r {} -i - -c:v {} -pix_fmt yuv420p",
+        export.size.0, export.size.1, export.fps, codec
+    );
+    let mut args: Vec<String> = args.split(' ').map(|arg| arg.to_string()).collect();
+    args.push(output.to_string_lossy().into_owned());
+    Ok(args)
+}
+
+// Renders the zoom into `view` as a video, piping the frames through ffmpeg
+pub(crate) fn export_video(
+    export: &Export,
+    view: &View,
+    default_position: &Position,
+    progress: impl FnMut(usize, usize) -> bool,
+) -> Result<(), String> {
+    write_atomically(&export.output, |path| {
+        let mut ffmpeg = std::process::Command::new("ffmpeg")
+            .args(ffmpeg_args(export, path)?)
+            .stdin(std::process::Stdio::piped())
+            .spawn()
+            .map_err(|error| format!("couldn't run ffmpeg: {}", error))?;
+        let mut stdin = ffmpeg.stdin.take().unwrap();
+        let result = render_animation(export, view, default_position, progress, |frame| {
+            stdin.write_all(&frame.pixels)
+        });
+        // Closing stdin tells ffmpeg the video is over
+        drop(stdin);
+        let status = ffmpeg
+            .wait()
+            .map_err(|error| format!("ffmpeg: {}", error))?;
+        match result {
+            Err(error) if error.kind() == std::io::ErrorKind::Interrupted => {
+                Err(format!("{}: {}", export.output.display(), error))
+            }
+            _ if !status.success() => Err(format!("ffmpeg failed: {}", status)),
+            result => result.map_err(|error| format!("ffmpeg: {}", error)),
+        }
+    })
+}
+
+// One image a batch job file asks for
+#[derive(Debug)]
+struct Job {
+    output: std::path::PathBuf,
+    size: (u16, u16),
+    view: View,
+}
+
+impl Job {
+    // Job files are a JSON list of views, each with an output path and a size as well. The
+    // config file is left out so a job file renders the same anywhere.
+    fn parse_list(text: &str, default_position: &Position) -> Result<Vec<Job>, String> {
+        let value: serde_json::Value =
+            serde_json::from_str(text).map_err(|error| error.to_string())?;
+        let jobs = value.as_array().ok_or("jobs must be a list")?;
+        jobs.iter()
+            .enumerate()
+            .map(|(number, job)| {
+                Job::parse(job, default_position)
+                    .map_err(|error| format!("job {}: {}", number + 1, error))
+            })
+            .collect()
+    }
+
+    fn parse(value: &serde_json::Value, default_position: &Position) -> Result<Job, String> {
+        let mut object = value.as_object().ok_or("must be an object")?.clone();
+
+        let output = match object.remove("output") {
+            Some(serde_json::Value::String(path)) => std::path::PathBuf::from(path),
+            Some(_) => return Err("output must be a string".to_string()),
+            None => return Err("missing output".to_string()),
+        };
+        // Catch a bad format before spending any time rendering
+        if !matches!(
+            output.extension().and_then(|extension| extension.to_str()),
+            Some("png" | "ppm")
+        ) {
+            return Err(format!(
+                "output must be a .png or .ppm file: {}",
+                output.display()
+            ));
+        }
+        let size = match object.remove("size") {
+            Some(serde_json::Value::String(size)) => parse_size(&size)?,
+            Some(_) => return Err("size must be a string".to_string()),
+            None => (1920, 1080),
+        };
+        Ok(Job {
+            output,
+            size,
+            view: View::from_json(&object, default_position)?,
+        })
+    }
+}
+
+// Renders every job, carrying on past ones that can't be written
+pub(crate) fn run_batch(path: &std::path::Path, default_position: &Position) -> Result<(), String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    let jobs = Job::parse_list(&text, default_position)?;
+
+    let mut failed = 0;
+    for job in &jobs {
+        let image = image::Image::render(
+            job.size.0,
+            job.size.1,
+            &job.view.position,
+            job.view.max_iterations,
+            &job.view.parameters,
+        );
+        match write_atomically(&job.output, |path| image.save(path)) {
+            Ok(()) => println!("wrote {}", job.output.display()),
+            Err(error) => {
+                eprintln!("{}", error);
+                failed += 1;
+            }
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        _ => Err(format!("{} of {} jobs failed", failed, jobs.len())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DEFAULT_PARAMETERS, JULIA_INDEX};
+
+    #[test]
+    fn test_job() {
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let parse = |text: &str| Job::parse_list(text, &default_position);
+        let jobs = parse(
+            r#"[
+                {"output": "seahorse.png", "center": "-0.745,0.113", "zoom": 500,
+                 "size": "320x200", "palette": "fire", "fractal": "julia"},
+                {"output": "whole.ppm", "center": [-0.5, 0], "iterations": 50}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].output, std::path::Path::new("seahorse.png"));
+        assert_eq!(jobs[0].size, (320, 200));
+        let center = jobs[0].view.position.center();
+        assert_eq!((center.0.to_f64(), center.1.to_f64()), (-0.745, 0.113));
+        assert!((jobs[0].view.position.width() - 3.0 / 500.0).abs() < 1e-12);
+        assert_eq!(
+            jobs[0].view.max_iterations,
+            u32x1::splat(iterations_for_zoom(500.0))
+        );
+        assert_eq!(jobs[0].view.parameters.palette_index, 2);
+        assert_eq!(jobs[0].view.parameters.fractal_index, JULIA_INDEX);
+        // Settings that aren't given are the defaults
+        assert_eq!(jobs[1].size, (1920, 1080));
+        assert_eq!(jobs[1].view.position.center(), default_position.center());
+        assert_eq!(jobs[1].view.max_iterations, u32x1::splat(50));
+        assert_eq!(jobs[1].view.parameters, DEFAULT_PARAMETERS);
+
+        let error = |text: &str| parse(text).err().unwrap();
+        assert_eq!(error("{}"), "jobs must be a list");
+        assert_eq!(error(r#"[{"size": "1x1"}]"#), "job 1: missing output");
+        assert_eq!(
+            error(r#"[{"output": "a.png"}, {"output": "b.jpg"}]"#),
+            "job 2: output must be a .png or .ppm file: b.jpg"
+        );
+        assert_eq!(
+            error(r#"[{"output": "a.png", "zoom": -1}]"#),
+            "job 1: zoom must be a positive number"
+        );
+        assert_eq!(
+            error(r#"[{"output": "a.png", "center": [1]}]"#),
+            "job 1: center must be \"X,Y\" or [X, Y]"
+        );
+        assert_eq!(
+            error(r#"[{"output": "a.png", "color": "red"}]"#),
+            "job 1: unknown setting: color"
+        );
+    }
+
+    #[test]
+    fn test_zoom_frames() {
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let mut view = View::parse(
+            r#"{"center": "-0.745,0.113", "zoom": 1000}"#,
+            &default_position,
+        )
+        .unwrap();
+        let frames = zoom_frames(&view, &default_position, 5, 0);
+        assert_eq!(frames.len(), 5);
+        // The zoom starts from the default view and ends on the view
+        assert!((frames[0].0.width() - 3.0).abs() < 1e-9);
+        assert_eq!(frames[0].1, u32x1::splat(iterations_for_zoom(1.0)));
+        assert!((frames[4].0.width() - 3.0 / 1000.0).abs() < 1e-12);
+        let center = frames[4].0.center();
+        assert!((center.0.to_f64() + 0.745).abs() < 1e-12);
+        assert_eq!(frames[4].1, u32x1::splat(iterations_for_zoom(1000.0)));
+        // Each frame magnifies by the same factor
+        let ratio = frames[1].0.width() / frames[2].0.width();
+        assert!((ratio - 1000f64.powf(0.25)).abs() < 1e-9);
+
+        view.auto_iterations = false;
+        view.max_iterations = u32x1::splat(300);
+        let frames = zoom_frames(&view, &default_position, 1, 0);
+        assert_eq!(frames.len(), 1);
+        assert!((frames[0].0.width() - 3.0 / 1000.0).abs() < 1e-12);
+        assert_eq!(frames[0].1, u32x1::splat(300));
+
+        // Easing in spends longer on the wide views
+        view.auto_iterations = true;
+        let frames = zoom_frames(&view, &default_position, 3, 1);
+        let expected = 3.0 / 1000f64.powf(0.25);
+        assert!((frames[1].0.width() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_export_progress() {
+        let second = std::time::Duration::from_secs(1);
+        assert_eq!(
+            export_progress(10, 100, second * 5),
+            "frame 10 of 100, 0:45 left"
+        );
+        assert_eq!(
+            export_progress(1, 200, second * 2),
+            "frame 1 of 200, 6:38 left"
+        );
+        assert_eq!(
+            export_progress(50, 50, second * 30),
+            "frame 50 of 50, 0:00 left"
+        );
+    }
+
+    #[test]
+    fn test_cancelled_export() {
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let view = View::parse(r#"{"zoom": 10}"#, &default_position).unwrap();
+        let directory = std::env::temp_dir().join(format!("export-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let export = Export {
+            output: directory.join("zoom.gif"),
+            size: (16, 8),
+            frames: 4,
+            ..Export::default()
+        };
+
+        // Nothing is left behind by an export that stops partway
+        let error = export_gif(&export, &view, &default_position, |frame, _| frame < 2);
+        assert!(error.unwrap_err().ends_with("export cancelled"));
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 0);
+
+        let mut reported = Vec::new();
+        export_gif(&export, &view, &default_position, |frame, frames| {
+            reported.push((frame, frames));
+            true
+        })
+        .unwrap();
+        assert_eq!(reported.last(), Some(&(4, 4)));
+        let names: Vec<_> = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, ["zoom.gif"]);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_ffmpeg_args() {
+        let export = Export {
+            output: "my zoom.webm".into(),
+            size: (640, 360),
+            fps: 24,
+            ..Export::default()
+        };
+        let args = ffmpeg_args(&export, &export.output).unwrap();
+        assert_eq!(args[args.len() - 1], "my zoom.webm");
+        let args = args.join(" ");
+        assert!(args.contains("-s 640x360 -r 24 -i - -c:v libvpx-vp9"));
+        let export = Export {
+            output: "zoom.MP4".into(),
+            ..export
+        };
+        assert!(ffmpeg_args(&export, &export.output)
+            .unwrap()
+            .contains(&"libx264".to_string()));
+
+        let error = |output: &str, size| {
+            ffmpeg_args(
+                &Export {
+                    output: output.into(),
+                    size,
+                    ..Export::default()
+                },
+                std::path::Path::new(output),
+            )
+            .unwrap_err()
+        };
+        assert_eq!(
+            error("zoom.avi", (640, 360)),
+            "unsupported video format: avi"
+        );
+        assert_eq!(error("zoom.mp4", (641, 360)), "video sizes must be even");
+    }
+}
@@ -0,0 +1,620 @@
+//! Renders views to images, one pixel per subpixel, and encodes them as PNG or PPM files or
+//! the frames of an animated GIF.
+
+use crate::{
+    render_buffer, u32x1, ColorTable, FractalParameters, FrameBuffer, Position, TileCache,
+    DEFAULT_TILE_SIZE,
+};
+
+/// A rendered image.
+pub struct Image {
+    /// Pixels across.
+    pub width: u16,
+    /// Pixels down.
+    pub height: u16,
+    /// The red, green and blue bytes of every pixel, row by row.
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// Renders a `width` by `height` image of a view. The view keeps its horizontal extent
+    /// and has its vertical one fitted so pixels come out square.
+    pub fn render(
+        width: u16,
+        height: u16,
+        position: &Position,
+        max_iterations: u32x1,
+        parameters: &FractalParameters,
+    ) -> Image {
+        Image::render_cached(
+            width,
+            height,
+            position,
+            max_iterations,
+            parameters,
+            &mut TileCache::new(0),
+        )
+    }
+
+    /// Like `render`, but reuses tiles of the same view from `cache`, e.g. when only the
+    /// palette has changed.
+    pub fn render_cached(
+        width: u16,
+        height: u16,
+        position: &Position,
+        max_iterations: u32x1,
+        parameters: &FractalParameters,
+        cache: &mut TileCache,
+    ) -> Image {
+        let frame = Image::frame(width, height, position, max_iterations, parameters, cache);
+        Image::from_frame(&frame, width, height, &frame.color_table())
+    }
+
+    /// The cells a `width` by `height` image of a view is colored from, reusing tiles from
+    /// `cache` like `render_cached`. Keeping these rather than the image lets it be colored
+    /// again with [`Image::from_frame`].
+    pub fn frame(
+        width: u16,
+        height: u16,
+        position: &Position,
+        max_iterations: u32x1,
+        parameters: &FractalParameters,
+        cache: &mut TileCache,
+    ) -> FrameBuffer {
+        // Every cell has 2x2 subpixels, so odd sizes render one extra pixel and crop it
+        let columns = width.div_ceil(2).max(1);
+        let rows = height.div_ceil(2).max(1);
+        let center = position.center();
+        let plane_height = position.width() * rows as f64 / columns as f64;
+        let view = Position {
+            top: &center.1 - plane_height / 2.0,
+            bottom: &center.1 + plane_height / 2.0,
+            ..position.clone()
+        };
+        let (buffer, _) = render_buffer(
+            columns,
+            rows,
+            &view,
+            max_iterations,
+            parameters,
+            DEFAULT_TILE_SIZE,
+            None,
+            cache,
+        );
+        buffer
+    }
+
+    /// Colors a `width` by `height` image from `frame`, the cells [`Image::frame`] gave for
+    /// that size.
+    pub fn from_frame(frame: &FrameBuffer, width: u16, height: u16, colors: &ColorTable) -> Image {
+        let mut pixels = Vec::with_capacity(width as usize * height as usize * 3);
+        let cells = frame.shaded_cells(colors);
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                let cell = cells[y / 2 * frame.width as usize + x / 2];
+                pixels.extend(colors.color(cell[y % 2][x % 2]));
+            }
+        }
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// The image as a binary PPM file.
+    pub fn ppm(&self) -> Vec<u8> {
+        let mut file = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        file.extend(&self.pixels);
+        file
+    }
+
+    /// The image as a PNG file.
+    pub fn png(&self) -> Vec<u8> {
+        // Each row starts with the filter it was encoded with, and none is used
+        let mut rows = Vec::with_capacity(self.pixels.len() + self.height as usize);
+        for row in self.pixels.chunks(self.width as usize * 3) {
+            rows.push(0);
+            rows.extend(row);
+        }
+
+        let mut header = Vec::new();
+        header.extend((self.width as u32).to_be_bytes());
+        header.extend((self.height as u32).to_be_bytes());
+        // 8 bits per channel, RGB, deflate, no interlacing
+        header.extend([8, 2, 0, 0, 0]);
+
+        let mut file = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+        push_chunk(&mut file, b"IHDR", &header);
+        push_chunk(&mut file, b"IDAT", &zlib(&rows));
+        push_chunk(&mut file, b"IEND", &[]);
+        file
+    }
+
+    /// Writes the image to `path`, as PNG or PPM depending on its extension.
+    pub fn save(&self, path: &std::path::Path) -> Result<(), String> {
+        let extension = path
+            .extension()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_lowercase();
+        let file = match extension.as_str() {
+            "png" => self.png(),
+            "ppm" => self.ppm(),
+            _ => return Err(format!("unsupported image format: {}", path.display())),
+        };
+        std::fs::write(path, file).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+}
+
+/// Writes images as the frames of an animated GIF that loops forever.
+pub struct GifWriter<W: std::io::Write> {
+    writer: W,
+    width: u16,
+    height: u16,
+    delay: u16,
+}
+
+impl<W: std::io::Write> GifWriter<W> {
+    /// Starts an animation of `width` by `height` frames, each shown for `delay` hundredths
+    /// of a second.
+    pub fn new(mut writer: W, width: u16, height: u16, delay: u16) -> std::io::Result<Self> {
+        writer.write_all(b"GIF89a")?;
+        writer.write_all(&width.to_le_bytes())?;
+        writer.write_all(&height.to_le_bytes())?;
+        // Every frame brings its own colors, so there's no global color table
+        writer.write_all(&[0, 0, 0])?;
+        writer.write_all(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\0\0\0")?;
+        Ok(GifWriter {
+            writer,
+            width,
+            height,
+            delay,
+        })
+    }
+
+    /// Adds a frame, which has to be the size of the animation.
+    pub fn write_frame(&mut self, image: &Image) -> std::io::Result<()> {
+        assert_eq!((image.width, image.height), (self.width, self.height));
+        let (colors, indices) = quantize(&image.pixels);
+
+        // Leave each frame in place under the next, which covers it completely anyway
+        self.writer.write_all(&[0x21, 0xf9, 4, 1 << 2])?;
+        self.writer.write_all(&self.delay.to_le_bytes())?;
+        self.writer.write_all(&[0, 0])?;
+
+        self.writer.write_all(&[0x2c, 0, 0, 0, 0])?;
+        self.writer.write_all(&self.width.to_le_bytes())?;
+        self.writer.write_all(&self.height.to_le_bytes())?;
+        // A local table of 256 colors
+        self.writer.write_all(&[0x80 | 7])?;
+        let mut table = colors.concat();
+        table.resize(256 * 3, 0);
+        self.writer.write_all(&table)?;
+
+        self.writer.write_all(&[8])?;
+        for block in lzw(&indices).chunks(255) {
+            self.writer.write_all(&[block.len() as u8])?;
+            self.writer.write_all(block)?;
+        }
+        self.writer.write_all(&[0])
+    }
+
+    /// Ends the animation, handing back the writer.
+    pub fn finish(mut self) -> std::io::Result<W> {
+        self.writer.write_all(&[0x3b])?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+// Picks at most 256 colors for a frame, returning them and each pixel's index into them. A
+// frame with more colors keeps the most common ones and has the rest drawn with the closest.
+fn quantize(pixels: &[u8]) -> (Vec<[u8; 3]>, Vec<u8>) {
+    let mut counts = std::collections::HashMap::<[u8; 3], usize>::new();
+    for pixel in pixels.chunks(3) {
+        *counts.entry([pixel[0], pixel[1], pixel[2]]).or_default() += 1;
+    }
+    let mut colors: Vec<([u8; 3], usize)> = counts.into_iter().collect();
+    // Ties are broken by the color so a frame always comes out the same
+    colors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let colors: Vec<[u8; 3]> = colors.into_iter().take(256).map(|(color, _)| color).collect();
+
+    let mut indices: std::collections::HashMap<[u8; 3], u8> = colors
+        .iter()
+        .enumerate()
+        .map(|(index, color)| (*color, index as u8))
+        .collect();
+    let closest = |color: [u8; 3]| {
+        let distance = |other: &[u8; 3]| -> i32 {
+            (0..3)
+                .map(|channel| (color[channel] as i32 - other[channel] as i32).pow(2))
+                .sum()
+        };
+        (0..colors.len()).min_by_key(|index| distance(&colors[*index])).unwrap() as u8
+    };
+    let pixels = pixels
+        .chunks(3)
+        .map(|pixel| {
+            let color = [pixel[0], pixel[1], pixel[2]];
+            *indices.entry(color).or_insert_with(|| closest(color))
+        })
+        .collect();
+    (colors, pixels)
+}
+
+// GIF's variable-width LZW, starting from 9 bit codes for 8 bit indices and clearing the
+// table when it fills up at 12 bits
+fn lzw(indices: &[u8]) -> Vec<u8> {
+    const CLEAR: u32 = 256;
+    const END: u32 = 257;
+    const FIRST_CODE: u32 = 258;
+    const MAX_CODES: u32 = 4096;
+
+    let mut writer = BitWriter {
+        bytes: Vec::new(),
+        buffer: 0,
+        count: 0,
+    };
+    let mut table = std::collections::HashMap::<(u32, u8), u32>::new();
+    let mut next = FIRST_CODE;
+    let mut bits = 9;
+    writer.write(CLEAR, bits);
+
+    let mut prefix = None;
+    for &index in indices {
+        let Some(code) = prefix else {
+            prefix = Some(index as u32);
+            continue;
+        };
+        if let Some(&extended) = table.get(&(code, index)) {
+            prefix = Some(extended);
+            continue;
+        }
+
+        writer.write(code, bits);
+        if next == MAX_CODES {
+            writer.write(CLEAR, bits);
+            table.clear();
+            next = FIRST_CODE;
+            bits = 9;
+        } else {
+            // The decoder widens its codes once the next free one no longer fits
+            if next >= 1 << bits && bits < 12 {
+                bits += 1;
+            }
+            table.insert((code, index), next);
+            next += 1;
+        }
+        prefix = Some(index as u32);
+    }
+    if let Some(code) = prefix {
+        writer.write(code, bits);
+        if next >= 1 << bits && bits < 12 {
+            bits += 1;
+        }
+    }
+    writer.write(END, bits);
+    writer.finish()
+}
+
+fn push_chunk(file: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    file.extend((data.len() as u32).to_be_bytes());
+    let start = file.len();
+    file.extend(kind);
+    file.extend(data);
+    let crc = crc32(&file[start..]);
+    file.extend(crc.to_be_bytes());
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let table: Vec<u32> = (0..256)
+        .map(|byte| {
+            (0..8).fold(byte, |crc, _| {
+                if crc & 1 == 1 {
+                    0xedb88320 ^ (crc >> 1)
+                } else {
+                    crc >> 1
+                }
+            })
+        })
+        .collect();
+    !bytes.iter().fold(!0, |crc, byte| {
+        table[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (a, b) = bytes.iter().fold((1u32, 0u32), |(a, b), byte| {
+        let a = (a + *byte as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+    (b << 16) | a
+}
+
+// Packs codes into bytes starting from the least significant bit, as deflate does
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, bits: u32) {
+        self.buffer |= (value as u64) << self.count;
+        self.count += bits;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // Huffman codes go most significant bit first
+    fn write_code(&mut self, code: u32, bits: u32) {
+        self.write(code.reverse_bits() >> (32 - bits), bits);
+    }
+
+    // A literal byte, the end of the block or a match length, in the fixed Huffman code
+    fn write_symbol(&mut self, symbol: u32) {
+        match symbol {
+            0..=143 => self.write_code(0x30 + symbol, 8),
+            144..=255 => self.write_code(0x190 + symbol - 144, 9),
+            256..=279 => self.write_code(symbol - 256, 7),
+            _ => self.write_code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+const LENGTH_BASES: [u32; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u32; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASES: [u32; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u32; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+// Compresses with one fixed Huffman block, matching each position against the last one that
+// started with the same three bytes. Fractals have long runs of flat color, which this is
+// enough to squeeze.
+fn zlib(data: &[u8]) -> Vec<u8> {
+    const WINDOW: usize = 32768;
+    let hash = |at: usize| {
+        ((data[at] as usize) << 10 ^ (data[at + 1] as usize) << 5 ^ data[at + 2] as usize) & 0x7fff
+    };
+
+    let mut writer = BitWriter {
+        bytes: vec![0x78, 0x01],
+        buffer: 0,
+        count: 0,
+    };
+    // The final block, with fixed codes
+    writer.write(1, 1);
+    writer.write(1, 2);
+
+    let mut latest = vec![usize::MAX; 1 << 15];
+    let mut at = 0;
+    while at < data.len() {
+        let mut length = 0;
+        let mut distance = 0;
+        if at + 3 <= data.len() {
+            let candidate = std::mem::replace(&mut latest[hash(at)], at);
+            if candidate != usize::MAX && at - candidate <= WINDOW {
+                length = data[candidate..]
+                    .iter()
+                    .zip(&data[at..])
+                    .take(258)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                distance = at - candidate;
+            }
+        }
+
+        if length < 3 {
+            writer.write_symbol(data[at] as u32);
+            at += 1;
+            continue;
+        }
+
+        let code = LENGTH_BASES.partition_point(|base| *base <= length as u32) - 1;
+        writer.write_symbol(257 + code as u32);
+        writer.write(length as u32 - LENGTH_BASES[code], LENGTH_EXTRA_BITS[code]);
+        let code = DISTANCE_BASES.partition_point(|base| *base <= distance as u32) - 1;
+        writer.write_code(code as u32, 5);
+        writer.write(
+            distance as u32 - DISTANCE_BASES[code],
+            DISTANCE_EXTRA_BITS[code],
+        );
+
+        for skipped in at + 1..(at + length).min(data.len().saturating_sub(2)) {
+            latest[hash(skipped)] = skipped;
+        }
+        at += length;
+    }
+    writer.write_symbol(256);
+
+    let mut stream = writer.finish();
+    stream.extend(adler32(data).to_be_bytes());
+    stream
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let image = Image::render(
+            7,
+            5,
+            &position,
+            u32x1::splat(50),
+            &crate::DEFAULT_PARAMETERS,
+        );
+        assert_eq!((image.width, image.height), (7, 5));
+        assert_eq!(image.pixels.len(), 7 * 5 * 3);
+
+        let ppm = image.ppm();
+        assert!(ppm.starts_with(b"P6\n7 5\n255\n"));
+        assert_eq!(ppm.len(), 11 + 7 * 5 * 3);
+
+        // Cached tiles are recolored for another palette rather than rendered again
+        let mut cache = TileCache::new(16);
+        let fire = crate::FractalParameters {
+            palette_index: 2,
+            ..crate::DEFAULT_PARAMETERS
+        };
+        let render = |parameters, cache: &mut TileCache| {
+            Image::render_cached(7, 5, &position, u32x1::splat(50), parameters, cache).pixels
+        };
+        render(&crate::DEFAULT_PARAMETERS, &mut cache);
+        assert_eq!(render(&crate::DEFAULT_PARAMETERS, &mut cache), image.pixels);
+        assert_eq!(
+            render(&fire, &mut cache),
+            Image::render(7, 5, &position, u32x1::splat(50), &fire).pixels
+        );
+        // And so are frames kept apart from the cache
+        let frame = Image::frame(
+            7,
+            5,
+            &position,
+            u32x1::splat(50),
+            &fire,
+            &mut TileCache::new(0),
+        );
+        let colors = ColorTable::new(frame.max_iterations, &crate::DEFAULT_PARAMETERS);
+        assert_eq!(
+            Image::from_frame(&frame, 7, 5, &colors).pixels,
+            image.pixels
+        );
+    }
+
+    #[test]
+    fn test_png() {
+        let image = Image {
+            width: 2,
+            height: 1,
+            pixels: vec![255, 0, 0, 0, 0, 255],
+        };
+        let png = image.png();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x02\0\0\0\x01"));
+        // Every file ends with the same empty IEND chunk
+        assert!(png.ends_with(b"\0\0\0\0IEND\xae\x42\x60\x82"));
+        assert_eq!(
+            image.save(std::path::Path::new("out.jpg")),
+            Err("unsupported image format: out.jpg".to_string())
+        );
+    }
+
+    // Decodes a GIF frame's LZW data back into indices
+    fn unlzw(bytes: &[u8]) -> Vec<u8> {
+        let mut bit = 0;
+        let mut read = |bits: u32| {
+            let code = (0..bits).fold(0, |code, offset| {
+                let position = bit + offset as usize;
+                code | (((bytes[position / 8] >> (position % 8)) & 1) as u32) << offset
+            });
+            bit += bits as usize;
+            code
+        };
+        let mut table: Vec<Vec<u8>> = Vec::new();
+        let mut bits = 9;
+        let mut previous: Option<Vec<u8>> = None;
+        let mut output = Vec::new();
+        loop {
+            let code = read(bits);
+            match code {
+                256 => {
+                    table = (0..=255).map(|index| vec![index]).collect();
+                    table.extend([Vec::new(), Vec::new()]);
+                    bits = 9;
+                    previous = None;
+                    continue;
+                }
+                257 => return output,
+                _ => (),
+            }
+            let entry = match (table.get(code as usize), &previous) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(previous)) => [previous.clone(), vec![previous[0]]].concat(),
+                (None, None) => panic!("code {} before any other", code),
+            };
+            if let Some(previous) = previous.filter(|_| table.len() < 4096) {
+                table.push([previous, vec![entry[0]]].concat());
+            }
+            if table.len() == 1 << bits && bits < 12 {
+                bits += 1;
+            }
+            output.extend(&entry);
+            previous = Some(entry);
+        }
+    }
+
+    #[test]
+    fn test_gif() {
+        // Long and varied enough to fill the code table and clear it again
+        let mut state = 1u32;
+        let indices: Vec<u8> = (0..40000)
+            .map(|index| {
+                state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                if index % 7 == 0 {
+                    (state >> 24) as u8
+                } else {
+                    (index / 300) as u8
+                }
+            })
+            .collect();
+        assert_eq!(unlzw(&lzw(&indices)), indices);
+        assert_eq!(unlzw(&lzw(&[5])), [5]);
+
+        // 256 shades of red twice over, and 44 rarer ones with a touch of green
+        let pixels: Vec<u8> = (0..556u32)
+            .flat_map(|value| [value as u8, (value / 512) as u8, 0])
+            .collect();
+        let (colors, indices) = quantize(&pixels);
+        assert_eq!(colors.len(), 256);
+        // Colors that didn't make the table are drawn with the closest that did
+        for (pixel, index) in pixels.chunks(3).zip(&indices) {
+            assert_eq!(colors[*index as usize], [pixel[0], 0, 0]);
+        }
+
+        let image = Image {
+            width: 2,
+            height: 1,
+            pixels: vec![255, 0, 0, 0, 0, 255],
+        };
+        let mut gif = GifWriter::new(Vec::new(), 2, 1, 10).unwrap();
+        gif.write_frame(&image).unwrap();
+        gif.write_frame(&image).unwrap();
+        let gif = gif.finish().unwrap();
+        assert!(gif.starts_with(b"GIF89a\x02\0\x01\0"));
+        assert_eq!(gif.iter().filter(|byte| **byte == 0x2c).count(), 2);
+        assert_eq!(gif.last(), Some(&0x3b));
+    }
+
+    #[test]
+    fn test_zlib() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+        // A run compresses to a literal and a match against itself
+        let compressed = zlib(&[7; 1000]);
+        assert!(compressed.len() < 20);
+        assert!(compressed.ends_with(&adler32(&[7; 1000]).to_be_bytes()));
+    }
+}
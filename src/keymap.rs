@@ -0,0 +1,407 @@
+// Actions and the keys bound to them, and the keystroke files that replay them.
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) enum Action {
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    ZoomIn,
+    ZoomOut,
+    MoreIterations,
+    FewerIterations,
+}
+
+const ACTIONS: [(&str, Action); 8] = [
+    ("pan_up", Action::PanUp),
+    ("pan_down", Action::PanDown),
+    ("pan_left", Action::PanLeft),
+    ("pan_right", Action::PanRight),
+    ("zoom_in", Action::ZoomIn),
+    ("zoom_out", Action::ZoomOut),
+    ("more_iterations", Action::MoreIterations),
+    ("fewer_iterations", Action::FewerIterations),
+];
+
+const NAMED_KEYS: [(&str, crossterm::event::KeyCode); 16] = [
+    ("Up", crossterm::event::KeyCode::Up),
+    ("Down", crossterm::event::KeyCode::Down),
+    ("Left", crossterm::event::KeyCode::Left),
+    ("Right", crossterm::event::KeyCode::Right),
+    ("PageUp", crossterm::event::KeyCode::PageUp),
+    ("PageDown", crossterm::event::KeyCode::PageDown),
+    ("Home", crossterm::event::KeyCode::Home),
+    ("End", crossterm::event::KeyCode::End),
+    ("Space", crossterm::event::KeyCode::Char(' ')),
+    ("Enter", crossterm::event::KeyCode::Enter),
+    ("Esc", crossterm::event::KeyCode::Esc),
+    ("Tab", crossterm::event::KeyCode::Tab),
+    ("BackTab", crossterm::event::KeyCode::BackTab),
+    ("Backspace", crossterm::event::KeyCode::Backspace),
+    ("Delete", crossterm::event::KeyCode::Delete),
+    ("Insert", crossterm::event::KeyCode::Insert),
+];
+
+fn parse_key(name: &str) -> Result<crossterm::event::KeyCode, String> {
+    if let Some((_, code)) = NAMED_KEYS.iter().find(|(key, _)| *key == name) {
+        return Ok(*code);
+    }
+
+    let mut characters = name.chars();
+    match (characters.next(), characters.next()) {
+        (Some(character), None) => Ok(crossterm::event::KeyCode::Char(character)),
+        _ => match name
+            .strip_prefix('F')
+            .and_then(|number| number.parse().ok())
+        {
+            Some(number @ 1..=24) => Ok(crossterm::event::KeyCode::F(number)),
+            _ => Err(format!("unknown key: {}", name)),
+        },
+    }
+}
+
+fn key_name(code: crossterm::event::KeyCode) -> String {
+    match NAMED_KEYS.iter().find(|(_, key)| *key == code) {
+        Some((name, _)) => name.to_string(),
+        None => match code {
+            crossterm::event::KeyCode::Char(character) => character.to_string(),
+            crossterm::event::KeyCode::F(number) => format!("F{}", number),
+            _ => format!("{:?}", code),
+        },
+    }
+}
+
+// A key pressed some time into a run. Recordings have one per line, as the seconds since
+// the start and the key name, with ctrl+ and alt+ in front when they were held
+#[derive(PartialEq, Debug)]
+pub(crate) struct Keystroke {
+    pub(crate) time: std::time::Duration,
+    pub(crate) event: crossterm::event::KeyEvent,
+}
+
+impl Keystroke {
+    fn parse(line: &str) -> Result<Keystroke, String> {
+        let (time, mut name) = line
+            .trim()
+            .split_once(' ')
+            .ok_or("expected the seconds and a key")?;
+        let time = time
+            .parse()
+            .ok()
+            .filter(|time: &f64| time.is_finite() && *time >= 0.0)
+            .ok_or(format!("invalid time: {}", time))?;
+        let mut modifiers = crossterm::event::KeyModifiers::NONE;
+        loop {
+            if let Some(rest) = name.strip_prefix("ctrl+").filter(|rest| !rest.is_empty()) {
+                modifiers |= crossterm::event::KeyModifiers::CONTROL;
+                name = rest;
+            } else if let Some(rest) = name.strip_prefix("alt+").filter(|rest| !rest.is_empty()) {
+                modifiers |= crossterm::event::KeyModifiers::ALT;
+                name = rest;
+            } else {
+                break;
+            }
+        }
+        Ok(Keystroke {
+            time: std::time::Duration::from_secs_f64(time),
+            event: crossterm::event::KeyEvent::new(parse_key(name)?, modifiers),
+        })
+    }
+
+    pub(crate) fn line(&self) -> String {
+        format!(
+            "{:.3} {}",
+            self.time.as_secs_f64(),
+            key_event_name(&self.event)
+        )
+    }
+}
+
+// The key with its modifiers, the way recordings write it
+pub(crate) fn key_event_name(event: &crossterm::event::KeyEvent) -> String {
+    let mut name = String::new();
+    if event
+        .modifiers
+        .contains(crossterm::event::KeyModifiers::CONTROL)
+    {
+        name.push_str("ctrl+");
+    }
+    if event
+        .modifiers
+        .contains(crossterm::event::KeyModifiers::ALT)
+    {
+        name.push_str("alt+");
+    }
+    name.push_str(&key_name(event.code));
+    name
+}
+
+// A line of the --log-file log: the seconds since the viewer started and the event, then its
+// fields as key=value pairs, with values that have spaces in them quoted
+pub(crate) fn log_line(
+    elapsed: std::time::Duration,
+    event: &str,
+    fields: &[(&str, String)],
+) -> String {
+    let mut line = format!("t={:.3} event={}", elapsed.as_secs_f64(), event);
+    for (key, value) in fields {
+        if value.is_empty() || value.contains([' ', '"', '=']) {
+            line.push_str(&format!(" {}={:?}", key, value));
+        } else {
+            line.push_str(&format!(" {}={}", key, value));
+        }
+    }
+    line
+}
+
+// Blank lines and lines starting with # are skipped, so recordings can be edited by hand
+pub(crate) fn parse_keystrokes(
+    text: &str,
+) -> Result<std::collections::VecDeque<Keystroke>, String> {
+    let mut keystrokes = std::collections::VecDeque::new();
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        let keystroke =
+            Keystroke::parse(line).map_err(|error| format!("line {}: {}", number + 1, error))?;
+        if keystrokes
+            .back()
+            .is_some_and(|last: &Keystroke| last.time > keystroke.time)
+        {
+            return Err(format!("line {}: keys must be in time order", number + 1));
+        }
+        keystrokes.push_back(keystroke);
+    }
+    Ok(keystrokes)
+}
+
+#[derive(Clone)]
+pub(crate) struct Keymap {
+    bindings: Vec<(crossterm::event::KeyCode, Action)>,
+}
+
+impl Keymap {
+    pub(crate) fn preset(name: &str) -> Option<Keymap> {
+        let bindings: &[(Action, &[&str])] = match name {
+            "default" => &[
+                (Action::PanUp, &["w"]),
+                (Action::PanDown, &["s"]),
+                (Action::PanLeft, &["a"]),
+                (Action::PanRight, &["d"]),
+                (Action::ZoomIn, &["Up"]),
+                (Action::ZoomOut, &["Down"]),
+                (Action::MoreIterations, &["="]),
+                (Action::FewerIterations, &["-"]),
+            ],
+            "arrows" => &[
+                (Action::PanUp, &["Up", "w"]),
+                (Action::PanDown, &["Down", "s"]),
+                (Action::PanLeft, &["Left", "a"]),
+                (Action::PanRight, &["Right", "d"]),
+                (Action::ZoomIn, &["+", "PageUp"]),
+                (Action::ZoomOut, &["-", "PageDown"]),
+                (Action::MoreIterations, &["*"]),
+                (Action::FewerIterations, &["/"]),
+            ],
+            "vim" => &[
+                (Action::PanUp, &["k"]),
+                (Action::PanDown, &["j"]),
+                (Action::PanLeft, &["h"]),
+                (Action::PanRight, &["l"]),
+                (Action::ZoomIn, &["+", "Up"]),
+                (Action::ZoomOut, &["-", "Down"]),
+                (Action::MoreIterations, &["*"]),
+                (Action::FewerIterations, &["/"]),
+            ],
+            _ => return None,
+        };
+
+        let mut keymap = Keymap {
+            bindings: Vec::new(),
+        };
+        for (action, keys) in bindings {
+            let codes = keys.iter().map(|key| parse_key(key).unwrap()).collect();
+            keymap.bind(*action, codes);
+        }
+        Some(keymap)
+    }
+
+    pub(crate) fn parse(text: &str) -> Result<Keymap, String> {
+        let table = text
+            .parse::<toml::Table>()
+            .map_err(|error| error.message().to_string())?;
+        Keymap::from_table(&table)
+    }
+
+    pub(crate) fn from_table(table: &toml::Table) -> Result<Keymap, String> {
+        let mut keymap = match table.get("preset") {
+            None => Keymap::preset("default").unwrap(),
+            Some(toml::Value::String(name)) => {
+                Keymap::preset(name).ok_or(format!("unknown preset: {}", name))?
+            }
+            Some(_) => return Err("preset must be a string".to_string()),
+        };
+
+        let keys = match table.get("keys") {
+            None => return Ok(keymap),
+            Some(toml::Value::Table(keys)) => keys,
+            Some(_) => return Err("keys must be a table".to_string()),
+        };
+        for (name, value) in keys {
+            let action = ACTIONS
+                .iter()
+                .find(|(action_name, _)| action_name == name)
+                .map(|(_, action)| *action)
+                .ok_or(format!("unknown action: {}", name))?;
+            let codes = match value {
+                toml::Value::String(key) => vec![parse_key(key)?],
+                toml::Value::Array(values) => values
+                    .iter()
+                    .map(|value| match value {
+                        toml::Value::String(key) => parse_key(key),
+                        _ => Err(format!("{}: keys must be strings", name)),
+                    })
+                    .collect::<Result<Vec<_>, String>>()?,
+                _ => return Err(format!("{}: keys must be strings", name)),
+            };
+            keymap.bind(action, codes);
+        }
+
+        Ok(keymap)
+    }
+
+    fn bind(&mut self, action: Action, codes: Vec<crossterm::event::KeyCode>) {
+        // A key only ever triggers one action, and rebinding an action replaces its keys
+        self.bindings
+            .retain(|(code, bound)| *bound != action && !codes.contains(code));
+        self.bindings
+            .extend(codes.into_iter().map(|code| (code, action)));
+    }
+
+    pub(crate) fn action(&self, code: crossterm::event::KeyCode) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == code)
+            .map(|(_, action)| *action)
+    }
+
+    pub(crate) fn keys(&self, action: Action) -> String {
+        let names: Vec<String> = self
+            .bindings
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(code, _)| key_name(*code))
+            .collect();
+        names.join(",")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(parse_key("w"), Ok(crossterm::event::KeyCode::Char('w')));
+        assert_eq!(parse_key("PageUp"), Ok(crossterm::event::KeyCode::PageUp));
+        assert_eq!(parse_key("Space"), Ok(crossterm::event::KeyCode::Char(' ')));
+        assert!(parse_key("").is_err());
+        assert!(parse_key("up").is_err());
+        assert_eq!(key_name(crossterm::event::KeyCode::Left), "Left");
+        assert_eq!(key_name(crossterm::event::KeyCode::Char('+')), "+");
+        assert_eq!(parse_key("F3"), Ok(crossterm::event::KeyCode::F(3)));
+        assert!(parse_key("F0").is_err());
+        assert_eq!(key_name(crossterm::event::KeyCode::F(12)), "F12");
+        assert_eq!(key_name(crossterm::event::KeyCode::Esc), "Esc");
+    }
+
+    #[test]
+    fn test_keystrokes() {
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+        let keystrokes =
+            parse_keystrokes("# a demo\n0 z\n\n0.5 ctrl+alt+c\n 1.25 Space\n2 +\n").unwrap();
+        assert_eq!(
+            keystrokes,
+            [
+                (0.0, KeyCode::Char('z'), KeyModifiers::NONE),
+                (
+                    0.5,
+                    KeyCode::Char('c'),
+                    KeyModifiers::CONTROL | KeyModifiers::ALT
+                ),
+                (1.25, KeyCode::Char(' '), KeyModifiers::NONE),
+                (2.0, KeyCode::Char('+'), KeyModifiers::NONE),
+            ]
+            .map(|(time, code, modifiers)| Keystroke {
+                time: std::time::Duration::from_secs_f64(time),
+                event: KeyEvent::new(code, modifiers),
+            })
+        );
+        // Recorded lines read back as the same keys
+        for keystroke in &keystrokes {
+            assert_eq!(&Keystroke::parse(&keystroke.line()).unwrap(), keystroke);
+        }
+        assert_eq!(keystrokes[1].line(), "0.500 ctrl+alt+c");
+        assert_eq!(
+            Keystroke::parse("1 ctrl++").unwrap().event,
+            KeyEvent::new(KeyCode::Char('+'), KeyModifiers::CONTROL)
+        );
+
+        assert_eq!(
+            parse_keystrokes("1 z\n0.5 x").err(),
+            Some("line 2: keys must be in time order".to_string())
+        );
+        assert_eq!(
+            parse_keystrokes("z").err(),
+            Some("line 1: expected the seconds and a key".to_string())
+        );
+        assert_eq!(
+            parse_keystrokes("-1 z").err(),
+            Some("line 1: invalid time: -1".to_string())
+        );
+        assert_eq!(
+            parse_keystrokes("1 Enterr").err(),
+            Some("line 1: unknown key: Enterr".to_string())
+        );
+    }
+
+    #[test]
+    fn test_keymap() {
+        let keymap = Keymap::preset("default").unwrap();
+        assert_eq!(
+            keymap.action(crossterm::event::KeyCode::Char('w')),
+            Some(Action::PanUp)
+        );
+        assert_eq!(
+            keymap.action(crossterm::event::KeyCode::Up),
+            Some(Action::ZoomIn)
+        );
+        assert_eq!(keymap.action(crossterm::event::KeyCode::Char('q')), None);
+
+        let keymap = Keymap::parse("preset = \"vim\"\n").unwrap();
+        assert_eq!(
+            keymap.action(crossterm::event::KeyCode::Char('h')),
+            Some(Action::PanLeft)
+        );
+        assert_eq!(keymap.action(crossterm::event::KeyCode::Char('w')), None);
+        assert_eq!(keymap.keys(Action::ZoomIn), "+,Up");
+
+        let keymap = Keymap::parse(
+            "preset = \"arrows\"\n[keys]\nzoom_in = \"Up\"\nzoom_out = [\"z\", \"Down\"]\n",
+        )
+        .unwrap();
+        assert_eq!(
+            keymap.action(crossterm::event::KeyCode::Up),
+            Some(Action::ZoomIn)
+        );
+        assert_eq!(keymap.action(crossterm::event::KeyCode::Char('+')), None);
+        assert_eq!(keymap.keys(Action::PanUp), "w");
+        assert_eq!(keymap.keys(Action::ZoomOut), "z,Down");
+
+        assert!(Keymap::parse("preset = \"emacs\"").is_err());
+        assert!(Keymap::parse("[keys]\nfly = \"f\"").is_err());
+        assert!(Keymap::parse("[keys]\npan_up = 1").is_err());
+        assert!(Keymap::parse("[keys").is_err());
+    }
+}
@@ -0,0 +1,5529 @@
+//! Renders the Mandelbrot set and related escape-time fractals as 24-bit colored block
+//! characters for a terminal.
+//!
+//! Every terminal cell is split into 2x2 subpixels and drawn as a quadrant glyph with a
+//! foreground and background color. [`render_buffer`] calculates a view into a
+//! [`FrameBuffer`] of those cells, which can be turned into escape-sequence text with
+//! [`FrameBuffer::text`]. [`Renderer`] does the same on a worker thread. Coordinates on the
+//! plane are kept as [`Fixed`] point numbers so views can go deeper than `f64` allows.
+//!
+//! With the default `tui` feature the interactive viewer is here as well: `app::run` takes
+//! the same arguments as the `mandelbrot_set` binary, which only passes on its command line.
+//!
+//! ```
+//! # #![cfg_attr(feature = "nightly-simd", feature(portable_simd))]
+//! use mandelbrot_set::*;
+//!
+//! let position = Position::new(-1.0, 1.0, -2.0, 1.0);
+//! let mut cache = TileCache::new(0);
+//! let (buffer, _) = render_buffer(
+//!     80,
+//!     24,
+//!     &position,
+//!     u32x1::splat(100),
+//!     &DEFAULT_PARAMETERS,
+//!     DEFAULT_TILE_SIZE,
+//!     None,
+//!     &mut cache,
+//! );
+//! assert_eq!(buffer.cells().len(), 80 * 24);
+//! print!("{}", buffer.text());
+//! ```
+#![cfg_attr(feature = "nightly-simd", feature(portable_simd))]
+#![warn(missing_docs)]
+#[cfg(feature = "nightly-simd")]
+pub use std::simd::{f64x1, u32x1};
+#[cfg(feature = "nightly-simd")]
+use std::simd::prelude::{Select, SimdFloat, SimdPartialOrd};
+#[cfg(feature = "nightly-simd")]
+use std::simd::{Mask, Simd};
+
+#[cfg(not(feature = "nightly-simd"))]
+mod simd;
+#[cfg(not(feature = "nightly-simd"))]
+pub use simd::{f64x1, u32x1};
+#[cfg(not(feature = "nightly-simd"))]
+use simd::{Mask, Select, Simd, SimdFloat, SimdPartialOrd};
+
+#[cfg(feature = "tui")]
+pub mod app;
+#[cfg(feature = "tui")]
+mod bench;
+#[cfg(feature = "tui")]
+mod cli;
+#[cfg(feature = "tui")]
+mod commands;
+#[cfg(feature = "tui")]
+mod config;
+mod double_double;
+#[cfg(feature = "tui")]
+mod explore;
+#[cfg(feature = "tui")]
+mod export;
+pub mod image;
+#[cfg(feature = "tui")]
+mod keymap;
+#[cfg(feature = "tui")]
+mod menu;
+#[cfg(feature = "tui")]
+mod overlay;
+pub mod plugin;
+pub mod script;
+#[cfg(feature = "tui")]
+mod session;
+#[cfg(feature = "tui")]
+mod telnet;
+#[cfg(feature = "tui")]
+mod terminal;
+#[cfg(feature = "tui")]
+mod tile_server;
+
+use double_double::DoubleDouble;
+use rayon::prelude::*;
+use std::sync::{Arc, RwLock};
+
+/// Display names of the built-in fractals. [`fractal_names`] adds any registered at run time.
+pub const FRACTAL_NAMES: [&str; 4] =
+    ["Mandelbrot Set", "Sinking Ship", "Julia Set", "Newton Fractal"];
+
+const QUADRANTS: [&str; 4] = ["▖", "▘", "▝", "▗"];
+const TWO_QUADRANTS: [&str; 6] = ["▚", "▞", "▄", "▀", "▌", "▐"];
+const THREE_QUADRANTS: [&str; 4] = ["▙", "▟", "▛", "▜"];
+const FULL_BLOCK: [&str; 2] = ["█", " "];
+
+/// The index of the Julia set in [`FRACTAL_NAMES`].
+pub const JULIA_INDEX: usize = 2;
+
+/// The index of the Newton fractal in [`FRACTAL_NAMES`], which counts the steps Newton's method
+/// takes to find a root of z^3 - 1 rather than the steps to escape.
+pub const NEWTON_INDEX: usize = 3;
+
+/// Where each of [`FRACTAL_NAMES`] is best seen from: a center, and a zoom relative to the
+/// default view of the Mandelbrot set. The Sinking Ship's own ship is far out on its left.
+pub const FRACTAL_VIEWS: [((f64, f64), f64); 4] = [
+    ((-0.5, 0.0), 1.0),
+    ((-1.76, -0.03), 12.0),
+    ((0.0, 0.0), 0.75),
+    ((0.0, 0.0), 0.75),
+];
+
+/// A fractal added at run time, such as a scripted formula or a plugin.
+pub trait Kernel: Send + Sync {
+    /// The iteration at which the orbit of the point `(x, y)` escapes, or `max_iterations`
+    /// if it doesn't.
+    fn iterate(&self, x: f64, y: f64, max_iterations: u32, parameters: &FractalParameters) -> u32;
+}
+
+/// A palette added at run time, mapping an iteration count and the limit to an RGB color.
+pub type CustomPalette = Arc<dyn Fn(u32, u32) -> [u8; 3] + Send + Sync>;
+
+// Fractals and palettes added after the built-in ones. Entries are never removed, only
+// replaced, so their indices stay valid for the rest of the run.
+static CUSTOM_FRACTALS: RwLock<Vec<(&str, Arc<dyn Kernel>)>> = RwLock::new(Vec::new());
+static CUSTOM_PALETTES: RwLock<Vec<(&str, CustomPalette)>> = RwLock::new(Vec::new());
+
+/// Adds a fractal after the built-in ones, returning its [`FractalParameters::fractal_index`].
+/// A fractal added again under the same name replaces the earlier one and keeps its index.
+///
+/// Added fractals are always calculated in `f64`, one point at a time.
+pub fn register_fractal(name: &str, kernel: Arc<dyn Kernel>) -> usize {
+    let mut fractals = CUSTOM_FRACTALS.write().unwrap();
+    FRACTAL_NAMES.len() + register(&mut fractals, name, kernel)
+}
+
+fn register<T>(entries: &mut Vec<(&'static str, T)>, name: &str, entry: T) -> usize {
+    if let Some(index) = entries.iter().position(|(existing, _)| *existing == name) {
+        entries[index].1 = entry;
+        return index;
+    }
+    // Names live as long as the registry, which is the rest of the run
+    entries.push((Box::leak(name.into()), entry));
+    entries.len() - 1
+}
+
+/// The names of the built-in fractals followed by the added ones, indexed by
+/// [`FractalParameters::fractal_index`].
+pub fn fractal_names() -> Vec<&'static str> {
+    let fractals = CUSTOM_FRACTALS.read().unwrap();
+    FRACTAL_NAMES
+        .iter()
+        .copied()
+        .chain(fractals.iter().map(|(name, _)| *name))
+        .collect()
+}
+
+fn custom_fractal(fractal_index: usize) -> Option<Arc<dyn Kernel>> {
+    let fractals = CUSTOM_FRACTALS.read().unwrap();
+    let (_, kernel) = fractals.get(fractal_index.checked_sub(FRACTAL_NAMES.len())?)?;
+    Some(kernel.clone())
+}
+
+/// Adds a palette after the built-in ones, returning its
+/// [`FractalParameters::palette_index`]. A palette added again under the same name replaces
+/// the earlier one and keeps its index.
+pub fn register_palette(name: &str, palette: CustomPalette) -> usize {
+    let mut palettes = CUSTOM_PALETTES.write().unwrap();
+    PALETTES.len() + register(&mut palettes, name, palette)
+}
+
+/// The names of the built-in palettes followed by the added ones, indexed by
+/// [`FractalParameters::palette_index`].
+pub fn palette_names() -> Vec<&'static str> {
+    let palettes = CUSTOM_PALETTES.read().unwrap();
+    PALETTE_NAMES
+        .iter()
+        .copied()
+        .chain(palettes.iter().map(|(name, _)| *name))
+        .collect()
+}
+
+fn custom_palette(palette_index: usize) -> Option<CustomPalette> {
+    let palettes = CUSTOM_PALETTES.read().unwrap();
+    let (_, palette) = palettes.get(palette_index.checked_sub(PALETTES.len())?)?;
+    Some(palette.clone())
+}
+const DEFAULT_JULIA_CONSTANT: (f64, f64) = (0.156, 0.8);
+
+/// Everything apart from the view and the iteration limit that decides how a frame looks.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct FractalParameters {
+    /// Index into [`fractal_names`].
+    pub fractal_index: usize,
+    /// The constant `c` the Julia set is drawn for.
+    pub julia_constant: (f64, f64),
+    /// The point `z` starts from in the Mandelbrot set, where the pixel is `c`. The Julia set
+    /// is the same iteration the other way round, starting `z` at the pixel with
+    /// `julia_constant` for `c`.
+    pub initial_z: (f64, f64),
+    /// Index into [`palette_names`].
+    pub palette_index: usize,
+    /// Index into [`COLORINGS`].
+    pub coloring_index: usize,
+    /// How far round the palette colors are rotated, as a fraction of the iteration limit.
+    /// Only the fractional part counts.
+    pub palette_phase: f64,
+    /// Orbits count as escaped once they are this far from the origin.
+    pub escape_radius: f64,
+    /// Samples taken along each axis of a subpixel.
+    pub supersampling: u32,
+    /// Index into [`BACKEND_NAMES`].
+    pub backend_index: usize,
+    /// Terms of the series that lets perturbed pixels skip their first iterations, up to
+    /// [`MAX_SERIES_ORDER`], with 0 turning the skip off.
+    pub series_order: usize,
+    /// How small the highest series term must stay next to the linear one to keep skipping.
+    /// Larger tolerances skip more iterations but lose accuracy.
+    pub series_tolerance: f64,
+    /// Whether each tile's iteration limit is lowered to a few times the slowest escape a
+    /// coarse sample of it finds, when none of the sample is inside. Cells reaching that
+    /// limit count as inside, which is faster where the cycle checks miss the inside of the
+    /// set, as they do for perturbation, but loses the odd filament escaping past it.
+    pub tile_limits: bool,
+}
+
+/// How deep views are calculated: perturbation only once f64 runs out of precision, always
+/// in plain f64, always by perturbation, or in double-double arithmetic with twice f64's
+/// precision once f64 runs out, and by perturbation only once that does too.
+pub const BACKEND_NAMES: [&str; 4] = ["Auto", "Double", "Perturbation", "Double-double"];
+
+/// The most terms the series approximation can have.
+pub const MAX_SERIES_ORDER: usize = 16;
+
+/// The Mandelbrot set with the first palette and coloring, as the viewer starts up.
+pub const DEFAULT_PARAMETERS: FractalParameters = FractalParameters {
+    fractal_index: 0,
+    julia_constant: DEFAULT_JULIA_CONSTANT,
+    initial_z: (0.0, 0.0),
+    palette_index: 0,
+    coloring_index: 0,
+    palette_phase: 0.0,
+    escape_radius: 2.0,
+    supersampling: 1,
+    backend_index: 0,
+    series_order: 3,
+    series_tolerance: 1e-6,
+    tile_limits: false,
+};
+
+const PERIODICITY_TOLERANCE: f64 = 1e-13;
+
+// Brent's cycle detection: compare against an orbit point saved at doubling intervals
+struct Periodicity<const N: usize> {
+    saved: (Simd<f64, N>, Simd<f64, N>),
+    step: u32,
+    interval: u32,
+}
+
+impl<const N: usize> Periodicity<N> {
+    fn new() -> Periodicity<N> {
+        Periodicity {
+            saved: (Simd::splat(f64::NAN), Simd::splat(f64::NAN)),
+            step: 0,
+            interval: 1,
+        }
+    }
+
+    #[inline]
+    fn is_cycle(&mut self, x: Simd<f64, N>, y: Simd<f64, N>) -> Mask<i64, N> {
+        let tolerance = Simd::splat(PERIODICITY_TOLERANCE);
+        let cycled = (x - self.saved.0).abs().simd_lt(tolerance)
+            & (y - self.saved.1).abs().simd_lt(tolerance);
+
+        self.step += 1;
+        if self.step == self.interval {
+            self.saved = (x, y);
+            self.step = 0;
+            self.interval *= 2;
+        }
+        cycled
+    }
+}
+
+// Where orbits start from, or carry on from: `z` and the iteration it was reached at
+type OrbitStart<const N: usize> = (Simd<f64, N>, Simd<f64, N>, Simd<u32, N>);
+
+// Returns the iteration counts with where each orbit stopped, which is NaN for the ones found
+// to stay inside
+#[inline]
+fn escape_time<const N: usize>(
+    (mut x, mut y, mut iteration): OrbitStart<N>,
+    mut inside: Mask<i64, N>,
+    max_iterations: Simd<u32, N>,
+    parameters: &FractalParameters,
+    step: impl Fn(Simd<f64, N>, Simd<f64, N>) -> (Simd<f64, N>, Simd<f64, N>),
+) -> (Simd<u32, N>, (Simd<f64, N>, Simd<f64, N>)) {
+    let escape_radius = Simd::splat(parameters.escape_radius);
+    let bailout = escape_radius * escape_radius;
+    let mut periodicity = Periodicity::new();
+
+    // Every lane steps in lockstep, but only the ones still orbiting count iterations
+    let mut active =
+        !inside & (x * x + y * y).simd_le(bailout) & iteration.simd_lt(max_iterations).cast();
+    while active.any() {
+        (x, y) = step(x, y);
+        iteration += active.cast::<i32>().select(Simd::splat(1), Simd::splat(0));
+
+        let cycled = periodicity.is_cycle(x, y) & active;
+        inside |= cycled;
+        active &=
+            !cycled & (x * x + y * y).simd_le(bailout) & iteration.simd_lt(max_iterations).cast();
+    }
+
+    let nan = Simd::splat(f64::NAN);
+    (
+        inside.cast::<i32>().select(max_iterations, iteration),
+        (inside.select(nan, x), inside.select(nan, y)),
+    )
+}
+
+#[inline]
+fn mandelbrot<const N: usize>(
+    scaled_x: Simd<f64, N>,
+    scaled_y: Simd<f64, N>,
+    start: Option<OrbitStart<N>>,
+    max_iterations: Simd<u32, N>,
+    parameters: &FractalParameters,
+) -> (Simd<u32, N>, (Simd<f64, N>, Simd<f64, N>)) {
+    // Points inside the main cardioid or the period-2 bulb never escape, when orbits start
+    // from 0
+    let quarter_x = scaled_x - Simd::splat(0.25);
+    let q = quarter_x * quarter_x + scaled_y * scaled_y;
+    let bulb_x = scaled_x + Simd::splat(1.0);
+    let inside = if parameters.initial_z == (0.0, 0.0) {
+        (q * (q + quarter_x)).simd_le(Simd::splat(0.25) * scaled_y * scaled_y)
+            | (bulb_x * bulb_x + scaled_y * scaled_y).simd_le(Simd::splat(0.0625))
+    } else {
+        Mask::splat(false)
+    };
+
+    let (x, y) = parameters.initial_z;
+    let start = start.unwrap_or((Simd::splat(x), Simd::splat(y), Simd::splat(0)));
+    escape_time(start, inside, max_iterations, parameters, |x, y| {
+        (
+            x * x - y * y + scaled_x,
+            Simd::splat(2.0) * x * y + scaled_y,
+        )
+    })
+}
+
+#[inline]
+fn sinking_ship<const N: usize>(
+    scaled_x: Simd<f64, N>,
+    scaled_y: Simd<f64, N>,
+    start: Option<OrbitStart<N>>,
+    max_iterations: Simd<u32, N>,
+    parameters: &FractalParameters,
+) -> (Simd<u32, N>, (Simd<f64, N>, Simd<f64, N>)) {
+    let inside = Mask::splat(false);
+    escape_time(
+        start.unwrap_or((scaled_x, scaled_y, Simd::splat(0))),
+        inside,
+        max_iterations,
+        parameters,
+        |zx, zy| {
+            (
+                zx * zx - zy * zy + scaled_x,
+                (Simd::splat(2.0) * zx * zy).abs() + scaled_y,
+            )
+        },
+    )
+}
+
+#[inline]
+fn julia<const N: usize>(
+    scaled_x: Simd<f64, N>,
+    scaled_y: Simd<f64, N>,
+    start: Option<OrbitStart<N>>,
+    max_iterations: Simd<u32, N>,
+    parameters: &FractalParameters,
+) -> (Simd<u32, N>, (Simd<f64, N>, Simd<f64, N>)) {
+    let cx = Simd::splat(parameters.julia_constant.0);
+    let cy = Simd::splat(parameters.julia_constant.1);
+
+    let inside = Mask::splat(false);
+    escape_time(
+        start.unwrap_or((scaled_x, scaled_y, Simd::splat(0))),
+        inside,
+        max_iterations,
+        parameters,
+        |zx, zy| (zx * zx - zy * zy + cx, Simd::splat(2.0) * zx * zy + cy),
+    )
+}
+
+// The roots of z^3 - 1, which Newton's method converges to from almost every point
+const NEWTON_ROOTS: [(f64, f64); 3] = [
+    (1.0, 0.0),
+    (-0.5, 0.866_025_403_784_438_6),
+    (-0.5, -0.866_025_403_784_438_6),
+];
+// Orbits closer than this to a root, squared, have converged to it
+const NEWTON_TOLERANCE: f64 = 1e-12;
+
+// Returns the steps each point takes to reach a root, with where its orbit stopped: at the
+// root, or where it was at the limit. Points that never reach one count as inside.
+#[inline]
+fn newton<const N: usize>(
+    (mut x, mut y, mut iteration): OrbitStart<N>,
+    max_iterations: Simd<u32, N>,
+) -> (Simd<u32, N>, (Simd<f64, N>, Simd<f64, N>)) {
+    let tolerance = Simd::splat(NEWTON_TOLERANCE);
+    let converged = |x: Simd<f64, N>, y: Simd<f64, N>| {
+        NEWTON_ROOTS
+            .iter()
+            .fold(Mask::splat(false), |converged, &(root_x, root_y)| {
+                let (dx, dy) = (x - Simd::splat(root_x), y - Simd::splat(root_y));
+                converged | (dx * dx + dy * dy).simd_lt(tolerance)
+            })
+    };
+
+    // Orbits carried on from NaN have already stopped, and NaN isn't even equal to itself
+    let mut active =
+        x.simd_le(x) & !converged(x, y) & iteration.simd_lt(max_iterations).cast();
+    while active.any() {
+        // z - (z^3 - 1) / 3z^2 = 2z / 3 + 1 / 3z^2
+        let (square_x, square_y) = (x * x - y * y, Simd::splat(2.0) * x * y);
+        let scale = Simd::splat(3.0) * (square_x * square_x + square_y * square_y);
+        let two_thirds = Simd::splat(2.0 / 3.0);
+        // Orbits that have stopped stay where they are, so they can be carried on from there
+        x = active.select(two_thirds * x + square_x / scale, x);
+        y = active.select(two_thirds * y - square_y / scale, y);
+        iteration += active.cast::<i32>().select(Simd::splat(1), Simd::splat(0));
+        active &= !converged(x, y) & iteration.simd_lt(max_iterations).cast();
+    }
+    (iteration, (x, y))
+}
+
+// Which of NEWTON_ROOTS an orbit stopped at, or NO_ROOT if it didn't stop at any
+fn root_index((x, y): (f64, f64)) -> u32 {
+    NEWTON_ROOTS
+        .iter()
+        .position(|(root_x, root_y)| {
+            (x - root_x) * (x - root_x) + (y - root_y) * (y - root_y) < NEWTON_TOLERANCE
+        })
+        .map_or(NO_ROOT, |index| index as u32)
+}
+
+#[inline]
+fn iterate<const N: usize>(
+    scaled_x: Simd<f64, N>,
+    scaled_y: Simd<f64, N>,
+    max_iterations: Simd<u32, N>,
+    parameters: &FractalParameters,
+) -> Simd<u32, N> {
+    iterate_from(scaled_x, scaled_y, None, max_iterations, parameters).0
+}
+
+// Iterates from `start` if given, returning where the orbits stopped too. Orbits of custom
+// fractals can't be carried on, so they all stop at NaN.
+#[inline]
+fn iterate_from<const N: usize>(
+    scaled_x: Simd<f64, N>,
+    scaled_y: Simd<f64, N>,
+    start: Option<OrbitStart<N>>,
+    max_iterations: Simd<u32, N>,
+    parameters: &FractalParameters,
+) -> (Simd<u32, N>, (Simd<f64, N>, Simd<f64, N>)) {
+    // Direct calls rather than a table of function pointers, so the kernels can be
+    // inlined into (and compiled for) the wide SIMD entry point below
+    match parameters.fractal_index {
+        0 => mandelbrot(scaled_x, scaled_y, start, max_iterations, parameters),
+        1 => sinking_ship(scaled_x, scaled_y, start, max_iterations, parameters),
+        JULIA_INDEX => julia(scaled_x, scaled_y, start, max_iterations, parameters),
+        NEWTON_INDEX => newton(
+            start.unwrap_or((scaled_x, scaled_y, Simd::splat(0))),
+            max_iterations,
+        ),
+        index => match custom_fractal(index) {
+            Some(kernel) => (
+                Simd::from_array(std::array::from_fn(|lane| {
+                    kernel.iterate(
+                        scaled_x[lane],
+                        scaled_y[lane],
+                        max_iterations[lane],
+                        parameters,
+                    )
+                })),
+                (Simd::splat(f64::NAN), Simd::splat(f64::NAN)),
+            ),
+            None => julia(scaled_x, scaled_y, start, max_iterations, parameters),
+        },
+    }
+}
+
+/// The iteration at which the orbit of a point escapes, or `max_iterations` if it doesn't.
+pub fn iterate_point(x: f64, y: f64, max_iterations: u32x1, parameters: &FractalParameters) -> u32x1 {
+    let iteration = iterate::<4>(
+        Simd::splat(x),
+        Simd::splat(y),
+        Simd::splat(max_iterations[0]),
+        parameters,
+    );
+    u32x1::splat(iteration[0])
+}
+
+/// The period of the cycle the orbit of a point in the Mandelbrot or Julia set settles into, or
+/// `None` if the point escapes, its orbit doesn't settle within `max_iterations`, or the
+/// fractal is another one.
+pub fn orbit_period(
+    x: f64,
+    y: f64,
+    max_iterations: u32x1,
+    parameters: &FractalParameters,
+) -> Option<u32> {
+    let (start, c) = match parameters.fractal_index {
+        0 => (parameters.initial_z, (x, y)),
+        JULIA_INDEX => ((x, y), parameters.julia_constant),
+        _ => return None,
+    };
+    let step = |(zx, zy): (f64, f64)| (zx * zx - zy * zy + c.0, 2.0 * zx * zy + c.1);
+    let bailout = parameters.escape_radius * parameters.escape_radius;
+    // The return is looked for less strictly than the cycle, so rounding can't hide it
+    let tolerance = PERIODICITY_TOLERANCE * 1000.0;
+
+    let mut periodicity = Periodicity::<1>::new();
+    let mut z = start;
+    for _ in 0..max_iterations[0] {
+        z = step(z);
+        if z.0 * z.0 + z.1 * z.1 > bailout {
+            return None;
+        }
+        if periodicity.is_cycle(Simd::splat(z.0), Simd::splat(z.1)).any() {
+            // The orbit has reached its cycle, so count the steps back round to this point
+            let mut point = z;
+            for period in 1..=max_iterations[0] {
+                point = step(point);
+                if (point.0 - z.0).abs() < tolerance && (point.1 - z.1).abs() < tolerance {
+                    return Some(period);
+                }
+            }
+            return None;
+        }
+    }
+    None
+}
+
+// Orbits are followed well past the escape radius for the distance estimate to settle
+const DISTANCE_BAILOUT: f64 = 1e10;
+
+/// An estimate of the distance from a point outside the Mandelbrot or Julia set to the set,
+/// with the unit direction the set lies in, or `None` if the point doesn't escape within
+/// `max_iterations` or the fractal is another one. The set is at least a quarter of the
+/// estimate away.
+pub fn distance_estimate(
+    x: f64,
+    y: f64,
+    max_iterations: u32x1,
+    parameters: &FractalParameters,
+) -> Option<(f64, (f64, f64))> {
+    // The derivative is by c for the Mandelbrot set and by the starting point for Julia sets
+    let (mut z, c, mut dz, added) = match parameters.fractal_index {
+        0 => (parameters.initial_z, (x, y), (0.0, 0.0), 1.0),
+        JULIA_INDEX => ((x, y), parameters.julia_constant, (1.0, 0.0), 0.0),
+        _ => return None,
+    };
+    for _ in 0..max_iterations[0] {
+        dz = (
+            2.0 * (z.0 * dz.0 - z.1 * dz.1) + added,
+            2.0 * (z.0 * dz.1 + z.1 * dz.0),
+        );
+        z = (z.0 * z.0 - z.1 * z.1 + c.0, 2.0 * z.0 * z.1 + c.1);
+        let norm = z.0 * z.0 + z.1 * z.1;
+        if norm > DISTANCE_BAILOUT {
+            // 2 |z| ln |z| / |dz|
+            let distance = norm.sqrt() * norm.ln() / (dz.0 * dz.0 + dz.1 * dz.1).sqrt();
+            // The potential rises fastest along conj(dz / z), away from the set
+            let rise = (
+                (dz.0 * z.0 + dz.1 * z.1) / norm,
+                -(dz.1 * z.0 - dz.0 * z.1) / norm,
+            );
+            let length = rise.0.hypot(rise.1);
+            return Some((distance, (-rise.0 / length, -rise.1 / length)));
+        }
+    }
+    None
+}
+
+/// A binary fixed-point number, value / 2^bits, for coordinates and orbits that need more
+/// than f64 precision.
+#[derive(Clone, Debug)]
+pub struct Fixed {
+    value: num_bigint::BigInt,
+    bits: u32,
+}
+
+impl Fixed {
+    fn from_f64(number: f64, bits: u32) -> Fixed {
+        // A finite f64 is exactly a 53-bit integer times a power of two
+        let raw = number.to_bits();
+        let exponent_bits = ((raw >> 52) & 0x7ff) as i64;
+        let fraction = raw & ((1 << 52) - 1);
+        let (mantissa, exponent) = if exponent_bits == 0 {
+            (fraction, -1074)
+        } else {
+            (fraction | 1 << 52, exponent_bits - 1075)
+        };
+
+        let shift = exponent + bits as i64;
+        let magnitude = num_bigint::BigInt::from(mantissa);
+        let value = if shift >= 0 {
+            magnitude << shift
+        } else {
+            magnitude >> -shift
+        };
+        Fixed {
+            value: if number < 0.0 { -value } else { value },
+            bits,
+        }
+    }
+
+    /// The nearest `f64`, which loses the precision of deep coordinates.
+    pub fn to_f64(&self) -> f64 {
+        // Keep the top 64 bits of the magnitude and scale them back down
+        let magnitude = self.value.magnitude();
+        let shift = magnitude.bits().saturating_sub(64);
+        let top = (magnitude >> shift)
+            .to_u64_digits()
+            .first()
+            .copied()
+            .unwrap_or(0);
+        let number = top as f64 * 2f64.powi(shift as i32 - self.bits as i32);
+        if self.value.sign() == num_bigint::Sign::Minus {
+            -number
+        } else {
+            number
+        }
+    }
+
+    // The nearest double-double, from the nearest f64 and what that leaves out
+    fn to_double_double(&self) -> DoubleDouble {
+        let hi = self.to_f64();
+        DoubleDouble::from(hi) + DoubleDouble::from((self - hi).to_f64())
+    }
+
+    fn aligned(&self, bits: u32) -> num_bigint::BigInt {
+        &self.value << (bits - self.bits)
+    }
+
+    fn with_bits(&self, bits: u32) -> Fixed {
+        let value = if bits >= self.bits {
+            self.aligned(bits)
+        } else {
+            &self.value >> (self.bits - bits)
+        };
+        Fixed { value, bits }
+    }
+
+    /// Parses a decimal number such as `-0.75` or `1.5e-20` without going through `f64`.
+    pub fn parse(text: &str) -> Result<Fixed, String> {
+        let invalid = || format!("invalid number: {}", text);
+        let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (
+                mantissa,
+                exponent
+                    .parse::<i64>()
+                    .ok()
+                    .filter(|exponent| exponent.abs() <= MAX_DECIMAL_EXPONENT)
+                    .ok_or_else(invalid)?,
+            ),
+            None => (text, 0),
+        };
+        let (negative, mantissa) = match mantissa.strip_prefix('-') {
+            Some(mantissa) => (true, mantissa),
+            None => (false, mantissa.strip_prefix('+').unwrap_or(mantissa)),
+        };
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let digits = format!("{}{}", whole, fraction);
+        if digits.is_empty() || !digits.chars().all(|digit| digit.is_ascii_digit()) {
+            return Err(invalid());
+        }
+
+        // The number is digits * 10^scale; keep enough bits for every digit given
+        let digits: num_bigint::BigInt = digits.parse().map_err(|_| invalid())?;
+        let scale = exponent - fraction.len() as i64;
+        let bits = ((-scale).max(0) as f64 * std::f64::consts::LOG2_10) as u32 + 64;
+        let power = num_bigint::BigInt::from(10).pow(scale.unsigned_abs() as u32);
+        let value = if scale >= 0 {
+            (digits * power) << bits
+        } else {
+            (digits << bits) / power
+        };
+        Ok(Fixed {
+            value: if negative { -value } else { value },
+            bits,
+        })
+    }
+
+    /// Formats the number rounded to `places` decimal places.
+    pub fn to_decimal(&self, places: usize) -> String {
+        // Round the magnitude to the nearest multiple of 10^-places
+        let scaled = self.value.magnitude() * num_bigint::BigUint::from(10u32).pow(places as u32);
+        let half = num_bigint::BigUint::from(1u32) << self.bits;
+        let rounded = (scaled * 2u32 + half) >> (self.bits + 1);
+
+        let digits = format!("{:0>width$}", rounded.to_string(), width = places + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - places);
+        let negative = self.value.sign() == num_bigint::Sign::Minus && rounded.bits() > 0;
+        let sign = if negative { "-" } else { "" };
+        if places == 0 {
+            format!("{}{}", sign, whole)
+        } else {
+            format!("{}{}.{}", sign, whole, fraction)
+        }
+    }
+}
+
+impl PartialEq for Fixed {
+    fn eq(&self, other: &Fixed) -> bool {
+        let bits = self.bits.max(other.bits);
+        self.aligned(bits) == other.aligned(bits)
+    }
+}
+
+impl std::ops::Add for &Fixed {
+    type Output = Fixed;
+
+    fn add(self, other: &Fixed) -> Fixed {
+        let bits = self.bits.max(other.bits);
+        Fixed {
+            value: self.aligned(bits) + other.aligned(bits),
+            bits,
+        }
+    }
+}
+
+impl std::ops::Sub for &Fixed {
+    type Output = Fixed;
+
+    fn sub(self, other: &Fixed) -> Fixed {
+        let bits = self.bits.max(other.bits);
+        Fixed {
+            value: self.aligned(bits) - other.aligned(bits),
+            bits,
+        }
+    }
+}
+
+impl std::ops::Mul for &Fixed {
+    type Output = Fixed;
+
+    fn mul(self, other: &Fixed) -> Fixed {
+        let bits = self.bits.max(other.bits);
+        Fixed {
+            value: (&self.value * &other.value) >> (self.bits + other.bits - bits),
+            bits,
+        }
+    }
+}
+
+impl std::ops::Add<f64> for &Fixed {
+    type Output = Fixed;
+
+    fn add(self, other: f64) -> Fixed {
+        self + &Fixed::from_f64(other, self.bits)
+    }
+}
+
+impl std::ops::Sub<f64> for &Fixed {
+    type Output = Fixed;
+
+    fn sub(self, other: f64) -> Fixed {
+        self - &Fixed::from_f64(other, self.bits)
+    }
+}
+
+impl std::ops::Mul<f64> for &Fixed {
+    type Output = Fixed;
+
+    fn mul(self, other: f64) -> Fixed {
+        self * &Fixed::from_f64(other, self.bits)
+    }
+}
+
+// Parsed numbers are scaled by 10^exponent, so keep that from running away
+const MAX_DECIMAL_EXPONENT: i64 = 400;
+
+// Fractional bits needed to resolve a given distance on the plane, plus headroom for the
+// orbit's own rounding. No f64 distance is smaller than 2^-1074.
+fn precision_bits(distance: f64) -> u32 {
+    (-distance.log2()).clamp(0.0, 1074.0) as u32 + 64
+}
+
+/// Converts a point on the plane from `f64`.
+pub fn fixed_point(point: (f64, f64)) -> (Fixed, Fixed) {
+    let bits = precision_bits(f64::EPSILON);
+    (
+        Fixed::from_f64(point.0, bits),
+        Fixed::from_f64(point.1, bits),
+    )
+}
+
+/// How a view's rows and columns are laid over the plane.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Projection {
+    /// In straight lines, as a rectangle.
+    Flat,
+    /// Columns go once round the center and rows in toward it, each a fixed fraction
+    /// smaller than the one above, so a whole range of zooms fits in the frame and spirals
+    /// around the center unwrap into straight bands.
+    LogPolar,
+}
+
+/// The projections in the order of [`PROJECTION_NAMES`].
+pub const PROJECTIONS: [Projection; 2] = [Projection::Flat, Projection::LogPolar];
+
+/// Display names of [`PROJECTIONS`].
+pub const PROJECTION_NAMES: [&str; 2] = ["Flat", "Log-polar"];
+
+/// The rectangle of the complex plane a frame shows, turned around its center by `rotation`
+/// and laid out by `projection`.
+///
+/// The edges are kept at whatever precision the view's size needs, so deep views can still
+/// be moved around and bookmarked. Only the (small) extents are plain f64s.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Position {
+    /// The imaginary part along the first row, before the view is turned.
+    pub top: Fixed,
+    /// The imaginary part along the last row, before the view is turned.
+    pub bottom: Fixed,
+    /// The real part along the first column, before the view is turned.
+    pub left: Fixed,
+    /// The real part along the last column, before the view is turned.
+    pub right: Fixed,
+    /// How far the rows are turned from the real axis toward the imaginary one, in radians.
+    pub rotation: f64,
+    /// How the rows and columns are laid over the plane. The edges still give the extents
+    /// that zooming scales.
+    pub projection: Projection,
+}
+
+// An offset turned by `angle` radians from the real axis toward the imaginary one. No angle
+// leaves it exactly as it was.
+fn turn((x, y): (f64, f64), angle: f64) -> (f64, f64) {
+    let (sin, cos) = angle.sin_cos();
+    (x * cos - y * sin, x * sin + y * cos)
+}
+
+impl Position {
+    /// Creates a view from its edges, at a precision that suits its size.
+    pub fn new(top: f64, bottom: f64, left: f64, right: f64) -> Position {
+        let bits = precision_bits((bottom - top).min(right - left));
+        Position {
+            top: Fixed::from_f64(top, bits),
+            bottom: Fixed::from_f64(bottom, bits),
+            left: Fixed::from_f64(left, bits),
+            right: Fixed::from_f64(right, bits),
+            rotation: 0.0,
+            projection: Projection::Flat,
+        }
+    }
+
+    fn around(
+        x: &Fixed,
+        y: &Fixed,
+        width: f64,
+        height: f64,
+        rotation: f64,
+        projection: Projection,
+    ) -> Position {
+        let bits = precision_bits(width.min(height));
+        let (x, y) = (x.with_bits(bits), y.with_bits(bits));
+        Position {
+            top: &y - height / 2.0,
+            bottom: &y + height / 2.0,
+            left: &x - width / 2.0,
+            right: &x + width / 2.0,
+            rotation,
+            projection,
+        }
+    }
+
+    /// The real extent of the view.
+    pub fn width(&self) -> f64 {
+        (&self.right - &self.left).to_f64()
+    }
+
+    /// The imaginary extent of the view.
+    pub fn height(&self) -> f64 {
+        (&self.bottom - &self.top).to_f64()
+    }
+
+    /// The point in the middle of the view.
+    pub fn center(&self) -> (Fixed, Fixed) {
+        (
+            &(&self.left + &self.right) * 0.5,
+            &(&self.top + &self.bottom) * 0.5,
+        )
+    }
+
+    /// The view moved by `x` and `y` on the plane.
+    pub fn translated(&self, x: f64, y: f64) -> Position {
+        Position {
+            top: &self.top + y,
+            bottom: &self.bottom + y,
+            left: &self.left + x,
+            right: &self.right + x,
+            rotation: self.rotation,
+            projection: self.projection,
+        }
+    }
+
+    /// The view moved by `x` along its rows and `y` down its columns, which are the plane's
+    /// axes unless it is turned.
+    pub fn moved(&self, x: f64, y: f64) -> Position {
+        let (x, y) = turn((x, y), self.rotation);
+        self.translated(x, y)
+    }
+
+    /// The view turned `angle` radians further around its center.
+    pub fn rotated(&self, angle: f64) -> Position {
+        Position {
+            rotation: (self.rotation + angle).rem_euclid(std::f64::consts::TAU),
+            ..self.clone()
+        }
+    }
+
+    // Whether rows and columns run straight along the plane's axes
+    fn is_plain(&self) -> bool {
+        self.rotation == 0.0 && self.projection == Projection::Flat
+    }
+
+    // The radius of the first row of a log-polar view, which passes through the corners the
+    // flat view would have
+    fn outer_radius(&self) -> f64 {
+        self.width().hypot(self.height()) / 2.0
+    }
+
+    // How many powers of e the radius shrinks by from the first row of a log-polar view to
+    // the last, which keeps shapes in proportion in a view that is
+    fn log_polar_span(&self) -> f64 {
+        std::f64::consts::TAU * self.height() / self.width()
+    }
+
+    // How far the point `x` along the rows and `y` down the columns is from the center. This
+    // is the stage that places every pixel, before the kernels see it.
+    fn center_offset(&self, x: f64, y: f64) -> (f64, f64) {
+        let offset = match self.projection {
+            Projection::Flat => (x - self.width() / 2.0, y - self.height() / 2.0),
+            Projection::LogPolar => {
+                let angle = x / self.width() * std::f64::consts::TAU;
+                let radius =
+                    self.outer_radius() * (-y / self.height() * self.log_polar_span()).exp();
+                (radius * angle.cos(), radius * angle.sin())
+            }
+        };
+        turn(offset, self.rotation)
+    }
+
+    /// The point `x` along the rows and `y` down the columns from the view's first corner.
+    pub fn point_at(&self, x: f64, y: f64) -> (Fixed, Fixed) {
+        if self.is_plain() {
+            return (&self.left + x, &self.top + y);
+        }
+        let center = self.center();
+        let offset = self.center_offset(x, y);
+        (&center.0 + offset.0, &center.1 + offset.1)
+    }
+
+    /// How far `point` is along the rows and down the columns from the view's first corner.
+    pub fn offset_of(&self, point: &(Fixed, Fixed)) -> (f64, f64) {
+        if self.is_plain() {
+            return (
+                (&point.0 - &self.left).to_f64(),
+                (&point.1 - &self.top).to_f64(),
+            );
+        }
+        let center = self.center();
+        let offset = (
+            (&point.0 - &center.0).to_f64(),
+            (&point.1 - &center.1).to_f64(),
+        );
+        let (x, y) = turn(offset, -self.rotation);
+        match self.projection {
+            Projection::Flat => (x + self.width() / 2.0, y + self.height() / 2.0),
+            Projection::LogPolar => {
+                let angle = y.atan2(x).rem_euclid(std::f64::consts::TAU);
+                let depth = -(x.hypot(y) / self.outer_radius()).ln() / self.log_polar_span();
+                (
+                    angle / std::f64::consts::TAU * self.width(),
+                    depth * self.height(),
+                )
+            }
+        }
+    }
+
+    /// The view of the same size centered on `point`.
+    pub fn centered_at(&self, point: &(Fixed, Fixed)) -> Position {
+        Position::around(
+            &point.0,
+            &point.1,
+            self.width(),
+            self.height(),
+            self.rotation,
+            self.projection,
+        )
+    }
+
+    /// The view around the same center, turned the same way, but `width` by `height`.
+    pub fn resized(&self, width: f64, height: f64) -> Position {
+        let center = self.center();
+        Position::around(
+            &center.0,
+            &center.1,
+            width,
+            height,
+            self.rotation,
+            self.projection,
+        )
+    }
+
+    /// The view around the same center at `zoom` times the magnification of `default_position`.
+    pub fn with_zoom(&self, default_position: &Position, zoom: f64) -> Position {
+        let scale = default_position.width() / self.width();
+        self.zoomed_at(&self.center(), scale / zoom)
+    }
+
+    /// The view scaled by `factor` around `point`, which stays at the same place in the frame.
+    pub fn zoomed_at(&self, point: &(Fixed, Fixed), factor: f64) -> Position {
+        // Scale the center's distance to the anchor, which keeps the anchor in place
+        let center = self.center();
+        Position::around(
+            &(&point.0 + &(&(&center.0 - &point.0) * factor)),
+            &(&point.1 + &(&(&center.1 - &point.1) * factor)),
+            self.width() * factor,
+            self.height() * factor,
+            self.rotation,
+            self.projection,
+        )
+    }
+}
+
+// The angle a fraction `t` of the way from one view's rotation to another's, turning the
+// short way round
+fn interpolate_rotation(from: f64, to: f64, t: f64) -> f64 {
+    let turn =
+        (to - from + std::f64::consts::PI).rem_euclid(std::f64::consts::TAU) - std::f64::consts::PI;
+    from + turn * t
+}
+
+/// The view a fraction `t` of the way from `from` to `to`.
+pub fn interpolate_position(from: &Position, to: &Position, t: f64) -> Position {
+    // Interpolate the size geometrically so zooming runs at a constant visual speed
+    let from_center = from.center();
+    let to_center = to.center();
+    let center = (
+        &from_center.0 + &(&(&to_center.0 - &from_center.0) * t),
+        &from_center.1 + &(&(&to_center.1 - &from_center.1) * t),
+    );
+    let width = from.width() * (to.width() / from.width()).powf(t);
+    let height = from.height() * (to.height() / from.height()).powf(t);
+    let rotation = interpolate_rotation(from.rotation, to.rotation, t);
+
+    Position::around(&center.0, &center.1, width, height, rotation, to.projection)
+}
+
+/// The view a fraction `t` of the way along a zoom from `from` into `to`. Unlike
+/// [`interpolate_position`], the center closes in as fast as the view shrinks, so `to` stays
+/// in sight however deep it is.
+pub fn zoom_position(from: &Position, to: &Position, t: f64) -> Position {
+    let width = from.width() * (to.width() / from.width()).powf(t);
+    let height = from.height() * (to.height() / from.height()).powf(t);
+    // How much of the way from the target back to the start the center still has to go
+    let remaining = if from.width() == to.width() {
+        1.0 - t
+    } else {
+        (width - to.width()) / (from.width() - to.width())
+    };
+    let from_center = from.center();
+    let to_center = to.center();
+    Position::around(
+        &(&to_center.0 + &(&(&from_center.0 - &to_center.0) * remaining)),
+        &(&to_center.1 + &(&(&from_center.1 - &to_center.1) * remaining)),
+        width,
+        height,
+        interpolate_rotation(from.rotation, to.rotation, t),
+        to.projection,
+    )
+}
+
+/// Cells along each side of the square tiles a frame is split into for rendering.
+pub const DEFAULT_TILE_SIZE: u16 = 16;
+
+#[derive(PartialEq, Debug)]
+struct Pixel {
+    character: char,
+    foreground_color: [u8; 3],
+    background_color: Option<[u8; 3]>,
+}
+
+fn scale_number(
+    number: f64x1,
+    in_min: f64x1,
+    in_max: f64x1,
+    out_min: f64x1,
+    out_max: f64x1,
+) -> f64x1 {
+    (number - in_min) * (out_max - out_min) / (in_max - in_min) + out_min
+}
+
+/// The point on the plane in the middle of a cell of a `width` by `height` frame.
+pub fn cell_to_plane(
+    column: u16,
+    row: u16,
+    width: u16,
+    height: u16,
+    position: &Position,
+) -> (Fixed, Fixed) {
+    // Each cell covers a 2x2 block of subpixels; use the middle of the block
+    let x = (column as f64 * 2.0 + 1.0) / (width as f64 * 2.0) * position.width();
+    let y = (row as f64 * 2.0 + 1.0) / (height as f64 * 2.0) * position.height();
+    position.point_at(x, y)
+}
+
+/// The fractional cell of a `width` by `height` frame that a point on the plane falls in.
+pub fn plane_to_cell(
+    point: &(Fixed, Fixed),
+    width: u16,
+    height: u16,
+    position: &Position,
+) -> (f64, f64) {
+    let (x, y) = position.offset_of(point);
+    (
+        x / position.width() * width as f64,
+        y / position.height() * height as f64,
+    )
+}
+
+// Terminal cells are roughly twice as tall as they are wide
+const CELL_ASPECT_RATIO: f64 = 2.0;
+
+/// The view with its height adjusted so the plane isn't stretched in a `width` by `height` frame.
+pub fn fit_aspect(position: &Position, width: u16, height: u16) -> Position {
+    if width == 0 || height == 0 {
+        return position.clone();
+    }
+
+    // Keep the horizontal extent and adjust the vertical one so the plane isn't stretched
+    let center = position.center();
+    let plane_height = position.width() * height as f64 * CELL_ASPECT_RATIO / width as f64;
+    Position {
+        top: &center.1 - plane_height / 2.0,
+        bottom: &center.1 + plane_height / 2.0,
+        ..position.clone()
+    }
+}
+
+/// The largest part of `position`, around its center, in the proportions of `shape`.
+pub fn inscribed(position: &Position, shape: &Position) -> Position {
+    let aspect = shape.width() / shape.height();
+    let center = position.center();
+    if position.width() > position.height() * aspect {
+        let half_width = position.height() * aspect / 2.0;
+        Position {
+            left: &center.0 - half_width,
+            right: &center.0 + half_width,
+            ..position.clone()
+        }
+    } else {
+        let half_height = position.width() / aspect / 2.0;
+        Position {
+            top: &center.1 - half_height,
+            bottom: &center.1 + half_height,
+            ..position.clone()
+        }
+    }
+}
+
+/// The view that fills a `width` by `height` frame without stretching the plane, around the
+/// part of `position` in the proportions of `shape` and leaving room for bars at the sides or
+/// the top and bottom.
+pub fn letterbox(position: &Position, shape: &Position, width: u16, height: u16) -> Position {
+    if width == 0 || height == 0 {
+        return position.clone();
+    }
+
+    let framed = inscribed(position, shape);
+    let aspect = width as f64 / (height as f64 * CELL_ASPECT_RATIO);
+    let center = framed.center();
+    if framed.width() > framed.height() * aspect {
+        let half_height = framed.width() / aspect / 2.0;
+        Position {
+            top: &center.1 - half_height,
+            bottom: &center.1 + half_height,
+            ..framed
+        }
+    } else {
+        let half_width = framed.height() * aspect / 2.0;
+        Position {
+            left: &center.0 - half_width,
+            right: &center.0 + half_width,
+            ..framed
+        }
+    }
+}
+
+/// The view of the rectangle between two corners, or `None` if it has no area.
+pub fn box_zoom(
+    position: &Position,
+    corner_a: &(Fixed, Fixed),
+    corner_b: &(Fixed, Fixed),
+) -> Option<Position> {
+    let center = (
+        &(&corner_a.0 + &corner_b.0) * 0.5,
+        &(&corner_a.1 + &corner_b.1) * 0.5,
+    );
+    // The box's sides run along the view's rows and columns
+    let (width, height) = turn(
+        (
+            (&corner_a.0 - &corner_b.0).to_f64(),
+            (&corner_a.1 - &corner_b.1).to_f64(),
+        ),
+        -position.rotation,
+    );
+    let (mut width, mut height) = (width.abs(), height.abs());
+    if width == 0.0 || height == 0.0 {
+        return None;
+    }
+
+    // Grow the box along one axis so the view keeps its current aspect ratio
+    let aspect_ratio = position.width() / position.height();
+    if width / height < aspect_ratio {
+        width = height * aspect_ratio;
+    } else {
+        height = width / aspect_ratio;
+    }
+
+    Some(Position::around(
+        &center.0,
+        &center.1,
+        width,
+        height,
+        position.rotation,
+        position.projection,
+    ))
+}
+
+fn get_pixel(blocks: [[bool; 2]; 2]) -> char {
+    match blocks {
+        [[true, true], [true, true]] => FULL_BLOCK[0].chars().next().unwrap(),
+        [[false, false], [false, false]] => FULL_BLOCK[1].chars().next().unwrap(),
+        [[false, true], [true, true]] => THREE_QUADRANTS[1].chars().next().unwrap(),
+        [[true, false], [true, true]] => THREE_QUADRANTS[0].chars().next().unwrap(),
+        [[true, true], [false, true]] => THREE_QUADRANTS[3].chars().next().unwrap(),
+        [[true, true], [true, false]] => THREE_QUADRANTS[2].chars().next().unwrap(),
+        [[false, false], [true, true]] => TWO_QUADRANTS[2].chars().next().unwrap(),
+        [[true, false], [false, true]] => TWO_QUADRANTS[0].chars().next().unwrap(),
+        [[true, true], [false, false]] => TWO_QUADRANTS[3].chars().next().unwrap(),
+        [[false, true], [true, false]] => TWO_QUADRANTS[1].chars().next().unwrap(),
+        [[false, true], [false, true]] => TWO_QUADRANTS[4].chars().next().unwrap(),
+        [[true, false], [true, false]] => TWO_QUADRANTS[5].chars().next().unwrap(),
+        [[false, false], [false, true]] => QUADRANTS[3].chars().next().unwrap(),
+        [[false, true], [false, false]] => QUADRANTS[2].chars().next().unwrap(),
+        [[true, false], [false, false]] => QUADRANTS[1].chars().next().unwrap(),
+        [[false, false], [true, false]] => QUADRANTS[0].chars().next().unwrap(),
+    }
+}
+
+fn hsl_to_rgb<const N: usize>(hsl: [Simd<f64, N>; 3]) -> [Simd<f64, N>; 3] {
+    let s = hsl[1] / Simd::splat(100.0);
+    let l = hsl[2] / Simd::splat(100.0);
+    let k = |n: Simd<f64, N>| (n + hsl[0] / Simd::splat(30.0)) % Simd::splat(12.0);
+
+    let a = s * l.simd_min(Simd::splat(1.0) - l);
+    let f = |n: Simd<f64, N>| {
+        l - a
+            * (-Simd::<f64, N>::splat(1.0)).simd_max(
+                (k(n) - Simd::splat(3.0))
+                    .simd_min((Simd::splat(9.0) - k(n)).simd_min(Simd::splat(1.0))),
+            )
+    };
+    [
+        Simd::splat(255.0) * f(Simd::splat(0.0)),
+        Simd::splat(255.0) * f(Simd::splat(8.0)),
+        Simd::splat(255.0) * f(Simd::splat(4.0)),
+    ]
+}
+
+fn get_color(iteration: u32x1, max_iterations: u32x1) -> [f64x1; 3] {
+    get_colors([iteration[0]], max_iterations)
+}
+
+// The rainbow colors of a lane of distinct iteration counts at once
+fn get_colors<const N: usize>(iterations: [u32; N], max_iterations: u32x1) -> [Simd<f64, N>; 3] {
+    let h = Simd::from_array(iterations.map(|iteration| iteration as f64)) * Simd::splat(360.0)
+        / Simd::splat(max_iterations[0] as f64);
+    let mut colors =
+        hsl_to_rgb([h, Simd::splat(100.0), Simd::splat(50.0)]).map(|channel| channel.to_array());
+
+    // Points inside the set are black and those escaping at once white
+    for (lane, &iteration) in iterations.iter().enumerate() {
+        let fixed = if iteration == max_iterations[0] {
+            0.0
+        } else if iteration == 0 {
+            255.0
+        } else {
+            continue;
+        };
+        for channel in &mut colors {
+            channel[lane] = fixed;
+        }
+    }
+    colors.map(Simd::from_array)
+}
+
+fn gradient<const N: usize>(stops: &[[f64; 3]], t: [f64; N]) -> [Simd<f64, N>; 3] {
+    let mut starts = [[0.0; N]; 3];
+    let mut ends = [[0.0; N]; 3];
+    let mut fractions = [0.0; N];
+    for (lane, t) in t.into_iter().enumerate() {
+        let position = t.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+        let index = (position as usize).min(stops.len() - 2);
+        fractions[lane] = position - index as f64;
+        for channel in 0..3 {
+            starts[channel][lane] = stops[index][channel];
+            ends[channel][lane] = stops[index + 1][channel];
+        }
+    }
+
+    let fractions = Simd::from_array(fractions);
+    std::array::from_fn(|channel| {
+        let start = Simd::from_array(starts[channel]);
+        start + (Simd::from_array(ends[channel]) - start) * fractions
+    })
+}
+
+fn gradient_color(iteration: u32x1, max_iterations: u32x1, stops: &[[f64; 3]]) -> [f64x1; 3] {
+    gradient_colors([iteration[0]], max_iterations, stops)
+}
+
+fn gradient_colors<const N: usize>(
+    iterations: [u32; N],
+    max_iterations: u32x1,
+    stops: &[[f64; 3]],
+) -> [Simd<f64, N>; 3] {
+    let t = iterations.map(|iteration| iteration as f64 / max_iterations[0] as f64);
+    let mut colors = gradient(stops, t).map(|channel| channel.to_array());
+    for (lane, &iteration) in iterations.iter().enumerate() {
+        if iteration == max_iterations[0] {
+            for channel in &mut colors {
+                channel[lane] = 0.0;
+            }
+        }
+    }
+    colors.map(Simd::from_array)
+}
+
+// The stops of the gradient palettes, which follow the rainbow in PALETTES
+const GRADIENTS: [&[[f64; 3]]; 3] = [
+    &[[0.0, 0.0, 0.0], [255.0, 255.0, 255.0]],
+    &[
+        [0.0, 0.0, 0.0],
+        [180.0, 0.0, 0.0],
+        [255.0, 160.0, 0.0],
+        [255.0, 255.0, 200.0],
+    ],
+    &[
+        [0.0, 0.0, 40.0],
+        [0.0, 80.0, 180.0],
+        [0.0, 220.0, 255.0],
+        [255.0, 255.0, 255.0],
+    ],
+];
+
+// Colors a lane of distinct iteration counts with a built-in palette, as PALETTES would one
+// by one
+fn palette_colors<const N: usize>(
+    palette_index: usize,
+    iterations: [u32; N],
+    max_iterations: u32x1,
+) -> [Simd<f64, N>; 3] {
+    match palette_index {
+        0 => get_colors(iterations, max_iterations),
+        _ => gradient_colors(iterations, max_iterations, GRADIENTS[palette_index - 1]),
+    }
+}
+
+type Palette = fn(u32x1, u32x1) -> [f64x1; 3];
+
+/// Display names of the built-in palettes, indexed like [`PALETTES`]. [`palette_names`] adds
+/// any registered at run time.
+pub const PALETTE_NAMES: [&str; 4] = ["Rainbow", "Grayscale", "Fire", "Ocean"];
+
+/// Palettes map an iteration count, given the limit, to an RGB color.
+pub const PALETTES: [Palette; 4] = [
+    get_color,
+    |iteration: u32x1, max_iterations: u32x1| {
+        gradient_color(iteration, max_iterations, GRADIENTS[0])
+    },
+    |iteration: u32x1, max_iterations: u32x1| {
+        gradient_color(iteration, max_iterations, GRADIENTS[1])
+    },
+    |iteration: u32x1, max_iterations: u32x1| {
+        gradient_color(iteration, max_iterations, GRADIENTS[2])
+    },
+];
+
+// Colorings remap an iteration count onto the palette before it is looked up
+type Coloring = fn(u32x1, u32x1) -> u32x1;
+
+/// Display names of the colorings, indexed like [`COLORINGS`].
+pub const COLORING_NAMES: [&str; 4] = ["Linear", "Logarithmic", "Cyclic", "Boundaries"];
+const COLORING_CYCLE: u32 = 32;
+
+// Boundaries are found from the roots of each subpixel's neighbours as frames are shaded, so
+// the coloring itself leaves counts alone
+const BOUNDARIES_INDEX: usize = 3;
+const BOUNDARY_COLOR: [u8; 3] = [255, 255, 255];
+
+/// Colorings remap an iteration count, given the limit, before a palette colors it. The last
+/// draws only the boundaries between the basins of the Newton fractal, where neighbouring
+/// points converge to different roots, as bright lines on a dark background. Other fractals
+/// have no basins, so it colors them like the first.
+pub const COLORINGS: [Coloring; 4] = [
+    |iteration: u32x1, _: u32x1| iteration,
+    |iteration: u32x1, max_iterations: u32x1| {
+        if iteration == max_iterations {
+            return iteration;
+        }
+        let scale = (iteration[0] as f64).ln_1p() / (max_iterations[0] as f64).ln_1p();
+        u32x1::splat((scale * max_iterations[0] as f64) as u32)
+    },
+    |iteration: u32x1, max_iterations: u32x1| {
+        if iteration == max_iterations {
+            return iteration;
+        }
+        let phase = iteration[0] % COLORING_CYCLE;
+        u32x1::splat((phase as u64 * max_iterations[0] as u64 / COLORING_CYCLE as u64) as u32)
+    },
+    |iteration: u32x1, _: u32x1| iteration,
+];
+
+// Whether frames are drawn as the boundaries between basins, which only the Newton fractal has
+fn draws_boundaries(parameters: &FractalParameters) -> bool {
+    parameters.coloring_index == BOUNDARIES_INDEX && parameters.fractal_index == NEWTON_INDEX
+}
+
+/// The color of every iteration count up to the limit, worked out once per palette and
+/// coloring rather than for every cell. Counts past the first [`COLOR_TABLE_SIZE`] are
+/// colored as they are looked up instead, so the table stays the same size however high the
+/// limit goes.
+pub struct ColorTable {
+    max_iterations: u32x1,
+    palette_index: usize,
+    coloring_index: usize,
+    palette_phase: f64,
+    custom: Option<CustomPalette>,
+    shift: u64,
+    colors: Vec<[u8; 3]>,
+    inside: [u8; 3],
+    boundaries: bool,
+}
+
+/// How many iteration counts a [`ColorTable`] keeps colors for.
+pub const COLOR_TABLE_SIZE: u32 = 1 << 16;
+
+// Distinct iteration counts colored at once while building a table
+const COLOR_LANES: usize = 8;
+
+impl ColorTable {
+    /// Builds the table for the iteration counts up to `max_iterations`.
+    pub fn new(max_iterations: u32x1, parameters: &FractalParameters) -> ColorTable {
+        let mut table = ColorTable {
+            max_iterations,
+            palette_index: parameters.palette_index,
+            coloring_index: parameters.coloring_index,
+            palette_phase: parameters.palette_phase,
+            custom: custom_palette(parameters.palette_index),
+            // Points inside the set keep their color however far the palette is rotated
+            shift: (parameters.palette_phase.rem_euclid(1.0) * max_iterations[0] as f64) as u64,
+            colors: Vec::new(),
+            inside: [0; 3],
+            boundaries: draws_boundaries(parameters),
+        };
+        table.inside = table.shade([max_iterations[0]])[0];
+
+        let counts: Vec<u32> = (0..max_iterations[0].min(COLOR_TABLE_SIZE)).collect();
+        let mut colors = Vec::with_capacity(counts.len());
+        for chunk in counts.chunks(COLOR_LANES) {
+            // A short final chunk repeats its last count in the spare lanes
+            let lanes = std::array::from_fn(|lane| chunk[lane.min(chunk.len() - 1)]);
+            colors.extend_from_slice(&table.shade::<COLOR_LANES>(lanes)[..chunk.len()]);
+        }
+        table.colors = colors;
+        table
+    }
+
+    // Colors a lane of distinct iteration counts with the coloring, phase and palette
+    fn shade<const N: usize>(&self, iterations: [u32; N]) -> [[u8; 3]; N] {
+        let max_iterations = self.max_iterations;
+        if self.boundaries {
+            // The boundary pass leaves boundaries at 0 and everything else at the limit
+            return iterations.map(|iteration| {
+                if iteration < max_iterations[0] {
+                    BOUNDARY_COLOR
+                } else {
+                    [0, 0, 0]
+                }
+            });
+        }
+        let coloring = COLORINGS[self.coloring_index];
+        let remapped = iterations.map(|iteration| {
+            let iteration = coloring(u32x1::splat(iteration), max_iterations);
+            if iteration < max_iterations {
+                ((iteration[0] as u64 + self.shift) % max_iterations[0] as u64) as u32
+            } else {
+                iteration[0]
+            }
+        });
+
+        match &self.custom {
+            Some(custom) => remapped.map(|iteration| custom(iteration, max_iterations[0])),
+            None => {
+                let palette_index = self.palette_index.min(PALETTES.len() - 1);
+                let channels = palette_colors(palette_index, remapped, max_iterations);
+                std::array::from_fn(|lane| channels.map(|channel| channel[lane] as u8))
+            }
+        }
+    }
+
+    // Whether cells only show their basin boundaries under this table
+    fn boundaries(&self) -> bool {
+        self.boundaries
+    }
+
+    /// Whether the table colors frames with this limit, palette, coloring and phase.
+    pub fn matches(&self, max_iterations: u32x1, parameters: &FractalParameters) -> bool {
+        self.max_iterations == max_iterations
+            && self.palette_index == parameters.palette_index
+            && self.coloring_index == parameters.coloring_index
+            && self.palette_phase == parameters.palette_phase
+            && self.boundaries == draws_boundaries(parameters)
+    }
+
+    /// The red, green and blue of an iteration count. Counts past the limit get the limit's
+    /// color.
+    pub fn color(&self, iteration: u32x1) -> [u8; 3] {
+        match self.colors.get(iteration[0] as usize) {
+            _ if iteration >= self.max_iterations => self.inside,
+            Some(color) => *color,
+            None => self.shade([iteration[0]])[0],
+        }
+    }
+}
+
+// Calculates cells along with where their orbits stopped, carrying on from `starts` if given.
+// Orbits are only kept without supersampling, since samples average together.
+#[inline(always)]
+#[allow(clippy::too_many_arguments)]
+fn calculate_lanes<const N: usize>(
+    cells: &[(u16, u16)],
+    starts: Option<&[(Cell, Orbit)]>,
+    width: u16,
+    height: u16,
+    position: &Position,
+    max_iterations: u32x1,
+    parameters: &FractalParameters,
+) -> Vec<(Cell, Orbit)> {
+    let samples = parameters.supersampling.max(1);
+    let (left, right) = (position.left.to_f64(), position.right.to_f64());
+    let (top, bottom) = (position.top.to_f64(), position.bottom.to_f64());
+    let center = ((left + right) / 2.0, (top + bottom) / 2.0);
+    let mut output = Vec::with_capacity(cells.len());
+
+    // Each group of four lanes holds the 2x2 subpixels of one cell
+    for (chunk_index, chunk) in cells.chunks(N / 4).enumerate() {
+        let start = starts.map(|starts| {
+            let starts = &starts[chunk_index * (N / 4)..][..chunk.len()];
+            let (mut x, mut y, mut iteration) = ([0.0; N], [0.0; N], [0; N]);
+            for lane in 0..N {
+                let (cell, orbit) = &starts[(lane / 4).min(chunk.len() - 1)];
+                let (subpixel_x, subpixel_y) = (lane % 2, lane % 4 / 2);
+                (x[lane], y[lane]) = orbit[subpixel_y][subpixel_x];
+                iteration[lane] = cell[subpixel_y][subpixel_x][0];
+            }
+            (
+                Simd::from_array(x),
+                Simd::from_array(y),
+                Simd::from_array(iteration),
+            )
+        });
+        let mut iterations = Simd::<u32, N>::splat(0);
+        let mut stopped = (Simd::splat(f64::NAN), Simd::splat(f64::NAN));
+        for sample_y in 0..samples {
+            for sample_x in 0..samples {
+                let mut scaled_x = [0.0; N];
+                let mut scaled_y = [0.0; N];
+                for lane in 0..N {
+                    // A short final chunk repeats its last cell in the spare lanes
+                    let (pixel_x, pixel_y) = chunk[(lane / 4).min(chunk.len() - 1)];
+                    let (subpixel_x, subpixel_y) = ((lane % 2) as u16, (lane % 4 / 2) as u16);
+                    scaled_x[lane] = scale_number(
+                        f64x1::splat(
+                            pixel_x as f64 * 2.0
+                                + subpixel_x as f64
+                                + sample_x as f64 / samples as f64,
+                        ),
+                        f64x1::splat(0.0),
+                        f64x1::splat(width as f64 * 2.0),
+                        f64x1::splat(left),
+                        f64x1::splat(right),
+                    )[0];
+                    scaled_y[lane] = scale_number(
+                        f64x1::splat(
+                            pixel_y as f64 * 2.0
+                                + subpixel_y as f64
+                                + sample_y as f64 / samples as f64,
+                        ),
+                        f64x1::splat(0.0),
+                        f64x1::splat(height as f64 * 2.0),
+                        f64x1::splat(top),
+                        f64x1::splat(bottom),
+                    )[0];
+                    if !position.is_plain() {
+                        let offset =
+                            position.center_offset(scaled_x[lane] - left, scaled_y[lane] - top);
+                        scaled_x[lane] = center.0 + offset.0;
+                        scaled_y[lane] = center.1 + offset.1;
+                    }
+                }
+
+                let (counts, orbits) = iterate_from(
+                    Simd::from_array(scaled_x),
+                    Simd::from_array(scaled_y),
+                    start,
+                    Simd::splat(max_iterations[0]),
+                    parameters,
+                );
+                iterations += counts;
+                if samples == 1 {
+                    stopped = orbits;
+                }
+            }
+        }
+
+        // Average the grid of samples spread evenly over each subpixel
+        let iterations = iterations / Simd::splat(samples * samples);
+        for index in 0..chunk.len() {
+            let mut cell = [[u32x1::splat(0); 2]; 2];
+            let mut orbit = [[(f64::NAN, f64::NAN); 2]; 2];
+            for subpixel in 0..4 {
+                let lane = index * 4 + subpixel;
+                cell[subpixel / 2][subpixel % 2] = u32x1::splat(iterations[lane]);
+                orbit[subpixel / 2][subpixel % 2] = (stopped.0[lane], stopped.1[lane]);
+            }
+            output.push((cell, orbit));
+        }
+    }
+
+    output
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+#[allow(clippy::too_many_arguments)]
+fn calculate_lanes_avx512(
+    cells: &[(u16, u16)],
+    starts: Option<&[(Cell, Orbit)]>,
+    width: u16,
+    height: u16,
+    position: &Position,
+    max_iterations: u32x1,
+    parameters: &FractalParameters,
+) -> Vec<(Cell, Orbit)> {
+    calculate_lanes::<8>(
+        cells,
+        starts,
+        width,
+        height,
+        position,
+        max_iterations,
+        parameters,
+    )
+}
+
+fn wide_simd() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    {
+        std::is_x86_feature_detected!("avx512f")
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        false
+    }
+}
+
+/// How many points the kernels iterate at once on this CPU.
+pub fn simd_lanes() -> usize {
+    if wide_simd() {
+        8
+    } else {
+        4
+    }
+}
+
+fn calculate_cells(
+    cells: &[(u16, u16)],
+    width: u16,
+    height: u16,
+    position: &Position,
+    max_iterations: u32x1,
+    parameters: &FractalParameters,
+) -> Vec<Cell> {
+    calculate_orbits(
+        cells,
+        None,
+        width,
+        height,
+        position,
+        max_iterations,
+        parameters,
+    )
+    .into_iter()
+    .map(|(cell, _)| cell)
+    .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn calculate_orbits(
+    cells: &[(u16, u16)],
+    starts: Option<&[(Cell, Orbit)]>,
+    width: u16,
+    height: u16,
+    position: &Position,
+    max_iterations: u32x1,
+    parameters: &FractalParameters,
+) -> Vec<(Cell, Orbit)> {
+    #[cfg(target_arch = "x86_64")]
+    if wide_simd() {
+        // Safety: the CPU has just reported AVX-512 support
+        return unsafe {
+            calculate_lanes_avx512(
+                cells,
+                starts,
+                width,
+                height,
+                position,
+                max_iterations,
+                parameters,
+            )
+        };
+    }
+
+    calculate_lanes::<4>(
+        cells,
+        starts,
+        width,
+        height,
+        position,
+        max_iterations,
+        parameters,
+    )
+}
+
+fn complex_mul(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+fn complex_add(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+// Below this subpixel spacing, neighbouring pixels stop having distinct f64 coordinates
+const PERTURBATION_THRESHOLD: f64 = 1e-12;
+// And below this one, distinct double-double coordinates
+const DOUBLE_DOUBLE_THRESHOLD: f64 = 1e-28;
+
+fn subpixel_spacing(width: u16, position: &Position) -> f64 {
+    let spacing = position.width() / (width.max(1) as f64 * 2.0);
+    match position.projection {
+        Projection::Flat => spacing,
+        // Log-polar subpixels are closest together round the last row, the smallest circle
+        Projection::LogPolar => {
+            let radius = position.outer_radius() * (-position.log_polar_span()).exp();
+            spacing * std::f64::consts::TAU * radius / position.width()
+        }
+    }
+}
+
+fn uses_perturbation(width: u16, position: &Position, parameters: &FractalParameters) -> bool {
+    // Only the Mandelbrot and Julia sets have perturbed kernels. The Sinking Ship's absolute
+    // value doesn't perturb as a plain polynomial.
+    if parameters.fractal_index != 0 && parameters.fractal_index != JULIA_INDEX {
+        return false;
+    }
+    match parameters.backend_index {
+        0 => subpixel_spacing(width, position) < PERTURBATION_THRESHOLD,
+        1 => false,
+        2 => true,
+        _ => subpixel_spacing(width, position) < DOUBLE_DOUBLE_THRESHOLD,
+    }
+}
+
+fn uses_double_double(width: u16, position: &Position, parameters: &FractalParameters) -> bool {
+    // Custom fractals and the Newton fractal only have f64 kernels, and fractals without a
+    // perturbed kernel carry on in double-double as deep as they go
+    parameters.backend_index == 3
+        && parameters.fractal_index < NEWTON_INDEX
+        && subpixel_spacing(width, position) < PERTURBATION_THRESHOLD
+        && !uses_perturbation(width, position, parameters)
+}
+
+/// Whether the view is too deep for neighbouring subpixels to get distinct coordinates.
+pub fn precision_exhausted(width: u16, position: &Position, parameters: &FractalParameters) -> bool {
+    // Neighbouring subpixels closer than one f64 step at the center share coordinates,
+    // which renders as blocks of identical pixels
+    let center = position.center();
+    let magnitude = center.0.to_f64().abs().max(center.1.to_f64().abs());
+    let ulp = f64::from_bits(magnitude.to_bits() + 1) - magnitude;
+    // Double-double steps are about one f64 epsilon of an f64 step
+    let step = if uses_double_double(width, position, parameters) {
+        ulp * f64::EPSILON
+    } else {
+        ulp
+    };
+    !uses_perturbation(width, position, parameters) && subpixel_spacing(width, position) < step
+}
+
+// A high-precision orbit through the view's center. Every pixel then iterates only its
+// small difference from that orbit, which f64 holds accurately at any depth.
+struct ReferenceOrbit {
+    orbit: Vec<(f64, f64)>,
+    julia: bool,
+    // Iterations every pixel skips, and the series coefficients for the delta at that point,
+    // lowest power first
+    skipped: usize,
+    coefficients: Vec<(f64, f64)>,
+}
+
+impl ReferenceOrbit {
+    fn new(
+        center: (&Fixed, &Fixed),
+        radius: f64,
+        max_iterations: u32,
+        parameters: &FractalParameters,
+    ) -> ReferenceOrbit {
+        let julia = parameters.fractal_index == JULIA_INDEX;
+        let bits = center.0.bits.max(center.1.bits);
+        let (cx, cy) = if julia {
+            (
+                Fixed::from_f64(parameters.julia_constant.0, bits),
+                Fixed::from_f64(parameters.julia_constant.1, bits),
+            )
+        } else {
+            (center.0.clone(), center.1.clone())
+        };
+        let (mut zx, mut zy) = if julia {
+            (center.0.clone(), center.1.clone())
+        } else {
+            (
+                Fixed::from_f64(parameters.initial_z.0, bits),
+                Fixed::from_f64(parameters.initial_z.1, bits),
+            )
+        };
+
+        let bailout = parameters.escape_radius * parameters.escape_radius;
+        let two = Fixed::from_f64(2.0, bits);
+        let mut orbit = vec![(zx.to_f64(), zy.to_f64())];
+        // Always keep a next point, so a pixel can step from any stored one
+        while orbit.len() <= max_iterations.max(1) as usize {
+            let next_x = &(&(&zx * &zx) - &(&zy * &zy)) + &cx;
+            zy = &(&two * &(&zx * &zy)) + &cy;
+            zx = next_x;
+
+            let point = (zx.to_f64(), zy.to_f64());
+            orbit.push(point);
+            if point.0 * point.0 + point.1 * point.1 > bailout {
+                break;
+            }
+        }
+
+        // Track delta_n = a_1*e + a_2*e^2 + ... + a_k*e^k for a pixel offset e, while the
+        // highest term is negligible next to the linear one for every pixel within the radius.
+        // A linear series checks the quadratic term it leaves out.
+        let order = parameters.series_order.min(MAX_SERIES_ORDER);
+        let checked = order.max(2) - 1;
+        let mut coefficients = vec![(0.0, 0.0); checked + 1];
+        coefficients[0].0 = if julia { 1.0 } else { 0.0 };
+        let mut skipped = 0;
+        let steps = if order == 0 { 0 } else { orbit.len() - 1 };
+        for point in &orbit[..steps] {
+            // a_n' = 2 * Z * a_n + the sum of a_i * a_j over i + j = n, plus 1 for a_1
+            let two_z = (point.0 * 2.0, point.1 * 2.0);
+            let next: Vec<(f64, f64)> = (0..=checked)
+                .map(|power| {
+                    let products = (0..power).map(|first| {
+                        complex_mul(coefficients[first], coefficients[power - 1 - first])
+                    });
+                    products.fold(complex_mul(two_z, coefficients[power]), complex_add)
+                })
+                .collect();
+            let next_a = complex_add(next[0], (if julia { 0.0 } else { 1.0 }, 0.0));
+
+            let magnitude = |z: (f64, f64)| z.0.hypot(z.1);
+            let error = magnitude(next[checked]) * radius.powi(checked as i32);
+            // An overflow to NaN or infinity ends the skip too
+            if !error.is_finite() || error > parameters.series_tolerance * magnitude(next_a) {
+                break;
+            }
+            coefficients = next;
+            coefficients[0] = next_a;
+            skipped += 1;
+        }
+        coefficients.truncate(order.max(1));
+
+        ReferenceOrbit {
+            orbit,
+            julia,
+            skipped,
+            coefficients,
+        }
+    }
+
+    fn for_view(
+        width: u16,
+        position: &Position,
+        max_iterations: u32x1,
+        parameters: &FractalParameters,
+    ) -> Option<ReferenceOrbit> {
+        if !uses_perturbation(width, position, parameters) {
+            return None;
+        }
+
+        let center = position.center();
+        let bits = precision_bits(subpixel_spacing(width, position));
+        let radius = position.width().hypot(position.height()) / 2.0;
+        Some(ReferenceOrbit::new(
+            (&center.0.with_bits(bits), &center.1.with_bits(bits)),
+            radius,
+            max_iterations[0],
+            parameters,
+        ))
+    }
+
+    fn iterate(&self, offset: (f64, f64), max_iterations: u32, bailout: f64) -> u32 {
+        let delta_c = if self.julia { (0.0, 0.0) } else { offset };
+        let mut delta = self.coefficients.iter().rev().fold((0.0, 0.0), |delta, a| {
+            complex_mul(complex_add(delta, *a), offset)
+        });
+
+        let mut reference = self.skipped;
+        let mut iteration = self.skipped as u32;
+        while iteration < max_iterations {
+            let point = self.orbit[reference];
+            let z = complex_add(point, delta);
+            let magnitude = z.0 * z.0 + z.1 * z.1;
+            if magnitude > bailout {
+                break;
+            }
+
+            // Once the pixel is nearer zero than the reference, the delta can no longer
+            // track it precisely (a glitch), so rebase it onto the start of the orbit. This
+            // also covers the reference escaping before the pixel does.
+            if magnitude < delta.0 * delta.0 + delta.1 * delta.1
+                || reference == self.orbit.len() - 1
+            {
+                delta = (z.0 - self.orbit[0].0, z.1 - self.orbit[0].1);
+                reference = 0;
+            }
+
+            // delta' = 2 * Z * delta + delta^2 + delta_c
+            let point = self.orbit[reference];
+            let linear = complex_mul((point.0 * 2.0, point.1 * 2.0), delta);
+            delta = complex_add(complex_add(linear, complex_mul(delta, delta)), delta_c);
+            reference += 1;
+            iteration += 1;
+        }
+        iteration
+    }
+
+    fn calculate_cells(
+        &self,
+        cells: &[(u16, u16)],
+        width: u16,
+        height: u16,
+        position: &Position,
+        max_iterations: u32x1,
+        parameters: &FractalParameters,
+    ) -> Vec<Cell> {
+        let bailout = parameters.escape_radius * parameters.escape_radius;
+        calculate_offsets(cells, width, height, position, parameters, |offset| {
+            self.iterate(offset, max_iterations[0], bailout)
+        })
+    }
+}
+
+// The view's center in double-double. Pixels are iterated from there in double-double
+// arithmetic, which is slower than perturbation but needs no reference orbit, so it works
+// for every built-in fractal.
+struct ExtendedCenter {
+    center: (DoubleDouble, DoubleDouble),
+}
+
+impl ExtendedCenter {
+    fn for_view(
+        width: u16,
+        position: &Position,
+        parameters: &FractalParameters,
+    ) -> Option<ExtendedCenter> {
+        if !uses_double_double(width, position, parameters) {
+            return None;
+        }
+
+        let center = position.center();
+        Some(ExtendedCenter {
+            center: (center.0.to_double_double(), center.1.to_double_double()),
+        })
+    }
+
+    fn iterate(
+        &self,
+        offset: (f64, f64),
+        max_iterations: u32,
+        parameters: &FractalParameters,
+    ) -> u32 {
+        let pixel = (
+            self.center.0 + offset.0.into(),
+            self.center.1 + offset.1.into(),
+        );
+        let constant = |(x, y): (f64, f64)| (DoubleDouble::from(x), DoubleDouble::from(y));
+        // The same orbits as the f64 kernels, apart from the cycle checks
+        let (mut z, c) = match parameters.fractal_index {
+            0 => (constant(parameters.initial_z), pixel),
+            JULIA_INDEX => (pixel, constant(parameters.julia_constant)),
+            _ => (pixel, pixel),
+        };
+        let sinking_ship = parameters.fractal_index == 1;
+
+        let bailout = parameters.escape_radius * parameters.escape_radius;
+        let mut iteration = 0;
+        while iteration < max_iterations && z.0.hi * z.0.hi + z.1.hi * z.1.hi <= bailout {
+            let imaginary = (z.0 * z.1).doubled();
+            let imaginary = if sinking_ship {
+                imaginary.abs()
+            } else {
+                imaginary
+            };
+            z = (z.0 * z.0 - z.1 * z.1 + c.0, imaginary + c.1);
+            iteration += 1;
+        }
+        iteration
+    }
+
+    fn calculate_cells(
+        &self,
+        cells: &[(u16, u16)],
+        width: u16,
+        height: u16,
+        position: &Position,
+        max_iterations: u32x1,
+        parameters: &FractalParameters,
+    ) -> Vec<Cell> {
+        calculate_offsets(cells, width, height, position, parameters, |offset| {
+            self.iterate(offset, max_iterations[0], parameters)
+        })
+    }
+}
+
+// How a view too deep for f64 coordinates is calculated
+enum DeepKernel {
+    Perturbation(ReferenceOrbit),
+    DoubleDouble(ExtendedCenter),
+}
+
+impl DeepKernel {
+    fn for_view(
+        width: u16,
+        position: &Position,
+        max_iterations: u32x1,
+        parameters: &FractalParameters,
+    ) -> Option<DeepKernel> {
+        match ReferenceOrbit::for_view(width, position, max_iterations, parameters) {
+            Some(reference) => Some(DeepKernel::Perturbation(reference)),
+            None => {
+                ExtendedCenter::for_view(width, position, parameters).map(DeepKernel::DoubleDouble)
+            }
+        }
+    }
+
+    fn calculate_cells(
+        &self,
+        cells: &[(u16, u16)],
+        width: u16,
+        height: u16,
+        position: &Position,
+        max_iterations: u32x1,
+        parameters: &FractalParameters,
+    ) -> Vec<Cell> {
+        match self {
+            DeepKernel::Perturbation(reference) => reference.calculate_cells(
+                cells,
+                width,
+                height,
+                position,
+                max_iterations,
+                parameters,
+            ),
+            DeepKernel::DoubleDouble(center) => {
+                center.calculate_cells(cells, width, height, position, max_iterations, parameters)
+            }
+        }
+    }
+}
+
+// Calculates cells from each sample's offset from the view's center, for the kernels that
+// keep the center more precisely than f64 can
+fn calculate_offsets(
+    cells: &[(u16, u16)],
+    width: u16,
+    height: u16,
+    position: &Position,
+    parameters: &FractalParameters,
+    iterate: impl Fn((f64, f64)) -> u32,
+) -> Vec<Cell> {
+    let samples = parameters.supersampling.max(1);
+    // Offsets from the center never go through the center's own (rounded) coordinates
+    let offset = |x: f64, y: f64| {
+        let (width, height) = (position.width(), position.height());
+        match position.projection {
+            Projection::Flat => turn(((x - 0.5) * width, (y - 0.5) * height), position.rotation),
+            _ => position.center_offset(x * width, y * height),
+        }
+    };
+    let fraction = |subpixel: f64, sample: u32, extent: u16| {
+        (subpixel + sample as f64 / samples as f64) / (extent as f64 * 2.0)
+    };
+
+    cells
+        .iter()
+        .map(|(pixel_x, pixel_y)| {
+            let mut cell = [[u32x1::splat(0); 2]; 2];
+            for (subpixel_y, row) in cell.iter_mut().enumerate() {
+                for (subpixel_x, value) in row.iter_mut().enumerate() {
+                    let mut total = 0;
+                    for sample_y in 0..samples {
+                        for sample_x in 0..samples {
+                            let x = *pixel_x as f64 * 2.0 + subpixel_x as f64;
+                            let y = *pixel_y as f64 * 2.0 + subpixel_y as f64;
+                            total += iterate(offset(
+                                fraction(x, sample_x, width),
+                                fraction(y, sample_y, height),
+                            ));
+                        }
+                    }
+                    *value = u32x1::splat(total / (samples * samples));
+                }
+            }
+            cell
+        })
+        .collect()
+}
+
+// Subpixels further apart than this fraction of the iteration range count as an edge
+const EDGE_CONTRAST: u64 = 50;
+
+fn is_edge(a: u32, b: u32, max_iterations: u32) -> bool {
+    (a == max_iterations) != (b == max_iterations)
+        || a.abs_diff(b) as u64 * EDGE_CONTRAST > max_iterations as u64
+}
+
+// A root buffer's value for subpixels whose orbits didn't reach a root within the limit
+const NO_ROOT: u32 = u32::MAX;
+
+// The root each subpixel of a Newton fractal view converges to, laid out like the frame's
+// cells, with the iterations it took
+fn calculate_roots(
+    width: u16,
+    height: u16,
+    position: &Position,
+    max_iterations: u32x1,
+    parameters: &FractalParameters,
+) -> (Vec<Cell>, u64) {
+    // Roots are read from where orbits stopped, which is only kept for single samples
+    let parameters = FractalParameters {
+        supersampling: 1,
+        ..*parameters
+    };
+    let orbits: Vec<(Cell, Orbit)> = (0..height)
+        .into_par_iter()
+        .flat_map_iter(|pixel_y| {
+            let coordinates: Vec<(u16, u16)> =
+                (0..width).map(|pixel_x| (pixel_x, pixel_y)).collect();
+            calculate_orbits(
+                &coordinates,
+                None,
+                width,
+                height,
+                position,
+                max_iterations,
+                &parameters,
+            )
+        })
+        .collect();
+    let iterations = count_iterations(orbits.iter().map(|(cell, _)| cell));
+    let roots = orbits
+        .iter()
+        .map(|(_, orbit)| orbit.map(|row| row.map(|point| u32x1::splat(root_index(point)))))
+        .collect();
+    (roots, iterations)
+}
+
+// Marks the subpixels that converge to a later root than any of their neighbours as 0, and the
+// rest as the limit, for the boundaries coloring to draw as lines one subpixel wide
+fn basin_boundaries(roots: &[Cell], width: u16, max_iterations: u32x1) -> Vec<Cell> {
+    let width = width.max(1) as usize;
+    let height = roots.len() / width;
+    let subpixel = |x: usize, y: usize| roots[y / 2 * width + x / 2][y % 2][x % 2][0];
+    (0..roots.len())
+        .into_par_iter()
+        .map(|index| {
+            let (pixel_x, pixel_y) = (index % width, index / width);
+            std::array::from_fn(|subpixel_y| {
+                std::array::from_fn(|subpixel_x| {
+                    let (x, y) = (pixel_x * 2 + subpixel_x, pixel_y * 2 + subpixel_y);
+                    let root = subpixel(x, y);
+                    // Going below 0 wraps round past the edge of the frame
+                    let neighbours = [
+                        (x.wrapping_sub(1), y),
+                        (x + 1, y),
+                        (x, y.wrapping_sub(1)),
+                        (x, y + 1),
+                    ];
+                    let boundary = neighbours
+                        .iter()
+                        .filter(|(x, y)| *x < width * 2 && *y < height * 2)
+                        .any(|&(x, y)| root > subpixel(x, y));
+                    if boundary {
+                        u32x1::splat(0)
+                    } else {
+                        max_iterations
+                    }
+                })
+            })
+        })
+        .collect()
+}
+
+// Samples every subpixel once, then only supersamples the cells on an edge. Edges are found
+// from the cell and its direct neighbours, so the result doesn't depend on how cells are batched.
+fn calculate_adaptive(
+    cells: &[(u16, u16)],
+    width: u16,
+    height: u16,
+    position: &Position,
+    max_iterations: u32x1,
+    parameters: &FractalParameters,
+    deep: Option<&DeepKernel>,
+) -> Vec<Cell> {
+    let compute = |cells: &[(u16, u16)], parameters: &FractalParameters| match deep {
+        Some(deep) => {
+            deep.calculate_cells(cells, width, height, position, max_iterations, parameters)
+        }
+        None => calculate_cells(cells, width, height, position, max_iterations, parameters),
+    };
+    if parameters.supersampling <= 1 {
+        return compute(cells, parameters);
+    }
+
+    let mut coordinates: Vec<(u16, u16)> = cells
+        .iter()
+        .flat_map(|&(pixel_x, pixel_y)| {
+            [
+                Some((pixel_x, pixel_y)),
+                pixel_x.checked_sub(1).map(|x| (x, pixel_y)),
+                (pixel_x + 1 < width).then_some((pixel_x + 1, pixel_y)),
+                pixel_y.checked_sub(1).map(|y| (pixel_x, y)),
+                (pixel_y + 1 < height).then_some((pixel_x, pixel_y + 1)),
+            ]
+        })
+        .flatten()
+        .collect();
+    coordinates.sort_unstable();
+    coordinates.dedup();
+    let single = FractalParameters {
+        supersampling: 1,
+        ..*parameters
+    };
+    let sampled: std::collections::HashMap<(u16, u16), Cell> = coordinates
+        .iter()
+        .copied()
+        .zip(compute(&coordinates, &single))
+        .collect();
+
+    let max_iterations = max_iterations[0];
+    let subpixel = |pixel_x: i32, pixel_y: i32, subpixel_x: i32, subpixel_y: i32| {
+        // Subpixels one step outside the cell come from its neighbours
+        let (x, y) = (pixel_x * 2 + subpixel_x, pixel_y * 2 + subpixel_y);
+        let cell = sampled.get(&(x.div_euclid(2) as u16, y.div_euclid(2) as u16))?;
+        Some(cell[y.rem_euclid(2) as usize][x.rem_euclid(2) as usize][0])
+    };
+    let on_edge = |&(pixel_x, pixel_y): &(u16, u16)| {
+        let (pixel_x, pixel_y) = (pixel_x as i32, pixel_y as i32);
+        (0..2).any(|subpixel_y| {
+            (0..2).any(|subpixel_x| {
+                let value = subpixel(pixel_x, pixel_y, subpixel_x, subpixel_y).unwrap_or(0);
+                [(-1, 0), (1, 0), (0, -1), (0, 1)].iter().any(|(dx, dy)| {
+                    subpixel(pixel_x, pixel_y, subpixel_x + dx, subpixel_y + dy)
+                        .is_some_and(|other| is_edge(value, other, max_iterations))
+                })
+            })
+        })
+    };
+
+    let edges: Vec<(u16, u16)> = cells.iter().copied().filter(on_edge).collect();
+    let mut refined = edges.iter().zip(compute(&edges, parameters));
+    let mut next = refined.next();
+    cells
+        .iter()
+        .map(|coordinate| match next {
+            Some((edge, cell)) if edge == coordinate => {
+                next = refined.next();
+                cell
+            }
+            _ => sampled[coordinate],
+        })
+        .collect()
+}
+
+fn shade_pixel(subpixel_values: [[u32x1; 2]; 2], colors: &ColorTable) -> Pixel {
+    let subpixels_average = (subpixel_values[0][0]
+        + subpixel_values[0][1]
+        + subpixel_values[1][0]
+        + subpixel_values[1][1])
+        / u32x1::splat(4);
+
+    let mut subpixels = [[false; 2]; 2];
+    let mut subpixels_on_values = Vec::new();
+    let mut subpixels_off_values = Vec::new();
+
+    for subpixel_y in 0..2 {
+        for subpixel_x in 0..2 {
+            if subpixel_values[subpixel_y as usize][subpixel_x as usize] >= subpixels_average {
+                subpixels_on_values.push(subpixel_values[subpixel_y as usize][subpixel_x as usize]);
+                subpixels[subpixel_y as usize][subpixel_x as usize] = true;
+            } else {
+                subpixels_off_values
+                    .push(subpixel_values[subpixel_y as usize][subpixel_x as usize]);
+            }
+        }
+    }
+
+    if subpixels_on_values.len() == 4 {
+        Pixel {
+            character: get_pixel(subpixels),
+            foreground_color: colors.color(subpixels_average),
+            background_color: None,
+        }
+    } else {
+        let mut subpixels_on_average = u32x1::splat(0);
+        if !subpixels_on_values.is_empty() {
+            for subpixel_on_value in &subpixels_on_values {
+                subpixels_on_average += subpixel_on_value;
+            }
+            subpixels_on_average /= u32x1::splat(subpixels_on_values.len() as u32);
+        }
+
+        let mut subpixels_off_average = u32x1::splat(0);
+        if !subpixels_off_values.is_empty() {
+            for subpixel_off_value in &subpixels_off_values {
+                subpixels_off_average += subpixel_off_value;
+            }
+            subpixels_off_average /= u32x1::splat(subpixels_off_values.len() as u32);
+        }
+
+        Pixel {
+            character: get_pixel(subpixels),
+            foreground_color: colors.color(subpixels_on_average),
+            background_color: Some(colors.color(subpixels_off_average)),
+        }
+    }
+}
+
+// Shades the 2x4 subpixels of two cells stacked in one terminal cell as Braille dots, in the
+// same way as quadrants
+fn shade_braille(upper: Cell, lower: Cell, colors: &ColorTable) -> Pixel {
+    let subpixels = [upper[0], upper[1], lower[0], lower[1]];
+    let total: u64 = subpixels.iter().flatten().map(|value| value[0] as u64).sum();
+    let average = u32x1::splat((total / 8) as u32);
+
+    let mut dots = 0;
+    let (mut on_total, mut on_count) = (0, 0);
+    for (row, values) in subpixels.iter().enumerate() {
+        for (column, value) in values.iter().enumerate() {
+            if *value >= average {
+                dots |= BRAILLE_DOTS[row][column];
+                on_total += value[0] as u64;
+                on_count += 1;
+            }
+        }
+    }
+
+    if on_count == 8 {
+        Pixel {
+            character: '█',
+            foreground_color: colors.color(average),
+            background_color: None,
+        }
+    } else {
+        // The largest value is always on, so only the off ones can be missing
+        let off_average = (total - on_total) / (8 - on_count);
+        Pixel {
+            character: char::from_u32(0x2800 + dots).unwrap(),
+            foreground_color: colors.color(u32x1::splat((on_total / on_count) as u32)),
+            background_color: Some(colors.color(u32x1::splat(off_average as u32))),
+        }
+    }
+}
+
+/// The iteration counts of a cell's 2x2 subpixels.
+pub type Cell = [[u32x1; 2]; 2];
+
+// Where the orbit of each of a cell's subpixels stopped, so raising the limit can carry on from
+// there. It is NaN for the ones found to stay inside, and meaningless for the ones that escaped.
+type Orbit = [[(f64, f64); 2]; 2];
+
+// A calculated cell, with its orbit if it is kept
+type OrbitCell = (Cell, Option<Orbit>);
+
+// Where a subpixel that stopped at the limit `previous` carries on from up to `max_iterations`.
+// Escaped orbits get no `z` to step from and keep their count, and ones found to stay inside
+// are inside at any limit.
+fn carry_on(
+    mut cell: Cell,
+    mut orbit: Orbit,
+    previous: u32x1,
+    max_iterations: u32x1,
+) -> (Cell, Orbit) {
+    for (values, points) in cell.iter_mut().zip(&mut orbit) {
+        for (value, point) in values.iter_mut().zip(points) {
+            if *value < previous {
+                *point = (f64::NAN, f64::NAN);
+            } else if point.0.is_nan() {
+                *value = max_iterations;
+            }
+        }
+    }
+    (cell, orbit)
+}
+
+/// How terminal cells are split into subpixels: 2x2 quadrant blocks, 1x2 half blocks, which
+/// take half the work, or 2x4 Braille dots, which show twice the detail.
+pub const GRID_NAMES: [&str; 3] = ["Quadrants", "Half blocks", "Braille"];
+
+const HALF_BLOCKS_INDEX: usize = 1;
+
+/// The index of Braille in [`GRID_NAMES`], which consoles without the characters can't show.
+pub const BRAILLE_INDEX: usize = 2;
+
+// Bits of the Braille dots, down the left column then the right, with the bottom pair last
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// The cells to calculate for a frame of `width` by `height` terminal cells split as
+/// `grid_index` of [`GRID_NAMES`] says, and the view they cover. Half block frames calculate
+/// a cell for every two columns, and Braille ones two cells down each one.
+pub fn grid_frame(
+    grid_index: usize,
+    width: u16,
+    height: u16,
+    position: &Position,
+) -> (u16, u16, Position) {
+    match grid_index {
+        HALF_BLOCKS_INDEX if width % 2 == 1 => {
+            // The odd column is covered by a cell reaching past the right edge
+            let column = position.width() / width as f64;
+            let widened = position
+                .resized(position.width() + column, position.height())
+                .moved(column / 2.0, 0.0);
+            (width.div_ceil(2), height, widened)
+        }
+        HALF_BLOCKS_INDEX => (width / 2, height, position.clone()),
+        BRAILLE_INDEX => (width, height.saturating_mul(2), position.clone()),
+        _ => (width, height, position.clone()),
+    }
+}
+
+fn render_row(
+    pixel_y: u16,
+    width: u16,
+    height: u16,
+    position: &Position,
+    max_iterations: u32x1,
+    parameters: &FractalParameters,
+    colors: &ColorTable,
+) -> (String, u64) {
+    let coordinates: Vec<(u16, u16)> = (0..width).map(|pixel_x| (pixel_x, pixel_y)).collect();
+    let cells = calculate_cells(
+        &coordinates,
+        width,
+        height,
+        position,
+        max_iterations,
+        parameters,
+    );
+
+    (shade_row(&cells, colors), count_iterations(&cells))
+}
+
+fn count_iterations<'a>(cells: impl IntoIterator<Item = &'a Cell>) -> u64 {
+    cells
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|value| value[0] as u64)
+        .sum()
+}
+
+fn push_decimal(output: &mut String, value: u8) {
+    if value >= 100 {
+        output.push((b'0' + value / 100) as char);
+    }
+    if value >= 10 {
+        output.push((b'0' + value / 10 % 10) as char);
+    }
+    output.push((b'0' + value % 10) as char);
+}
+
+// Sets the terminal back to its own colors
+const RESET_COLOR: &str = "\x1b[0m";
+
+// Writes the escape sequence selecting a color without going through a formatter, since this
+// runs for most cells of every frame
+fn push_color(output: &mut String, [r, g, b]: [u8; 3], background: bool) {
+    output.push_str(if background {
+        "\x1b[48;2;"
+    } else {
+        "\x1b[38;2;"
+    });
+    push_decimal(output, r);
+    output.push(';');
+    push_decimal(output, g);
+    output.push(';');
+    push_decimal(output, b);
+    output.push('m');
+}
+
+fn shade_row(cells: &[Cell], colors: &ColorTable) -> String {
+    let mut output = String::new();
+    write_row(&mut output, cells, colors);
+    output
+}
+
+fn write_row(output: &mut String, cells: &[Cell], colors: &ColorTable) {
+    write_pixels(output, cells.iter().map(|cell| shade_pixel(*cell, colors)));
+}
+
+fn write_pixels(output: &mut String, pixels: impl Iterator<Item = Pixel>) {
+    // Rows start from the terminal's own colors, which no cell has
+    let mut last_fg_color = None;
+    let mut last_bg_color = None;
+
+    for pixel in pixels {
+        let fg_color = pixel.foreground_color;
+        if Some(fg_color) != last_fg_color {
+            push_color(output, fg_color, false);
+            last_fg_color = Some(fg_color);
+        }
+
+        if let Some(bg_color) = pixel.background_color {
+            if Some(bg_color) != last_bg_color {
+                push_color(output, bg_color, true);
+                last_bg_color = Some(bg_color);
+            }
+        }
+
+        output.push(pixel.character);
+    }
+}
+
+// Joins shaded rows into the text of a frame, one line per row
+fn join_rows(rows: &[String], output: &mut String) {
+    output.clear();
+    output.reserve(rows.iter().map(|row| row.len() + 1).sum());
+    for (index, row) in rows.iter().enumerate() {
+        if index > 0 {
+            output.push('\n');
+        }
+        output.push_str(row);
+    }
+    output.push_str(RESET_COLOR);
+}
+
+/// Renders a view straight to escape-sequence text, one string per row, along with the
+/// iterations it took.
+pub fn render_rows(
+    width: u16,
+    height: u16,
+    position: &Position,
+    max_iterations: u32x1,
+    parameters: &FractalParameters,
+) -> (Vec<String>, u64) {
+    let colors = ColorTable::new(max_iterations, parameters);
+    if colors.boundaries() {
+        // Boundaries run across rows, so the whole frame's roots are found before any is shaded
+        let (roots, iterations) =
+            calculate_roots(width, height, position, max_iterations, parameters);
+        let rows = basin_boundaries(&roots, width, max_iterations)
+            .par_chunks(width.max(1) as usize)
+            .map(|row| shade_row(row, &colors))
+            .collect();
+        return (rows, iterations);
+    }
+
+    let (rows, iterations): (Vec<String>, Vec<u64>) = (0..height)
+        .into_par_iter()
+        .map(|pixel_y| {
+            render_row(
+                pixel_y,
+                width,
+                height,
+                position,
+                max_iterations,
+                parameters,
+                &colors,
+            )
+        })
+        .unzip();
+    (rows, iterations.iter().sum())
+}
+
+/// A rendered frame: the iteration counts of every cell, with what they were calculated for.
+pub struct FrameBuffer {
+    /// Columns of cells.
+    pub width: u16,
+    /// Rows of cells.
+    pub height: u16,
+    /// The view the cells were calculated for.
+    pub position: Position,
+    /// The iteration limit the cells were calculated with.
+    pub max_iterations: u32x1,
+    /// The parameters the cells were calculated with.
+    pub parameters: FractalParameters,
+    cells: Vec<Cell>,
+    // Where each cell's orbits stopped, for frames whose limit can be raised without starting
+    // over. Empty for the others.
+    orbits: Vec<Option<Orbit>>,
+}
+
+impl FrameBuffer {
+    /// How many whole cells `position` is moved from this frame's view, if it is only moved.
+    pub fn cell_shift(&self, position: &Position) -> Option<(i32, i32)> {
+        self.grid_offset(self.width, self.height, position)
+    }
+
+    // How many whole cells the first corner of a `width` by `height` frame of `position` is
+    // from this frame's, if that frame's cells are the same as this one's and line up with them
+    fn grid_offset(&self, width: u16, height: u16, position: &Position) -> Option<(i32, i32)> {
+        // Only a pure translation by whole cells lines the old samples up with the new ones
+        let cell_width = self.position.width() / self.width as f64;
+        let cell_height = self.position.height() / self.height as f64;
+        let same_size = (position.width() - cell_width * width as f64).abs() <= cell_width * 1e-6
+            && (position.height() - cell_height * height as f64).abs() <= cell_height * 1e-6;
+        let same_grid = position.rotation == self.position.rotation
+            && position.projection == Projection::Flat
+            && self.position.projection == Projection::Flat;
+        if !same_size || !same_grid {
+            return None;
+        }
+
+        let (x, y) = self.position.offset_of(&position.point_at(0.0, 0.0));
+        let (columns, rows) = (x / cell_width, y / cell_height);
+        let whole = |cells: f64| (cells - cells.round()).abs() < 1e-6;
+        if whole(columns) && whole(rows) {
+            Some((columns.round() as i32, rows.round() as i32))
+        } else {
+            None
+        }
+    }
+
+    /// The part of this frame that a `width` by `height` frame of `position` would show, if
+    /// `position` lies inside this frame's view and on its grid of cells.
+    pub fn cropped(&self, width: u16, height: u16, position: &Position) -> Option<FrameBuffer> {
+        let (column, row) = self.grid_offset(width, height, position)?;
+        if column < 0
+            || row < 0
+            || column + width as i32 > self.width as i32
+            || row + height as i32 > self.height as i32
+        {
+            return None;
+        }
+
+        let (column, row) = (column as usize, row as usize);
+        let cells = (row..row + height as usize)
+            .flat_map(|row| {
+                let start = row * self.width as usize + column;
+                self.cells[start..start + width as usize].iter().copied()
+            })
+            .collect();
+        Some(FrameBuffer {
+            width,
+            height,
+            position: position.clone(),
+            max_iterations: self.max_iterations,
+            parameters: self.parameters,
+            cells,
+            orbits: Vec::new(),
+        })
+    }
+
+    /// Moves a view of the same size onto this frame's grid of cells.
+    pub fn snap(&self, position: &Position) -> Position {
+        let cell_width = self.position.width() / self.width as f64;
+        let cell_height = self.position.height() / self.height as f64;
+        if (position.width() - self.position.width()).abs() > cell_width * 1e-6
+            || (position.height() - self.position.height()).abs() > cell_height * 1e-6
+            || position.rotation != self.position.rotation
+            || position.projection != Projection::Flat
+            || self.position.projection != Projection::Flat
+        {
+            return position.clone();
+        }
+
+        let (x, y) = self.position.offset_of(&position.point_at(0.0, 0.0));
+        let (columns, rows) = ((x / cell_width).round(), (y / cell_height).round());
+        self.position
+            .moved(columns * cell_width, rows * cell_height)
+    }
+
+    #[cfg(test)]
+    fn rows(&self) -> Vec<String> {
+        let colors = self.color_table();
+        self.shaded_cells(&colors)
+            .par_chunks(self.width.max(1) as usize)
+            .map(|row| shade_row(row, &colors))
+            .collect()
+    }
+
+    /// The iteration counts of each cell's subpixels, row by row.
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    /// The color table for this frame's limit, palette and coloring.
+    pub fn color_table(&self) -> ColorTable {
+        ColorTable::new(self.max_iterations, &self.parameters)
+    }
+
+    // The cells as `colors` shades them, which under the boundaries coloring are only the
+    // boundaries
+    pub(crate) fn shaded_cells(&self, colors: &ColorTable) -> std::borrow::Cow<'_, [Cell]> {
+        if colors.boundaries() {
+            let (roots, _) = calculate_roots(
+                self.width,
+                self.height,
+                &self.position,
+                self.max_iterations,
+                &self.parameters,
+            );
+            let cells = basin_boundaries(&roots, self.width, self.max_iterations);
+            std::borrow::Cow::Owned(cells)
+        } else {
+            std::borrow::Cow::Borrowed(&self.cells)
+        }
+    }
+
+    /// Shades the frame into escape-sequence text, one line per row.
+    pub fn text(&self) -> String {
+        let mut output = String::new();
+        self.write_text(&self.color_table(), &mut Vec::new(), &mut output);
+        output
+    }
+
+    // Shades the frame into `output`, reusing the allocations of `rows` and `output` from
+    // earlier frames
+    fn write_text(&self, colors: &ColorTable, rows: &mut Vec<String>, output: &mut String) {
+        rows.resize_with(self.height as usize, String::new);
+        rows.par_iter_mut()
+            .zip(self.shaded_cells(colors).par_chunks(self.width.max(1) as usize))
+            .for_each(|(row, cells)| {
+                row.clear();
+                write_row(row, cells, colors);
+            });
+        join_rows(rows, output);
+    }
+
+    // Shades each pair of rows into a line of Braille dots
+    fn write_braille_text(&self, colors: &ColorTable, rows: &mut Vec<String>, output: &mut String) {
+        let width = self.width.max(1) as usize;
+        rows.resize_with(self.height as usize / 2, String::new);
+        rows.par_iter_mut()
+            .zip(self.shaded_cells(colors).par_chunks(width * 2))
+            .for_each(|(row, cells)| {
+                row.clear();
+                let (upper, lower) = cells.split_at(width);
+                let pixels = upper
+                    .iter()
+                    .zip(lower)
+                    .map(|(upper, lower)| shade_braille(*upper, *lower, colors));
+                write_pixels(row, pixels);
+            });
+        join_rows(rows, output);
+    }
+
+    /// Shades a frame calculated for [`grid_frame`] into text for `width` by `height` terminal
+    /// cells of `position`, along with the frame stretched over those cells if it doesn't
+    /// already have one cell for each.
+    pub fn grid_text(
+        &self,
+        grid_index: usize,
+        width: u16,
+        height: u16,
+        position: &Position,
+    ) -> (String, Option<FrameBuffer>) {
+        let mut output = String::new();
+        let stretched = self.write_grid_text(
+            grid_index,
+            width,
+            height,
+            position,
+            &self.color_table(),
+            &mut Vec::new(),
+            &mut output,
+        );
+        (output, stretched)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn write_grid_text(
+        &self,
+        grid_index: usize,
+        width: u16,
+        height: u16,
+        position: &Position,
+        colors: &ColorTable,
+        rows: &mut Vec<String>,
+        output: &mut String,
+    ) -> Option<FrameBuffer> {
+        let stretched = |buffer: &FrameBuffer, width, height| {
+            ((buffer.width, buffer.height) != (width, height))
+                .then(|| buffer.resampled(width, height, position, &buffer.parameters))
+        };
+        if grid_index == BRAILLE_INDEX {
+            let dots = stretched(self, width, height.saturating_mul(2));
+            let dots = dots.as_ref().unwrap_or(self);
+            dots.write_braille_text(colors, rows, output);
+            stretched(dots, width, height)
+        } else {
+            // Half blocks are the quadrants of cells whose columns are the same
+            let stretched = stretched(self, width, height);
+            stretched
+                .as_ref()
+                .unwrap_or(self)
+                .write_text(colors, rows, output);
+            stretched
+        }
+    }
+
+    /// Stretches this frame over another view as a stand-in until that view has been rendered.
+    /// Parts of the view this frame doesn't cover are left as escaping straight away.
+    pub fn resampled(
+        &self,
+        width: u16,
+        height: u16,
+        position: &Position,
+        parameters: &FractalParameters,
+    ) -> FrameBuffer {
+        let columns = self.width as usize * 2;
+        let rows = self.height as usize * 2;
+        let scale_x =
+            position.width() / self.position.width() * columns as f64 / (width as f64 * 2.0);
+        let scale_y =
+            position.height() / self.position.height() * rows as f64 / (height as f64 * 2.0);
+        let (left, top) = self.position.offset_of(&position.point_at(0.0, 0.0));
+        let left = left / self.position.width() * columns as f64;
+        let top = top / self.position.height() * rows as f64;
+        // Subpixels of a view turned from this one are turned on the plane, then measured in
+        // this frame's subpixels. Projected views go through the plane itself.
+        let angle = position.rotation - self.position.rotation;
+        let projected =
+            position.projection != Projection::Flat || self.position.projection != Projection::Flat;
+        let subpixel_width = position.width() / (width as f64 * 2.0);
+        let subpixel_height = position.height() / (height as f64 * 2.0);
+
+        let sample = |subpixel_x: usize, subpixel_y: usize| {
+            let (x, y) = (subpixel_x as f64 + 0.5, subpixel_y as f64 + 0.5);
+            let (x, y) = if projected {
+                let point = position.point_at(x * subpixel_width, y * subpixel_height);
+                let (x, y) = self.position.offset_of(&point);
+                (
+                    x / self.position.width() * columns as f64,
+                    y / self.position.height() * rows as f64,
+                )
+            } else if angle == 0.0 {
+                (left + x * scale_x, top + y * scale_y)
+            } else {
+                let (x, y) = turn((x * subpixel_width, y * subpixel_height), angle);
+                (
+                    left + x / self.position.width() * columns as f64,
+                    top + y / self.position.height() * rows as f64,
+                )
+            };
+            let (x, y) = (x.floor(), y.floor());
+            if x < 0.0 || y < 0.0 || x >= columns as f64 || y >= rows as f64 {
+                return u32x1::splat(0);
+            }
+            let (x, y) = (x as usize, y as usize);
+            self.cells[y / 2 * self.width as usize + x / 2][y % 2][x % 2]
+        };
+
+        let cells = (0..height as usize)
+            .flat_map(|pixel_y| (0..width as usize).map(move |pixel_x| (pixel_x, pixel_y)))
+            .map(|(pixel_x, pixel_y)| {
+                let mut cell = [[u32x1::splat(0); 2]; 2];
+                for (subpixel_y, row) in cell.iter_mut().enumerate() {
+                    for (subpixel_x, value) in row.iter_mut().enumerate() {
+                        *value = sample(pixel_x * 2 + subpixel_x, pixel_y * 2 + subpixel_y);
+                    }
+                }
+                cell
+            })
+            .collect();
+
+        FrameBuffer {
+            width,
+            height,
+            position: position.clone(),
+            max_iterations: self.max_iterations,
+            parameters: *parameters,
+            cells,
+            orbits: Vec::new(),
+        }
+    }
+}
+
+/// The parameters with the palette, coloring and palette phase reset.
+///
+/// Palettes and colorings only change how cells are shaded, so the iteration counts computed
+/// under one can be reused for all of them.
+pub fn iteration_parameters(parameters: &FractalParameters) -> FractalParameters {
+    FractalParameters {
+        palette_index: 0,
+        coloring_index: 0,
+        palette_phase: 0.0,
+        ..*parameters
+    }
+}
+
+#[derive(PartialEq, Debug)]
+struct TileKey {
+    // The cheap fields come first so most mismatches are found before comparing coordinates
+    cell_width: f64,
+    cell_height: f64,
+    columns: u16,
+    rows: u16,
+    rotation: f64,
+    projection: Projection,
+    // Tiles of a projected view depend on where they are in the frame and its size, not only
+    // the point they start from, which is then the center
+    placement: Option<(u16, u16, u16, u16)>,
+    max_iterations: u32x1,
+    parameters: FractalParameters,
+    left: Fixed,
+    top: Fixed,
+}
+
+impl TileKey {
+    fn new(
+        position: &Position,
+        width: u16,
+        height: u16,
+        tile: (u16, u16, u16, u16),
+        max_iterations: u32x1,
+        parameters: &FractalParameters,
+    ) -> TileKey {
+        let (tile_x, tile_y, columns, rows) = tile;
+        let cell_width = position.width() / width as f64;
+        let cell_height = position.height() / height as f64;
+        let (placement, (left, top)) = match position.projection {
+            Projection::Flat => (
+                None,
+                position.point_at(tile_x as f64 * cell_width, tile_y as f64 * cell_height),
+            ),
+            _ => (Some((tile_x, tile_y, width, height)), position.center()),
+        };
+        TileKey {
+            cell_width,
+            cell_height,
+            columns,
+            rows,
+            rotation: position.rotation,
+            projection: position.projection,
+            placement,
+            max_iterations,
+            parameters: iteration_parameters(parameters),
+            left,
+            top,
+        }
+    }
+}
+
+const TILE_CACHE_CAPACITY: usize = 512;
+
+/// Recently rendered tiles, least recently used first, so returning to a view doesn't
+/// recompute it.
+pub struct TileCache {
+    tiles: std::collections::VecDeque<(TileKey, Vec<Cell>)>,
+    capacity: usize,
+}
+
+impl TileCache {
+    /// Creates a cache that holds up to `capacity` tiles.
+    pub fn new(capacity: usize) -> TileCache {
+        TileCache {
+            tiles: std::collections::VecDeque::new(),
+            capacity,
+        }
+    }
+
+    // A tile that is found moves to the back, as the most recently used
+    fn get(&mut self, key: &TileKey) -> Option<&Vec<Cell>> {
+        let index = self.tiles.iter().position(|(cached, _)| cached == key)?;
+        let tile = self.tiles.remove(index)?;
+        self.tiles.push_back(tile);
+        self.tiles.back().map(|(_, cells)| cells)
+    }
+
+    fn insert(&mut self, key: TileKey, cells: Vec<Cell>) {
+        if let Some(index) = self.tiles.iter().position(|(cached, _)| *cached == key) {
+            self.tiles.remove(index);
+        }
+        self.tiles.push_back((key, cells));
+        while self.tiles.len() > self.capacity {
+            self.tiles.pop_front();
+        }
+    }
+}
+
+// Fractals that are symmetric under conjugation look the same above and below the real axis.
+// When the view's sample grid lines up with its own reflection, subpixel row `r` mirrors row
+// `sum - r`, and this returns that sum.
+fn mirror_sum(height: u16, position: &Position, parameters: &FractalParameters) -> Option<i64> {
+    let symmetric = (parameters.fractal_index == 0 && parameters.initial_z.1 == 0.0)
+        || (parameters.fractal_index == JULIA_INDEX && parameters.julia_constant.1 == 0.0);
+    // Supersampled cells start from one sample at the top edge of each subpixel, which the
+    // reflection moves to the bottom edge. Turned and projected views have no rows along
+    // the axis.
+    if !symmetric || parameters.supersampling > 1 || !position.is_plain() {
+        return None;
+    }
+
+    // Subpixel rows are sampled along their top edges
+    let spacing = position.height() / (height as f64 * 2.0);
+    let reflected = -2.0 * position.top.to_f64() / spacing;
+    let rounded = reflected.round();
+    if (reflected - rounded).abs() > 1e-6 || rounded < 1.0 || rounded >= height as f64 * 4.0 {
+        return None;
+    }
+    Some(rounded as i64)
+}
+
+/// Renders a view into a frame buffer, along with the iterations it took.
+///
+/// Cells are reused from `previous` if the view has only moved by whole cells, and from
+/// `cache` for tiles that were rendered before.
+#[allow(clippy::too_many_arguments)]
+pub fn render_buffer(
+    width: u16,
+    height: u16,
+    position: &Position,
+    max_iterations: u32x1,
+    parameters: &FractalParameters,
+    tile_size: u16,
+    previous: Option<&FrameBuffer>,
+    cache: &mut TileCache,
+) -> (FrameBuffer, u64) {
+    let (buffer, iterations, _) = render_buffer_reporting(
+        width,
+        height,
+        position,
+        max_iterations,
+        parameters,
+        tile_size,
+        previous,
+        cache,
+        None,
+    );
+    (buffer, iterations)
+}
+
+// How many tiles of the frame being rendered are done, out of how many
+#[derive(Default)]
+struct RenderProgress {
+    done: std::sync::atomic::AtomicUsize,
+    total: std::sync::atomic::AtomicUsize,
+}
+
+impl RenderProgress {
+    fn start(&self, tiles: usize) {
+        self.done.store(0, std::sync::atomic::Ordering::Relaxed);
+        self.total
+            .store(tiles, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    fn tile_done(&self) {
+        self.done.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    fn finish(&self) {
+        self.total.store(0, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    // The share of tiles done, or nothing between frames
+    fn fraction(&self) -> Option<f64> {
+        let total = self.total.load(std::sync::atomic::Ordering::Relaxed);
+        let done = self.done.load(std::sync::atomic::Ordering::Relaxed);
+        (total > 0).then(|| done.min(total) as f64 / total as f64)
+    }
+}
+
+// One cell in this many of a tile is sampled to set its limit, which is this many times the
+// slowest escape among them, but never below the minimum, so shallow views aren't cut short
+const TILE_LIMIT_STRIDE: usize = 8;
+const TILE_LIMIT_FACTOR: u32 = 4;
+const TILE_LIMIT_MINIMUM: u32 = 1000;
+
+// Calculates every few cells at the full limit first, and the rest under a lower one if
+// none of those are inside and all escape well before it. Returns the cells with the
+// iterations spent on them, since the ones that reach the lower limit are filled in as
+// inside.
+fn calculate_limited(
+    cells: &[(u16, u16)],
+    max_iterations: u32x1,
+    compute: impl Fn(&[(u16, u16)], u32x1) -> Vec<OrbitCell>,
+) -> (Vec<OrbitCell>, u64) {
+    let is_sample = |index: usize| index.is_multiple_of(TILE_LIMIT_STRIDE);
+    let (samples, rest): (Vec<_>, Vec<_>) = cells
+        .iter()
+        .enumerate()
+        .partition(|(index, _)| is_sample(*index));
+    let samples: Vec<(u16, u16)> = samples.into_iter().map(|(_, cell)| *cell).collect();
+    let rest: Vec<(u16, u16)> = rest.into_iter().map(|(_, cell)| *cell).collect();
+
+    let sampled = compute(&samples, max_iterations);
+    let slowest = sampled
+        .iter()
+        .flat_map(|(cell, _)| cell.iter().flatten())
+        .map(|value| value[0])
+        .max()
+        .unwrap_or(0);
+    let limit = slowest
+        .saturating_mul(TILE_LIMIT_FACTOR)
+        .max(TILE_LIMIT_MINIMUM)
+        .min(max_iterations[0]);
+    let mut limited = compute(&rest, u32x1::splat(limit));
+    let iterations = count_iterations(sampled.iter().chain(&limited).map(|(cell, _)| cell));
+    if limit < max_iterations[0] {
+        for (cell, orbit) in &mut limited {
+            let reached = cell.iter().flatten().any(|value| value[0] >= limit);
+            for value in cell.iter_mut().flatten().filter(|value| value[0] >= limit) {
+                *value = max_iterations;
+            }
+            // An orbit stopped at the lower limit can't be carried on as if it were inside
+            if reached {
+                *orbit = None;
+            }
+        }
+    }
+
+    let mut sampled = sampled.into_iter();
+    let mut limited = limited.into_iter();
+    let cells = (0..cells.len())
+        .map(|index| {
+            if is_sample(index) {
+                sampled.next()
+            } else {
+                limited.next()
+            }
+        })
+        .map(Option::unwrap)
+        .collect();
+    (cells, iterations)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render_buffer_reporting(
+    width: u16,
+    height: u16,
+    position: &Position,
+    max_iterations: u32x1,
+    parameters: &FractalParameters,
+    tile_size: u16,
+    previous: Option<&FrameBuffer>,
+    cache: &mut TileCache,
+    progress: Option<&RenderProgress>,
+) -> (FrameBuffer, u64, Option<usize>) {
+    let shift = previous
+        .filter(|previous| {
+            previous.width == width
+                && previous.height == height
+                && previous.max_iterations == max_iterations
+                && iteration_parameters(&previous.parameters) == iteration_parameters(parameters)
+        })
+        .and_then(|previous| Some((previous, previous.cell_shift(position)?)));
+    let deep = DeepKernel::for_view(width, position, max_iterations, parameters);
+    // Orbits of the built-in kernels in f64 can be carried on. When only the limit has gone up
+    // since the last frame, the ones that ran out of iterations carry on from where they stopped.
+    let keep_orbits = parameters.supersampling <= 1
+        && parameters.fractal_index < FRACTAL_NAMES.len()
+        && deep.is_none();
+    let resume = previous.filter(|previous| {
+        keep_orbits
+            && !previous.orbits.is_empty()
+            && previous.width == width
+            && previous.height == height
+            && previous.position == *position
+            && previous.max_iterations[0] < max_iterations[0]
+            && iteration_parameters(&previous.parameters) == iteration_parameters(parameters)
+    });
+    // Rows whose subpixels all mirror rows on the other side of the axis are copied, not computed
+    let mirror = mirror_sum(height, position, parameters);
+    let mirrored = |pixel_y: u16| {
+        mirror.is_some_and(|sum| {
+            let first = pixel_y as i64 * 2;
+            first * 2 > sum && first < sum
+        })
+    };
+
+    // Square tiles balance the load better than rows when the slow pixels are bunched together
+    let tile_size = tile_size.max(1);
+    let tiles: Vec<(u16, u16)> = (0..height)
+        .step_by(tile_size as usize)
+        .flat_map(|tile_y| {
+            (0..width)
+                .step_by(tile_size as usize)
+                .map(move |tile_x| (tile_x, tile_y))
+        })
+        .collect();
+    if let Some(progress) = progress {
+        progress.start(tiles.len());
+    }
+
+    let keys: Vec<TileKey> = tiles
+        .iter()
+        .map(|&(tile_x, tile_y)| {
+            let columns = tile_size.min(width - tile_x);
+            let rows = tile_size.min(height - tile_y);
+            TileKey::new(
+                position,
+                width,
+                height,
+                (tile_x, tile_y, columns, rows),
+                max_iterations,
+                parameters,
+            )
+        })
+        .collect();
+    // Tiles are looked up before any are rendered, since finding one moves it in the cache
+    let hits: Vec<Option<Vec<Cell>>> = keys.iter().map(|key| cache.get(key).cloned()).collect();
+    let results: Vec<(TileKey, Vec<Option<OrbitCell>>, u64)> = tiles
+        .par_iter()
+        .zip(keys)
+        .zip(&hits)
+        .map(|((&(tile_x, tile_y), key), hit)| {
+            let columns = tile_size.min(width - tile_x);
+            let rows = tile_size.min(height - tile_y);
+            if let Some(cells) = hit {
+                if let Some(progress) = progress {
+                    progress.tile_done();
+                }
+                return (
+                    key,
+                    cells.iter().map(|cell| Some((*cell, None))).collect(),
+                    0,
+                );
+            }
+
+            let mut cells: Vec<Option<OrbitCell>> = vec![None; columns as usize * rows as usize];
+            let mut missing = Vec::new();
+            let mut resumed = Vec::new();
+            for pixel_y in tile_y..tile_y + rows {
+                for pixel_x in tile_x..tile_x + columns {
+                    // Reuse the cell that showed this part of the plane last frame, if any
+                    let index = (pixel_y - tile_y) as usize * columns as usize
+                        + (pixel_x - tile_x) as usize;
+                    let reused = shift.and_then(|(previous, (columns, rows))| {
+                        let source_x = pixel_x as i32 + columns;
+                        let source_y = pixel_y as i32 + rows;
+                        if (0..width as i32).contains(&source_x)
+                            && (0..height as i32).contains(&source_y)
+                        {
+                            let source = source_y as usize * width as usize + source_x as usize;
+                            let orbit = previous.orbits.get(source).copied().flatten();
+                            Some((previous.cells[source], orbit))
+                        } else {
+                            None
+                        }
+                    });
+                    let carried = resume.and_then(|previous| {
+                        let source = pixel_y as usize * width as usize + pixel_x as usize;
+                        Some((previous.cells[source], previous.orbits[source]?))
+                    });
+                    match (reused, carried) {
+                        (Some(cell), _) => cells[index] = Some(cell),
+                        _ if mirrored(pixel_y) => (),
+                        (None, Some(stopped)) => resumed.push(((pixel_x, pixel_y), stopped)),
+                        (None, None) => missing.push((pixel_x, pixel_y)),
+                    }
+                }
+            }
+
+            let compute = |cells: &[(u16, u16)], max_iterations: u32x1| -> Vec<OrbitCell> {
+                if keep_orbits {
+                    calculate_orbits(
+                        cells,
+                        None,
+                        width,
+                        height,
+                        position,
+                        max_iterations,
+                        parameters,
+                    )
+                    .into_iter()
+                    .map(|(cell, orbit)| (cell, Some(orbit)))
+                    .collect()
+                } else {
+                    calculate_adaptive(
+                        cells,
+                        width,
+                        height,
+                        position,
+                        max_iterations,
+                        parameters,
+                        deep.as_ref(),
+                    )
+                    .into_iter()
+                    .map(|cell| (cell, None))
+                    .collect()
+                }
+            };
+            let (computed, computed_iterations) = if parameters.tile_limits {
+                calculate_limited(&missing, max_iterations, compute)
+            } else {
+                let computed = compute(&missing, max_iterations);
+                let iterations = count_iterations(computed.iter().map(|(cell, _)| cell));
+                (computed, iterations)
+            };
+            let (resumed, stopped): (Vec<_>, Vec<_>) = resumed.into_iter().unzip();
+            let starts: Vec<(Cell, Orbit)> = match resume {
+                Some(previous) => stopped
+                    .iter()
+                    .map(|&(cell, orbit)| {
+                        carry_on(cell, orbit, previous.max_iterations, max_iterations)
+                    })
+                    .collect(),
+                None => Vec::new(),
+            };
+            let continued = calculate_orbits(
+                &resumed,
+                Some(&starts),
+                width,
+                height,
+                position,
+                max_iterations,
+                parameters,
+            );
+            // Only the iterations past where the carried on orbits stopped are new
+            let iterations = computed_iterations
+                + count_iterations(continued.iter().map(|(cell, _)| cell))
+                - count_iterations(stopped.iter().map(|(cell, _)| cell));
+            let continued = continued
+                .into_iter()
+                .map(|(cell, orbit)| (cell, Some(orbit)));
+            for ((pixel_x, pixel_y), cell) in missing
+                .iter()
+                .chain(&resumed)
+                .zip(computed.into_iter().chain(continued))
+            {
+                let index =
+                    (pixel_y - tile_y) as usize * columns as usize + (pixel_x - tile_x) as usize;
+                cells[index] = Some(cell);
+            }
+            if let Some(progress) = progress {
+                progress.tile_done();
+            }
+
+            (key, cells, iterations)
+        })
+        .collect();
+
+    let tile_indices = |(tile_x, tile_y): (u16, u16), key: &TileKey| {
+        let columns = key.columns as usize;
+        (0..columns * key.rows as usize).map(move |index| {
+            let pixel_x = tile_x as usize + index % columns;
+            let pixel_y = tile_y as usize + index / columns;
+            pixel_y * width as usize + pixel_x
+        })
+    };
+
+    let mut frame: Vec<Option<OrbitCell>> = vec![None; width as usize * height as usize];
+    let mut iterations = 0;
+    for (tile, (key, tile_cells, tile_iterations)) in tiles.iter().zip(&results) {
+        for (index, cell) in tile_indices(*tile, key).zip(tile_cells) {
+            frame[index] = *cell;
+        }
+        iterations += tile_iterations;
+    }
+
+    if let Some(sum) = mirror {
+        for pixel_y in (0..height).filter(|pixel_y| mirrored(*pixel_y)) {
+            for pixel_x in 0..width as usize {
+                let index = pixel_y as usize * width as usize + pixel_x;
+                if frame[index].is_some() {
+                    continue;
+                }
+                let mut cell = [[u32x1::splat(0); 2]; 2];
+                for (subpixel_y, row) in cell.iter_mut().enumerate() {
+                    let source = (sum - (pixel_y as i64 * 2 + subpixel_y as i64)) as usize;
+                    if let Some((source_cell, _)) = frame[source / 2 * width as usize + pixel_x] {
+                        *row = source_cell[source % 2];
+                    }
+                }
+                frame[index] = Some((cell, None));
+            }
+        }
+    }
+
+    let orbits = if keep_orbits {
+        frame
+            .iter()
+            .map(|cell| cell.and_then(|(_, orbit)| orbit))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let cells: Vec<Cell> = frame
+        .into_iter()
+        .map(|cell| cell.map_or([[u32x1::splat(0); 2]; 2], |(cell, _)| cell))
+        .collect();
+    for ((tile, (key, _, _)), hit) in tiles.into_iter().zip(results).zip(hits) {
+        if hit.is_none() {
+            let tile_cells = tile_indices(tile, &key).map(|index| cells[index]).collect();
+            cache.insert(key, tile_cells);
+        }
+    }
+
+    let buffer = FrameBuffer {
+        width,
+        height,
+        position: position.clone(),
+        max_iterations,
+        parameters: *parameters,
+        cells,
+        orbits,
+    };
+    // Every pixel skips the same iterations by the series
+    let skipped = match deep {
+        Some(DeepKernel::Perturbation(reference)) => Some(reference.skipped),
+        _ => None,
+    };
+    (buffer, iterations, skipped)
+}
+
+struct RenderJob {
+    generation: u64,
+    width: u16,
+    height: u16,
+    position: Position,
+    max_iterations: u32x1,
+    parameters: FractalParameters,
+    tile_size: u16,
+    scale: u16,
+    grid_index: usize,
+    // Whether cells and colors kept from earlier frames are out of date
+    reset: bool,
+}
+
+/// A frame finished by a [`Renderer`].
+pub struct RenderedFrame {
+    generation: u64,
+    /// The calculated cells.
+    pub buffer: std::sync::Arc<FrameBuffer>,
+    /// How many cells of the frame share each calculated cell.
+    pub scale: u16,
+    /// The frame shaded into escape-sequence text.
+    pub text: String,
+    /// Iterations calculated for the frame, not counting reused cells.
+    pub iterations: u64,
+    /// Iterations the series approximation skipped for each pixel, if the frame was
+    /// calculated by perturbation.
+    pub skipped_iterations: Option<usize>,
+    /// How long the frame took to render and shade.
+    pub frame_time: std::time::Duration,
+}
+
+/// Renders frames on a worker thread so input keeps being handled while a frame is calculated.
+pub struct Renderer {
+    jobs: std::sync::mpsc::Sender<RenderJob>,
+    frames: std::sync::mpsc::Receiver<RenderedFrame>,
+    generation: u64,
+    completed: u64,
+    progress: Arc<RenderProgress>,
+    reset: bool,
+    grid_index: usize,
+}
+
+impl Renderer {
+    /// Starts the worker thread, which stops once the renderer is dropped.
+    pub fn spawn() -> Renderer {
+        let (jobs, job_receiver) = std::sync::mpsc::channel::<RenderJob>();
+        let (frame_sender, frames) = std::sync::mpsc::channel();
+        let progress = Arc::new(RenderProgress::default());
+        let worker_progress = progress.clone();
+
+        std::thread::spawn(move || {
+            // The worker keeps the last finished frame so the next one can reuse its cells
+            let mut previous: Option<std::sync::Arc<FrameBuffer>> = None;
+            let mut cache = TileCache::new(TILE_CACHE_CAPACITY);
+            let mut rows = Vec::new();
+            let mut colors = ColorTable::new(u32x1::splat(0), &DEFAULT_PARAMETERS);
+            while let Ok(mut job) = job_receiver.recv() {
+                // Only the newest view matters when several were requested during a render
+                let mut reset = job.reset;
+                while let Ok(newer) = job_receiver.try_recv() {
+                    reset |= newer.reset;
+                    job = newer;
+                }
+                if reset {
+                    previous = None;
+                    cache = TileCache::new(TILE_CACHE_CAPACITY);
+                }
+
+                let frame_start = std::time::Instant::now();
+                let (width, height, position) = grid_frame(
+                    job.grid_index,
+                    job.width.div_ceil(job.scale),
+                    job.height.div_ceil(job.scale),
+                    &job.position,
+                );
+                let (buffer, iterations, skipped_iterations) = render_buffer_reporting(
+                    width,
+                    height,
+                    &position,
+                    job.max_iterations,
+                    &job.parameters,
+                    job.tile_size,
+                    previous.as_deref(),
+                    &mut cache,
+                    Some(&worker_progress),
+                );
+                worker_progress.finish();
+                let buffer = std::sync::Arc::new(buffer);
+                previous = Some(buffer.clone());
+                // The color table only has to be rebuilt when the palette or the limit changes
+                if reset || !colors.matches(buffer.max_iterations, &buffer.parameters) {
+                    colors = buffer.color_table();
+                }
+                // Reduced frames and other grids are stretched back over the whole view, but
+                // only the calculated cells are kept for reuse
+                let mut text = String::new();
+                let buffer = buffer
+                    .write_grid_text(
+                        job.grid_index,
+                        job.width,
+                        job.height,
+                        &job.position,
+                        &colors,
+                        &mut rows,
+                        &mut text,
+                    )
+                    .map_or(buffer, std::sync::Arc::new);
+
+                let frame = RenderedFrame {
+                    generation: job.generation,
+                    buffer,
+                    scale: job.scale,
+                    text,
+                    iterations,
+                    skipped_iterations,
+                    frame_time: frame_start.elapsed(),
+                };
+                if frame_sender.send(frame).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Renderer {
+            jobs,
+            frames,
+            generation: 0,
+            completed: 0,
+            progress,
+            reset: false,
+            grid_index: 0,
+        }
+    }
+
+    /// Queues a frame of `width` by `height` cells. Older requests that haven't started yet are
+    /// dropped.
+    ///
+    /// A scale above 1 calculates only every scale-th cell in each direction.
+    #[allow(clippy::too_many_arguments)]
+    pub fn request(
+        &mut self,
+        width: u16,
+        height: u16,
+        position: &Position,
+        max_iterations: u32x1,
+        parameters: &FractalParameters,
+        tile_size: u16,
+        scale: u16,
+    ) {
+        self.generation += 1;
+        // The worker only stops once the renderer is dropped, so sending can't fail
+        let _ = self.jobs.send(RenderJob {
+            generation: self.generation,
+            width,
+            height,
+            position: position.clone(),
+            max_iterations,
+            parameters: *parameters,
+            tile_size,
+            scale,
+            grid_index: self.grid_index,
+            reset: std::mem::take(&mut self.reset),
+        });
+    }
+
+    /// Splits the terminal cells of frames requested from now on as `grid_index` of
+    /// [`GRID_NAMES`] says. Frames are still returned with a cell for each terminal cell.
+    pub fn set_grid(&mut self, grid_index: usize) {
+        self.grid_index = grid_index;
+    }
+
+    /// Makes the next frame start afresh instead of reusing the cells and colors of earlier
+    /// ones, for when a registered fractal or palette has been replaced.
+    pub fn reset(&mut self) {
+        self.reset = true;
+    }
+
+    /// Whether a requested frame hasn't been returned yet.
+    pub fn pending(&self) -> bool {
+        self.completed < self.generation
+    }
+
+    /// The share of the frame being calculated that is done, from 0 to 1, or `None` when no
+    /// frame is being calculated.
+    pub fn progress(&self) -> Option<f64> {
+        self.progress.fraction()
+    }
+
+    /// Returns the most recent finished frame, skipping any that are already out of date.
+    pub fn latest(&mut self) -> Option<RenderedFrame> {
+        let frame = self.frames.try_iter().last()?;
+        self.completed = frame.generation;
+        Some(frame)
+    }
+
+    #[cfg(test)]
+    fn wait(&mut self) -> RenderedFrame {
+        let frame = self.frames.recv().unwrap();
+        self.completed = frame.generation;
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale_number() {
+        assert_eq!(
+            scale_number(f64x1::splat(0.0), f64x1::splat(0.0), f64x1::splat(1.0), f64x1::splat(0.0), f64x1::splat(10.0)),
+            f64x1::splat(0.0)
+        );
+        assert_eq!(
+            scale_number(f64x1::splat(1.0), f64x1::splat(0.0), f64x1::splat(1.0), f64x1::splat(0.0), f64x1::splat(10.0)),
+            f64x1::splat(10.0)
+        );
+        assert_eq!(
+            scale_number(f64x1::splat(0.5), f64x1::splat(0.0), f64x1::splat(1.0), f64x1::splat(0.0), f64x1::splat(10.0)),
+            f64x1::splat(5.0)
+        );
+    }
+
+    #[test]
+    fn test_cell_to_plane() {
+        let position = Position::new(-1.0, 1.0, -2.0, 2.0);
+        assert_eq!(
+            cell_to_plane(0, 0, 4, 2, &position),
+            fixed_point((-1.5, -0.5))
+        );
+        assert_eq!(
+            cell_to_plane(3, 1, 4, 2, &position),
+            fixed_point((1.5, 0.5))
+        );
+        assert_eq!(
+            cell_to_plane(1, 0, 2, 1, &position),
+            fixed_point((1.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn test_position_centered_at() {
+        let position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let centered = position.centered_at(&fixed_point((1.0, 2.0)));
+        assert_eq!(centered.center(), fixed_point((1.0, 2.0)));
+        assert_eq!(centered.width(), position.width());
+        assert_eq!(centered.height(), position.height());
+    }
+
+    #[test]
+    fn test_position_zoomed_at() {
+        let position = Position::new(-1.0, 1.0, -2.0, 2.0);
+        assert_eq!(
+            position.zoomed_at(&fixed_point((0.0, 0.0)), 0.5),
+            Position::new(-0.5, 0.5, -1.0, 1.0)
+        );
+        assert_eq!(
+            position.zoomed_at(&fixed_point((2.0, 1.0)), 0.5),
+            Position::new(0.0, 1.0, 0.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn test_plane_to_cell() {
+        let position = Position::new(-1.0, 1.0, -2.0, 2.0);
+        assert_eq!(
+            plane_to_cell(&fixed_point((-2.0, -1.0)), 4, 2, &position),
+            (0.0, 0.0)
+        );
+        assert_eq!(
+            plane_to_cell(&fixed_point((1.5, 0.5)), 4, 2, &position),
+            (3.5, 1.5)
+        );
+    }
+
+    #[test]
+    fn test_box_zoom() {
+        let position = Position::new(-1.0, 1.0, -2.0, 2.0);
+        assert_eq!(
+            box_zoom(
+                &position,
+                &fixed_point((0.0, 0.0)),
+                &fixed_point((1.0, 0.5))
+            ),
+            Some(Position::new(0.0, 0.5, 0.0, 1.0))
+        );
+        // A tall box is widened to keep the 2:1 aspect ratio
+        assert_eq!(
+            box_zoom(
+                &position,
+                &fixed_point((1.0, -1.0)),
+                &fixed_point((0.0, 1.0))
+            ),
+            Some(Position::new(-1.0, 1.0, -1.5, 2.5))
+        );
+        assert_eq!(
+            box_zoom(
+                &position,
+                &fixed_point((1.0, 1.0)),
+                &fixed_point((1.0, 2.0))
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_rotation() {
+        let position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let turned = position.rotated(std::f64::consts::FRAC_PI_2);
+        let close = |a: (f64, f64), b: (f64, f64)| (a.0 - b.0).abs() + (a.1 - b.1).abs() < 1e-12;
+        let to_f64 = |point: (Fixed, Fixed)| (point.0.to_f64(), point.1.to_f64());
+
+        // A quarter turn around the center sends the first corner to the top right
+        assert_eq!(turned.center(), position.center());
+        let corner = turned.point_at(0.0, 0.0);
+        assert!(close(to_f64(corner.clone()), (0.5, -1.5)));
+        assert!(close(turned.offset_of(&corner), (0.0, 0.0)));
+        assert!(close(to_f64(turned.moved(1.0, 0.0).center()), (-0.5, 1.0)));
+        let (column, row) = plane_to_cell(&cell_to_plane(3, 2, 30, 10, &turned), 30, 10, &turned);
+        assert!(close((column, row), (3.5, 2.5)));
+        assert_eq!(turned.rotated(std::f64::consts::PI * 1.5).rotation, 0.0);
+
+        // Every subpixel is sampled at its corner on the turned grid
+        let max_iterations = u32x1::splat(50);
+        let (buffer, _) = render_buffer(
+            8,
+            4,
+            &turned,
+            max_iterations,
+            &DEFAULT_PARAMETERS,
+            DEFAULT_TILE_SIZE,
+            None,
+            &mut TileCache::new(0),
+        );
+        for (index, cell) in buffer.cells.iter().enumerate() {
+            for (subpixel_y, row) in cell.iter().enumerate() {
+                for (subpixel_x, value) in row.iter().enumerate() {
+                    let x = (index % 8 * 2 + subpixel_x) as f64 / 16.0 * turned.width();
+                    let y = (index / 8 * 2 + subpixel_y) as f64 / 8.0 * turned.height();
+                    let (x, y) = to_f64(turned.point_at(x, y));
+                    assert_eq!(
+                        *value,
+                        iterate_point(x, y, max_iterations, &DEFAULT_PARAMETERS)
+                    );
+                }
+            }
+        }
+
+        // Cells only carry over between views turned the same way
+        assert_eq!(
+            buffer.cell_shift(&turned.moved(turned.width() / 4.0, 0.0)),
+            Some((2, 0))
+        );
+        assert_eq!(buffer.cell_shift(&position), None);
+        assert_eq!(buffer.snap(&turned.moved(0.01, 0.0)), turned);
+    }
+
+    #[test]
+    fn test_log_polar() {
+        let flat = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let position = Position {
+            projection: Projection::LogPolar,
+            ..flat.clone()
+        };
+        let close = |a: (f64, f64), b: (f64, f64)| (a.0 - b.0).abs() + (a.1 - b.1).abs() < 1e-12;
+        let to_f64 = |point: (Fixed, Fixed)| (point.0.to_f64(), point.1.to_f64());
+
+        // The first row runs round the circle through the flat view's corners, and the rows
+        // below it shrink toward the center
+        let radius = 13f64.sqrt() / 2.0;
+        assert!(close(
+            to_f64(position.point_at(0.0, 0.0)),
+            (-0.5 + radius, 0.0)
+        ));
+        assert!(close(to_f64(position.point_at(0.75, 0.0)), (-0.5, radius)));
+        let inner = radius * (-std::f64::consts::TAU * 2.0 / 3.0).exp();
+        assert!(close(
+            to_f64(position.point_at(1.5, 2.0)),
+            (-0.5 - inner, 0.0)
+        ));
+        let point = position.point_at(1.2, 0.7);
+        assert!(close(position.offset_of(&point), (1.2, 0.7)));
+
+        // Every subpixel is sampled where its corner lands
+        let max_iterations = u32x1::splat(50);
+        let (buffer, _) = render_buffer(
+            8,
+            4,
+            &position,
+            max_iterations,
+            &DEFAULT_PARAMETERS,
+            DEFAULT_TILE_SIZE,
+            None,
+            &mut TileCache::new(0),
+        );
+        for (index, cell) in buffer.cells.iter().enumerate() {
+            for (subpixel_y, row) in cell.iter().enumerate() {
+                for (subpixel_x, value) in row.iter().enumerate() {
+                    let x = (index % 8 * 2 + subpixel_x) as f64 / 16.0 * position.width();
+                    let y = (index / 8 * 2 + subpixel_y) as f64 / 8.0 * position.height();
+                    let (x, y) = to_f64(position.point_at(x, y));
+                    assert_eq!(
+                        *value,
+                        iterate_point(x, y, max_iterations, &DEFAULT_PARAMETERS)
+                    );
+                }
+            }
+        }
+        assert_eq!(buffer.cell_shift(&position), None);
+
+        // The rows near the center need perturbation well before a flat view of the same size
+        let deep = flat.with_zoom(&flat, 3e9);
+        assert!(!uses_perturbation(80, &deep, &DEFAULT_PARAMETERS));
+        let deep = Position {
+            projection: Projection::LogPolar,
+            ..deep
+        };
+        assert!(uses_perturbation(80, &deep, &DEFAULT_PARAMETERS));
+    }
+
+    #[test]
+    fn test_interpolate_position() {
+        let from = Position::new(-1.0, 1.0, -2.0, 2.0);
+        let to = Position::new(0.75, 1.25, 0.5, 1.5);
+        assert_eq!(interpolate_position(&from, &to, 0.0), from);
+        assert_eq!(interpolate_position(&from, &to, 1.0), to);
+
+        let halfway = interpolate_position(&from, &to, 0.5);
+        assert_eq!(halfway.center(), fixed_point((0.5, 0.5)));
+        assert_eq!(halfway.width(), 2.0);
+        assert_eq!(halfway.height(), 1.0);
+    }
+
+    #[test]
+    fn test_zoom_position() {
+        let from = Position::new(-1.0, 1.0, -2.0, 2.0);
+        let to = Position::new(0.75, 1.25, 0.5, 1.5);
+        assert_eq!(zoom_position(&from, &to, 0.0), from);
+        assert_eq!(zoom_position(&from, &to, 1.0).center(), to.center());
+
+        let halfway = zoom_position(&from, &to, 0.5);
+        assert_eq!(halfway.width(), 2.0);
+        let center = halfway.center();
+        assert!((center.0.to_f64() - 2.0 / 3.0).abs() < 1e-12);
+        assert!((center.1.to_f64() - 2.0 / 3.0).abs() < 1e-12);
+
+        // A deep target stays on screen all the way down
+        let deep = Position::new(0.1, 0.1 + 2e-12, 0.3, 0.3 + 3e-12);
+        let target = deep.center();
+        for step in 0..=20 {
+            let view = zoom_position(&from, &deep, step as f64 / 20.0);
+            let (x, y) = view.center();
+            assert!((&target.0 - &x).to_f64().abs() <= view.width() / 2.0);
+            assert!((&target.1 - &y).to_f64().abs() <= view.height() / 2.0);
+        }
+    }
+
+    #[test]
+    fn test_get_pixel() {
+        assert_eq!(
+            get_pixel([[false, false], [false, false]]),
+            FULL_BLOCK[1].chars().next().unwrap()
+        );
+        assert_eq!(
+            get_pixel([[true, true], [true, true]]),
+            FULL_BLOCK[0].chars().next().unwrap()
+        );
+        assert_eq!(
+            get_pixel([[false, true], [true, true]]),
+            THREE_QUADRANTS[1].chars().next().unwrap()
+        );
+        assert_eq!(
+            get_pixel([[true, false], [true, true]]),
+            THREE_QUADRANTS[0].chars().next().unwrap()
+        );
+        assert_eq!(
+            get_pixel([[true, true], [false, true]]),
+            THREE_QUADRANTS[3].chars().next().unwrap()
+        );
+        assert_eq!(
+            get_pixel([[true, true], [true, false]]),
+            THREE_QUADRANTS[2].chars().next().unwrap()
+        );
+        assert_eq!(
+            get_pixel([[false, false], [true, true]]),
+            TWO_QUADRANTS[2].chars().next().unwrap()
+        );
+        assert_eq!(
+            get_pixel([[true, false], [false, true]]),
+            TWO_QUADRANTS[0].chars().next().unwrap()
+        );
+        assert_eq!(
+            get_pixel([[true, true], [false, false]]),
+            TWO_QUADRANTS[3].chars().next().unwrap()
+        );
+        assert_eq!(
+            get_pixel([[false, true], [true, false]]),
+            TWO_QUADRANTS[1].chars().next().unwrap()
+        );
+        assert_eq!(
+            get_pixel([[false, true], [false, true]]),
+            TWO_QUADRANTS[4].chars().next().unwrap()
+        );
+        assert_eq!(
+            get_pixel([[true, false], [true, false]]),
+            TWO_QUADRANTS[5].chars().next().unwrap()
+        );
+        assert_eq!(
+            get_pixel([[false, false], [false, true]]),
+            QUADRANTS[3].chars().next().unwrap()
+        );
+        assert_eq!(
+            get_pixel([[false, true], [false, false]]),
+            QUADRANTS[2].chars().next().unwrap()
+        );
+        assert_eq!(
+            get_pixel([[true, false], [false, false]]),
+            QUADRANTS[1].chars().next().unwrap()
+        );
+        assert_eq!(
+            get_pixel([[false, false], [true, false]]),
+            QUADRANTS[0].chars().next().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_hsl_to_rgb() {
+        assert_eq!(
+            hsl_to_rgb([f64x1::splat(0.0), f64x1::splat(100.0), f64x1::splat(50.0)]),
+            [f64x1::splat(255.0), f64x1::splat(0.0), f64x1::splat(0.0)]
+        );
+        assert_eq!(
+            hsl_to_rgb([f64x1::splat(120.0), f64x1::splat(100.0), f64x1::splat(50.0)]),
+            [f64x1::splat(0.0), f64x1::splat(255.0), f64x1::splat(0.0)]
+        );
+        assert_eq!(
+            hsl_to_rgb([f64x1::splat(240.0), f64x1::splat(100.0), f64x1::splat(50.0)]),
+            [f64x1::splat(0.0), f64x1::splat(0.0), f64x1::splat(255.0)]
+        );
+        assert_eq!(
+            hsl_to_rgb([f64x1::splat(60.0), f64x1::splat(100.0), f64x1::splat(50.0)]),
+            [f64x1::splat(255.0), f64x1::splat(255.0), f64x1::splat(0.0)]
+        );
+        assert_eq!(
+            hsl_to_rgb([f64x1::splat(180.0), f64x1::splat(100.0), f64x1::splat(50.0)]),
+            [f64x1::splat(0.0), f64x1::splat(255.0), f64x1::splat(255.0)]
+        );
+        assert_eq!(
+            hsl_to_rgb([f64x1::splat(300.0), f64x1::splat(100.0), f64x1::splat(50.0)]),
+            [f64x1::splat(255.0), f64x1::splat(0.0), f64x1::splat(255.0)]
+        );
+        assert_eq!(
+            hsl_to_rgb([f64x1::splat(0.0), f64x1::splat(0.0), f64x1::splat(0.0)]),
+            [f64x1::splat(0.0), f64x1::splat(0.0), f64x1::splat(0.0)]
+        );
+    }
+
+    #[test]
+    fn test_get_color() {
+        assert_eq!(
+            get_color(u32x1::splat(0), u32x1::splat(100)),
+            [f64x1::splat(255.0); 3]
+        );
+        assert_eq!(
+            get_color(u32x1::splat(100), u32x1::splat(100)),
+            [f64x1::splat(0.0); 3]
+        );
+        assert_eq!(
+            get_color(u32x1::splat(50), u32x1::splat(100)),
+            [f64x1::splat(0.0), f64x1::splat(255.0), f64x1::splat(255.0)]
+        );
+    }
+
+    #[test]
+    fn test_gradient() {
+        let stops = [[0.0, 0.0, 0.0], [100.0, 200.0, 50.0], [200.0, 200.0, 250.0]];
+        assert_eq!(gradient(&stops, [0.0]), [f64x1::splat(0.0); 3]);
+        assert_eq!(
+            gradient(&stops, [0.25]),
+            [f64x1::splat(50.0), f64x1::splat(100.0), f64x1::splat(25.0)]
+        );
+        assert_eq!(
+            gradient(&stops, [1.0]),
+            [
+                f64x1::splat(200.0),
+                f64x1::splat(200.0),
+                f64x1::splat(250.0)
+            ]
+        );
+        assert_eq!(
+            gradient_color(u32x1::splat(100), u32x1::splat(100), &stops),
+            [f64x1::splat(0.0); 3]
+        );
+        assert_eq!(
+            gradient_colors([0, 25, 50, 100], u32x1::splat(100), &stops)[0].to_array(),
+            [0.0, 50.0, 100.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn test_colorings() {
+        let max_iterations = u32x1::splat(100);
+        for coloring in COLORINGS {
+            assert_eq!(coloring(max_iterations, max_iterations), max_iterations);
+            assert_eq!(coloring(u32x1::splat(0), max_iterations), u32x1::splat(0));
+        }
+        assert_eq!(COLORINGS[0](u32x1::splat(42), max_iterations), u32x1::splat(42));
+        assert_eq!(COLORINGS[1](u32x1::splat(9), max_iterations), u32x1::splat(49));
+        assert_eq!(COLORINGS[2](u32x1::splat(40), max_iterations), u32x1::splat(25));
+    }
+
+    #[test]
+    fn test_boundaries() {
+        let max_iterations = u32x1::splat(100);
+        let cell = |values: [[u32; 2]; 2]| values.map(|row| row.map(u32x1::splat));
+        // Only the side of a basin boundary with the later root is drawn, and points that reach
+        // no root border every basin
+        let roots = [cell([[0, 0], [0, 0]]), cell([[1, 1], [0, 0]])];
+        assert_eq!(
+            basin_boundaries(&roots, 2, max_iterations),
+            vec![cell([[100, 100], [100, 100]]), cell([[0, 0], [100, 100]])]
+        );
+        let roots = [cell([[NO_ROOT, 2], [2, 2]])];
+        assert_eq!(
+            basin_boundaries(&roots, 1, max_iterations),
+            vec![cell([[0, 100], [100, 100]])]
+        );
+
+        let parameters = FractalParameters {
+            fractal_index: NEWTON_INDEX,
+            coloring_index: BOUNDARIES_INDEX,
+            ..DEFAULT_PARAMETERS
+        };
+        let colors = ColorTable::new(max_iterations, &parameters);
+        assert_eq!(colors.color(u32x1::splat(0)), BOUNDARY_COLOR);
+        assert_eq!(colors.color(max_iterations), [0, 0, 0]);
+        assert!(!colors.matches(max_iterations, &DEFAULT_PARAMETERS));
+
+        // Rows rendered straight to text find boundaries across rows as frames do, and only some
+        // subpixels are on one
+        let position = Position::new(-1.5, 1.5, -2.0, 2.0);
+        let (buffer, _) = render_buffer(
+            30,
+            10,
+            &position,
+            max_iterations,
+            &parameters,
+            DEFAULT_TILE_SIZE,
+            None,
+            &mut TileCache::new(0),
+        );
+        let (rows, _) = render_rows(30, 10, &position, max_iterations, &parameters);
+        assert_eq!(rows, buffer.rows());
+        assert!(buffer.text().contains("255;255;255"));
+        let boundaries = buffer
+            .shaded_cells(&colors)
+            .iter()
+            .flatten()
+            .flatten()
+            .filter(|value| value[0] == 0)
+            .count();
+        assert!(boundaries > 0 && boundaries < 30 * 10 * 2);
+
+        // Fractals without basins are colored as they are by counts
+        let escaping = FractalParameters {
+            coloring_index: BOUNDARIES_INDEX,
+            ..DEFAULT_PARAMETERS
+        };
+        let linear = render_rows(30, 10, &position, max_iterations, &DEFAULT_PARAMETERS);
+        assert_eq!(render_rows(30, 10, &position, max_iterations, &escaping), linear);
+    }
+
+    #[test]
+    fn test_fit_aspect() {
+        let position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let fitted = fit_aspect(&position, 60, 10);
+        assert_eq!(fitted.center(), position.center());
+        assert_eq!(fitted.width(), position.width());
+        assert_eq!(fitted.height(), 1.0);
+        assert_eq!(fit_aspect(&fitted, 60, 10), fitted);
+    }
+
+    #[test]
+    fn test_letterbox() {
+        let shape = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let tall = Position::new(-2.0, 2.0, -1.0, 0.0);
+        let framed = inscribed(&tall, &shape);
+        assert_eq!(framed.center(), tall.center());
+        assert_eq!((framed.width(), framed.height()), (1.0, 2.0 / 3.0));
+
+        // A wide frame gets bars at the sides and a tall one at the top and bottom
+        let wide = letterbox(&shape, &shape, 60, 10);
+        assert_eq!((wide.width(), wide.height()), (6.0, 2.0));
+        let narrow = letterbox(&shape, &shape, 30, 20);
+        assert_eq!((narrow.width(), narrow.height()), (3.0, 4.0));
+        // The part in proportion stays the same however often it is fitted
+        assert_eq!(letterbox(&wide, &shape, 60, 10), wide);
+        assert_eq!(letterbox(&wide, &shape, 30, 20), narrow);
+    }
+
+    #[test]
+    fn test_render_buffer() {
+        let position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let max_iterations = u32x1::splat(50);
+        let (buffer, iterations) = render_buffer(
+            30,
+            10,
+            &position,
+            max_iterations,
+            &DEFAULT_PARAMETERS,
+            DEFAULT_TILE_SIZE,
+            None,
+            &mut TileCache::new(0),
+        );
+        // The bottom four rows mirror rows above the real axis, so only the rest is computed
+        assert_eq!(iterations, count_iterations(&buffer.cells[..30 * 6]));
+
+        // Tiles that don't divide the frame evenly still cover every cell exactly once
+        let (tiled, tiled_iterations) = render_buffer(
+            30,
+            10,
+            &position,
+            max_iterations,
+            &DEFAULT_PARAMETERS,
+            7,
+            None,
+            &mut TileCache::new(0),
+        );
+        assert_eq!(tiled.cells, buffer.cells);
+        assert_eq!(tiled_iterations, iterations);
+
+        // Shifting by three columns and one row only computes the exposed strips
+        let shifted = position.translated(3.0 * 0.1, 0.2);
+        assert_eq!(buffer.cell_shift(&shifted), Some((3, 1)));
+        let (reused, _) = render_buffer(
+            30,
+            10,
+            &shifted,
+            max_iterations,
+            &DEFAULT_PARAMETERS,
+            DEFAULT_TILE_SIZE,
+            Some(&buffer),
+            &mut TileCache::new(0),
+        );
+        let (fresh, _) = render_buffer(
+            30,
+            10,
+            &shifted,
+            max_iterations,
+            &DEFAULT_PARAMETERS,
+            DEFAULT_TILE_SIZE,
+            None,
+            &mut TileCache::new(0),
+        );
+        assert_eq!(reused.cells[0], buffer.cells[30 + 3]);
+        assert_eq!(reused.rows(), fresh.rows());
+
+        assert_eq!(buffer.cell_shift(&position.translated(0.05, 0.0)), None);
+        assert_eq!(
+            buffer.cell_shift(&position.zoomed_at(&fixed_point((0.0, 0.0)), 0.5)),
+            None
+        );
+        assert_eq!(
+            buffer.snap(&position.translated(0.26, -0.01)),
+            position.translated(0.30000000000000004, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_color_table() {
+        let max_iterations = u32x1::splat(100);
+        let parameters = FractalParameters {
+            palette_index: 2,
+            coloring_index: 1,
+            ..DEFAULT_PARAMETERS
+        };
+        let colors = ColorTable::new(max_iterations, &parameters);
+        for iteration in [0, 1, 50, 99, 100] {
+            let rgb = PALETTES[2](
+                COLORINGS[1](u32x1::splat(iteration), max_iterations),
+                max_iterations,
+            );
+            let expected = [rgb[0][0] as u8, rgb[1][0] as u8, rgb[2][0] as u8];
+            assert_eq!(colors.color(u32x1::splat(iteration)), expected);
+        }
+        assert_eq!(
+            colors.color(u32x1::splat(500)),
+            colors.color(max_iterations)
+        );
+
+        // Tables colored a chunk of counts at a time match the palettes one by one, up to a
+        // limit that leaves a short final chunk
+        let max_iterations = u32x1::splat(37);
+        for (palette_index, palette) in PALETTES.iter().enumerate() {
+            let parameters = FractalParameters {
+                palette_index,
+                ..DEFAULT_PARAMETERS
+            };
+            let colors = ColorTable::new(max_iterations, &parameters);
+            for iteration in 0..=37 {
+                let rgb = palette(u32x1::splat(iteration), max_iterations);
+                let expected = rgb.map(|channel| channel[0] as u8);
+                assert_eq!(colors.color(u32x1::splat(iteration)), expected);
+            }
+        }
+        let max_iterations = u32x1::splat(100);
+
+        // Counts past the table are colored as they are looked up, so the highest limit
+        // doesn't need a color for every count
+        let highest = u32x1::splat(u32::MAX);
+        for coloring_index in 0..COLORINGS.len() {
+            let parameters = FractalParameters {
+                coloring_index,
+                palette_phase: 0.25,
+                ..DEFAULT_PARAMETERS
+            };
+            let table = ColorTable::new(highest, &parameters);
+            assert_eq!(table.colors.len(), COLOR_TABLE_SIZE as usize);
+            assert_eq!(table.color(highest), [0, 0, 0]);
+            for iteration in [COLOR_TABLE_SIZE - 1, COLOR_TABLE_SIZE, 1 << 30, u32::MAX - 1] {
+                let color = table.color(u32x1::splat(iteration));
+                assert_eq!(color, table.shade([iteration])[0]);
+            }
+        }
+        let table = ColorTable::new(highest, &DEFAULT_PARAMETERS);
+        let rgb = PALETTES[0](u32x1::splat(1 << 31), highest).map(|channel| channel[0] as u8);
+        assert_eq!(table.color(u32x1::splat(1 << 31)), rgb);
+
+        assert!(colors.matches(max_iterations, &parameters));
+        assert!(!colors.matches(u32x1::splat(200), &parameters));
+        assert!(!colors.matches(max_iterations, &DEFAULT_PARAMETERS));
+        let other_fractal = FractalParameters {
+            fractal_index: 1,
+            ..parameters
+        };
+        assert!(colors.matches(max_iterations, &other_fractal));
+
+        // Rotating the palette moves every escaped color along by the same amount
+        let rotated = FractalParameters {
+            palette_phase: 1.25,
+            coloring_index: 0,
+            ..parameters
+        };
+        assert!(!colors.matches(max_iterations, &rotated));
+        let linear = FractalParameters {
+            coloring_index: 0,
+            ..parameters
+        };
+        let colors = ColorTable::new(max_iterations, &linear);
+        let rotated = ColorTable::new(max_iterations, &rotated);
+        let color = |colors: &ColorTable, iteration| colors.color(u32x1::splat(iteration));
+        assert_eq!(color(&rotated, 10), color(&colors, 35));
+        assert_eq!(color(&rotated, 90), color(&colors, 15));
+        assert_eq!(rotated.color(max_iterations), colors.color(max_iterations));
+    }
+
+    #[test]
+    fn test_write_text() {
+        for (color, expected) in [
+            ([0, 7, 255], "\x1b[38;2;0;7;255m\x1b[48;2;0;7;255m"),
+            ([10, 99, 100], "\x1b[38;2;10;99;100m\x1b[48;2;10;99;100m"),
+        ] {
+            let mut output = String::new();
+            push_color(&mut output, color, false);
+            push_color(&mut output, color, true);
+            assert_eq!(output, expected);
+        }
+
+        let position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let (buffer, _) = render_buffer(
+            30,
+            10,
+            &position,
+            u32x1::splat(50),
+            &DEFAULT_PARAMETERS,
+            DEFAULT_TILE_SIZE,
+            None,
+            &mut TileCache::new(0),
+        );
+        let expected = format!("{}\x1b[0m", buffer.rows().join("\n"));
+        assert_eq!(buffer.text(), expected);
+
+        // Buffers left over from a bigger frame are cleared before they're reused
+        let mut rows = vec!["stale".to_string(); 12];
+        let mut output = "stale".to_string();
+        buffer.write_text(&buffer.color_table(), &mut rows, &mut output);
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_resampled() {
+        let position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let max_iterations = u32x1::splat(50);
+        let (buffer, _) = render_buffer(
+            30,
+            10,
+            &position,
+            max_iterations,
+            &DEFAULT_PARAMETERS,
+            DEFAULT_TILE_SIZE,
+            None,
+            &mut TileCache::new(0),
+        );
+        assert_eq!(
+            buffer
+                .resampled(30, 10, &position, &DEFAULT_PARAMETERS)
+                .cells,
+            buffer.cells
+        );
+
+        // Zooming in by two spreads each subpixel over a whole cell
+        let zoomed = position.zoomed_at(&position.center(), 0.5);
+        let preview = buffer.resampled(30, 10, &zoomed, &DEFAULT_PARAMETERS);
+        assert_eq!(preview.position, zoomed);
+        assert_eq!(preview.cells[0], [[buffer.cells[2 * 30 + 7][1][1]; 2]; 2]);
+
+        // Zooming out leaves a border the old frame knows nothing about
+        let preview = buffer.resampled(
+            30,
+            10,
+            &position.zoomed_at(&position.center(), 2.0),
+            &DEFAULT_PARAMETERS,
+        );
+        assert_eq!(preview.cells[0], [[u32x1::splat(0); 2]; 2]);
+        assert_eq!(preview.max_iterations, max_iterations);
+    }
+
+    #[test]
+    fn test_cropped() {
+        let position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let (buffer, _) = render_buffer(
+            30,
+            10,
+            &position,
+            u32x1::splat(50),
+            &DEFAULT_PARAMETERS,
+            DEFAULT_TILE_SIZE,
+            None,
+            &mut TileCache::new(0),
+        );
+        let (cell_width, cell_height) = (position.width() / 30.0, position.height() / 10.0);
+        // 10x4 cells from the sixth column and fourth row
+        let center = position.point_at(10.0 * cell_width, 5.0 * cell_height);
+        let view = position
+            .centered_at(&center)
+            .resized(10.0 * cell_width, 4.0 * cell_height);
+
+        let cropped = buffer.cropped(10, 4, &view).unwrap();
+        assert_eq!(cropped.position, view);
+        assert_eq!(cropped.cells[0], buffer.cells[3 * 30 + 5]);
+        assert_eq!(cropped.cells[3 * 10 + 9], buffer.cells[6 * 30 + 14]);
+        assert_eq!(
+            buffer.cropped(30, 10, &position).unwrap().cells,
+            buffer.cells
+        );
+
+        // Only views on the frame's grid and inside it can be cut out
+        assert!(buffer
+            .cropped(10, 4, &view.moved(cell_width / 2.0, 0.0))
+            .is_none());
+        assert!(buffer
+            .cropped(10, 4, &view.moved(20.0 * cell_width, 0.0))
+            .is_none());
+        assert!(buffer.cropped(20, 8, &view).is_none());
+    }
+
+    #[test]
+    fn test_grid_text() {
+        let position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let render = |grid_index, width, height| {
+            let (columns, rows, grid_position) = grid_frame(grid_index, width, height, &position);
+            let (buffer, _) = render_buffer(
+                columns,
+                rows,
+                &grid_position,
+                u32x1::splat(50),
+                &DEFAULT_PARAMETERS,
+                DEFAULT_TILE_SIZE,
+                None,
+                &mut TileCache::new(0),
+            );
+            let (text, stretched) = buffer.grid_text(grid_index, width, height, &position);
+            (buffer, text, stretched)
+        };
+
+        // Quadrant frames are shaded as they are
+        let (buffer, text, stretched) = render(0, 30, 10);
+        assert!(stretched.is_none());
+        assert_eq!(text, buffer.text());
+
+        // Each half block cell shows one calculated column, even with an odd one left over
+        let (buffer, text, stretched) = render(HALF_BLOCKS_INDEX, 31, 10);
+        assert_eq!((buffer.width, buffer.height), (16, 10));
+        let stretched = stretched.unwrap();
+        assert_eq!((stretched.width, stretched.height), (31, 10));
+        assert!(stretched
+            .cells
+            .iter()
+            .all(|cell| cell[0][0] == cell[0][1] && cell[1][0] == cell[1][1]));
+        assert_eq!(
+            stretched.cells[2 * 31 + 3][1][0],
+            buffer.cells[2 * 16 + 1][1][1]
+        );
+        assert_eq!(text, stretched.text());
+
+        // Braille frames calculate two rows of cells for each line of dots
+        let (buffer, text, stretched) = render(BRAILLE_INDEX, 30, 10);
+        assert_eq!((buffer.width, buffer.height), (30, 20));
+        let stretched = stretched.unwrap();
+        assert_eq!((stretched.width, stretched.height), (30, 10));
+        assert_eq!(text.lines().count(), 10);
+        assert!(text.chars().any(|c| ('\u{2801}'..'\u{28ff}').contains(&c)));
+        assert!(!text.contains(['▀', '▄', '▌', '▐']));
+
+        let colors = ColorTable::new(u32x1::splat(50), &DEFAULT_PARAMETERS);
+        let [high, low] = [10, 0].map(|value| [[u32x1::splat(value); 2]; 2]);
+        assert_eq!(
+            shade_braille(high, low, &colors),
+            Pixel {
+                character: '⠛',
+                foreground_color: colors.color(u32x1::splat(10)),
+                background_color: Some(colors.color(u32x1::splat(0))),
+            }
+        );
+        assert_eq!(shade_braille(low, low, &colors).character, '█');
+    }
+
+    #[test]
+    fn test_calculate_limited() {
+        // A stand-in kernel where one cell is inside and one escapes slowly
+        let escape = |(pixel_x, _): (u16, u16), inside: u16| match pixel_x {
+            _ if pixel_x == inside => u32::MAX,
+            5 => 3000,
+            _ => 500,
+        };
+        let compute = |inside: u16| {
+            move |cells: &[(u16, u16)], max_iterations: u32x1| -> Vec<OrbitCell> {
+                let cells = cells.iter().map(|&cell| {
+                    let value = u32x1::splat(escape(cell, inside).min(max_iterations[0]));
+                    ([[value; 2]; 2], Some([[(0.0, 0.0); 2]; 2]))
+                });
+                cells.collect()
+            }
+        };
+        let cells: Vec<(u16, u16)> = (0..16).map(|pixel_x| (pixel_x, 0)).collect();
+        let max_iterations = u32x1::splat(100000);
+
+        // The samples at 0 and 8 escape at 500, so the rest stop at 2000
+        let (limited, iterations) = calculate_limited(&cells, max_iterations, compute(3));
+        let value = |cell: &OrbitCell| cell.0[0][0][0];
+        assert_eq!(value(&limited[3]), 100000);
+        assert!(limited[3].1.is_none());
+        // Losing the slow escape with it
+        assert_eq!(value(&limited[5]), 100000);
+        assert_eq!(value(&limited[6]), 500);
+        assert!(limited[6].1.is_some());
+        assert_eq!(iterations, (14 * 500 + 2 * 2000) * 4);
+
+        // A sample inside keeps the full limit
+        let (full, _) = calculate_limited(&cells, max_iterations, compute(8));
+        assert_eq!(value(&full[5]), 3000);
+        assert_eq!(value(&full[8]), 100000);
+
+        // Limits never go below the minimum, so shallow views render the same
+        let position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let render = |tile_limits| {
+            let parameters = FractalParameters {
+                tile_limits,
+                ..DEFAULT_PARAMETERS
+            };
+            let (buffer, _) = render_buffer(
+                30,
+                10,
+                &position,
+                u32x1::splat(500),
+                &parameters,
+                DEFAULT_TILE_SIZE,
+                None,
+                &mut TileCache::new(0),
+            );
+            buffer.cells
+        };
+        assert_eq!(render(true), render(false));
+    }
+
+    #[test]
+    fn test_resume() {
+        let position = Position::new(-0.2, 0.2, -0.95, -0.55);
+        let render = |max_iterations, parameters, previous: Option<&FrameBuffer>| {
+            render_buffer(
+                24,
+                12,
+                &position,
+                u32x1::splat(max_iterations),
+                &parameters,
+                DEFAULT_TILE_SIZE,
+                previous,
+                &mut TileCache::new(0),
+            )
+        };
+        let (low, _) = render(50, DEFAULT_PARAMETERS, None);
+        let (fresh, fresh_iterations) = render(400, DEFAULT_PARAMETERS, None);
+        assert_eq!(low.orbits.len(), 24 * 12);
+
+        // Raising the limit carries the orbits on to the same cells with less work
+        let (raised, raised_iterations) = render(400, DEFAULT_PARAMETERS, Some(&low));
+        assert_eq!(raised.cells, fresh.cells);
+        assert!(raised_iterations < fresh_iterations);
+        let (again, _) = render(1000, DEFAULT_PARAMETERS, Some(&raised));
+        assert_eq!(again.cells, render(1000, DEFAULT_PARAMETERS, None).0.cells);
+
+        // Supersampled orbits average together, so aren't kept
+        let supersampled = FractalParameters {
+            supersampling: 2,
+            ..DEFAULT_PARAMETERS
+        };
+        assert!(render(50, supersampled, None).0.orbits.is_empty());
+    }
+
+    #[test]
+    fn test_mirror_sum() {
+        let position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        assert_eq!(mirror_sum(10, &position, &DEFAULT_PARAMETERS), Some(20));
+        assert_eq!(mirror_sum(7, &position, &DEFAULT_PARAMETERS), Some(14));
+        let sinking_ship = FractalParameters {
+            fractal_index: 1,
+            ..DEFAULT_PARAMETERS
+        };
+        assert_eq!(mirror_sum(10, &position, &sinking_ship), None);
+        let julia = FractalParameters {
+            fractal_index: JULIA_INDEX,
+            ..DEFAULT_PARAMETERS
+        };
+        assert_eq!(mirror_sum(10, &position, &julia), None);
+        let real_julia = FractalParameters {
+            julia_constant: (-0.8, 0.0),
+            ..julia
+        };
+        assert_eq!(mirror_sum(10, &position, &real_julia), Some(20));
+
+        let supersampled = FractalParameters {
+            supersampling: 3,
+            ..DEFAULT_PARAMETERS
+        };
+        assert_eq!(mirror_sum(10, &position, &supersampled), None);
+        assert_eq!(
+            mirror_sum(10, &position.translated(0.0, 0.03), &DEFAULT_PARAMETERS),
+            None
+        );
+        let offset = position.translated(0.0, 0.1);
+        assert_eq!(mirror_sum(10, &offset, &DEFAULT_PARAMETERS), Some(18));
+        assert_eq!(
+            mirror_sum(10, &position.translated(0.0, 3.0), &DEFAULT_PARAMETERS),
+            None
+        );
+
+        // Mirrored rows match what computing them directly gives
+        let coordinates: Vec<(u16, u16)> = (0..10)
+            .flat_map(|pixel_y| (0..30).map(move |pixel_x| (pixel_x, pixel_y)))
+            .collect();
+        for position in [position, offset] {
+            let max_iterations = u32x1::splat(50);
+            let (buffer, _) = render_buffer(
+                30,
+                10,
+                &position,
+                max_iterations,
+                &DEFAULT_PARAMETERS,
+                DEFAULT_TILE_SIZE,
+                None,
+                &mut TileCache::new(0),
+            );
+            let direct = calculate_cells(
+                &coordinates,
+                30,
+                10,
+                &position,
+                max_iterations,
+                &DEFAULT_PARAMETERS,
+            );
+            assert_eq!(buffer.cells, direct);
+        }
+    }
+
+    #[test]
+    fn test_calculate_adaptive() {
+        let position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let max_iterations = u32x1::splat(50);
+        let supersampled = FractalParameters {
+            supersampling: 3,
+            ..DEFAULT_PARAMETERS
+        };
+        let coordinates: Vec<(u16, u16)> = (0..10)
+            .flat_map(|pixel_y| (0..30).map(move |pixel_x| (pixel_x, pixel_y)))
+            .collect();
+        let adaptive = calculate_adaptive(
+            &coordinates,
+            30,
+            10,
+            &position,
+            max_iterations,
+            &supersampled,
+            None,
+        );
+        let full = calculate_cells(
+            &coordinates,
+            30,
+            10,
+            &position,
+            max_iterations,
+            &supersampled,
+        );
+        let single = calculate_cells(
+            &coordinates,
+            30,
+            10,
+            &position,
+            max_iterations,
+            &DEFAULT_PARAMETERS,
+        );
+
+        // Only cells on an edge pay for the extra samples
+        let refined = (0..coordinates.len())
+            .filter(|index| adaptive[*index] != single[*index])
+            .count();
+        assert!(refined > 0 && refined < coordinates.len() / 2);
+        for index in 0..coordinates.len() {
+            assert!(adaptive[index] == full[index] || adaptive[index] == single[index]);
+        }
+
+        // Splitting the cells into batches finds the same edges
+        let (first, second) = coordinates.split_at(100);
+        let mut batched = calculate_adaptive(
+            first,
+            30,
+            10,
+            &position,
+            max_iterations,
+            &supersampled,
+            None,
+        );
+        batched.extend(calculate_adaptive(
+            second,
+            30,
+            10,
+            &position,
+            max_iterations,
+            &supersampled,
+            None,
+        ));
+        assert_eq!(batched, adaptive);
+
+        assert!(is_edge(3, 40, 50));
+        assert!(is_edge(49, 50, 50));
+        assert!(!is_edge(3, 4, 50));
+    }
+
+    #[test]
+    fn test_tile_cache() {
+        let position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let max_iterations = u32x1::splat(50);
+        let mut cache = TileCache::new(TILE_CACHE_CAPACITY);
+        let (buffer, iterations) = render_buffer(
+            30,
+            10,
+            &position,
+            max_iterations,
+            &DEFAULT_PARAMETERS,
+            8,
+            None,
+            &mut cache,
+        );
+        assert_eq!(cache.tiles.len(), 8);
+        assert!(iterations > 0);
+
+        // Coming back to a view after a zoom, or with another palette, costs no iterations
+        let zoomed = position.zoomed_at(&fixed_point((-0.5, 0.0)), 0.5);
+        render_buffer(
+            30,
+            10,
+            &zoomed,
+            max_iterations,
+            &DEFAULT_PARAMETERS,
+            8,
+            None,
+            &mut cache,
+        );
+        let parameters = FractalParameters {
+            palette_index: 1,
+            ..DEFAULT_PARAMETERS
+        };
+        let (cached, iterations) = render_buffer(
+            30,
+            10,
+            &position,
+            max_iterations,
+            &parameters,
+            8,
+            None,
+            &mut cache,
+        );
+        assert_eq!(iterations, 0);
+        assert_eq!(cached.cells, buffer.cells);
+        assert_eq!(cache.tiles.len(), 16);
+
+        // Changing anything that affects the iteration counts misses the cache
+        let (_, iterations) = render_buffer(
+            30,
+            10,
+            &position,
+            u32x1::splat(60),
+            &DEFAULT_PARAMETERS,
+            8,
+            None,
+            &mut cache,
+        );
+        assert!(iterations > 0);
+
+        // The least recently used tiles are dropped first
+        let mut small = TileCache::new(8);
+        render_buffer(
+            30,
+            10,
+            &position,
+            max_iterations,
+            &DEFAULT_PARAMETERS,
+            8,
+            None,
+            &mut small,
+        );
+        render_buffer(
+            30,
+            10,
+            &zoomed,
+            max_iterations,
+            &DEFAULT_PARAMETERS,
+            8,
+            None,
+            &mut small,
+        );
+        let key = TileKey::new(
+            &position,
+            30,
+            10,
+            (0, 0, 8, 8),
+            max_iterations,
+            &DEFAULT_PARAMETERS,
+        );
+        assert_eq!(small.tiles.len(), 8);
+        assert!(small.get(&key).is_none());
+
+        // Tiles used again are kept over ones that have been left since
+        let mut cache = TileCache::new(16);
+        let render = |position: &Position, cache: &mut TileCache| {
+            render_buffer(
+                30,
+                10,
+                position,
+                max_iterations,
+                &DEFAULT_PARAMETERS,
+                8,
+                None,
+                cache,
+            )
+        };
+        render(&position, &mut cache);
+        render(&zoomed, &mut cache);
+        assert_eq!(render(&position, &mut cache).1, 0);
+        render(&position.zoomed_at(&fixed_point((0.25, 0.0)), 0.5), &mut cache);
+        assert!(cache.get(&key).is_some());
+        assert!(render(&zoomed, &mut cache).1 > 0);
+    }
+
+    #[test]
+    fn test_register() {
+        struct Constant(u32);
+        impl Kernel for Constant {
+            fn iterate(&self, _: f64, _: f64, _: u32, _: &FractalParameters) -> u32 {
+                self.0
+            }
+        }
+
+        // Registering a name again replaces the fractal in its place
+        let index = register_fractal("Test Constant", Arc::new(Constant(3)));
+        assert_eq!(fractal_names()[index], "Test Constant");
+        assert_eq!(register_fractal("Test Constant", Arc::new(Constant(5))), index);
+        let kernel = custom_fractal(index).unwrap();
+        assert_eq!(kernel.iterate(0.0, 0.0, 10, &DEFAULT_PARAMETERS), 5);
+        let count = fractal_names().len();
+        assert_eq!(register_fractal("Test Constant", Arc::new(Constant(7))), index);
+        assert_eq!(fractal_names().len(), count);
+    }
+
+    #[test]
+    fn test_renderer() {
+        let position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let max_iterations = u32x1::splat(50);
+        let mut renderer = Renderer::spawn();
+        assert!(!renderer.pending());
+
+        renderer.request(
+            30,
+            10,
+            &position,
+            max_iterations,
+            &DEFAULT_PARAMETERS,
+            DEFAULT_TILE_SIZE,
+            1,
+        );
+        assert!(renderer.pending());
+        let rendered = renderer.wait();
+        assert!(!renderer.pending());
+        assert_eq!(renderer.progress(), None);
+
+        // Every tile counts toward the progress, cached or not
+        let progress = RenderProgress::default();
+        let mut cache = TileCache::new(TILE_CACHE_CAPACITY);
+        for _ in 0..2 {
+            render_buffer_reporting(
+                30,
+                10,
+                &position,
+                max_iterations,
+                &DEFAULT_PARAMETERS,
+                4,
+                None,
+                &mut cache,
+                Some(&progress),
+            );
+            assert_eq!(progress.fraction(), Some(1.0));
+        }
+        progress.finish();
+        assert_eq!(progress.fraction(), None);
+
+        let (buffer, iterations) = render_buffer(
+            30,
+            10,
+            &position,
+            max_iterations,
+            &DEFAULT_PARAMETERS,
+            DEFAULT_TILE_SIZE,
+            None,
+            &mut TileCache::new(0),
+        );
+        assert_eq!(rendered.iterations, iterations);
+        assert_eq!(rendered.text, buffer.text());
+
+        // The worker reuses its previous frame when the view has only been shifted
+        let shifted = position.translated(3.0 * 0.1, 0.2);
+        renderer.request(
+            30,
+            10,
+            &shifted,
+            max_iterations,
+            &DEFAULT_PARAMETERS,
+            DEFAULT_TILE_SIZE,
+            1,
+        );
+        let rendered = renderer.wait();
+        let (fresh, _) = render_buffer(
+            30,
+            10,
+            &shifted,
+            max_iterations,
+            &DEFAULT_PARAMETERS,
+            DEFAULT_TILE_SIZE,
+            None,
+            &mut TileCache::new(0),
+        );
+        assert_eq!(rendered.buffer.rows(), fresh.rows());
+        assert!(rendered.iterations < count_iterations(&fresh.cells));
+
+        // A reduced frame calculates a quarter of the cells but still fills the view
+        renderer.request(
+            30,
+            10,
+            &position,
+            max_iterations,
+            &DEFAULT_PARAMETERS,
+            DEFAULT_TILE_SIZE,
+            2,
+        );
+        let rendered = renderer.wait();
+        assert_eq!(rendered.scale, 2);
+        assert_eq!((rendered.buffer.width, rendered.buffer.height), (30, 10));
+        assert_eq!(rendered.buffer.cells.len(), 30 * 10);
+        assert!(rendered.iterations < iterations);
+    }
+
+    #[test]
+    fn test_interior_check() {
+        let max_iterations = u32x1::splat(1000);
+        let mandelbrot = |x: f64, y: f64| iterate_point(x, y, max_iterations, &DEFAULT_PARAMETERS);
+
+        // Cardioid, bulb, and just outside the cardioid's cusp
+        assert_eq!(mandelbrot(-0.5, 0.0), max_iterations);
+        assert_eq!(mandelbrot(0.2, 0.3), max_iterations);
+        assert_eq!(mandelbrot(-1.1, 0.1), max_iterations);
+        assert!(mandelbrot(0.3, 0.0) < max_iterations);
+        assert!(mandelbrot(-0.75, 0.1) < max_iterations);
+    }
+
+    #[test]
+    fn test_periodicity() {
+        let mut periodicity = Periodicity::new();
+        let orbit = [(0.5, 0.0), (-0.5, 0.0)];
+        let cycle = (0..8).map(|step| orbit[step % 2]).position(|(x, y)| {
+            periodicity
+                .is_cycle(Simd::<f64, 4>::splat(x), Simd::splat(y))
+                .all()
+        });
+        assert_eq!(cycle, Some(2));
+
+        // A period-3 bulb point is caught long before the iteration limit
+        let max_iterations = u32x1::splat(u32::MAX);
+        let iteration = iterate_point(-0.122, 0.745, max_iterations, &DEFAULT_PARAMETERS);
+        assert_eq!(iteration, max_iterations);
+    }
+
+    #[test]
+    fn test_initial_z() {
+        let max_iterations = u32x1::splat(100);
+        let starting = |initial_z: (f64, f64)| FractalParameters {
+            initial_z,
+            ..DEFAULT_PARAMETERS
+        };
+
+        // Starting at the pixel skips the first step from 0
+        let iteration = iterate_point(0.5, 0.5, max_iterations, &DEFAULT_PARAMETERS);
+        assert_eq!(
+            iterate_point(0.5, 0.5, max_iterations, &starting((0.5, 0.5))),
+            iteration - u32x1::splat(1)
+        );
+        // The cardioid shortcut doesn't hold for other starts
+        assert_eq!(
+            iterate_point(0.0, 0.0, max_iterations, &starting((3.0, 0.0))),
+            u32x1::splat(0)
+        );
+
+        // The Mandelbrot set from w at c is the Julia set for c at w
+        let julia = |julia_constant: (f64, f64)| FractalParameters {
+            fractal_index: JULIA_INDEX,
+            julia_constant,
+            ..DEFAULT_PARAMETERS
+        };
+        for (w, c) in [((0.1, 0.2), (-0.8, 0.156)), ((-0.4, 0.0), (0.285, 0.01))] {
+            assert_eq!(
+                iterate_point(c.0, c.1, max_iterations, &starting(w)),
+                iterate_point(w.0, w.1, max_iterations, &julia(c))
+            );
+            assert_eq!(
+                orbit_period(c.0, c.1, max_iterations, &starting(w)),
+                orbit_period(w.0, w.1, max_iterations, &julia(c))
+            );
+        }
+    }
+
+    #[test]
+    fn test_newton() {
+        let max_iterations = u32x1::splat(100);
+        let parameters = FractalParameters {
+            fractal_index: NEWTON_INDEX,
+            ..DEFAULT_PARAMETERS
+        };
+        let newton_point = |x, y| iterate_point(x, y, max_iterations, &parameters);
+        // A root is already reached, points near one take a few steps, and 0, where the
+        // derivative vanishes, never gets anywhere
+        assert_eq!(newton_point(1.0, 0.0), u32x1::splat(0));
+        assert!(newton_point(1.5, 0.2) < u32x1::splat(10));
+        assert_eq!(newton_point(0.0, 0.0), max_iterations);
+
+        // Orbits stop at the root they reach
+        let root = |x, y| {
+            let start = (Simd::<f64, 4>::splat(x), Simd::splat(y), Simd::splat(0));
+            let (_, (x, y)) = newton(start, Simd::splat(100));
+            root_index((x[0], y[0]))
+        };
+        assert_eq!(root(2.0, 0.0), 0);
+        assert_eq!(root(-1.0, 1.0), 1);
+        assert_eq!(root(-1.0, -1.0), 2);
+        assert_eq!(root_index((0.5, 0.5)), NO_ROOT);
+
+        // Raising the limit carries on the orbits that hadn't reached a root yet
+        let position = Position::new(-1.5, 1.5, -2.0, 2.0);
+        let render = |max_iterations, previous: Option<&FrameBuffer>| {
+            render_buffer(
+                24,
+                12,
+                &position,
+                u32x1::splat(max_iterations),
+                &parameters,
+                DEFAULT_TILE_SIZE,
+                previous,
+                &mut TileCache::new(0),
+            )
+        };
+        let (low, _) = render(4, None);
+        let (fresh, fresh_iterations) = render(50, None);
+        let (raised, raised_iterations) = render(50, Some(&low));
+        assert_eq!(raised.cells, fresh.cells);
+        assert!(raised_iterations < fresh_iterations);
+    }
+
+    #[test]
+    fn test_orbit_period() {
+        let max_iterations = u32x1::splat(10000);
+        let period = |x, y| orbit_period(x, y, max_iterations, &DEFAULT_PARAMETERS);
+        assert_eq!(period(0.0, 0.0), Some(1));
+        assert_eq!(period(-0.2, 0.1), Some(1));
+        assert_eq!(period(-1.0, 0.0), Some(2));
+        assert_eq!(period(-0.122, 0.745), Some(3));
+        assert_eq!(period(-1.755, 0.0), Some(3));
+        assert_eq!(period(1.0, 1.0), None);
+
+        let julia = FractalParameters {
+            fractal_index: JULIA_INDEX,
+            julia_constant: (-1.0, 0.0),
+            ..DEFAULT_PARAMETERS
+        };
+        assert_eq!(orbit_period(0.1, 0.0, max_iterations, &julia), Some(2));
+        let sinking_ship = FractalParameters {
+            fractal_index: 1,
+            ..DEFAULT_PARAMETERS
+        };
+        assert_eq!(orbit_period(0.0, 0.0, max_iterations, &sinking_ship), None);
+    }
+
+    #[test]
+    fn test_distance_estimate() {
+        let max_iterations = u32x1::splat(1000);
+        // The nearest point of the set to 1 is the cusp at 0.25
+        let (distance, direction) =
+            distance_estimate(1.0, 0.0, max_iterations, &DEFAULT_PARAMETERS).unwrap();
+        assert!((0.75..3.0).contains(&distance), "{}", distance);
+        assert!(direction.0 < -0.999 && direction.1.abs() < 1e-9);
+        let (_, direction) =
+            distance_estimate(0.0, 2.0, max_iterations, &DEFAULT_PARAMETERS).unwrap();
+        assert!(direction.1 < -0.9);
+        assert_eq!(distance_estimate(-0.5, 0.0, max_iterations, &DEFAULT_PARAMETERS), None);
+
+        // The Julia set of 0 is the unit circle
+        let julia = FractalParameters {
+            fractal_index: JULIA_INDEX,
+            julia_constant: (0.0, 0.0),
+            ..DEFAULT_PARAMETERS
+        };
+        let (distance, direction) = distance_estimate(0.0, 3.0, max_iterations, &julia).unwrap();
+        assert!((2.0..8.0).contains(&distance), "{}", distance);
+        assert!(direction.1 < -0.999);
+    }
+
+    #[test]
+    fn test_calculate_lanes() {
+        let position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let parameters = FractalParameters {
+            supersampling: 2,
+            ..DEFAULT_PARAMETERS
+        };
+        let cells = [(0, 0), (5, 3), (7, 4)];
+        let max_iterations = u32x1::splat(100);
+
+        let lanes = |wide: bool| {
+            let lanes = if wide {
+                calculate_lanes::<8>(&cells, None, 10, 6, &position, max_iterations, &parameters)
+            } else {
+                calculate_lanes::<4>(&cells, None, 10, 6, &position, max_iterations, &parameters)
+            };
+            lanes.into_iter().map(|(cell, _)| cell).collect::<Vec<Cell>>()
+        };
+        let (narrow, wide) = (lanes(false), lanes(true));
+        assert_eq!(narrow.len(), 3);
+        assert_eq!(narrow, wide);
+        assert_eq!(
+            narrow[1..],
+            calculate_cells(&cells[1..], 10, 6, &position, max_iterations, &parameters)
+        );
+    }
+
+    #[test]
+    fn test_fixed() {
+        let bits = 128;
+        for number in [0.0, 1.5, -0.75, 3.0e-20, -1234.5678] {
+            assert_eq!(Fixed::from_f64(number, bits).to_f64(), number);
+        }
+
+        let a = Fixed::from_f64(1.25, bits);
+        let b = Fixed::from_f64(-0.5, bits);
+        assert_eq!((&a + &b).to_f64(), 0.75);
+        assert_eq!((&a - &b).to_f64(), 1.75);
+        assert_eq!((&a * &b).to_f64(), -0.625);
+
+        // Values keep far more precision than an f64 does
+        let tiny = Fixed::from_f64(2f64.powi(-100), bits);
+        assert_eq!((&(&a + &tiny) - &a).to_f64(), 2f64.powi(-100));
+        assert_eq!(precision_bits(1.0), 64);
+        assert_eq!(precision_bits(0.25), 66);
+    }
+
+    #[test]
+    fn test_fixed_decimal() {
+        let number = Fixed::parse("-0.7436438870371587047521915061147").unwrap();
+        assert_eq!(number.to_decimal(31), "-0.7436438870371587047521915061147");
+        assert_eq!(number.to_decimal(3), "-0.744");
+        assert_eq!(Fixed::parse("1.5e-3").unwrap().to_decimal(4), "0.0015");
+        assert_eq!(Fixed::parse("+25").unwrap().to_decimal(0), "25");
+        assert_eq!(Fixed::parse("-0.00001").unwrap().to_decimal(2), "0.00");
+
+        assert!(Fixed::parse("").is_err());
+        assert!(Fixed::parse("abc").is_err());
+        assert!(Fixed::parse("inf").is_err());
+        assert!(Fixed::parse("1e100000").is_err());
+    }
+
+    #[test]
+    fn test_reference_orbit() {
+        // At a depth f64 still handles, perturbation should agree with direct iteration
+        let center = (-0.743643887037158, 0.131825904205311);
+        let position = Position::new(center.1 - 2e-7, center.1 + 2e-7, center.0 - 4e-7, center.0 + 4e-7);
+        let max_iterations = u32x1::splat(500);
+        let cells: Vec<(u16, u16)> = (0..8)
+            .flat_map(|pixel_y| (0..16).map(move |pixel_x| (pixel_x, pixel_y)))
+            .collect();
+
+        for fractal_index in [0, JULIA_INDEX] {
+            let parameters = FractalParameters {
+                fractal_index,
+                ..DEFAULT_PARAMETERS
+            };
+            let bits = precision_bits(1e-9);
+            let reference = ReferenceOrbit::new(
+                (
+                    &Fixed::from_f64(center.0, bits),
+                    &Fixed::from_f64(center.1, bits),
+                ),
+                position.width().hypot(position.height()) / 2.0,
+                max_iterations[0],
+                &parameters,
+            );
+            assert!(reference.skipped > 0);
+
+            let direct = calculate_cells(&cells, 16, 8, &position, max_iterations, &parameters);
+            let perturbed =
+                reference.calculate_cells(&cells, 16, 8, &position, max_iterations, &parameters);
+            let differences = direct
+                .iter()
+                .flatten()
+                .flatten()
+                .zip(perturbed.iter().flatten().flatten())
+                .filter(|(direct, perturbed)| direct[0].abs_diff(perturbed[0]) > 1)
+                .count();
+            assert!(differences <= 8, "{differences} subpixels differ");
+
+            // More terms and a larger tolerance skip further, and no terms skip nothing
+            let skipped = |series_order, series_tolerance| {
+                let parameters = FractalParameters {
+                    series_order,
+                    series_tolerance,
+                    ..parameters
+                };
+                ReferenceOrbit::new(
+                    (
+                        &Fixed::from_f64(center.0, bits),
+                        &Fixed::from_f64(center.1, bits),
+                    ),
+                    position.width().hypot(position.height()) / 2.0,
+                    max_iterations[0],
+                    &parameters,
+                )
+                .skipped
+            };
+            assert_eq!(skipped(0, 1e-6), 0);
+            assert!(skipped(1, 1e-6) <= reference.skipped);
+            assert!(skipped(8, 1e-6) >= reference.skipped);
+            assert!(skipped(3, 1e-3) >= reference.skipped);
+        }
+
+        // Only views too deep for f64 take the perturbation path
+        let shallow = DEFAULT_PARAMETERS;
+        assert!(ReferenceOrbit::for_view(16, &position, max_iterations, &shallow).is_none());
+        let deep = Position::new(center.1 - 1e-14, center.1 + 1e-14, center.0 - 2e-14, center.0 + 2e-14);
+        assert!(ReferenceOrbit::for_view(16, &deep, max_iterations, &shallow).is_some());
+
+        // Unless a backend is picked explicitly
+        let double = FractalParameters {
+            backend_index: 1,
+            ..DEFAULT_PARAMETERS
+        };
+        assert!(ReferenceOrbit::for_view(16, &deep, max_iterations, &double).is_none());
+        let perturbation = FractalParameters {
+            backend_index: 2,
+            ..DEFAULT_PARAMETERS
+        };
+        assert!(ReferenceOrbit::for_view(16, &position, max_iterations, &perturbation).is_some());
+
+        // Renders report the skip only when they take the perturbation path
+        let mut cache = TileCache::new(4);
+        let render = |parameters: &FractalParameters, cache: &mut TileCache| {
+            render_buffer_reporting(
+                16,
+                8,
+                &position,
+                max_iterations,
+                parameters,
+                16,
+                None,
+                cache,
+                None,
+            )
+            .2
+        };
+        assert_eq!(render(&double, &mut cache), None);
+        assert!(render(&perturbation, &mut cache).is_some_and(|skipped| skipped > 0));
+    }
+
+    #[test]
+    fn test_wide_frames() {
+        // Subpixel coordinates past the range of a u16 still land across the frame
+        let position = Position::new(-0.1, 0.1, -1.0, 0.5);
+        let max_iterations = u32x1::splat(100);
+        let cells = [(0, 0), (39999, 0)];
+        let direct = calculate_cells(
+            &cells,
+            40000,
+            1,
+            &position,
+            max_iterations,
+            &DEFAULT_PARAMETERS,
+        );
+        let center = position.center();
+        let extended = ExtendedCenter {
+            center: (center.0.to_double_double(), center.1.to_double_double()),
+        };
+        let precise = extended.calculate_cells(
+            &cells,
+            40000,
+            1,
+            &position,
+            max_iterations,
+            &DEFAULT_PARAMETERS,
+        );
+        assert_eq!(direct, precise);
+        // The left edge is in the period 2 bulb, and the right one escapes within a few
+        // iterations, where a wrapped coordinate would land in the cardioid
+        assert!(direct[1].iter().flatten().all(|value| value[0] < 10));
+        assert!(direct[0].iter().flatten().all(|value| value[0] == 100));
+    }
+
+    #[test]
+    fn test_double_double() {
+        // Where f64 is still precise, double-double orbits agree with it
+        let position = Position::new(-0.2, 0.2, -0.9, -0.5);
+        let max_iterations = u32x1::splat(200);
+        let cells: Vec<(u16, u16)> = (0..8)
+            .flat_map(|pixel_y| (0..16).map(move |pixel_x| (pixel_x, pixel_y)))
+            .collect();
+        // The Newton fractal has no double-double kernel
+        for fractal_index in 0..NEWTON_INDEX {
+            let parameters = FractalParameters {
+                fractal_index,
+                initial_z: if fractal_index == 0 {
+                    (0.1, 0.0)
+                } else {
+                    (0.0, 0.0)
+                },
+                ..DEFAULT_PARAMETERS
+            };
+            let center = position.center();
+            let extended = ExtendedCenter {
+                center: (center.0.to_double_double(), center.1.to_double_double()),
+            };
+            let direct = calculate_cells(&cells, 16, 8, &position, max_iterations, &parameters);
+            let precise =
+                extended.calculate_cells(&cells, 16, 8, &position, max_iterations, &parameters);
+            let differences = direct
+                .iter()
+                .flatten()
+                .flatten()
+                .zip(precise.iter().flatten().flatten())
+                .filter(|(direct, precise)| direct[0] != precise[0])
+                .count();
+            assert!(differences <= 4, "{differences} subpixels differ");
+        }
+
+        // Deeper, it agrees with perturbation
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let center = (
+            Fixed::parse("-0.743643887037158704752191506114774").unwrap(),
+            Fixed::parse("0.131825904205311970493132056385139").unwrap(),
+        );
+        let deep = default_position
+            .with_zoom(&default_position, 1e16)
+            .centered_at(&center);
+        let max_iterations = u32x1::splat(10000);
+        let double_double = FractalParameters {
+            backend_index: 3,
+            ..DEFAULT_PARAMETERS
+        };
+        let perturbation = FractalParameters {
+            backend_index: 2,
+            ..DEFAULT_PARAMETERS
+        };
+        let deep_cells = |parameters: &FractalParameters| {
+            let kernel = DeepKernel::for_view(16, &deep, max_iterations, parameters).unwrap();
+            kernel.calculate_cells(&cells, 16, 8, &deep, max_iterations, parameters)
+        };
+        assert!(matches!(
+            DeepKernel::for_view(16, &deep, max_iterations, &double_double),
+            Some(DeepKernel::DoubleDouble(_))
+        ));
+        let precise = deep_cells(&double_double);
+        let perturbed = deep_cells(&perturbation);
+        let differences = precise
+            .iter()
+            .flatten()
+            .flatten()
+            .zip(perturbed.iter().flatten().flatten())
+            .filter(|(precise, perturbed)| precise[0].abs_diff(perturbed[0]) > 1)
+            .count();
+        // A few orbits that nearly reach the limit are chaotic enough to escape elsewhere
+        assert!(differences <= 24, "{differences} subpixels differ");
+        // With more than one value, so the subpixels weren't rounded together
+        let first = precise[0][0][0];
+        assert!(precise
+            .iter()
+            .flatten()
+            .flatten()
+            .any(|value| *value != first));
+
+        // Shallow views stay in f64, and views past double-double go on by perturbation,
+        // or run out of precision for fractals without a perturbed kernel
+        assert!(DeepKernel::for_view(16, &position, max_iterations, &double_double).is_none());
+        let deeper = default_position
+            .with_zoom(&default_position, 1e30)
+            .centered_at(&center);
+        assert!(matches!(
+            DeepKernel::for_view(16, &deeper, max_iterations, &double_double),
+            Some(DeepKernel::Perturbation(_))
+        ));
+        let sinking_ship = FractalParameters {
+            fractal_index: 1,
+            ..double_double
+        };
+        assert!(uses_double_double(16, &deeper, &sinking_ship));
+        assert!(!precision_exhausted(16, &deep, &sinking_ship));
+        let deepest = default_position
+            .with_zoom(&default_position, 1e40)
+            .centered_at(&center);
+        assert!(precision_exhausted(16, &deepest, &sinking_ship));
+    }
+
+    #[test]
+    fn test_calculate_pixel() {
+        assert_eq!(
+            shade_pixel(calculate_cells(&[(0, 0)], 1, 1, &Position::new(-1.0, 1.0, -2.0, 1.0), u32x1::splat(100), &DEFAULT_PARAMETERS)[0], &ColorTable::new(u32x1::splat(100), &DEFAULT_PARAMETERS)),
+            Pixel {
+                character: TWO_QUADRANTS[2].chars().next().unwrap(),
+                foreground_color: [0, 0, 0],
+                background_color: Some([255, 30, 0]),
+            }
+        );
+        assert_eq!(
+            shade_pixel(calculate_cells(&[(0, 0)], 1, 1, &Position::new(-1.0, 1.0, -2.0, 1.0), u32x1::splat(0), &DEFAULT_PARAMETERS)[0], &ColorTable::new(u32x1::splat(0), &DEFAULT_PARAMETERS)),
+            Pixel {
+                character: FULL_BLOCK[0].chars().next().unwrap(),
+                foreground_color: [0, 0, 0],
+                background_color: None,
+            }
+        );
+    }
+
+    // A frame as plain text, one line per row: each cell's character followed by its
+    // foreground and background colors
+    fn golden_text(buffer: &FrameBuffer) -> String {
+        let colors = buffer.color_table();
+        let color_text = |[r, g, b]: [u8; 3]| format!("{:02x}{:02x}{:02x}", r, g, b);
+        let mut text = String::new();
+        for row in buffer.cells().chunks(buffer.width as usize) {
+            let cells: Vec<String> = row
+                .iter()
+                .map(|cell| {
+                    let pixel = shade_pixel(*cell, &colors);
+                    let background = pixel.background_color.map_or("-".to_string(), color_text);
+                    format!(
+                        "{}{}/{}",
+                        pixel.character,
+                        color_text(pixel.foreground_color),
+                        background
+                    )
+                })
+                .collect();
+            text.push_str(&cells.join(" "));
+            text.push('\n');
+        }
+        text
+    }
+
+    #[test]
+    fn test_golden_frames() {
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let seahorse_valley = default_position
+            .with_zoom(&default_position, 1000.0)
+            .centered_at(&fixed_point((-0.745, 0.113)));
+        let views = [
+            ("mandelbrot", default_position.clone(), 100, DEFAULT_PARAMETERS),
+            (
+                "sinking-ship",
+                default_position.clone(),
+                100,
+                FractalParameters {
+                    fractal_index: 1,
+                    ..DEFAULT_PARAMETERS
+                },
+            ),
+            (
+                "julia",
+                Position::new(-1.2, 1.2, -1.8, 1.8),
+                100,
+                FractalParameters {
+                    fractal_index: JULIA_INDEX,
+                    ..DEFAULT_PARAMETERS
+                },
+            ),
+            (
+                "mandelbrot-perturbation",
+                seahorse_valley,
+                500,
+                FractalParameters {
+                    backend_index: 2,
+                    ..DEFAULT_PARAMETERS
+                },
+            ),
+            (
+                "mandelbrot-fire-cyclic",
+                default_position,
+                100,
+                FractalParameters {
+                    palette_index: 2,
+                    coloring_index: 2,
+                    supersampling: 2,
+                    ..DEFAULT_PARAMETERS
+                },
+            ),
+        ];
+
+        // Run with UPDATE_GOLDEN=1 to rewrite the files after an intended change to the output
+        let directory = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+        let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+        for (name, position, max_iterations, parameters) in views {
+            let (buffer, _) = render_buffer(
+                20,
+                8,
+                &position,
+                u32x1::splat(max_iterations),
+                &parameters,
+                DEFAULT_TILE_SIZE,
+                None,
+                &mut TileCache::new(0),
+            );
+            let text = golden_text(&buffer);
+            let path = directory.join(format!("{}.txt", name));
+            if update {
+                std::fs::create_dir_all(&directory).unwrap();
+                std::fs::write(&path, &text).unwrap();
+            }
+            let expected = std::fs::read_to_string(&path)
+                .unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+            assert_eq!(text, expected, "{} no longer matches {}", name, path.display());
+        }
+    }
+}
@@ -0,0 +1,444 @@
+// The settings and picker menus, and the crosshair and toast readouts.
+
+use crate::app::{
+    adjust_step, fractal_label, step_iterations, FRAME_BUDGETS, SERIES_TOLERANCE_RANGE,
+    TILE_SIZE_RANGE, ZOOM_STEP_RANGE,
+};
+use crate::{
+    palette_names, u32x1, Fixed, FractalParameters, COLORINGS, COLORING_NAMES, MAX_SERIES_ORDER,
+};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) enum MenuKind {
+    Fractal,
+    Palette,
+}
+
+#[derive(PartialEq, Debug)]
+pub(crate) enum MenuAction {
+    None,
+    Select(usize),
+    Close,
+}
+
+pub(crate) struct Menu {
+    pub(crate) kind: MenuKind,
+    title: &'static str,
+    items: Vec<&'static str>,
+    selected: usize,
+}
+
+impl Menu {
+    pub(crate) fn new(
+        kind: MenuKind,
+        title: &'static str,
+        items: &[&'static str],
+        selected: usize,
+    ) -> Menu {
+        Menu {
+            kind,
+            title,
+            items: items.to_vec(),
+            selected,
+        }
+    }
+
+    pub(crate) fn handle_key(&mut self, code: crossterm::event::KeyCode) -> MenuAction {
+        match code {
+            crossterm::event::KeyCode::Up => {
+                self.selected = (self.selected + self.items.len() - 1) % self.items.len();
+                MenuAction::None
+            }
+            crossterm::event::KeyCode::Down => {
+                self.selected = (self.selected + 1) % self.items.len();
+                MenuAction::None
+            }
+            crossterm::event::KeyCode::Enter => MenuAction::Select(self.selected),
+            crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Char('q') => {
+                MenuAction::Close
+            }
+            _ => MenuAction::None,
+        }
+    }
+
+    pub(crate) fn lines(&self) -> Vec<String> {
+        let mut lines = vec![self.title.to_string(), String::new()];
+        for (index, item) in self.items.iter().enumerate() {
+            let marker = if index == self.selected { "▶" } else { " " };
+            lines.push(format!("{} {}", marker, item));
+        }
+        lines
+    }
+}
+
+pub(crate) const SETTINGS: [&str; 14] = [
+    "Max iterations",
+    "Auto iterations",
+    "Escape radius",
+    "Supersampling",
+    "Palette",
+    "Coloring",
+    "Palette phase",
+    "Aspect ratio",
+    "Zoom step",
+    "Tile size",
+    "Frame budget",
+    "Series order",
+    "Series tolerance",
+    "Tile limits",
+];
+
+const PALETTE_PHASE_STEPS: usize = 20;
+
+// How the plane is fitted to the terminal: stretched over it, kept in proportion by showing
+// more or less of it, or kept to the shape of the default view with bars round it
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Aspect {
+    Stretch,
+    Correct,
+    Letterbox,
+}
+
+const ASPECTS: [Aspect; 3] = [Aspect::Stretch, Aspect::Correct, Aspect::Letterbox];
+const ASPECT_NAMES: [&str; 3] = ["Stretch", "Correct", "Letterbox"];
+
+pub(crate) struct Settings<'a> {
+    pub(crate) max_iterations: &'a mut u32x1,
+    pub(crate) auto_iterations: &'a mut bool,
+    pub(crate) parameters: &'a mut FractalParameters,
+    pub(crate) aspect: &'a mut Aspect,
+    pub(crate) zoom_step: &'a mut f64,
+    pub(crate) tile_size: &'a mut u16,
+    pub(crate) frame_budget: &'a mut u64,
+}
+
+impl Settings<'_> {
+    fn value(&self, index: usize) -> String {
+        match index {
+            0 => self.max_iterations[0].to_string(),
+            1 => if *self.auto_iterations { "On" } else { "Off" }.to_string(),
+            2 => self.parameters.escape_radius.to_string(),
+            3 => {
+                let samples = self.parameters.supersampling;
+                format!("{}x{}", samples, samples)
+            }
+            4 => palette_names()[self.parameters.palette_index].to_string(),
+            5 => COLORING_NAMES[self.parameters.coloring_index].to_string(),
+            6 => format!(
+                "{:.0}%",
+                self.parameters.palette_phase.rem_euclid(1.0) * 100.0
+            ),
+            7 => ASPECT_NAMES[*self.aspect as usize].to_string(),
+            8 => format!("{:.1}%", *self.zoom_step * 100.0),
+            9 => format!("{}x{}", self.tile_size, self.tile_size),
+            10 if *self.frame_budget == 0 => "Off".to_string(),
+            10 => format!("{} ms", self.frame_budget),
+            11 if self.parameters.series_order == 0 => "Off".to_string(),
+            11 => self.parameters.series_order.to_string(),
+            12 => format!("{:e}", self.parameters.series_tolerance),
+            _ => if self.parameters.tile_limits { "On" } else { "Off" }.to_string(),
+        }
+    }
+
+    pub(crate) fn adjust(&mut self, index: usize, increase: bool) {
+        let cycle = |value: usize, count: usize| {
+            if increase {
+                (value + 1) % count
+            } else {
+                (value + count - 1) % count
+            }
+        };
+
+        match index {
+            0 => {
+                *self.max_iterations = step_iterations(*self.max_iterations, increase);
+                *self.auto_iterations = false;
+            }
+            1 => *self.auto_iterations = !*self.auto_iterations,
+            2 => {
+                let radius = self.parameters.escape_radius + if increase { 1.0 } else { -1.0 };
+                self.parameters.escape_radius = radius.clamp(2.0, 100.0);
+            }
+            3 => {
+                let samples = if increase {
+                    self.parameters.supersampling + 1
+                } else {
+                    self.parameters.supersampling - 1
+                };
+                self.parameters.supersampling = samples.clamp(1, 4);
+            }
+            4 => {
+                self.parameters.palette_index =
+                    cycle(self.parameters.palette_index, palette_names().len());
+            }
+            5 => {
+                self.parameters.coloring_index =
+                    cycle(self.parameters.coloring_index, COLORINGS.len());
+            }
+            6 => {
+                // Whole steps, so going round never drifts off them
+                let phase = self.parameters.palette_phase.rem_euclid(1.0);
+                let step = (phase * PALETTE_PHASE_STEPS as f64).round();
+                let step = cycle(step as usize % PALETTE_PHASE_STEPS, PALETTE_PHASE_STEPS);
+                self.parameters.palette_phase = step as f64 / PALETTE_PHASE_STEPS as f64;
+            }
+            7 => *self.aspect = ASPECTS[cycle(*self.aspect as usize, ASPECTS.len())],
+            8 => *self.zoom_step = adjust_step(*self.zoom_step, increase, ZOOM_STEP_RANGE),
+            9 => {
+                let size = if increase {
+                    *self.tile_size * 2
+                } else {
+                    *self.tile_size / 2
+                };
+                *self.tile_size = size.clamp(TILE_SIZE_RANGE.0, TILE_SIZE_RANGE.1);
+            }
+            10 => {
+                let index = FRAME_BUDGETS
+                    .iter()
+                    .position(|budget| budget == self.frame_budget)
+                    .unwrap_or(0);
+                *self.frame_budget = FRAME_BUDGETS[cycle(index, FRAME_BUDGETS.len())];
+            }
+            11 => {
+                let order = if increase {
+                    self.parameters.series_order + 1
+                } else {
+                    self.parameters.series_order.saturating_sub(1)
+                };
+                self.parameters.series_order = order.min(MAX_SERIES_ORDER);
+            }
+            12 => {
+                // Tenfold steps, rounded so they stay on powers of ten
+                let exponent = self.parameters.series_tolerance.log10().round()
+                    + if increase { 1.0 } else { -1.0 };
+                let (low, high) = SERIES_TOLERANCE_RANGE;
+                self.parameters.series_tolerance = 10f64.powf(exponent).clamp(low, high);
+            }
+            _ => self.parameters.tile_limits = !self.parameters.tile_limits,
+        }
+    }
+
+    pub(crate) fn lines(&self, selected: usize) -> Vec<String> {
+        let mut lines = vec!["Settings".to_string(), String::new()];
+        for (index, name) in SETTINGS.iter().enumerate() {
+            let marker = if index == selected { "▶" } else { " " };
+            lines.push(format!("{} {:<16}◀ {} ▶", marker, name, self.value(index)));
+        }
+        lines.push(String::new());
+        lines.push("Up/Down select, Left/Right change, Tab close".to_string());
+        lines
+    }
+}
+
+pub(crate) fn render_crosshair(column: u16, row: u16) -> String {
+    format!(
+        "{}{}{}┼{}",
+        crossterm::cursor::MoveTo(column, row),
+        crossterm::style::SetForegroundColor(crossterm::style::Color::White),
+        crossterm::style::SetBackgroundColor(crossterm::style::Color::Black),
+        crossterm::style::ResetColor
+    )
+}
+
+pub(crate) fn crosshair_lines(
+    point: &(Fixed, Fixed),
+    iteration: u32x1,
+    max_iterations: u32x1,
+    period: Option<u32>,
+) -> Vec<String> {
+    let escape = if iteration == max_iterations {
+        match period {
+            Some(period) => format!("in set, period {}", period),
+            None => "in set".to_string(),
+        }
+    } else {
+        format!("escapes after {}", iteration[0])
+    };
+
+    vec![
+        format!("x  {}", point.0.to_decimal(17)),
+        format!("y  {}", point.1.to_decimal(17)),
+        escape,
+    ]
+}
+
+pub(crate) fn toast_text(
+    previous_iterations: u32x1,
+    previous_parameters: &FractalParameters,
+    max_iterations: u32x1,
+    parameters: &FractalParameters,
+) -> Option<String> {
+    if parameters.fractal_index != previous_parameters.fractal_index
+        || parameters.julia_constant != previous_parameters.julia_constant
+    {
+        Some(fractal_label(parameters))
+    } else if parameters.palette_index != previous_parameters.palette_index {
+        Some(format!("palette: {}", palette_names()[parameters.palette_index]))
+    } else if parameters.coloring_index != previous_parameters.coloring_index {
+        Some(format!("coloring: {}", COLORING_NAMES[parameters.coloring_index]))
+    } else if max_iterations != previous_iterations {
+        Some(format!("max iterations: {}", max_iterations[0]))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixed_point, DEFAULT_PARAMETERS, DEFAULT_TILE_SIZE, PALETTES, PALETTE_NAMES};
+
+    #[test]
+    fn test_crosshair_lines() {
+        assert_eq!(
+            crosshair_lines(
+                &fixed_point((-0.5, 0.25)),
+                u32x1::splat(100),
+                u32x1::splat(100),
+                None
+            ),
+            vec![
+                "x  -0.50000000000000000".to_string(),
+                "y  0.25000000000000000".to_string(),
+                "in set".to_string(),
+            ]
+        );
+        assert_eq!(
+            crosshair_lines(
+                &fixed_point((1.0, 1.0)),
+                u32x1::splat(3),
+                u32x1::splat(100),
+                None
+            )[2],
+            "escapes after 3"
+        );
+        assert_eq!(
+            crosshair_lines(
+                &fixed_point((-1.0, 0.0)),
+                u32x1::splat(100),
+                u32x1::splat(100),
+                Some(2)
+            )[2],
+            "in set, period 2"
+        );
+    }
+
+    #[test]
+    fn test_menu() {
+        let mut menu = Menu::new(MenuKind::Palette, "Palette", &PALETTE_NAMES, 0);
+        assert_eq!(menu.handle_key(crossterm::event::KeyCode::Up), MenuAction::None);
+        assert_eq!(menu.selected, PALETTE_NAMES.len() - 1);
+        assert_eq!(menu.handle_key(crossterm::event::KeyCode::Down), MenuAction::None);
+        assert_eq!(menu.handle_key(crossterm::event::KeyCode::Down), MenuAction::None);
+        assert_eq!(menu.handle_key(crossterm::event::KeyCode::Enter), MenuAction::Select(1));
+        assert_eq!(menu.handle_key(crossterm::event::KeyCode::Esc), MenuAction::Close);
+        assert_eq!(menu.lines()[3], "▶ Grayscale");
+    }
+
+    #[test]
+    fn test_settings() {
+        let mut max_iterations = u32x1::splat(10);
+        let mut auto_iterations = true;
+        let mut parameters = DEFAULT_PARAMETERS;
+        let mut aspect = Aspect::Stretch;
+        let mut zoom_step = 0.1;
+        let mut tile_size = DEFAULT_TILE_SIZE;
+        let mut frame_budget = FRAME_BUDGETS[2];
+        let mut settings = Settings {
+            max_iterations: &mut max_iterations,
+            auto_iterations: &mut auto_iterations,
+            parameters: &mut parameters,
+            aspect: &mut aspect,
+            zoom_step: &mut zoom_step,
+            tile_size: &mut tile_size,
+            frame_budget: &mut frame_budget,
+        };
+
+        settings.adjust(0, false);
+        assert_eq!(settings.value(0), "10");
+        assert_eq!(settings.value(1), "Off");
+        settings.adjust(1, true);
+        assert_eq!(settings.value(1), "On");
+        settings.adjust(3, true);
+        assert_eq!(settings.value(3), "2x2");
+        settings.adjust(4, false);
+        assert_eq!(settings.value(4), PALETTE_NAMES[PALETTES.len() - 1]);
+        assert_eq!(settings.value(6), "0%");
+        settings.adjust(6, false);
+        assert_eq!(settings.value(6), "95%");
+        settings.adjust(6, true);
+        settings.adjust(6, true);
+        assert_eq!(settings.value(6), "5%");
+        settings.adjust(7, true);
+        assert_eq!(settings.value(7), "Correct");
+        settings.adjust(7, true);
+        assert_eq!(settings.value(7), "Letterbox");
+        settings.adjust(7, true);
+        assert_eq!(settings.value(7), "Stretch");
+        settings.adjust(7, false);
+        assert_eq!(settings.value(7), "Letterbox");
+        settings.adjust(8, true);
+        assert_eq!(settings.value(8), "20.0%");
+        settings.adjust(9, true);
+        assert_eq!(settings.value(9), "32x32");
+        settings.adjust(9, true);
+        settings.adjust(9, true);
+        assert_eq!(settings.value(9), "64x64");
+        assert_eq!(settings.value(10), "50 ms");
+        settings.adjust(10, false);
+        settings.adjust(10, false);
+        assert_eq!(settings.value(10), "Off");
+        assert_eq!(settings.value(11), "3");
+        settings.adjust(11, false);
+        settings.adjust(11, false);
+        settings.adjust(11, false);
+        settings.adjust(11, false);
+        assert_eq!(settings.value(11), "Off");
+        assert_eq!(settings.value(12), "1e-6");
+        settings.adjust(12, true);
+        assert_eq!(settings.value(12), "1e-5");
+        for _ in 0..10 {
+            settings.adjust(12, true);
+        }
+        assert_eq!(settings.value(12), "1e-1");
+        assert_eq!(settings.value(13), "Off");
+        settings.adjust(13, false);
+        assert_eq!(settings.value(13), "On");
+        assert_eq!(parameters.palette_phase, 0.05);
+    }
+
+    #[test]
+    fn test_toast_text() {
+        let iterations = u32x1::splat(100);
+        let parameters = DEFAULT_PARAMETERS;
+        assert!(toast_text(iterations, &parameters, iterations, &parameters).is_none());
+        assert_eq!(
+            toast_text(iterations, &parameters, u32x1::splat(150), &parameters),
+            Some("max iterations: 150".to_string())
+        );
+        assert_eq!(
+            toast_text(
+                iterations,
+                &parameters,
+                iterations,
+                &FractalParameters {
+                    fractal_index: 1,
+                    ..parameters
+                }
+            ),
+            Some("Sinking Ship".to_string())
+        );
+        assert_eq!(
+            toast_text(
+                iterations,
+                &parameters,
+                iterations,
+                &FractalParameters {
+                    palette_index: 2,
+                    ..parameters
+                }
+            ),
+            Some(format!("palette: {}", PALETTE_NAMES[2]))
+        );
+    }
+}
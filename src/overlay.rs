@@ -0,0 +1,1083 @@
+// What is drawn over the frame: overlays, the minimap, axes, paths, histograms, the
+// view description and its sonification, and the help screen.
+
+use crate::app::{
+    decimal_places, format_zoom, fractal_label, trimmed_decimal, KEYBINDINGS, KEYMAP_HELP,
+};
+use crate::explore::boundary_distance;
+use crate::keymap::Keymap;
+use crate::menu::render_crosshair;
+use crate::{
+    fixed_point, inscribed, iterate_point, orbit_period, palette_names, plane_to_cell, render_rows,
+    simd_lanes, u32x1, Cell, Fixed, FractalParameters, FrameBuffer, Position, Projection,
+    JULIA_INDEX,
+};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) enum Anchor {
+    Center,
+    TopLeft,
+    TopRight,
+    Right,
+    Bottom,
+}
+
+pub(crate) fn render_overlay(lines: &[String], anchor: Anchor, width: u16, height: u16) -> String {
+    let max_inner_width = (width as usize).saturating_sub(4);
+    let max_inner_height = (height as usize).saturating_sub(2);
+
+    let inner_width = lines
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(max_inner_width);
+    let visible_lines = &lines[..lines.len().min(max_inner_height)];
+
+    let free_columns = (width as usize).saturating_sub(inner_width + 4);
+    let free_rows = (height as usize).saturating_sub(visible_lines.len() + 2);
+    let (left, top) = match anchor {
+        Anchor::Center => (free_columns / 2, free_rows / 2),
+        Anchor::TopLeft => (0, 0),
+        Anchor::TopRight => (free_columns, 0),
+        Anchor::Right => (free_columns, free_rows / 2),
+        Anchor::Bottom => (free_columns / 2, free_rows),
+    };
+    let (left, top) = (left as u16, top as u16);
+
+    let mut output = format!(
+        "{}{}",
+        crossterm::style::SetForegroundColor(crossterm::style::Color::White),
+        crossterm::style::SetBackgroundColor(crossterm::style::Color::Black),
+    );
+
+    output.push_str(&format!(
+        "{}┌{}┐",
+        crossterm::cursor::MoveTo(left, top),
+        "─".repeat(inner_width + 2)
+    ));
+    for (index, line) in visible_lines.iter().enumerate() {
+        let line: String = line.chars().take(inner_width).collect();
+        output.push_str(&format!(
+            "{}│ {:<inner_width$} │",
+            crossterm::cursor::MoveTo(left, top + 1 + index as u16),
+            line,
+            inner_width = inner_width
+        ));
+    }
+    output.push_str(&format!(
+        "{}└{}┘",
+        crossterm::cursor::MoveTo(left, top + 1 + visible_lines.len() as u16),
+        "─".repeat(inner_width + 2)
+    ));
+
+    output.push_str(&format!("{}", crossterm::style::ResetColor));
+    output
+}
+
+pub(crate) fn render_selection(start: (u16, u16), end: (u16, u16)) -> String {
+    let left = start.0.min(end.0);
+    let right = start.0.max(end.0);
+    let top = start.1.min(end.1);
+    let bottom = start.1.max(end.1);
+
+    let mut output = format!(
+        "{}",
+        crossterm::style::SetForegroundColor(crossterm::style::Color::White)
+    );
+
+    for column in left..=right {
+        output.push_str(&format!("{}─", crossterm::cursor::MoveTo(column, top)));
+        output.push_str(&format!("{}─", crossterm::cursor::MoveTo(column, bottom)));
+    }
+    for row in top..=bottom {
+        output.push_str(&format!("{}│", crossterm::cursor::MoveTo(left, row)));
+        output.push_str(&format!("{}│", crossterm::cursor::MoveTo(right, row)));
+    }
+    output.push_str(&format!("{}┌", crossterm::cursor::MoveTo(left, top)));
+    output.push_str(&format!("{}┐", crossterm::cursor::MoveTo(right, top)));
+    output.push_str(&format!("{}└", crossterm::cursor::MoveTo(left, bottom)));
+    output.push_str(&format!("{}┘", crossterm::cursor::MoveTo(right, bottom)));
+
+    output.push_str(&format!("{}", crossterm::style::ResetColor));
+    output
+}
+
+// The kernels currently evaluate one pixel per vector
+
+fn format_rate(value: f64) -> String {
+    if value >= 1e9 {
+        format!("{:.2}G", value / 1e9)
+    } else if value >= 1e6 {
+        format!("{:.2}M", value / 1e6)
+    } else if value >= 1e3 {
+        format!("{:.2}k", value / 1e3)
+    } else {
+        format!("{:.0}", value)
+    }
+}
+
+const HISTOGRAM_BAR_WIDTH: usize = 16;
+
+// The escape iterations of a frame in equal bins up to the limit, with how much reaches the
+// limit or comes close to it. Bars are on a log scale so that the few counts near the limit
+// still show up next to the many that escape early
+pub(crate) fn histogram_lines(cells: &[Cell], max_iterations: u32x1, bins: usize) -> Vec<String> {
+    let limit = max_iterations[0].max(1) as u64;
+    let bins = bins.max(1);
+    let mut counts = vec![0u64; bins];
+    let (mut inside, mut near_limit, mut total) = (0, 0, 0);
+    for iteration in cells.iter().flatten().flatten() {
+        let iteration = iteration[0] as u64;
+        total += 1;
+        if iteration >= limit {
+            inside += 1;
+            continue;
+        }
+        counts[(iteration * bins as u64 / limit) as usize] += 1;
+        if iteration * 10 >= limit * 9 {
+            near_limit += 1;
+        }
+    }
+
+    let largest = (*counts.iter().max().unwrap_or(&0) as f64).ln_1p();
+    let percent = |count: u64| count as f64 * 100.0 / total.max(1) as f64;
+    let mut lines = vec![format!("Iterations up to {}", limit)];
+    for (index, count) in counts.iter().enumerate() {
+        let length = if *count == 0 {
+            0
+        } else {
+            // Anything counted gets at least a sliver
+            ((*count as f64).ln_1p() / largest * HISTOGRAM_BAR_WIDTH as f64).ceil() as usize
+        };
+        let start = index as u64 * limit / bins as u64;
+        lines.push(format!("{:>7} {}", start, "█".repeat(length)));
+    }
+    lines.push(format!("{:<16}{:.1}%", "Inside", percent(inside)));
+    lines.push(format!(
+        "{:<16}{:.1}%",
+        "Near the limit",
+        percent(near_limit)
+    ));
+    lines
+}
+
+// A plain description of a frame for reading aloud: where it is, how much of it is in the
+// set and where, and what the spread of escape iterations says about the detail in it
+pub(crate) fn describe_view(buffer: &FrameBuffer, default_position: &Position) -> Vec<String> {
+    let parameters = &buffer.parameters;
+    let position = &buffer.position;
+    let center = position.center();
+    let zoom = default_position.width() / position.width();
+    let places = decimal_places(zoom);
+    let mut lines = vec![
+        format!(
+            "{} in the {} palette",
+            fractal_label(parameters),
+            palette_names()[parameters.palette_index]
+        ),
+        format!(
+            "Center {}, {} at zoom {}, up to {} iterations",
+            trimmed_decimal(&center.0, places),
+            trimmed_decimal(&center.1, places),
+            format_zoom(zoom),
+            buffer.max_iterations[0]
+        ),
+    ];
+
+    // Count the points inside the set in each third of the view across and down
+    let limit = buffer.max_iterations[0].max(1);
+    let width = buffer.width.max(1) as usize;
+    let height = buffer.height.max(1) as usize;
+    let mut regions = [0u64; 9];
+    let (mut inside, mut near_limit, mut quick, mut total) = (0u64, 0u64, 0u64, 0u64);
+    for (index, cell) in buffer.cells().iter().enumerate() {
+        let region = (index / width * 3 / height).min(2) * 3 + (index % width * 3 / width);
+        for iteration in cell.iter().flatten() {
+            let iteration = iteration[0];
+            total += 1;
+            if iteration >= limit {
+                inside += 1;
+                regions[region] += 1;
+            } else if iteration as u64 * 10 >= limit as u64 * 9 {
+                near_limit += 1;
+            } else if iteration < 10 {
+                quick += 1;
+            }
+        }
+    }
+    let percent = |count: u64| count as f64 * 100.0 / total.max(1) as f64;
+
+    lines.push(if inside == 0 {
+        "None of the view is inside the set".to_string()
+    } else if inside == total {
+        "All of the view is inside the set".to_string()
+    } else {
+        let (region, count) = regions
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, count)| **count)
+            .unwrap();
+        let place = if *count * 2 > inside {
+            match (
+                ["top ", "", "bottom "][region / 3],
+                ["left", "", "right"][region % 3],
+            ) {
+                ("", "") => " in the middle".to_string(),
+                (row, "") => format!(" at the {}", row.trim_end()),
+                (row, column) => format!(" at the {}{}", row, column),
+            }
+        } else {
+            String::new()
+        };
+        format!(
+            "{:.0}% of the view is inside the set{}",
+            percent(inside).max(1.0),
+            if place.is_empty() {
+                ", spread across it"
+            } else {
+                &place
+            },
+        )
+    });
+
+    // The histogram's shape tells the kind of place apart
+    lines.push(if inside == total {
+        "Deep inside a bulb, with no edge in view".to_string()
+    } else if percent(near_limit) >= 5.0 {
+        format!(
+            "Fine detail: {:.0}% of the view nearly reaches the iteration limit, so raising \
+             it may show more",
+            percent(near_limit)
+        )
+    } else if inside > 0 {
+        "The edge of the set runs through the view".to_string()
+    } else if percent(quick) >= 90.0 {
+        "Open space: nearly every point escapes within 10 iterations".to_string()
+    } else {
+        "Near filaments of the set, which the bands of color follow".to_string()
+    });
+
+    let (x, y) = (center.0.to_f64(), center.1.to_f64());
+    let iteration = iterate_point(x, y, buffer.max_iterations, parameters);
+    lines.push(if iteration == buffer.max_iterations {
+        match orbit_period(x, y, buffer.max_iterations, parameters) {
+            Some(period) => format!("The middle is inside the set, with period {}", period),
+            None => "The middle is inside the set".to_string(),
+        }
+    } else {
+        let distance = match boundary_distance(position, buffer.max_iterations, parameters) {
+            Some(Some(distance)) => format!(", {:.2e} from the set", distance),
+            _ => String::new(),
+        };
+        let plural = if iteration[0] == 1 { "" } else { "s" };
+        format!(
+            "The middle escapes after {} iteration{}{}",
+            iteration[0], plural, distance
+        )
+    });
+    lines
+}
+
+// The pitches of points that escape straight away and of those that reach the limit, in hertz
+const TONE_RANGE: (f32, f32) = (220.0, 1760.0);
+#[cfg(feature = "sound")]
+const TONE_VOLUME: f32 = 0.2;
+
+// The pitch a point that escaped at `iteration` is played at, rising on a log scale towards the
+// limit like the histogram's bins; points in the set are silent
+pub(crate) fn tone_frequency(iteration: u32x1, max_iterations: u32x1) -> Option<f32> {
+    let (iteration, limit) = (iteration[0], max_iterations[0].max(1));
+    if iteration >= limit {
+        return None;
+    }
+    let fraction = (iteration as f32).ln_1p() / (limit as f32).ln_1p();
+    Some(TONE_RANGE.0 * (TONE_RANGE.1 / TONE_RANGE.0).powf(fraction))
+}
+
+// A sine tone on the default sound output, which glides to each pitch it is set to
+#[cfg(feature = "sound")]
+struct Tone {
+    // The pitch as the bits of an f32, with 0 for silence
+    frequency: std::sync::Arc<std::sync::atomic::AtomicU32>,
+    _stream: cpal::Stream,
+}
+
+#[cfg(feature = "sound")]
+impl Tone {
+    fn start() -> Result<Tone, String> {
+        use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or("no sound output device")?;
+        let config = device
+            .default_output_config()
+            .map_err(|error| error.to_string())?;
+        let frequency = std::sync::Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::F32 => Tone::stream::<f32>(&device, config.config(), &frequency),
+            cpal::SampleFormat::I16 => Tone::stream::<i16>(&device, config.config(), &frequency),
+            cpal::SampleFormat::U16 => Tone::stream::<u16>(&device, config.config(), &frequency),
+            format => Err(format!(
+                "sound output of {} samples isn't supported",
+                format
+            )),
+        }?;
+        stream.play().map_err(|error| error.to_string())?;
+        Ok(Tone {
+            frequency,
+            _stream: stream,
+        })
+    }
+
+    fn stream<T: cpal::SizedSample + cpal::FromSample<f32>>(
+        device: &cpal::Device,
+        config: cpal::StreamConfig,
+        frequency: &std::sync::Arc<std::sync::atomic::AtomicU32>,
+    ) -> Result<cpal::Stream, String> {
+        use cpal::traits::DeviceTrait;
+
+        let frequency = frequency.clone();
+        let sample_rate = config.sample_rate as f32;
+        let channels = (config.channels as usize).max(1);
+        // Pitch and volume follow their targets over a few milliseconds, so that changes
+        // don't click
+        let smoothing = 1.0 - (-1.0 / (0.005 * sample_rate)).exp();
+        let (mut phase, mut pitch, mut volume) = (0.0f32, 0.0f32, 0.0f32);
+        device
+            .build_output_stream(
+                config,
+                move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+                    let target =
+                        f32::from_bits(frequency.load(std::sync::atomic::Ordering::Relaxed));
+                    let target_volume = if target > 0.0 { TONE_VOLUME } else { 0.0 };
+                    for frame in data.chunks_mut(channels) {
+                        if target > 0.0 {
+                            // Coming out of silence starts on the pitch rather than sliding up
+                            pitch = if volume < 1e-4 {
+                                target
+                            } else {
+                                pitch + (target - pitch) * smoothing
+                            };
+                        }
+                        volume += (target_volume - volume) * smoothing;
+                        phase = (phase + pitch / sample_rate).fract();
+                        let sample = volume * (phase * std::f32::consts::TAU).sin();
+                        frame.fill(T::from_sample(sample));
+                    }
+                },
+                |_| {},
+                None,
+            )
+            .map_err(|error| error.to_string())
+    }
+
+    fn set(&self, frequency: Option<f32>) {
+        self.frequency.store(
+            frequency.unwrap_or(0.0).to_bits(),
+            std::sync::atomic::Ordering::Relaxed,
+        );
+    }
+}
+
+// Builds without the sound feature have no audio backend
+#[cfg(not(feature = "sound"))]
+pub(crate) struct Tone;
+
+#[cfg(not(feature = "sound"))]
+impl Tone {
+    pub(crate) fn start() -> Result<Tone, String> {
+        Err("built without sound; rebuild with --features sound".to_string())
+    }
+
+    pub(crate) fn set(&self, _frequency: Option<f32>) {}
+}
+
+pub(crate) fn stats_lines(
+    frame_time: std::time::Duration,
+    iterations: u64,
+    pixels: u64,
+    series: Option<(usize, usize)>,
+) -> Vec<String> {
+    let seconds = frame_time.as_secs_f64().max(f64::EPSILON);
+
+    let mut lines = vec![
+        format!("{:<14}{:.1} ms", "Frame time", frame_time.as_secs_f64() * 1000.0),
+        format!("{:<14}{:.1}", "FPS", 1.0 / seconds),
+        format!("{:<14}{}", "Iterations/s", format_rate(iterations as f64 / seconds)),
+        format!("{:<14}{}", "Pixels/s", format_rate(pixels as f64 / seconds)),
+        format!("{:<14}{}", "Threads", rayon::current_num_threads()),
+        format!("{:<14}{} (100% used)", "SIMD lanes", simd_lanes()),
+    ];
+    if let Some((order, skipped)) = series {
+        let order = if order == 0 {
+            "Off".to_string()
+        } else {
+            order.to_string()
+        };
+        lines.push(format!("{:<14}{}", "Series order", order));
+        lines.push(format!("{:<14}{} per pixel", "Skipped", skipped));
+    }
+    lines
+}
+
+pub(crate) const MINIMAP_SIZE: (u16, u16) = (24, 8);
+pub(crate) const MINIMAP_ITERATIONS: u32 = 100;
+
+fn minimap_region(position: &Position, default_position: &Position) -> ((u16, u16), (u16, u16)) {
+    let (width, height) = MINIMAP_SIZE;
+    // A turned view is marked by the box around its corners, and a log-polar one by the box
+    // around its first row, a circle
+    let corners = [
+        (0.0, 0.0),
+        (0.25, 0.0),
+        (0.5, 0.0),
+        (0.75, 0.0),
+        (1.0, 0.0),
+        (0.0, 1.0),
+        (1.0, 1.0),
+    ]
+    .map(|(x, y)| {
+        let corner = position.point_at(x * position.width(), y * position.height());
+        plane_to_cell(&corner, width, height, default_position)
+    });
+    let top_left = corners
+        .iter()
+        .fold((f64::INFINITY, f64::INFINITY), |(x, y), corner| {
+            (x.min(corner.0), y.min(corner.1))
+        });
+    let bottom_right = corners
+        .iter()
+        .fold((f64::NEG_INFINITY, f64::NEG_INFINITY), |(x, y), corner| {
+            (x.max(corner.0), y.max(corner.1))
+        });
+
+    // Cells the region touches, clamped so it is always drawn inside the inset
+    let clamp = |value: f64, max: u16| value.floor().clamp(0.0, (max - 1) as f64) as u16;
+    (
+        (clamp(top_left.0, width), clamp(top_left.1, height)),
+        (
+            clamp(bottom_right.0 - f64::EPSILON, width),
+            clamp(bottom_right.1 - f64::EPSILON, height),
+        ),
+    )
+}
+
+fn render_inset(rows: &[String], left: u16, top: u16) -> String {
+    let (inset_width, inset_height) = MINIMAP_SIZE;
+    let mut output = render_selection(
+        (left - 1, top - 1),
+        (left + inset_width, top + inset_height),
+    );
+    for (index, row) in rows.iter().enumerate() {
+        output.push_str(&format!(
+            "{}{}{}",
+            crossterm::cursor::MoveTo(left, top + index as u16),
+            row,
+            crossterm::style::ResetColor
+        ));
+    }
+    output
+}
+
+pub(crate) fn render_minimap(
+    rows: &[String],
+    position: &Position,
+    default_position: &Position,
+    width: u16,
+    height: u16,
+) -> String {
+    let (minimap_width, minimap_height) = MINIMAP_SIZE;
+    if width < minimap_width + 2 || height < minimap_height + 2 {
+        return String::new();
+    }
+
+    // Sit in the bottom right corner, leaving room for the border
+    let left = width - minimap_width - 1;
+    let top = height - minimap_height - 1;
+
+    let mut output = render_inset(rows, left, top);
+
+    let (start, end) = minimap_region(position, default_position);
+    if start == end {
+        output.push_str(&render_crosshair(left + start.0, top + start.1));
+    } else {
+        output.push_str(&render_selection(
+            (left + start.0, top + start.1),
+            (left + end.0, top + end.1),
+        ));
+    }
+    output
+}
+
+// Ticks land on round numbers about this many columns and rows apart
+const AXIS_TICK_SPACING: (u16, u16) = (12, 4);
+
+// The round values between start and end about spacing cells apart, and the decimal places
+// they need
+fn axis_ticks(start: f64, end: f64, cells: u16, spacing: u16) -> (Vec<f64>, usize) {
+    let rough = (end - start) * spacing as f64 / cells.max(1) as f64;
+    if !(rough.is_finite() && rough > 0.0) {
+        return (Vec::new(), 0);
+    }
+    let magnitude = 10f64.powf(rough.log10().floor());
+    let step = [1.0, 2.0, 5.0]
+        .iter()
+        .map(|multiple| multiple * magnitude)
+        .find(|step| *step >= rough)
+        .unwrap_or(10.0 * magnitude);
+    let places = (-step.log10().floor()).max(0.0) as usize;
+
+    let mut ticks = Vec::new();
+    let mut index = (start / step).ceil();
+    // Past the precision of f64 neighbouring ticks come out the same, so stop there
+    while index * step <= end && index + 1.0 != index && ticks.len() <= cells as usize {
+        // Adding zero turns -0 into 0
+        ticks.push(index * step + 0.0);
+        index += 1.0;
+    }
+    (ticks, places)
+}
+
+// Blanks the bars of a letterboxed frame starting at `column`, leaving the part of the view
+// in the proportions of `shape`
+pub(crate) fn render_letterbox(
+    width: u16,
+    height: u16,
+    position: &Position,
+    shape: &Position,
+    column: u16,
+) -> String {
+    let framed = inscribed(position, shape);
+    let cells = |offset: &Fixed, start: &Fixed, extent: f64, cells: u16| {
+        let fraction = (offset - start).to_f64() / extent;
+        (fraction * cells as f64).round().clamp(0.0, cells as f64) as u16
+    };
+    let left = cells(&framed.left, &position.left, position.width(), width);
+    let right = cells(&framed.right, &position.left, position.width(), width);
+    let top = cells(&framed.top, &position.top, position.height(), height);
+    let bottom = cells(&framed.bottom, &position.top, position.height(), height);
+
+    let mut output = crossterm::style::ResetColor.to_string();
+    let mut blank = |start: u16, end: u16, row: u16| {
+        if start < end {
+            output.push_str(&format!(
+                "{}{}",
+                crossterm::cursor::MoveTo(column + start, row),
+                " ".repeat((end - start) as usize)
+            ));
+        }
+    };
+    for row in 0..height {
+        if row < top || row >= bottom {
+            blank(0, width, row);
+        } else {
+            blank(0, left, row);
+            blank(right, width, row);
+        }
+    }
+    output
+}
+
+pub(crate) fn render_axes(width: u16, height: u16, position: &Position, grid: bool) -> String {
+    // Ticks are laid out along the rows and columns, which the axes of a turned or projected
+    // view don't follow
+    if position.rotation != 0.0 || position.projection != Projection::Flat {
+        return String::new();
+    }
+    let left = position.left.to_f64();
+    let top = position.top.to_f64();
+    let cell = |value: f64, start: f64, extent: f64, cells: u16| {
+        let cell = ((value - start) / extent * cells as f64).floor();
+        (0.0..cells as f64).contains(&cell).then_some(cell as u16)
+    };
+    let column = |x: f64| cell(x, left, position.width(), width);
+    let row = |y: f64| cell(y, top, position.height(), height);
+
+    let (x_ticks, x_places) = axis_ticks(left, left + position.width(), width, AXIS_TICK_SPACING.0);
+    let (y_ticks, y_places) = axis_ticks(top, top + position.height(), height, AXIS_TICK_SPACING.1);
+    let columns: Vec<(u16, f64)> = x_ticks
+        .iter()
+        .filter_map(|x| Some((column(*x)?, *x)))
+        .collect();
+    let rows: Vec<(u16, f64)> = y_ticks
+        .iter()
+        .filter_map(|y| Some((row(*y)?, *y)))
+        .collect();
+    let zero_column = column(0.0);
+    let zero_row = row(0.0);
+
+    let mut output = String::new();
+    let put = |output: &mut String, column: u16, row: u16, text: &str| {
+        output.push_str(&format!(
+            "{}{}",
+            crossterm::cursor::MoveTo(column, row),
+            text
+        ));
+    };
+
+    if grid {
+        let color = crossterm::style::SetForegroundColor(crossterm::style::Color::DarkGrey);
+        output.push_str(&color.to_string());
+        for (column, _) in &columns {
+            for row in 0..height {
+                put(&mut output, *column, row, "┊");
+            }
+        }
+        for (row, _) in &rows {
+            for column in 0..width {
+                put(&mut output, column, *row, "┈");
+            }
+        }
+    }
+
+    let color = crossterm::style::SetForegroundColor(crossterm::style::Color::White);
+    output.push_str(&color.to_string());
+    if let Some(zero_column) = zero_column {
+        for row in 0..height {
+            put(&mut output, zero_column, row, "│");
+        }
+        for (row, _) in &rows {
+            put(&mut output, zero_column, *row, "┼");
+        }
+    }
+    if let Some(zero_row) = zero_row {
+        for column in 0..width {
+            put(&mut output, column, zero_row, "─");
+        }
+        for (column, _) in &columns {
+            put(&mut output, *column, zero_row, "┼");
+        }
+    }
+
+    // Labels sit next to the axes, or along the bottom and left edges when they are off screen
+    let background = crossterm::style::SetBackgroundColor(crossterm::style::Color::Black);
+    output.push_str(&background.to_string());
+    let label_row = zero_row.map_or(height.saturating_sub(1), |row| {
+        (row + 1).min(height.saturating_sub(1))
+    });
+    let mut free_column = 0;
+    for (column, x) in &columns {
+        let label = format!("{:.*}", x_places, x);
+        let end = column + label.len() as u16;
+        if *column >= free_column && end <= width {
+            put(&mut output, *column, label_row, &label);
+            free_column = end + 1;
+        }
+    }
+    let label_column = zero_column.map_or(0, |column| column + 1);
+    for (row, y) in &rows {
+        let label = format!("{:.*}", y_places, y);
+        if label_column + label.len() as u16 <= width {
+            put(&mut output, label_column, *row, &label);
+        }
+    }
+
+    output.push_str(&crossterm::style::ResetColor.to_string());
+    output
+}
+
+// Distinct colors for the first periods, which then repeat
+const PERIOD_COLORS: [[u8; 3]; 8] = [
+    [40, 40, 120],
+    [150, 30, 30],
+    [30, 130, 50],
+    [170, 140, 20],
+    [120, 40, 140],
+    [20, 130, 140],
+    [190, 90, 20],
+    [110, 110, 110],
+];
+
+// Paints the cells of a frame that are inside the set with the period of their halves'
+// orbits, over the flat color the frame gives them
+pub(crate) fn render_period_tint(buffer: &FrameBuffer) -> String {
+    use rayon::prelude::*;
+
+    let parameters = &buffer.parameters;
+    if parameters.fractal_index != 0 && parameters.fractal_index != JULIA_INDEX {
+        return String::new();
+    }
+    let position = &buffer.position;
+    let color = |column: usize, half_row: usize| {
+        let (x, y) = position.point_at(
+            (column as f64 + 0.5) / buffer.width as f64 * position.width(),
+            (half_row as f64 + 0.5) / (buffer.height as f64 * 2.0) * position.height(),
+        );
+        let (x, y) = (x.to_f64(), y.to_f64());
+        // Orbits that don't settle in time are left black
+        let [r, g, b] = orbit_period(x, y, buffer.max_iterations, parameters)
+            .map_or([0, 0, 0], |period| {
+                PERIOD_COLORS[(period as usize - 1) % PERIOD_COLORS.len()]
+            });
+        crossterm::style::Color::Rgb { r, g, b }
+    };
+
+    let width = buffer.width.max(1) as usize;
+    let cells: Vec<String> = buffer
+        .cells()
+        .par_iter()
+        .enumerate()
+        .map(|(index, cell)| {
+            if cell
+                .iter()
+                .flatten()
+                .any(|iteration| *iteration != buffer.max_iterations)
+            {
+                return String::new();
+            }
+            let (column, row) = (index % width, index / width);
+            format!(
+                "{}{}{}▀",
+                crossterm::cursor::MoveTo(column as u16, row as u16),
+                crossterm::style::SetForegroundColor(color(column, row * 2)),
+                crossterm::style::SetBackgroundColor(color(column, row * 2 + 1)),
+            )
+        })
+        .collect();
+    let mut output = cells.concat();
+    output.push_str(&crossterm::style::ResetColor.to_string());
+    output
+}
+
+// Draws paths through points of the plane, skipping the parts that are off screen
+pub(crate) fn render_paths(
+    paths: &[&[(f64, f64)]],
+    width: u16,
+    height: u16,
+    position: &Position,
+) -> String {
+    let cell = |point: (f64, f64)| plane_to_cell(&fixed_point(point), width, height, position);
+    let (columns, rows) = (width as f64, height as f64);
+
+    let mut output = format!(
+        "{}",
+        crossterm::style::SetForegroundColor(crossterm::style::Color::Yellow)
+    );
+    let mut last = None;
+    for path in paths {
+        for pair in path.windows(2) {
+            let (start, end) = (cell(pair[0]), cell(pair[1]));
+            let off_screen = (start.0 < 0.0 && end.0 < 0.0)
+                || (start.0 >= columns && end.0 >= columns)
+                || (start.1 < 0.0 && end.1 < 0.0)
+                || (start.1 >= rows && end.1 >= rows);
+            // Log-polar columns wrap round, so a step across the seam isn't drawn over the frame
+            let wraps = position.projection == Projection::LogPolar
+                && (end.0 - start.0).abs() > columns / 2.0;
+            if off_screen || wraps {
+                continue;
+            }
+            // A sample every cell along the way, within reason for segments far longer than
+            // the screen
+            let length = (end.0 - start.0).abs().max((end.1 - start.1).abs());
+            let samples = length.ceil().clamp(1.0, (columns + rows) * 2.0) as usize;
+            for sample in 0..=samples {
+                let t = sample as f64 / samples as f64;
+                let column = (start.0 + (end.0 - start.0) * t).floor();
+                let row = (start.1 + (end.1 - start.1) * t).floor();
+                if !(0.0..columns).contains(&column) || !(0.0..rows).contains(&row) {
+                    continue;
+                }
+                let point = (column as u16, row as u16);
+                if last != Some(point) {
+                    output.push_str(&format!("{}•", crossterm::cursor::MoveTo(point.0, point.1)));
+                    last = Some(point);
+                }
+            }
+        }
+    }
+    output.push_str(&format!("{}", crossterm::style::ResetColor));
+    output
+}
+
+pub(crate) fn render_julia_preview(
+    julia_constant: (f64, f64),
+    parameters: &FractalParameters,
+    max_iterations: u32x1,
+    width: u16,
+    height: u16,
+) -> String {
+    let (preview_width, preview_height) = MINIMAP_SIZE;
+    if width < preview_width + 2 || height < preview_height + 2 {
+        return String::new();
+    }
+
+    let parameters = FractalParameters {
+        fractal_index: JULIA_INDEX,
+        julia_constant,
+        ..*parameters
+    };
+    let (rows, _) = render_rows(
+        preview_width,
+        preview_height,
+        &Position::new(-1.5, 1.5, -2.0, 2.0),
+        max_iterations,
+        &parameters,
+    );
+
+    // Sit in the bottom left corner, opposite the minimap
+    render_inset(&rows, 1, height - preview_height - 1)
+}
+
+pub(crate) fn help_lines(
+    keymap: &Keymap,
+    max_iterations: u32x1,
+    parameters: &FractalParameters,
+    zoom_step: f64,
+    pan_step: f64,
+) -> Vec<String> {
+    let mut entries: Vec<(String, &str)> = KEYMAP_HELP
+        .iter()
+        .map(|(first, second, description)| {
+            let keys = format!("{} / {}", keymap.keys(*first), keymap.keys(*second));
+            (keys, *description)
+        })
+        .collect();
+    entries.extend(
+        KEYBINDINGS
+            .iter()
+            .map(|(keys, description)| (keys.to_string(), *description)),
+    );
+    let key_width = entries
+        .iter()
+        .map(|(keys, _)| keys.chars().count() + 1)
+        .max()
+        .unwrap_or(0)
+        .max(12);
+
+    let mut lines = vec!["Keybindings".to_string(), String::new()];
+    for (keys, description) in entries {
+        lines.push(format!(
+            "{:<key_width$}{}",
+            keys,
+            description,
+            key_width = key_width
+        ));
+    }
+
+    lines.push(String::new());
+    lines.push("Settings".to_string());
+    lines.push(String::new());
+    lines.push(format!("{:<12}{}", "Fractal", fractal_label(parameters)));
+    lines.push(format!(
+        "{:<12}{}",
+        "Palette", palette_names()[parameters.palette_index]
+    ));
+    lines.push(format!("{:<12}{}", "Iterations", max_iterations[0]));
+    lines.push(format!("{:<12}{:.1}%", "Zoom step", zoom_step * 100.0));
+    lines.push(format!("{:<12}{:.1}%", "Pan step", pan_step * 100.0));
+
+    lines.push(String::new());
+    lines.push("Press any key to close".to_string());
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{letterbox, render_buffer, TileCache, DEFAULT_PARAMETERS, DEFAULT_TILE_SIZE};
+
+    #[test]
+    fn test_minimap_region() {
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        assert_eq!(
+            minimap_region(&default_position, &default_position),
+            ((0, 0), (MINIMAP_SIZE.0 - 1, MINIMAP_SIZE.1 - 1))
+        );
+
+        let zoomed = default_position.zoomed_at(&fixed_point((-0.4375, 0.125)), 0.001);
+        let (start, end) = minimap_region(&zoomed, &default_position);
+        assert_eq!(start, end);
+        assert_eq!(start, (12, 4));
+
+        // Zooming out past the full view is clamped to the inset
+        let zoomed_out = default_position.zoomed_at(&fixed_point((-0.5, 0.0)), 4.0);
+        assert_eq!(
+            minimap_region(&zoomed_out, &default_position),
+            ((0, 0), (MINIMAP_SIZE.0 - 1, MINIMAP_SIZE.1 - 1))
+        );
+    }
+
+    #[test]
+    fn test_render_letterbox() {
+        let shape = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let position = letterbox(&shape, &shape, 16, 4);
+        let bars = render_letterbox(16, 4, &position, &shape, 0);
+        let reset = crossterm::style::ResetColor.to_string();
+        let expected: String = (0..4)
+            .map(|row| format!("\x1b[{};1H  \x1b[{};15H  ", row + 1, row + 1))
+            .collect();
+        assert_eq!(bars, reset.clone() + expected.as_str());
+        // A frame in proportion has no bars
+        assert_eq!(render_letterbox(12, 4, &shape, &shape, 0), reset);
+    }
+
+    #[test]
+    fn test_render_axes() {
+        assert_eq!(axis_ticks(-2.0, 1.0, 30, 12), (vec![-2.0, 0.0], 0));
+        assert_eq!(axis_ticks(-1.0, 1.0, 10, 4), (vec![-1.0, 0.0, 1.0], 0));
+        assert_eq!(axis_ticks(0.1, 0.2, 40, 12).1, 2);
+        assert_eq!(axis_ticks(0.0, 0.0, 40, 12), (Vec::new(), 0));
+
+        let position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let axes = render_axes(30, 10, &position, false);
+        // The axes cross at the origin, two thirds of the way across and half way down
+        assert!(axes.contains("\x1b[6;21H┼"));
+        // The real values are labelled under the real axis
+        assert!(axes.contains("\x1b[7;1H-2"));
+        assert!(axes.contains("\x1b[7;21H0"));
+        assert!(!axes.contains('┊'));
+        assert!(render_axes(30, 10, &position, true).contains('┊'));
+    }
+
+    #[test]
+    fn test_render_overlay() {
+        let lines = ["Hello".to_string(), "Hi".to_string()];
+        let overlay = render_overlay(&lines, Anchor::Center, 20, 10);
+        assert!(overlay.contains(&format!("{}┌───────┐", crossterm::cursor::MoveTo(5, 3))));
+        assert!(overlay.contains(&format!("{}│ Hello │", crossterm::cursor::MoveTo(5, 4))));
+        assert!(overlay.contains(&format!("{}│ Hi    │", crossterm::cursor::MoveTo(5, 5))));
+        assert!(overlay.contains(&format!("{}└───────┘", crossterm::cursor::MoveTo(5, 6))));
+
+        let clipped = render_overlay(&["Hello".to_string()], Anchor::Center, 7, 3);
+        assert!(clipped.contains(&format!("{}│ Hel │", crossterm::cursor::MoveTo(0, 1))));
+
+        let corner = render_overlay(&lines, Anchor::TopLeft, 20, 10);
+        assert!(corner.contains(&format!("{}┌───────┐", crossterm::cursor::MoveTo(0, 0))));
+    }
+
+    #[test]
+    fn test_describe_view() {
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let describe = |position: &Position| {
+            let (buffer, _) = render_buffer(
+                40,
+                20,
+                position,
+                u32x1::splat(100),
+                &DEFAULT_PARAMETERS,
+                DEFAULT_TILE_SIZE,
+                None,
+                &mut TileCache::new(0),
+            );
+            describe_view(&buffer, &default_position)
+        };
+
+        let lines = describe(&default_position);
+        assert_eq!(lines[0], "Mandelbrot Set in the Rainbow palette");
+        assert_eq!(lines[1], "Center -0.5, 0 at zoom 1.00x, up to 100 iterations");
+        assert_eq!(lines[3], "The edge of the set runs through the view");
+        assert_eq!(lines[4], "The middle is inside the set");
+
+        let bulb = Position::new(-0.05, 0.05, -1.05, -0.95);
+        assert_eq!(
+            &describe(&bulb)[2..],
+            [
+                "All of the view is inside the set",
+                "Deep inside a bulb, with no edge in view",
+                "The middle is inside the set, with period 2",
+            ]
+        );
+
+        let outside = Position::new(1.0, 1.1, 1.0, 1.1);
+        let lines = describe(&outside);
+        assert_eq!(lines[2], "None of the view is inside the set");
+        assert!(lines[3].starts_with("Open space"));
+        assert!(lines[4].starts_with("The middle escapes after 2 iterations,"));
+
+        // The set in one corner of the view is placed there
+        let corner = Position::new(0.4, 1.0, 0.0, 1.0);
+        assert!(describe(&corner)[2].ends_with("inside the set at the top left"));
+    }
+
+    #[test]
+    fn test_tone_frequency() {
+        let limit = u32x1::splat(1000);
+        assert_eq!(tone_frequency(u32x1::splat(0), limit), Some(TONE_RANGE.0));
+        assert_eq!(tone_frequency(limit, limit), None);
+        // Points that take longer to escape play higher
+        let low = tone_frequency(u32x1::splat(10), limit).unwrap();
+        let high = tone_frequency(u32x1::splat(999), limit).unwrap();
+        assert!(TONE_RANGE.0 < low && low < high && high < TONE_RANGE.1);
+    }
+
+    #[test]
+    fn test_histogram_lines() {
+        let cell = |iteration| [[u32x1::splat(iteration); 2]; 2];
+        let cells = [cell(0), cell(5), cell(95), cell(100)];
+        let lines = histogram_lines(&cells, u32x1::splat(100), 10);
+        assert_eq!(lines.len(), 13);
+        assert_eq!(lines[0], "Iterations up to 100");
+        assert_eq!(lines[1], format!("      0 {}", "█".repeat(16)));
+        assert_eq!(lines[2], "     10 ");
+        assert_eq!(lines[10], format!("     90 {}", "█".repeat(12)));
+        assert_eq!(lines[11], "Inside          25.0%");
+        assert_eq!(lines[12], "Near the limit  25.0%");
+    }
+
+    #[test]
+    fn test_format_rate() {
+        assert_eq!(format_rate(12.3), "12");
+        assert_eq!(format_rate(4560.0), "4.56k");
+        assert_eq!(format_rate(7.5e6), "7.50M");
+        assert_eq!(format_rate(1.25e9), "1.25G");
+    }
+
+    #[test]
+    fn test_stats_lines() {
+        let frame_time = std::time::Duration::from_millis(10);
+        let lines = stats_lines(frame_time, 1000, 100, None);
+        assert_eq!(lines[0], "Frame time    10.0 ms");
+        assert_eq!(lines.len(), 6);
+
+        // Perturbed frames say how far the series took them
+        let lines = stats_lines(frame_time, 1000, 100, Some((3, 120)));
+        assert_eq!(lines[6], "Series order  3");
+        assert_eq!(lines[7], "Skipped       120 per pixel");
+        assert_eq!(
+            stats_lines(frame_time, 1000, 100, Some((0, 0)))[6],
+            "Series order  Off"
+        );
+    }
+
+    #[test]
+    fn test_render_period_tint() {
+        let render = |position: &Position, parameters: &FractalParameters| {
+            let (buffer, _) = render_buffer(
+                4,
+                2,
+                position,
+                u32x1::splat(1000),
+                parameters,
+                DEFAULT_TILE_SIZE,
+                None,
+                &mut TileCache::new(0),
+            );
+            render_period_tint(&buffer)
+        };
+        // Every cell of a view inside the period 2 bulb takes its color
+        let bulb = Position::new(-0.05, 0.05, -1.05, -0.95);
+        let tint = render(&bulb, &DEFAULT_PARAMETERS);
+        assert_eq!(tint.matches("\x1b[38;2;150;30;30m").count(), 8);
+        // Outside the set nothing is painted over
+        let outside = Position::new(1.0, 1.1, 1.0, 1.1);
+        assert_eq!(render(&outside, &DEFAULT_PARAMETERS), "\x1b[0m");
+        let sinking_ship = FractalParameters {
+            fractal_index: 1,
+            ..DEFAULT_PARAMETERS
+        };
+        assert_eq!(render(&bulb, &sinking_ship), "");
+    }
+
+    #[test]
+    fn test_render_paths() {
+        let position = Position::new(0.0, 1.0, 0.0, 4.0);
+        let across: &[(f64, f64)] = &[(0.5, 0.5), (3.5, 0.5)];
+        let away: &[(f64, f64)] = &[(10.0, 10.0), (20.0, 20.0)];
+        let output = render_paths(&[across, away], 4, 1, &position);
+        assert_eq!(output.matches('•').count(), 4);
+        assert!(output.contains("\x1b[1;4H•"));
+    }
+}
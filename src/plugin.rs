@@ -0,0 +1,170 @@
+//! Fractal kernels loaded from shared libraries, so new fractals can be distributed without
+//! rebuilding the viewer.
+//!
+//! A plugin exports one function with the C ABI, which stores the iteration at which the
+//! orbit of `(x, y)` escapes, or `max_iterations` if it doesn't, in `out`:
+//!
+//! ```c
+//! void mandelbrot_term_kernel(double x, double y, uint32_t max_iterations, uint32_t *out);
+//! ```
+//!
+//! It can also export `const char *mandelbrot_term_name(void)` to give the fractal a display
+//! name, which otherwise comes from the file name. Plugins are only supported on Unix.
+//!
+//! Frames are shared out between rayon's worker threads, and several frames can be rendered at
+//! once, so the kernel is called from many threads at the same time. It must be thread-safe
+//! and reentrant: keep all of a call's state in locals, and anything shared between calls
+//! read-only, or behind a lock or atomic. A kernel that keeps its orbit in a global or
+//! `static` variable gives wrong counts at random. The name function is only called once,
+//! while the plugin is loaded.
+
+use crate::{FractalParameters, Kernel};
+
+/// The signature of `mandelbrot_term_kernel`, which must be safe to call from several threads
+/// at once.
+pub type KernelFunction = unsafe extern "C" fn(f64, f64, u32, *mut u32);
+
+/// A kernel from a loaded plugin.
+pub struct Plugin {
+    /// The fractal's display name.
+    pub name: String,
+    kernel: KernelFunction,
+}
+
+impl Plugin {
+    /// Loads the plugin at `path`. The library stays loaded for the rest of the run, since
+    /// its kernel may be called at any time.
+    ///
+    /// # Safety
+    ///
+    /// Loading a library runs its initializers, and its symbols are called as if they have the
+    /// signatures documented above, so `path` must be a plugin that can be trusted to follow
+    /// them, including a kernel that is safe to call from several threads.
+    #[cfg(unix)]
+    pub unsafe fn load(path: &std::path::Path) -> Result<Plugin, String> {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path_text = std::ffi::CString::new(path.as_os_str().as_bytes())
+            .map_err(|_| "path contains a NUL byte".to_string())?;
+        // Safety: dlopen gets a NUL terminated path, and dlerror's message is copied before
+        // anything else can replace it
+        let handle = unsafe { libc::dlopen(path_text.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+        if handle.is_null() {
+            return Err(unsafe { dl_error() });
+        }
+
+        // Safety: the symbol names are NUL terminated, and the symbols are trusted to have
+        // the documented signatures
+        unsafe {
+            let kernel = libc::dlsym(handle, c"mandelbrot_term_kernel".as_ptr());
+            if kernel.is_null() {
+                libc::dlclose(handle);
+                return Err("no mandelbrot_term_kernel function".to_string());
+            }
+            let kernel = std::mem::transmute::<*mut libc::c_void, KernelFunction>(kernel);
+
+            let name_function = libc::dlsym(handle, c"mandelbrot_term_name".as_ptr());
+            let name = if name_function.is_null() {
+                None
+            } else {
+                let name_function = std::mem::transmute::<
+                    *mut libc::c_void,
+                    unsafe extern "C" fn() -> *const libc::c_char,
+                >(name_function);
+                let name = name_function();
+                (!name.is_null()).then(|| {
+                    std::ffi::CStr::from_ptr(name)
+                        .to_string_lossy()
+                        .into_owned()
+                })
+            };
+            let name = name.unwrap_or_else(|| {
+                path.file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned()
+            });
+            Ok(Plugin { name, kernel })
+        }
+    }
+
+    /// Loads the plugin at `path`. The library stays loaded for the rest of the run, since
+    /// its kernel may be called at any time.
+    ///
+    /// # Safety
+    ///
+    /// `path` must be a plugin that can be trusted, as on Unix.
+    #[cfg(not(unix))]
+    pub unsafe fn load(_path: &std::path::Path) -> Result<Plugin, String> {
+        Err("plugins are only supported on Unix".to_string())
+    }
+}
+
+// Safety: must be called straight after a failed dl* call on the same thread
+#[cfg(unix)]
+unsafe fn dl_error() -> String {
+    let error = libc::dlerror();
+    if error.is_null() {
+        "unknown error".to_string()
+    } else {
+        std::ffi::CStr::from_ptr(error)
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+impl Kernel for Plugin {
+    fn iterate(&self, x: f64, y: f64, max_iterations: u32, _: &FractalParameters) -> u32 {
+        // A kernel that doesn't store anything leaves the point inside the set
+        let mut iteration = max_iterations;
+        // Safety: the kernel gets a valid pointer to write its result to, and is trusted to be
+        // thread-safe as the module documentation requires, since this runs on every worker
+        unsafe { (self.kernel)(x, y, max_iterations, &mut iteration) };
+        iteration.min(max_iterations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_load() {
+        // Safety: the first path doesn't exist and the system library is already loaded, and
+        // neither has a kernel to call
+        let missing = unsafe { Plugin::load(std::path::Path::new("/nonexistent/plugin.so")) };
+        assert!(missing.err().unwrap().contains("/nonexistent/plugin.so"));
+
+        // Any library loads, but only plugins have the kernel
+        let library = if cfg!(target_os = "macos") {
+            "/usr/lib/libSystem.B.dylib"
+        } else {
+            "libc.so.6"
+        };
+        assert_eq!(
+            unsafe { Plugin::load(std::path::Path::new(library)) }.err(),
+            Some("no mandelbrot_term_kernel function".to_string())
+        );
+    }
+
+    #[test]
+    fn test_iterate() {
+        unsafe extern "C" fn kernel(x: f64, _: f64, max_iterations: u32, out: *mut u32) {
+            *out = if x < 0.0 { 7 } else { max_iterations + 10 };
+        }
+        let plugin = Plugin {
+            name: "Test".to_string(),
+            kernel,
+        };
+        assert_eq!(
+            plugin.iterate(-1.0, 0.0, 100, &crate::DEFAULT_PARAMETERS),
+            7
+        );
+        // Counts past the limit are clamped to it
+        assert_eq!(
+            plugin.iterate(1.0, 0.0, 100, &crate::DEFAULT_PARAMETERS),
+            100
+        );
+    }
+}
@@ -0,0 +1,599 @@
+//! A small expression language over complex numbers, for fractals and palettes defined in
+//! script files rather than compiled in.
+//!
+//! Expressions combine numbers, the imaginary unit `i`, named variables, `+ - * / ^` and
+//! the functions `re`, `im`, `abs`, `arg`, `conj`, `sqrt`, `exp`, `ln`, `sin` and `cos`.
+//! Integer powers such as `z^3` are multiplied out, so they cost no more than writing the
+//! products by hand.
+//!
+//! ```
+//! use mandelbrot_set::script::{Expression, Formula};
+//!
+//! let expression = Expression::parse("z^2 + c", &["z", "c"]).unwrap();
+//! assert_eq!(expression.evaluate(&[(1.0, 1.0), (0.5, 0.0)]), (0.5, 2.0));
+//!
+//! // The Burning Ship, registered like a built-in fractal
+//! let formula = Formula::new("0", "(abs(re(z)) + i*abs(im(z)))^2 + c", false).unwrap();
+//! let index = mandelbrot_set::register_fractal("Burning Ship", std::sync::Arc::new(formula));
+//! assert_eq!(mandelbrot_set::fractal_names()[index], "Burning Ship");
+//! ```
+
+use crate::{FractalParameters, Kernel};
+
+/// A complex number as its real and imaginary parts.
+pub type Complex = (f64, f64);
+
+// Integer powers are multiplied out up to this size, and anything larger goes through exp
+const MAX_INTEGER_POWER: f64 = 64.0;
+
+// Brackets, minus signs and powers can only nest this deep, which keeps a long run of them
+// from overflowing the stack while parsing
+const MAX_NESTING: usize = 256;
+// A long sum such as z + z + ... nests without brackets, one level per term, so expressions
+// are kept short enough to evaluate as well
+const MAX_TOKENS: usize = 4096;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Function {
+    Re,
+    Im,
+    Abs,
+    Arg,
+    Conj,
+    Sqrt,
+    Exp,
+    Ln,
+    Sin,
+    Cos,
+}
+
+const FUNCTIONS: [(&str, Function); 10] = [
+    ("re", Function::Re),
+    ("im", Function::Im),
+    ("abs", Function::Abs),
+    ("arg", Function::Arg),
+    ("conj", Function::Conj),
+    ("sqrt", Function::Sqrt),
+    ("exp", Function::Exp),
+    ("ln", Function::Ln),
+    ("sin", Function::Sin),
+    ("cos", Function::Cos),
+];
+
+#[derive(Clone, PartialEq, Debug)]
+enum Node {
+    Constant(Complex),
+    Variable(usize),
+    Negate(Box<Node>),
+    Add(Box<Node>, Box<Node>),
+    Subtract(Box<Node>, Box<Node>),
+    Multiply(Box<Node>, Box<Node>),
+    Divide(Box<Node>, Box<Node>),
+    IntegerPower(Box<Node>, i32),
+    Power(Box<Node>, Box<Node>),
+    Call(Function, Box<Node>),
+}
+
+fn multiply(a: Complex, b: Complex) -> Complex {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+fn divide(a: Complex, b: Complex) -> Complex {
+    let denominator = b.0 * b.0 + b.1 * b.1;
+    (
+        (a.0 * b.0 + a.1 * b.1) / denominator,
+        (a.1 * b.0 - a.0 * b.1) / denominator,
+    )
+}
+
+fn exp(a: Complex) -> Complex {
+    let magnitude = a.0.exp();
+    (magnitude * a.1.cos(), magnitude * a.1.sin())
+}
+
+fn ln(a: Complex) -> Complex {
+    (a.0.hypot(a.1).ln(), a.1.atan2(a.0))
+}
+
+fn integer_power(base: Complex, exponent: i32) -> Complex {
+    // Square and multiply, so z^2 is exactly z*z
+    let mut result = (1.0, 0.0);
+    let mut square = base;
+    let mut remaining = exponent.unsigned_abs();
+    let mut first = true;
+    while remaining > 0 {
+        if remaining & 1 == 1 {
+            result = if first {
+                square
+            } else {
+                multiply(result, square)
+            };
+            first = false;
+        }
+        remaining >>= 1;
+        if remaining > 0 {
+            square = multiply(square, square);
+        }
+    }
+    if exponent < 0 {
+        divide((1.0, 0.0), result)
+    } else {
+        result
+    }
+}
+
+fn call(function: Function, a: Complex) -> Complex {
+    match function {
+        Function::Re => (a.0, 0.0),
+        Function::Im => (a.1, 0.0),
+        Function::Abs => (a.0.hypot(a.1), 0.0),
+        Function::Arg => (a.1.atan2(a.0), 0.0),
+        Function::Conj => (a.0, -a.1),
+        Function::Sqrt => {
+            let magnitude = a.0.hypot(a.1);
+            let re = ((magnitude + a.0) / 2.0).sqrt();
+            let im = ((magnitude - a.0) / 2.0).sqrt();
+            (re, if a.1 < 0.0 { -im } else { im })
+        }
+        Function::Exp => exp(a),
+        Function::Ln => ln(a),
+        Function::Sin => (a.0.sin() * a.1.cosh(), a.0.cos() * a.1.sinh()),
+        Function::Cos => (a.0.cos() * a.1.cosh(), -(a.0.sin() * a.1.sinh())),
+    }
+}
+
+impl Node {
+    fn evaluate(&self, variables: &[Complex]) -> Complex {
+        match self {
+            Node::Constant(value) => *value,
+            Node::Variable(index) => variables[*index],
+            Node::Negate(a) => {
+                let a = a.evaluate(variables);
+                (-a.0, -a.1)
+            }
+            Node::Add(a, b) => {
+                let (a, b) = (a.evaluate(variables), b.evaluate(variables));
+                (a.0 + b.0, a.1 + b.1)
+            }
+            Node::Subtract(a, b) => {
+                let (a, b) = (a.evaluate(variables), b.evaluate(variables));
+                (a.0 - b.0, a.1 - b.1)
+            }
+            Node::Multiply(a, b) => multiply(a.evaluate(variables), b.evaluate(variables)),
+            Node::Divide(a, b) => divide(a.evaluate(variables), b.evaluate(variables)),
+            Node::IntegerPower(a, exponent) => integer_power(a.evaluate(variables), *exponent),
+            Node::Power(a, b) => {
+                let base = a.evaluate(variables);
+                if base == (0.0, 0.0) {
+                    return base;
+                }
+                exp(multiply(b.evaluate(variables), ln(base)))
+            }
+            Node::Call(function, a) => call(*function, a.evaluate(variables)),
+        }
+    }
+
+    // Work out parts that don't depend on any variable once, when the expression is parsed
+    fn folded(self) -> Node {
+        let constant = match &self {
+            Node::Constant(_) | Node::Variable(_) => return self,
+            Node::Negate(a) | Node::IntegerPower(a, _) | Node::Call(_, a) => a.is_constant(),
+            Node::Add(a, b)
+            | Node::Subtract(a, b)
+            | Node::Multiply(a, b)
+            | Node::Divide(a, b)
+            | Node::Power(a, b) => a.is_constant() && b.is_constant(),
+        };
+        if constant {
+            Node::Constant(self.evaluate(&[]))
+        } else {
+            self
+        }
+    }
+
+    fn is_constant(&self) -> bool {
+        matches!(self, Node::Constant(_))
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Symbol(char),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut characters = text.char_indices().peekable();
+    while let Some((start, character)) = characters.next() {
+        if character.is_whitespace() {
+            continue;
+        }
+        if character.is_ascii_digit() || character == '.' {
+            let mut end = start + character.len_utf8();
+            while let Some(&(index, next)) = characters.peek() {
+                // Exponents like 1e-3 carry their own sign
+                let exponent_sign =
+                    matches!(next, '+' | '-') && text[..index].ends_with(['e', 'E']);
+                if next.is_ascii_digit() || matches!(next, '.' | 'e' | 'E') || exponent_sign {
+                    end = index + next.len_utf8();
+                    characters.next();
+                } else {
+                    break;
+                }
+            }
+            let number = &text[start..end];
+            tokens.push(Token::Number(
+                number
+                    .parse()
+                    .map_err(|_| format!("invalid number: {}", number))?,
+            ));
+        } else if character.is_alphabetic() || character == '_' {
+            let mut end = start + character.len_utf8();
+            while let Some(&(index, next)) = characters.peek() {
+                if next.is_alphanumeric() || next == '_' {
+                    end = index + next.len_utf8();
+                    characters.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token::Name(text[start..end].to_string()));
+        } else if "+-*/^()".contains(character) {
+            tokens.push(Token::Symbol(character));
+        } else {
+            return Err(format!("unexpected character: {}", character));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    next: usize,
+    variables: &'a [&'a str],
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.next += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    // sum = product (('+' | '-') product)*
+    fn sum(&mut self) -> Result<Node, String> {
+        let mut node = self.product()?;
+        loop {
+            node = if self.eat('+') {
+                Node::Add(Box::new(node), Box::new(self.product()?)).folded()
+            } else if self.eat('-') {
+                Node::Subtract(Box::new(node), Box::new(self.product()?)).folded()
+            } else {
+                return Ok(node);
+            };
+        }
+    }
+
+    // product = unary (('*' | '/') unary)*
+    fn product(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        loop {
+            node = if self.eat('*') {
+                Node::Multiply(Box::new(node), Box::new(self.unary()?)).folded()
+            } else if self.eat('/') {
+                Node::Divide(Box::new(node), Box::new(self.unary()?)).folded()
+            } else {
+                return Ok(node);
+            };
+        }
+    }
+
+    // unary = '-' unary | power, so -z^2 is -(z^2). Every nested part of an expression is
+    // parsed through here, so this is where the nesting is counted.
+    fn unary(&mut self) -> Result<Node, String> {
+        if self.depth == MAX_NESTING {
+            return Err("expression nested too deeply".to_string());
+        }
+        self.depth += 1;
+        let node = if self.eat('-') {
+            self.unary().map(|node| Node::Negate(Box::new(node)).folded())
+        } else {
+            self.power()
+        };
+        self.depth -= 1;
+        node
+    }
+
+    // power = atom ('^' unary)?, which makes ^ right associative
+    fn power(&mut self) -> Result<Node, String> {
+        let base = self.atom()?;
+        if !self.eat('^') {
+            return Ok(base);
+        }
+        let exponent = self.unary()?;
+        Ok(match exponent {
+            Node::Constant((re, im))
+                if im == 0.0 && re.fract() == 0.0 && re.abs() <= MAX_INTEGER_POWER =>
+            {
+                Node::IntegerPower(Box::new(base), re as i32)
+            }
+            exponent => Node::Power(Box::new(base), Box::new(exponent)),
+        }
+        .folded())
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        let token = self
+            .tokens
+            .get(self.next)
+            .cloned()
+            .ok_or("unexpected end of expression")?;
+        self.next += 1;
+        match token {
+            Token::Number(value) => Ok(Node::Constant((value, 0.0))),
+            Token::Symbol('(') => {
+                let node = self.sum()?;
+                if !self.eat(')') {
+                    return Err("missing )".to_string());
+                }
+                Ok(node)
+            }
+            Token::Symbol(symbol) => Err(format!("unexpected {}", symbol)),
+            Token::Name(name) => {
+                if let Some(index) = self.variables.iter().position(|variable| *variable == name) {
+                    return Ok(Node::Variable(index));
+                }
+                if name == "i" {
+                    return Ok(Node::Constant((0.0, 1.0)));
+                }
+                if name == "pi" {
+                    return Ok(Node::Constant((std::f64::consts::PI, 0.0)));
+                }
+                let (_, function) = FUNCTIONS
+                    .iter()
+                    .find(|(function, _)| *function == name)
+                    .ok_or(format!("unknown name: {}", name))?;
+                if !self.eat('(') {
+                    return Err(format!("{} needs an argument in parentheses", name));
+                }
+                let argument = self.sum()?;
+                if !self.eat(')') {
+                    return Err("missing )".to_string());
+                }
+                Ok(Node::Call(*function, Box::new(argument)).folded())
+            }
+        }
+    }
+}
+
+/// A parsed expression, ready to be evaluated many times.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Expression {
+    root: Node,
+}
+
+impl Expression {
+    /// Parses `text`, where the names in `variables` stand for the values later passed to
+    /// [`Expression::evaluate`] in the same order.
+    pub fn parse(text: &str, variables: &[&str]) -> Result<Expression, String> {
+        let tokens = tokenize(text)?;
+        if tokens.len() > MAX_TOKENS {
+            return Err("expression too long".to_string());
+        }
+        let mut parser = Parser {
+            tokens,
+            next: 0,
+            variables,
+            depth: 0,
+        };
+        let root = parser.sum()?;
+        match parser.peek() {
+            None => Ok(Expression { root }),
+            Some(Token::Symbol(symbol)) => Err(format!("unexpected {}", symbol)),
+            Some(Token::Number(number)) => Err(format!("unexpected {}", number)),
+            Some(Token::Name(name)) => Err(format!("unexpected {}", name)),
+        }
+    }
+
+    /// The value of the expression for the given variables.
+    pub fn evaluate(&self, variables: &[Complex]) -> Complex {
+        self.root.evaluate(variables)
+    }
+}
+
+/// An escape-time fractal from a pair of expressions: `start` gives the first `z`, and
+/// `step` the next `z` from the current one.
+///
+/// Both can use `z`, `c` and `p`. `p` is the pixel. `c` is also the pixel, or the Julia
+/// constant for Julia-style formulas, which start from the pixel by default.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Formula {
+    start: Expression,
+    step: Expression,
+    julia: bool,
+}
+
+const FORMULA_VARIABLES: [&str; 3] = ["z", "c", "p"];
+
+impl Formula {
+    /// Parses a formula, with `julia` choosing whether `c` is the Julia constant.
+    pub fn new(start: &str, step: &str, julia: bool) -> Result<Formula, String> {
+        Ok(Formula {
+            start: Expression::parse(start, &FORMULA_VARIABLES)
+                .map_err(|error| format!("start: {}", error))?,
+            step: Expression::parse(step, &FORMULA_VARIABLES)
+                .map_err(|error| format!("step: {}", error))?,
+            julia,
+        })
+    }
+}
+
+impl Kernel for Formula {
+    fn iterate(&self, x: f64, y: f64, max_iterations: u32, parameters: &FractalParameters) -> u32 {
+        let pixel = (x, y);
+        let c = if self.julia {
+            parameters.julia_constant
+        } else {
+            pixel
+        };
+        let bailout = parameters.escape_radius * parameters.escape_radius;
+
+        let mut z = self.start.evaluate(&[(0.0, 0.0), c, pixel]);
+        let mut iteration = 0;
+        // NaN fails the comparison, so an orbit that blows up counts as escaped
+        while iteration < max_iterations && z.0 * z.0 + z.1 * z.1 <= bailout {
+            z = self.step.evaluate(&[z, c, pixel]);
+            iteration += 1;
+        }
+        iteration
+    }
+}
+
+/// A palette from three expressions for the red, green and blue channels, from 0 to 255.
+///
+/// They can use `t`, the iteration count as a fraction of the limit, along with `n`, the
+/// count itself, and `max`, the limit. Points that never escape are black, as in the
+/// built-in palettes.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ExpressionPalette {
+    channels: [Expression; 3],
+}
+
+const PALETTE_VARIABLES: [&str; 3] = ["t", "n", "max"];
+
+impl ExpressionPalette {
+    /// Parses the expressions for each channel.
+    pub fn new(red: &str, green: &str, blue: &str) -> Result<ExpressionPalette, String> {
+        let channel = |name: &str, text: &str| {
+            Expression::parse(text, &PALETTE_VARIABLES)
+                .map_err(|error| format!("{}: {}", name, error))
+        };
+        Ok(ExpressionPalette {
+            channels: [
+                channel("red", red)?,
+                channel("green", green)?,
+                channel("blue", blue)?,
+            ],
+        })
+    }
+
+    /// The color of an iteration count, given the limit.
+    pub fn color(&self, iteration: u32, max_iterations: u32) -> [u8; 3] {
+        if iteration == max_iterations {
+            return [0, 0, 0];
+        }
+        let variables = [
+            (iteration as f64 / max_iterations.max(1) as f64, 0.0),
+            (iteration as f64, 0.0),
+            (max_iterations as f64, 0.0),
+        ];
+        // Casting saturates, and sends NaN to 0
+        self.channels
+            .each_ref()
+            .map(|channel| channel.evaluate(&variables).0.clamp(0.0, 255.0) as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expression() {
+        let evaluate =
+            |text: &str, z: Complex| Expression::parse(text, &["z"]).map(|e| e.evaluate(&[z]));
+        assert_eq!(evaluate("1 + 2 * 3", (0.0, 0.0)), Ok((7.0, 0.0)));
+        assert_eq!(evaluate("2^3^2", (0.0, 0.0)), Ok((512.0, 0.0)));
+        assert_eq!(evaluate("-z^2", (0.0, 1.0)), Ok((1.0, 0.0)));
+        assert_eq!(evaluate("(1 + i) * (1 - i)", (0.0, 0.0)), Ok((2.0, 0.0)));
+        assert_eq!(evaluate("z^-1", (0.0, 2.0)), Ok((0.0, -0.5)));
+        assert_eq!(
+            evaluate("abs(re(z)) + i*abs(im(z))", (-3.0, -4.0)),
+            Ok((3.0, 4.0))
+        );
+        assert_eq!(evaluate("abs(z)", (3.0, 4.0)), Ok((5.0, 0.0)));
+        assert_eq!(evaluate("1.5e1 - 2E-1", (0.0, 0.0)), Ok((14.8, 0.0)));
+
+        let error = |text: &str| Expression::parse(text, &["z"]).unwrap_err();
+        assert_eq!(error("z +"), "unexpected end of expression");
+        assert_eq!(error("(z"), "missing )");
+        assert_eq!(error("w * 2"), "unknown name: w");
+        assert_eq!(error("z z"), "unexpected z");
+        assert_eq!(error("sin z"), "sin needs an argument in parentheses");
+        assert_eq!(error("z % 2"), "unexpected character: %");
+        let nested = "(".repeat(1000) + "z" + ")".repeat(1000).as_str();
+        assert_eq!(error(&nested), "expression nested too deeply");
+        assert_eq!(error(&("-".repeat(4000) + "z")), "expression nested too deeply");
+        assert_eq!(error(&"z^".repeat(2000)), "expression nested too deeply");
+        assert_eq!(error(&"(".repeat(100_000)), "expression too long");
+        assert_eq!(error(&"z + ".repeat(100_000)), "expression too long");
+        let deep = "(".repeat(200) + "z" + ")".repeat(200).as_str();
+        assert_eq!(evaluate(&deep, (1.0, 2.0)), Ok((1.0, 2.0)));
+        let long = "z + ".repeat(2000) + "z";
+        assert_eq!(evaluate(&long, (1.0, 2.0)), Ok((2001.0, 4002.0)));
+
+        // Everything without a variable is worked out when parsing
+        let folded = Expression::parse("z + 2 * (3 + i)", &["z"]).unwrap();
+        assert_eq!(
+            folded.root,
+            Node::Add(
+                Box::new(Node::Variable(0)),
+                Box::new(Node::Constant((6.0, 2.0)))
+            )
+        );
+    }
+
+    #[test]
+    fn test_formula() {
+        // The Mandelbrot set written out as a formula matches the built-in kernel
+        let formula = Formula::new("0", "z^2 + c", false).unwrap();
+        for (x, y) in [
+            (-0.75, 0.1),
+            (0.3, 0.5),
+            (-2.0, 1.0),
+            (-0.1, 0.0),
+            (0.26, 0.0),
+        ] {
+            assert_eq!(
+                formula.iterate(x, y, 200, &crate::DEFAULT_PARAMETERS),
+                crate::iterate_point(x, y, crate::u32x1::splat(200), &crate::DEFAULT_PARAMETERS)[0],
+            );
+        }
+
+        let julia = Formula::new("p", "z^2 + c", true).unwrap();
+        let parameters = FractalParameters {
+            fractal_index: crate::JULIA_INDEX,
+            ..crate::DEFAULT_PARAMETERS
+        };
+        assert_eq!(
+            julia.iterate(0.1, 0.2, 200, &parameters),
+            crate::iterate_point(0.1, 0.2, crate::u32x1::splat(200), &parameters)[0],
+        );
+
+        assert_eq!(
+            Formula::new("0", "z^2 + q", false),
+            Err("step: unknown name: q".to_string())
+        );
+    }
+
+    #[test]
+    fn test_expression_palette() {
+        let palette = ExpressionPalette::new("255 * t", "n", "max * 2").unwrap();
+        assert_eq!(palette.color(50, 100), [127, 50, 200]);
+        assert_eq!(palette.color(100, 100), [0, 0, 0]);
+        assert_eq!(
+            ExpressionPalette::new("t", "(", "t"),
+            Err("green: unexpected end of expression".to_string())
+        );
+    }
+}
@@ -0,0 +1,1159 @@
+// Locations, tours, the saved session and view files, and views imported from other
+// programs.
+
+use crate::app::{decimal_places, ease, trimmed_decimal, MAX_ITERATIONS, SERIES_TOLERANCE_RANGE};
+use crate::cli::{parse_center, parse_name, slug};
+use crate::commands::parse_number;
+use crate::explore::iterations_for_zoom;
+use crate::{
+    fixed_point, fractal_names, palette_names, register_palette, u32x1, zoom_position,
+    CustomPalette, Fixed, FractalParameters, Position, Projection, BACKEND_NAMES, COLORING_NAMES,
+    DEFAULT_PARAMETERS, JULIA_INDEX, MAX_SERIES_ORDER, PROJECTIONS, PROJECTION_NAMES,
+};
+
+pub(crate) const LOCATION_PREFIX: &str = "mandelbrot-term";
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Location {
+    pub(crate) fractal_index: usize,
+    pub(crate) center: (Fixed, Fixed),
+    pub(crate) zoom: f64,
+    pub(crate) max_iterations: u32,
+}
+
+impl Location {
+    pub(crate) fn encode(&self) -> String {
+        let places = decimal_places(self.zoom);
+        format!(
+            "{}:{}:{}:{}:{}:{}",
+            LOCATION_PREFIX,
+            self.fractal_index,
+            trimmed_decimal(&self.center.0, places),
+            trimmed_decimal(&self.center.1, places),
+            self.zoom,
+            self.max_iterations
+        )
+    }
+
+    pub(crate) fn position(&self, position: &Position, default_position: &Position) -> Position {
+        position
+            .with_zoom(default_position, self.zoom)
+            .centered_at(&self.center)
+    }
+
+    pub(crate) fn decode(text: &str) -> Result<Location, String> {
+        let fields: Vec<&str> = text.trim().split(':').collect();
+
+        match fields[..] {
+            [LOCATION_PREFIX, fractal_index, x, y, zoom, max_iterations] => {
+                let fractal_index = fractal_index
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|index| *index < fractal_names().len())
+                    .ok_or(format!("unknown fractal: {}", fractal_index))?;
+                let zoom = parse_number(zoom)?;
+                if zoom <= 0.0 {
+                    return Err("zoom must be positive".to_string());
+                }
+                let max_iterations = max_iterations
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|iterations| (1..=MAX_ITERATIONS).contains(iterations))
+                    .ok_or(format!("invalid iterations: {}", max_iterations))?;
+
+                Ok(Location {
+                    fractal_index,
+                    center: (Fixed::parse(x)?, Fixed::parse(y)?),
+                    zoom,
+                    max_iterations,
+                })
+            }
+            _ => Err("not a location".to_string()),
+        }
+    }
+}
+
+pub(crate) const TOUR: [(&str, f64, f64, f64, u32); 5] = [
+    ("Seahorse Valley", -0.75, 0.1, 25.0, 300),
+    ("Elephant Valley", 0.28, 0.008, 40.0, 300),
+    ("Misiurewicz point at c = i", 0.0, 1.0, 8.0, 200),
+    ("Misiurewicz point M(23,2)", -0.77568377, 0.13646737, 2000.0, 600),
+    ("Minibrot on the antenna", -1.985424253, 0.0, 20000.0, 1000),
+];
+
+#[derive(Clone)]
+pub(crate) struct TourStop {
+    pub(crate) caption: String,
+    pub(crate) location: Location,
+    // How long to stay before moving on to the next stop by itself
+    pub(crate) duration: Option<std::time::Duration>,
+}
+
+pub(crate) fn builtin_tour() -> Vec<TourStop> {
+    TOUR.iter()
+        .map(|(caption, x, y, zoom, max_iterations)| TourStop {
+            caption: caption.to_string(),
+            location: Location {
+                fractal_index: 0,
+                center: fixed_point((*x, *y)),
+                zoom: *zoom,
+                max_iterations: *max_iterations,
+            },
+            duration: None,
+        })
+        .collect()
+}
+
+pub(crate) fn parse_tour(text: &str) -> Result<Vec<TourStop>, String> {
+    // One stop per line: a copied location, then the caption
+    let mut stops = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (location, caption) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        stops.push(TourStop {
+            caption: caption.trim().to_string(),
+            location: Location::decode(location)
+                .map_err(|error| format!("line {}: {}", number + 1, error))?,
+            duration: None,
+        });
+    }
+
+    if stops.is_empty() {
+        return Err("tour has no stops".to_string());
+    }
+    Ok(stops)
+}
+
+fn state_dir() -> Option<std::path::PathBuf> {
+    let state = match std::env::var_os("XDG_STATE_HOME").filter(|path| !path.is_empty()) {
+        Some(path) => std::path::PathBuf::from(path),
+        None => std::path::PathBuf::from(std::env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(state.join("mandelbrot-term"))
+}
+
+fn session_path() -> Option<std::path::PathBuf> {
+    Some(state_dir()?.join("session.toml"))
+}
+
+// What is saved on exit so the next run can pick up where this one left off
+#[derive(PartialEq, Debug)]
+pub(crate) struct Session {
+    pub(crate) location: Location,
+    pub(crate) palette_index: usize,
+    pub(crate) julia_constant: (f64, f64),
+    pub(crate) auto_iterations: bool,
+    // Both left out of sessions saved before views could turn or be projected
+    pub(crate) rotation: f64,
+    pub(crate) projection: Projection,
+}
+
+impl Session {
+    fn encode(&self) -> String {
+        let mut table = toml::Table::new();
+        table.insert("location".to_string(), self.location.encode().into());
+        table.insert(
+            "palette".to_string(),
+            slug(palette_names()[self.palette_index]).into(),
+        );
+        table.insert(
+            "julia_constant".to_string(),
+            vec![self.julia_constant.0, self.julia_constant.1].into(),
+        );
+        table.insert("auto_iterations".to_string(), self.auto_iterations.into());
+        table.insert("rotation".to_string(), self.rotation.into());
+        table.insert(
+            "projection".to_string(),
+            slug(PROJECTION_NAMES[self.projection as usize]).into(),
+        );
+        table.to_string()
+    }
+
+    fn parse(text: &str) -> Result<Session, String> {
+        let table = text
+            .parse::<toml::Table>()
+            .map_err(|error| error.message().to_string())?;
+        let string = |key: &str| {
+            table
+                .get(key)
+                .and_then(|value| value.as_str())
+                .ok_or(format!("{} must be a string", key))
+        };
+
+        let julia_constant = match table
+            .get("julia_constant")
+            .and_then(|value| value.as_array())
+        {
+            Some(values) => match values[..] {
+                [toml::Value::Float(x), toml::Value::Float(y)] => Some((x, y)),
+                _ => None,
+            },
+            None => None,
+        };
+        Ok(Session {
+            location: Location::decode(string("location")?)?,
+            palette_index: parse_name(&palette_names(), "palette", string("palette")?)?,
+            julia_constant: julia_constant.ok_or("julia_constant must be two numbers")?,
+            auto_iterations: table
+                .get("auto_iterations")
+                .and_then(|value| value.as_bool())
+                .ok_or("auto_iterations must be a boolean")?,
+            rotation: match table.get("rotation") {
+                Some(value) => value.as_float().ok_or("rotation must be a number")?,
+                None => 0.0,
+            },
+            projection: match table.get("projection") {
+                Some(_) => {
+                    PROJECTIONS[parse_name(&PROJECTION_NAMES, "projection", string("projection")?)?]
+                }
+                None => Projection::Flat,
+            },
+        })
+    }
+}
+
+pub(crate) fn load_session() -> Result<Option<Session>, String> {
+    match session_path().and_then(|path| std::fs::read_to_string(path).ok()) {
+        Some(text) => Session::parse(&text).map(Some),
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn save_session(session: &Session) -> Result<(), String> {
+    let path = session_path().ok_or("no home directory to save the session in")?;
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)
+            .map_err(|error| format!("{}: {}", directory.display(), error))?;
+    }
+    std::fs::write(&path, session.encode())
+        .map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+// Everything that decides how a frame looks apart from its size, as the JSON objects of
+// view files and batch jobs
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct View {
+    pub(crate) position: Position,
+    pub(crate) max_iterations: u32x1,
+    // A view without an iteration count has its limit follow the zoom
+    pub(crate) auto_iterations: bool,
+    pub(crate) parameters: FractalParameters,
+    // The animation saved with the view, which keyframes never have themselves
+    pub(crate) keyframes: Vec<Keyframe>,
+}
+
+pub(crate) const KEYFRAME_SECONDS: f64 = 2.0;
+
+// A view an animation passes through, `seconds` after the keyframe before it
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Keyframe {
+    pub(crate) view: View,
+    pub(crate) seconds: f64,
+}
+
+impl View {
+    pub(crate) fn parse(text: &str, default_position: &Position) -> Result<View, String> {
+        let value: serde_json::Value =
+            serde_json::from_str(text).map_err(|error| error.to_string())?;
+        let object = value.as_object().ok_or("a view must be an object")?;
+        View::from_json(object, default_position)
+    }
+
+    pub(crate) fn from_json(
+        object: &serde_json::Map<String, serde_json::Value>,
+        default_position: &Position,
+    ) -> Result<View, String> {
+        let mut center = None;
+        let mut zoom = 1.0;
+        let mut rotation = 0.0;
+        let mut projection = Projection::Flat;
+        let mut iterations = None;
+        let mut parameters = DEFAULT_PARAMETERS;
+        let mut keyframes = Vec::new();
+        for (key, value) in object {
+            let string = || value.as_str().ok_or(format!("{} must be a string", key));
+            // Pairs of numbers are written [X, Y]
+            let number = |index: usize| value.get(index).and_then(|number| number.as_f64());
+            let pair = || match (
+                value.as_array().map(|numbers| numbers.len()),
+                number(0),
+                number(1),
+            ) {
+                (Some(2), Some(x), Some(y)) => Some((x, y)),
+                _ => None,
+            };
+            match key.as_str() {
+                "fractal" => {
+                    parameters.fractal_index = parse_name(&fractal_names(), "fractal", string()?)?
+                }
+                "palette" => {
+                    parameters.palette_index = parse_name(&palette_names(), "palette", string()?)?
+                }
+                "coloring" => {
+                    parameters.coloring_index = parse_name(&COLORING_NAMES, "coloring", string()?)?
+                }
+                "backend" => {
+                    parameters.backend_index = parse_name(&BACKEND_NAMES, "backend", string()?)?
+                }
+                "supersampling" => {
+                    parameters.supersampling = value
+                        .as_u64()
+                        .filter(|samples| (1..=4).contains(samples))
+                        .ok_or("supersampling must be between 1 and 4")?
+                        as u32
+                }
+                "series_order" => {
+                    parameters.series_order = value
+                        .as_u64()
+                        .filter(|order| *order <= MAX_SERIES_ORDER as u64)
+                        .ok_or(format!(
+                            "series_order must be between 0 and {}",
+                            MAX_SERIES_ORDER
+                        ))? as usize
+                }
+                "series_tolerance" => {
+                    let (low, high) = SERIES_TOLERANCE_RANGE;
+                    parameters.series_tolerance = value
+                        .as_f64()
+                        .filter(|tolerance| (low..=high).contains(tolerance))
+                        .ok_or(format!(
+                            "series_tolerance must be between {} and {}",
+                            low, high
+                        ))?
+                }
+                "tile_limits" => {
+                    parameters.tile_limits =
+                        value.as_bool().ok_or("tile_limits must be true or false")?
+                }
+                "julia_constant" => {
+                    parameters.julia_constant = pair().ok_or("julia_constant must be [X, Y]")?
+                }
+                "initial_z" => parameters.initial_z = pair().ok_or("initial_z must be [X, Y]")?,
+                "palette_phase" => {
+                    parameters.palette_phase =
+                        value.as_f64().ok_or("palette_phase must be a number")?
+                }
+                "keyframes" => {
+                    let list = value.as_array().ok_or("keyframes must be a list")?;
+                    for (index, keyframe) in list.iter().enumerate() {
+                        let keyframe = Keyframe::from_json(keyframe, index == 0, default_position)
+                            .map_err(|error| format!("keyframe {}: {}", index + 1, error))?;
+                        keyframes.push(keyframe);
+                    }
+                }
+                // Strings keep digits past what a JSON number can hold
+                "center" => {
+                    center = Some(match (value, pair()) {
+                        (serde_json::Value::String(text), _) => parse_center(text)?,
+                        (_, Some(point)) => fixed_point(point),
+                        _ => return Err("center must be \"X,Y\" or [X, Y]".to_string()),
+                    })
+                }
+                "zoom" => {
+                    zoom = value
+                        .as_f64()
+                        .filter(|zoom| *zoom > 0.0)
+                        .ok_or("zoom must be a positive number")?
+                }
+                "rotation" => {
+                    rotation = value
+                        .as_f64()
+                        .ok_or("rotation must be a number of degrees")?
+                }
+                "projection" => {
+                    let index = parse_name(&PROJECTION_NAMES, "projection", string()?)?;
+                    projection = PROJECTIONS[index];
+                }
+                "iterations" => {
+                    iterations = Some(
+                        value
+                            .as_u64()
+                            .filter(|count| (1..=MAX_ITERATIONS as u64).contains(count))
+                            .ok_or(format!(
+                                "iterations must be a positive integer up to {}",
+                                MAX_ITERATIONS
+                            ))?
+                            as u32,
+                    )
+                }
+                _ => return Err(format!("unknown setting: {}", key)),
+            }
+        }
+
+        let position = Position {
+            projection,
+            ..default_position
+                .with_zoom(default_position, zoom)
+                .rotated(f64::to_radians(rotation))
+        };
+        Ok(View {
+            position: match center {
+                Some(center) => position.centered_at(&center),
+                None => position,
+            },
+            max_iterations: u32x1::splat(iterations.unwrap_or_else(|| iterations_for_zoom(zoom))),
+            auto_iterations: iterations.is_none(),
+            parameters,
+            keyframes,
+        })
+    }
+
+    // The iteration count is always written, so a view renders the same after it is loaded
+    fn to_json(&self, default_position: &Position) -> serde_json::Value {
+        let zoom = default_position.width() / self.position.width();
+        let places = decimal_places(zoom);
+        let center = self.position.center();
+        let parameters = &self.parameters;
+        let mut json = serde_json::json!({
+            "fractal": slug(fractal_names()[parameters.fractal_index]),
+            "center": format!(
+                "{},{}",
+                trimmed_decimal(&center.0, places),
+                trimmed_decimal(&center.1, places)
+            ),
+            "zoom": zoom,
+            "iterations": self.max_iterations[0],
+            "palette": slug(palette_names()[parameters.palette_index]),
+            "coloring": slug(COLORING_NAMES[parameters.coloring_index]),
+            "supersampling": parameters.supersampling,
+            "backend": slug(BACKEND_NAMES[parameters.backend_index]),
+            "series_order": parameters.series_order,
+            "series_tolerance": parameters.series_tolerance,
+            "tile_limits": parameters.tile_limits,
+            "julia_constant": [parameters.julia_constant.0, parameters.julia_constant.1],
+            "initial_z": [parameters.initial_z.0, parameters.initial_z.1],
+            "palette_phase": parameters.palette_phase,
+        });
+        if self.position.rotation != 0.0 {
+            json["rotation"] = self.position.rotation.to_degrees().into();
+        }
+        if self.position.projection != Projection::Flat {
+            json["projection"] = slug(PROJECTION_NAMES[self.position.projection as usize]).into();
+        }
+        if !self.keyframes.is_empty() {
+            let keyframes = self.keyframes.iter().map(|keyframe| {
+                let mut json = keyframe.view.to_json(default_position);
+                json["seconds"] = keyframe.seconds.into();
+                json
+            });
+            json["keyframes"] = keyframes.collect();
+        }
+        json
+    }
+}
+
+impl Keyframe {
+    // A keyframe is a view with the time it takes to reach it, which the first one doesn't need
+    fn from_json(
+        value: &serde_json::Value,
+        first: bool,
+        default_position: &Position,
+    ) -> Result<Keyframe, String> {
+        let mut object = value
+            .as_object()
+            .ok_or("a keyframe must be an object")?
+            .clone();
+        let seconds = match object.remove("seconds") {
+            Some(seconds) => seconds
+                .as_f64()
+                .filter(|seconds| *seconds >= 0.0)
+                .ok_or("seconds must be a number that isn't negative")?,
+            None if first => 0.0,
+            None => KEYFRAME_SECONDS,
+        };
+        let view = View::from_json(&object, default_position)?;
+        if !view.keyframes.is_empty() {
+            return Err("keyframes can't have keyframes".to_string());
+        }
+        Ok(Keyframe { view, seconds })
+    }
+}
+
+// Moves the Julia constant around the origin rather than straight across, so constants on
+// a circle stay on it
+fn slerp_constant(from: (f64, f64), to: (f64, f64), t: f64) -> (f64, f64) {
+    let from_angle = from.1.atan2(from.0);
+    // Turn the short way round
+    let turn = (to.1.atan2(to.0) - from_angle + std::f64::consts::PI)
+        .rem_euclid(std::f64::consts::TAU)
+        - std::f64::consts::PI;
+    let from_radius = from.0.hypot(from.1);
+    let radius = from_radius + (to.0.hypot(to.1) - from_radius) * t;
+    let angle = from_angle + turn * t;
+    (radius * angle.cos(), radius * angle.sin())
+}
+
+// The view `t` of the way from one keyframe to the next. The zoom and iteration limit move
+// geometrically, and settings that can't be blended switch once the next keyframe is reached.
+fn blend_views(from: &View, to: &View, t: f64, default_position: &Position) -> View {
+    let position = zoom_position(&from.position, &to.position, t);
+    let auto_iterations = from.auto_iterations && to.auto_iterations;
+    let max_iterations = if auto_iterations {
+        iterations_for_zoom(default_position.width() / position.width())
+    } else {
+        let (from_limit, to_limit) = (from.max_iterations[0] as f64, to.max_iterations[0] as f64);
+        (from_limit * (to_limit / from_limit).powf(t)).round() as u32
+    };
+    let (from_parameters, to_parameters) = (&from.parameters, &to.parameters);
+    let blend = |from: f64, to: f64| from + (to - from) * t;
+    View {
+        position,
+        max_iterations: u32x1::splat(max_iterations),
+        auto_iterations,
+        parameters: FractalParameters {
+            julia_constant: slerp_constant(
+                from_parameters.julia_constant,
+                to_parameters.julia_constant,
+                t,
+            ),
+            initial_z: (
+                blend(from_parameters.initial_z.0, to_parameters.initial_z.0),
+                blend(from_parameters.initial_z.1, to_parameters.initial_z.1),
+            ),
+            palette_phase: blend(from_parameters.palette_phase, to_parameters.palette_phase),
+            escape_radius: blend(from_parameters.escape_radius, to_parameters.escape_radius),
+            ..*from_parameters
+        },
+        keyframes: Vec::new(),
+    }
+}
+
+pub(crate) fn keyframes_seconds(keyframes: &[Keyframe]) -> f64 {
+    keyframes.iter().skip(1).map(|keyframe| keyframe.seconds).sum()
+}
+
+// The view `seconds` into the keyframes, with each stretch between two of them eased
+pub(crate) fn keyframe_view(
+    keyframes: &[Keyframe],
+    default_position: &Position,
+    seconds: f64,
+    easing_index: usize,
+) -> View {
+    let mut start = 0.0;
+    for pair in keyframes.windows(2) {
+        let (from, to) = (&pair[0], &pair[1]);
+        if seconds < start + to.seconds {
+            let t = ease(easing_index, (seconds - start) / to.seconds);
+            return blend_views(&from.view, &to.view, t, default_position);
+        }
+        start += to.seconds;
+    }
+    keyframes[keyframes.len() - 1].view.clone()
+}
+
+// One view for each frame of the keyframes, starting on the first and ending on the last
+pub(crate) fn keyframe_frames(
+    keyframes: &[Keyframe],
+    default_position: &Position,
+    fps: u32,
+    easing_index: usize,
+) -> Vec<View> {
+    let seconds = keyframes_seconds(keyframes);
+    let frames = (seconds * fps as f64).round().max(1.0) as u32;
+    (0..=frames)
+        .map(|frame| {
+            let time = seconds * frame as f64 / frames as f64;
+            keyframe_view(keyframes, default_position, time, easing_index)
+        })
+        .collect()
+}
+
+// Kalles Fraktaler's zoom and UltraFractal's magnification of 1 both show a height of 4, twice
+// what the default view shows
+const IMPORTED_ZOOM_SCALE: f64 = 0.5;
+
+fn parse_zoom(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(zoom) if zoom.is_infinite() => Err(format!("zoom is too deep: {}", value)),
+        Ok(zoom) if zoom > 0.0 => Ok(zoom * IMPORTED_ZOOM_SCALE),
+        _ => Err(format!("invalid zoom: {}", value)),
+    }
+}
+
+// Kalles Fraktaler's .kfr locations and .kfp palettes are both lists of "Key: value" lines,
+// and either part can be left out. The palette is returned for the caller to register.
+fn import_kalles_fraktaler(
+    text: &str,
+    default_position: &Position,
+) -> Result<(View, Option<CustomPalette>), String> {
+    let settings: Vec<(&str, &str)> = text
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .collect();
+    let setting = |key: &str| {
+        settings
+            .iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| *value)
+    };
+    let number = |key: &str, default: f64| setting(key).map_or(Ok(default), parse_number);
+
+    if number("FractalType", 0.0)? != 0.0 || number("Power", 2.0)? != 2.0 {
+        return Err("only the Mandelbrot set can be imported".to_string());
+    }
+    let mut view = View {
+        position: default_position.clone(),
+        max_iterations: u32x1::splat(iterations_for_zoom(1.0)),
+        auto_iterations: true,
+        parameters: DEFAULT_PARAMETERS,
+        keyframes: Vec::new(),
+    };
+    let center = match (setting("Re"), setting("Im")) {
+        (Some(x), Some(y)) => Some((Fixed::parse(x)?, Fixed::parse(y)?)),
+        _ => None,
+    };
+    if let Some(center) = &center {
+        let zoom = setting("Zoom").map_or(Ok(IMPORTED_ZOOM_SCALE), parse_zoom)?;
+        view.position = default_position
+            .with_zoom(default_position, zoom)
+            .centered_at(center);
+        view.max_iterations = u32x1::splat(iterations_for_zoom(zoom));
+    }
+    if let Some(iterations) = setting("Iterations") {
+        let iterations = iterations
+            .parse::<u32>()
+            .ok()
+            .filter(|count| (1..=MAX_ITERATIONS).contains(count))
+            .ok_or(format!(
+                "Iterations must be a positive integer up to {}",
+                MAX_ITERATIONS
+            ))?;
+        view.max_iterations = u32x1::splat(iterations);
+        view.auto_iterations = false;
+    }
+    let palette = match setting("Colors") {
+        Some(colors) => {
+            let divide = number("IterDiv", 1.0)?;
+            if divide <= 0.0 {
+                return Err("IterDiv must be positive".to_string());
+            }
+            Some(kalles_fraktaler_palette(
+                colors,
+                divide,
+                number("ColorOffset", 0.0)?,
+            )?)
+        }
+        None => None,
+    };
+
+    if center.is_none() && palette.is_none() {
+        return Err("no location or colors".to_string());
+    }
+    Ok((view, palette))
+}
+
+fn kalles_fraktaler_palette(
+    colors: &str,
+    divide: f64,
+    offset: f64,
+) -> Result<CustomPalette, String> {
+    let channels = colors
+        .split(',')
+        .map(|channel| channel.trim())
+        .filter(|channel| !channel.is_empty())
+        .map(|channel| {
+            channel
+                .parse::<u8>()
+                .map_err(|_| format!("invalid color: {}", channel))
+        })
+        .collect::<Result<Vec<u8>, String>>()?;
+    if channels.is_empty() || channels.len() % 3 != 0 {
+        return Err("Colors must be red, green, blue triples".to_string());
+    }
+    let keys: Vec<[f64; 3]> = channels
+        .chunks(3)
+        .map(|key| [key[0] as f64, key[1] as f64, key[2] as f64])
+        .collect();
+
+    Ok(std::sync::Arc::new(move |iteration, max_iterations| {
+        if iteration >= max_iterations {
+            return [0, 0, 0];
+        }
+        // The key colors are spread evenly over 1024 shades, which repeat
+        let shade =
+            (iteration as f64 / divide + offset).rem_euclid(1024.0) / 1024.0 * keys.len() as f64;
+        let from = keys[shade as usize % keys.len()];
+        let to = keys[(shade as usize + 1) % keys.len()];
+        let t = shade.fract();
+        std::array::from_fn(|channel| (from[channel] + (to[channel] - from[channel]) * t) as u8)
+    }))
+}
+
+// Reads the first parameter set of an UltraFractal .upr file. Only the standard Mandelbrot
+// and Julia formulas are understood, and rotation is left out.
+fn import_ultra_fractal(text: &str, default_position: &Position) -> Result<View, String> {
+    let body = text
+        .split_once('{')
+        .and_then(|(_, body)| body.split('}').next())
+        .ok_or("no parameter set")?;
+
+    // Each section starts with a "name:" line, followed by key=value pairs where values with
+    // spaces are quoted. Keys are kept as "section.key" since sections reuse them.
+    let mut settings = Vec::new();
+    let mut section = "";
+    for line in body.lines().map(|line| line.trim()) {
+        if let Some(name) = line.strip_suffix(':').filter(|name| !name.contains('=')) {
+            section = name;
+            continue;
+        }
+        let mut rest = line;
+        while let Some((key, value)) = rest.split_once('=') {
+            let (value, remainder) = match value.strip_prefix('"') {
+                Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+                None => value.split_once(char::is_whitespace).unwrap_or((value, "")),
+            };
+            settings.push((format!("{}.{}", section, key.trim()), value));
+            rest = remainder;
+        }
+    }
+    let setting = |key: &str| {
+        settings
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| *value)
+    };
+    // Complex numbers are written X/Y
+    let complex = |key: &str| {
+        let value = setting(key).ok_or(format!("missing {}", key))?;
+        value
+            .split_once('/')
+            .ok_or(format!("invalid {}: {}", key, value))
+    };
+
+    let mut parameters = DEFAULT_PARAMETERS;
+    match setting("formula.entry") {
+        Some("Mandelbrot") => (),
+        Some("Julia") => {
+            let (x, y) = complex("formula.p_seed")?;
+            parameters.fractal_index = JULIA_INDEX;
+            parameters.julia_constant = (parse_number(x)?, parse_number(y)?);
+        }
+        Some(entry) => return Err(format!("unsupported formula: {}", entry)),
+        None => return Err("missing formula.entry".to_string()),
+    }
+    if setting("formula.p_power").is_some_and(|power| !matches!(power, "2" | "2/0")) {
+        return Err("only the second power can be imported".to_string());
+    }
+
+    let (x, y) = complex("mapping.center")?;
+    let zoom = setting("mapping.magn").map_or(Ok(IMPORTED_ZOOM_SCALE), parse_zoom)?;
+    let iterations = match setting("formula.maxiter") {
+        Some(iterations) => Some(
+            iterations
+                .parse::<u32>()
+                .ok()
+                .filter(|count| (1..=MAX_ITERATIONS).contains(count))
+                .ok_or(format!(
+                    "maxiter must be a positive integer up to {}",
+                    MAX_ITERATIONS
+                ))?,
+        ),
+        None => None,
+    };
+    Ok(View {
+        position: default_position
+            .with_zoom(default_position, zoom)
+            .centered_at(&(Fixed::parse(x)?, Fixed::parse(y)?)),
+        max_iterations: u32x1::splat(iterations.unwrap_or_else(|| iterations_for_zoom(zoom))),
+        auto_iterations: iterations.is_none(),
+        parameters,
+        keyframes: Vec::new(),
+    })
+}
+
+// Views are JSON, unless the extension says they come from another program
+pub(crate) fn load_view(
+    path: &std::path::Path,
+    default_position: &Position,
+) -> Result<View, String> {
+    let extension = path
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+    let text =
+        std::fs::read_to_string(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    let view = match extension.as_str() {
+        "kfr" | "kfp" => {
+            import_kalles_fraktaler(&text, default_position).map(|(mut view, palette)| {
+                // Imported palettes are named after their file
+                if let Some(palette) = palette {
+                    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+                    view.parameters.palette_index = register_palette(&name, palette);
+                }
+                view
+            })
+        }
+        "upr" => import_ultra_fractal(&text, default_position),
+        _ => View::parse(&text, default_position),
+    };
+    view.map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+pub(crate) fn save_view(
+    path: &std::path::Path,
+    view: &View,
+    default_position: &Position,
+) -> Result<(), String> {
+    if let Some(directory) = path
+        .parent()
+        .filter(|directory| !directory.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(directory)
+            .map_err(|error| format!("{}: {}", directory.display(), error))?;
+    }
+    let text = serde_json::to_string_pretty(&view.to_json(default_position)).unwrap();
+    std::fs::write(path, text + "\n").map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+// Where the export key puts views, named by when they were saved so they sort in order
+pub(crate) fn export_path() -> Option<std::path::PathBuf> {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    Some(
+        state_dir()?
+            .join("views")
+            .join(format!("view-{}.json", seconds)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location() {
+        let location = Location {
+            fractal_index: 1,
+            center: (
+                Fixed::parse("-0.743643887").unwrap(),
+                Fixed::parse("0.131825904").unwrap(),
+            ),
+            zoom: 1e9,
+            max_iterations: 500,
+        };
+        let encoded = location.encode();
+        assert_eq!(
+            encoded,
+            "mandelbrot-term:1:-0.743643887:0.131825904:1000000000:500"
+        );
+        assert_eq!(Location::decode(&format!(" {}\n", encoded)), Ok(location));
+
+        assert!(Location::decode("-0.5 0 1").is_err());
+        assert!(Location::decode("mandelbrot-term:9:0:0:1:100").is_err());
+        assert!(Location::decode("mandelbrot-term:0:0:0:-1:100").is_err());
+        assert!(Location::decode("mandelbrot-term:0:0:0:1:0").is_err());
+        assert!(Location::decode("mandelbrot-term:0:0:0:1:4000000000").is_err());
+    }
+
+    #[test]
+    fn test_parse_tour() {
+        let stops = parse_tour(
+            "# my tour\n\nmandelbrot-term:0:-0.75:0.1:25:300  Seahorse Valley\nmandelbrot-term:2:0:0:1:100\n",
+        )
+        .unwrap();
+        assert_eq!(stops.len(), 2);
+        assert_eq!(stops[0].caption, "Seahorse Valley");
+        assert_eq!(
+            stops[0].location.center,
+            (Fixed::parse("-0.75").unwrap(), Fixed::parse("0.1").unwrap())
+        );
+        assert_eq!(stops[1].caption, "");
+        assert_eq!(stops[1].location.fractal_index, 2);
+
+        assert_eq!(
+            parse_tour("mandelbrot-term:0:0:0:1:100 ok\n0 0 bad").err(),
+            Some("line 2: not a location".to_string())
+        );
+        assert!(parse_tour("# nothing here").is_err());
+        assert_eq!(builtin_tour().len(), TOUR.len());
+    }
+
+    #[test]
+    fn test_session() {
+        let session = Session {
+            location: Location {
+                fractal_index: JULIA_INDEX,
+                center: (Fixed::parse("-0.75").unwrap(), Fixed::parse("0.1").unwrap()),
+                zoom: 25.0,
+                max_iterations: 300,
+            },
+            palette_index: 2,
+            julia_constant: (-0.8, 0.156),
+            auto_iterations: false,
+            rotation: 30.0,
+            projection: Projection::LogPolar,
+        };
+        let encoded = session.encode();
+        assert!(encoded.contains("palette = \"fire\""));
+        assert_eq!(Session::parse(&encoded), Ok(session));
+        // Sessions from before rotation and projections start unturned and flat
+        let unturned = encoded
+            .replace("rotation = 30.0\n", "")
+            .replace("projection = \"log-polar\"\n", "");
+        let unturned = Session::parse(&unturned).unwrap();
+        assert_eq!(unturned.rotation, 0.0);
+        assert_eq!(unturned.projection, Projection::Flat);
+
+        assert_eq!(
+            Session::parse("palette = \"fire\"").err(),
+            Some("location must be a string".to_string())
+        );
+        assert_eq!(
+            Session::parse(
+                "location = \"mandelbrot-term:0:0:0:1:100\"\npalette = \"fire\"\njulia_constant = [0.0]"
+            )
+            .err(),
+            Some("julia_constant must be two numbers".to_string())
+        );
+        assert!(Session::parse(
+            "location = \"nowhere\"\npalette = \"fire\"\njulia_constant = [0.0, 0.0]\nauto_iterations = true"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_view() {
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let view = View {
+            position: default_position
+                .with_zoom(&default_position, 1e6)
+                .centered_at(&parse_center("-0.743643887037151,0.13182590420533").unwrap()),
+            max_iterations: u32x1::splat(2500),
+            auto_iterations: false,
+            parameters: FractalParameters {
+                fractal_index: JULIA_INDEX,
+                palette_index: 2,
+                coloring_index: 1,
+                supersampling: 2,
+                backend_index: 2,
+                series_order: 8,
+                series_tolerance: 1e-4,
+                tile_limits: true,
+                julia_constant: (-0.8, 0.156),
+                initial_z: (0.1, -0.2),
+                palette_phase: 0.5,
+                ..DEFAULT_PARAMETERS
+            },
+            keyframes: Vec::new(),
+        };
+        let json = view.to_json(&default_position);
+        assert_eq!(json["fractal"], "julia-set");
+        assert_eq!(json["center"], "-0.743643887037,0.131825904205");
+        assert_eq!(json["iterations"], 2500);
+        assert_eq!(json["palette"], "fire");
+        assert_eq!(json["coloring"], "logarithmic");
+
+        // The written view reads back the same, down to the digits the zoom needs
+        let parsed = View::parse(&json.to_string(), &default_position).unwrap();
+        assert_eq!(parsed.max_iterations, view.max_iterations);
+        assert!(!parsed.auto_iterations);
+        assert_eq!(parsed.parameters, view.parameters);
+        let (x, y) = parsed.position.center();
+        let (expected_x, expected_y) = view.position.center();
+        assert!((&x - &expected_x).to_f64().abs() < view.position.width() * 1e-6);
+        assert!((&y - &expected_y).to_f64().abs() < view.position.width() * 1e-6);
+        assert!((parsed.position.width() / view.position.width() - 1.0).abs() < 1e-12);
+
+        assert!(View::parse(r#"{"iterations": 4000000000}"#, &default_position).is_err());
+
+        // A view without a limit follows the zoom
+        let view = View::parse(r#"{"zoom": 100}"#, &default_position).unwrap();
+        assert!(view.auto_iterations);
+        assert_eq!(
+            view.max_iterations,
+            u32x1::splat(iterations_for_zoom(100.0))
+        );
+        assert!(view.to_json(&default_position).get("rotation").is_none());
+
+        // Rotations are written in degrees
+        let view = View::parse(r#"{"rotation": 90}"#, &default_position).unwrap();
+        assert_eq!(view.position.rotation, std::f64::consts::FRAC_PI_2);
+        assert_eq!(view.to_json(&default_position)["rotation"], 90.0);
+        let view = View::parse(r#"{"projection": "log-polar"}"#, &default_position).unwrap();
+        assert_eq!(view.position.projection, Projection::LogPolar);
+        assert_eq!(view.to_json(&default_position)["projection"], "log-polar");
+
+        let error = |text: &str| View::parse(text, &default_position).err().unwrap();
+        assert_eq!(error("[]"), "a view must be an object");
+        assert_eq!(
+            error(r#"{"julia_constant": [1, 2, 3]}"#),
+            "julia_constant must be [X, Y]"
+        );
+        assert_eq!(error(r#"{"palette": 2}"#), "palette must be a string");
+        assert_eq!(error(r#"{"palette": "teal"}"#), "unknown palette: teal");
+        assert_eq!(
+            error(r#"{"keyframes": [{}, {"seconds": -1}]}"#),
+            "keyframe 2: seconds must be a number that isn't negative"
+        );
+        assert_eq!(
+            error(r#"{"keyframes": [{"keyframes": [{}]}]}"#),
+            "keyframe 1: keyframes can't have keyframes"
+        );
+    }
+
+    #[test]
+    fn test_keyframes() {
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        let view = View::parse(
+            r#"{"keyframes": [
+                {"fractal": "julia", "julia_constant": [0.5, 0], "iterations": 100},
+                {"fractal": "julia", "julia_constant": [0, 0.5], "iterations": 400,
+                 "center": "-0.5,0", "zoom": 100, "palette_phase": 1, "seconds": 4},
+                {"zoom": 10}
+            ]}"#,
+            &default_position,
+        )
+        .unwrap();
+        let keyframes = &view.keyframes;
+        assert_eq!(keyframes.len(), 3);
+        assert_eq!(keyframes[0].seconds, 0.0);
+        assert_eq!(keyframes[2].seconds, KEYFRAME_SECONDS);
+        assert_eq!(keyframes_seconds(keyframes), 6.0);
+
+        // Halfway to the second keyframe the zoom, limit and constant are all halfway round
+        let halfway = keyframe_view(keyframes, &default_position, 2.0, 0);
+        assert!((halfway.position.width() - 3.0 / 10.0).abs() < 1e-9);
+        assert_eq!(halfway.max_iterations, u32x1::splat(200));
+        let (x, y) = halfway.parameters.julia_constant;
+        assert!((x - 0.5 * std::f64::consts::FRAC_1_SQRT_2).abs() < 1e-12);
+        assert!((y - x).abs() < 1e-12);
+        assert_eq!(halfway.parameters.palette_phase, 0.5);
+        assert_eq!(halfway.parameters.fractal_index, JULIA_INDEX);
+        // The last keyframe switches back to the Mandelbrot set once it is reached
+        let last = keyframe_view(keyframes, &default_position, 5.9, 0);
+        assert_eq!(last.parameters.fractal_index, JULIA_INDEX);
+        let last = keyframe_view(keyframes, &default_position, 6.0, 0);
+        assert_eq!(last, keyframes[2].view);
+
+        let frames = keyframe_frames(keyframes, &default_position, 2, 0);
+        assert_eq!(frames.len(), 13);
+        assert_eq!(frames[0], keyframes[0].view);
+        assert_eq!(frames[12], keyframes[2].view);
+
+        // Keyframes are written with their timings and read back the same
+        let json = view.to_json(&default_position);
+        assert_eq!(json["keyframes"][1]["seconds"], 4.0);
+        let parsed = View::parse(&json.to_string(), &default_position).unwrap();
+        assert_eq!(parsed.keyframes.len(), 3);
+        assert_eq!(parsed.keyframes[1].seconds, 4.0);
+        assert_eq!(
+            parsed.keyframes[1].view.parameters,
+            keyframes[1].view.parameters
+        );
+    }
+
+    #[test]
+    fn test_slerp_constant() {
+        // Constants on a circle stay on it, turning the short way round
+        let turned = slerp_constant((0.0, -0.8), (-0.8, 0.0), 0.5);
+        assert!((turned.0.hypot(turned.1) - 0.8).abs() < 1e-12);
+        assert!(turned.0 < 0.0 && turned.1 < 0.0);
+        let end = slerp_constant((0.3, 0.4), (-0.1, 0.2), 1.0);
+        assert!((end.0 + 0.1).abs() < 1e-12 && (end.1 - 0.2).abs() < 1e-12);
+        assert!((slerp_constant((1.0, 0.0), (2.0, 0.0), 0.25).0 - 1.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_import() {
+        let default_position = Position::new(-1.0, 1.0, -2.0, 1.0);
+        // Deep centers are compared without going through f64
+        let centered_at = |view: &View, x: &str, y: &str| {
+            let (center_x, center_y) = view.position.center();
+            let tolerance = view.position.width() * 1e-6;
+            (&center_x - &Fixed::parse(x).unwrap()).to_f64().abs() < tolerance
+                && (&center_y - &Fixed::parse(y).unwrap()).to_f64().abs() < tolerance
+        };
+
+        let kfr = "Re: -1.74909308303385781627831066864\r\nIm: -0.0000000000000018\r\n\
+                   Zoom: 2E10\r\nIterations: 12000\r\nIterDiv: 1.000000\r\n\
+                   Colors: 255,0,0,0,0,255,\r\nInteriorColor: 0,0,0,\r\nSmooth: 1\r\n";
+        let (view, palette) = import_kalles_fraktaler(kfr, &default_position).unwrap();
+        assert!(centered_at(
+            &view,
+            "-1.74909308303385781627831066864",
+            "-0.0000000000000018"
+        ));
+        assert!((default_position.width() / view.position.width() - 1e10).abs() < 1.0);
+        assert_eq!(view.max_iterations, u32x1::splat(12000));
+        assert!(!view.auto_iterations);
+        // Two key colors make 512 shades each, blending from one into the next
+        let palette = palette.unwrap();
+        assert_eq!(palette(0, 12000), [255, 0, 0]);
+        assert_eq!(palette(256, 12000), [127, 0, 127]);
+        assert_eq!(palette(512, 12000), [0, 0, 255]);
+        assert_eq!(palette(12000, 12000), [0, 0, 0]);
+
+        assert_eq!(
+            import_kalles_fraktaler("Smooth: 1", &default_position).err(),
+            Some("no location or colors".to_string())
+        );
+        assert_eq!(
+            import_kalles_fraktaler("Re: 0\nIm: 0\nZoom: 1E1000", &default_position).err(),
+            Some("zoom is too deep: 1E1000".to_string())
+        );
+        assert_eq!(
+            import_kalles_fraktaler("Re: 0\nIm: 0\nFractalType: 4", &default_position).err(),
+            Some("only the Mandelbrot set can be imported".to_string())
+        );
+        let too_many = format!("Re: 0\nIm: 0\nIterations: {}", MAX_ITERATIONS as u64 + 1);
+        assert_eq!(
+            import_kalles_fraktaler(&too_many, &default_position).err(),
+            Some(format!(
+                "Iterations must be a positive integer up to {}",
+                MAX_ITERATIONS
+            ))
+        );
+        assert!(import_kalles_fraktaler("Re: 0\nIm: 0\nIterations: 0", &default_position).is_err());
+
+        let upr = r#"
+Seahorse {
+fractal:
+  title="Seahorse valley" width=640 height=480 layers=1
+  credits="Someone;1/1/2020"
+layer:
+  caption="Background" opacity=100
+mapping:
+  center=-0.7445/0.1130 magn=250 angle=0
+formula:
+  maxiter=700 filename="Standard.ufm" entry="Mandelbrot" p_start=0/0 p_power=2/0
+inside:
+  transfer=none
+outside:
+  filename="Standard.ucl" entry="Smooth"
+}
+
+Other {
+}
+"#;
+        let view = import_ultra_fractal(upr, &default_position).unwrap();
+        assert!(centered_at(&view, "-0.7445", "0.113"));
+        assert!((default_position.width() / view.position.width() - 125.0).abs() < 1e-9);
+        assert_eq!(view.max_iterations, u32x1::splat(700));
+        assert_eq!(view.parameters.fractal_index, 0);
+
+        let julia = "J {\nmapping:\n  center=0/0\nformula:\n  entry=\"Julia\" p_seed=-0.8/0.156\n}";
+        let view = import_ultra_fractal(julia, &default_position).unwrap();
+        assert_eq!(view.parameters.fractal_index, JULIA_INDEX);
+        assert_eq!(view.parameters.julia_constant, (-0.8, 0.156));
+        assert!(view.auto_iterations);
+        assert_eq!(
+            import_ultra_fractal(&upr.replace("Mandelbrot", "Newton"), &default_position).err(),
+            Some("unsupported formula: Newton".to_string())
+        );
+        let too_many = upr.replace("maxiter=700", "maxiter=4000000000");
+        assert_eq!(
+            import_ultra_fractal(&too_many, &default_position).err(),
+            Some(format!(
+                "maxiter must be a positive integer up to {}",
+                MAX_ITERATIONS
+            ))
+        );
+    }
+}
@@ -0,0 +1,274 @@
+// Scalar stand-ins for the parts of std::simd the renderer uses, so the crate builds on
+// stable Rust. Each lane is a plain array element; the compiler is left to vectorize.
+
+use std::marker::PhantomData;
+use std::ops::{
+    Add, AddAssign, BitAnd, BitAndAssign, BitOr, BitOrAssign, Div, DivAssign, Index, Mul, Neg, Not,
+    Rem, Sub, SubAssign,
+};
+
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
+pub struct Simd<T, const N: usize>([T; N]);
+
+/// One `f64` lane, standing in for `std::simd::f64x1`.
+#[allow(non_camel_case_types)]
+pub type f64x1 = Simd<f64, 1>;
+/// One `u32` lane, standing in for `std::simd::u32x1`.
+#[allow(non_camel_case_types)]
+pub type u32x1 = Simd<u32, 1>;
+
+impl<T: Copy, const N: usize> Simd<T, N> {
+    pub fn splat(value: T) -> Simd<T, N> {
+        Simd([value; N])
+    }
+
+    pub fn from_array(array: [T; N]) -> Simd<T, N> {
+        Simd(array)
+    }
+
+    pub fn to_array(self) -> [T; N] {
+        self.0
+    }
+
+    fn map(self, f: impl Fn(T) -> T) -> Simd<T, N> {
+        Simd(self.0.map(f))
+    }
+
+    fn zip(self, other: Simd<T, N>, f: impl Fn(T, T) -> T) -> Simd<T, N> {
+        Simd(std::array::from_fn(|lane| f(self.0[lane], other.0[lane])))
+    }
+
+    fn compare<M>(self, other: Simd<T, N>, f: impl Fn(T, T) -> bool) -> Mask<M, N> {
+        Mask {
+            lanes: std::array::from_fn(|lane| f(self.0[lane], other.0[lane])),
+            element: PhantomData,
+        }
+    }
+}
+
+impl<T, const N: usize> Index<usize> for Simd<T, N> {
+    type Output = T;
+
+    fn index(&self, lane: usize) -> &T {
+        &self.0[lane]
+    }
+}
+
+macro_rules! lanewise {
+    ($trait:ident, $method:ident, $assign_trait:ident, $assign_method:ident) => {
+        impl<T: Copy + $trait<Output = T>, const N: usize> $trait for Simd<T, N> {
+            type Output = Simd<T, N>;
+
+            fn $method(self, other: Simd<T, N>) -> Simd<T, N> {
+                self.zip(other, |a, b| a.$method(b))
+            }
+        }
+
+        impl<T: Copy + $trait<Output = T>, const N: usize> $assign_trait for Simd<T, N> {
+            fn $assign_method(&mut self, other: Simd<T, N>) {
+                *self = self.$method(other);
+            }
+        }
+
+        impl<T: Copy + $trait<Output = T>, const N: usize> $assign_trait<&Simd<T, N>>
+            for Simd<T, N>
+        {
+            fn $assign_method(&mut self, other: &Simd<T, N>) {
+                *self = self.$method(*other);
+            }
+        }
+    };
+}
+
+lanewise!(Add, add, AddAssign, add_assign);
+lanewise!(Sub, sub, SubAssign, sub_assign);
+lanewise!(Div, div, DivAssign, div_assign);
+
+impl<T: Copy + Mul<Output = T>, const N: usize> Mul for Simd<T, N> {
+    type Output = Simd<T, N>;
+
+    fn mul(self, other: Simd<T, N>) -> Simd<T, N> {
+        self.zip(other, |a, b| a * b)
+    }
+}
+
+impl<T: Copy + Rem<Output = T>, const N: usize> Rem for Simd<T, N> {
+    type Output = Simd<T, N>;
+
+    fn rem(self, other: Simd<T, N>) -> Simd<T, N> {
+        self.zip(other, |a, b| a % b)
+    }
+}
+
+impl<const N: usize> Neg for Simd<f64, N> {
+    type Output = Simd<f64, N>;
+
+    fn neg(self) -> Simd<f64, N> {
+        self.map(|a| -a)
+    }
+}
+
+pub trait SimdFloat {
+    fn abs(self) -> Self;
+    fn simd_min(self, other: Self) -> Self;
+    fn simd_max(self, other: Self) -> Self;
+}
+
+impl<const N: usize> SimdFloat for Simd<f64, N> {
+    fn abs(self) -> Self {
+        self.map(f64::abs)
+    }
+
+    fn simd_min(self, other: Self) -> Self {
+        self.zip(other, f64::min)
+    }
+
+    fn simd_max(self, other: Self) -> Self {
+        self.zip(other, f64::max)
+    }
+}
+
+pub trait SimdPartialOrd {
+    type Mask;
+
+    fn simd_lt(self, other: Self) -> Self::Mask;
+    fn simd_le(self, other: Self) -> Self::Mask;
+}
+
+impl<const N: usize> SimdPartialOrd for Simd<f64, N> {
+    type Mask = Mask<i64, N>;
+
+    fn simd_lt(self, other: Self) -> Self::Mask {
+        self.compare(other, |a, b| a < b)
+    }
+
+    fn simd_le(self, other: Self) -> Self::Mask {
+        self.compare(other, |a, b| a <= b)
+    }
+}
+
+impl<const N: usize> SimdPartialOrd for Simd<u32, N> {
+    type Mask = Mask<i32, N>;
+
+    fn simd_lt(self, other: Self) -> Self::Mask {
+        self.compare(other, |a, b| a < b)
+    }
+
+    fn simd_le(self, other: Self) -> Self::Mask {
+        self.compare(other, |a, b| a <= b)
+    }
+}
+
+// The element type only mirrors std::simd, where it sets the width of each lane's mask
+#[derive(PartialEq, Debug)]
+pub struct Mask<T, const N: usize> {
+    lanes: [bool; N],
+    element: PhantomData<T>,
+}
+
+impl<T, const N: usize> Clone for Mask<T, N> {
+    fn clone(&self) -> Mask<T, N> {
+        *self
+    }
+}
+
+impl<T, const N: usize> Copy for Mask<T, N> {}
+
+impl<T, const N: usize> Mask<T, N> {
+    pub fn splat(value: bool) -> Mask<T, N> {
+        Mask {
+            lanes: [value; N],
+            element: PhantomData,
+        }
+    }
+
+    pub fn cast<U>(self) -> Mask<U, N> {
+        Mask {
+            lanes: self.lanes,
+            element: PhantomData,
+        }
+    }
+
+    pub fn any(self) -> bool {
+        self.lanes.iter().any(|lane| *lane)
+    }
+
+    #[cfg(test)]
+    pub fn all(self) -> bool {
+        self.lanes.iter().all(|lane| *lane)
+    }
+
+    fn zip(self, other: Mask<T, N>, f: impl Fn(bool, bool) -> bool) -> Mask<T, N> {
+        Mask {
+            lanes: std::array::from_fn(|lane| f(self.lanes[lane], other.lanes[lane])),
+            element: PhantomData,
+        }
+    }
+}
+
+impl<T, const N: usize> Not for Mask<T, N> {
+    type Output = Mask<T, N>;
+
+    fn not(self) -> Mask<T, N> {
+        Mask {
+            lanes: self.lanes.map(|lane| !lane),
+            element: PhantomData,
+        }
+    }
+}
+
+impl<T, const N: usize> BitAnd for Mask<T, N> {
+    type Output = Mask<T, N>;
+
+    fn bitand(self, other: Mask<T, N>) -> Mask<T, N> {
+        self.zip(other, |a, b| a && b)
+    }
+}
+
+impl<T, const N: usize> BitOr for Mask<T, N> {
+    type Output = Mask<T, N>;
+
+    fn bitor(self, other: Mask<T, N>) -> Mask<T, N> {
+        self.zip(other, |a, b| a || b)
+    }
+}
+
+impl<T, const N: usize> BitAndAssign for Mask<T, N> {
+    fn bitand_assign(&mut self, other: Mask<T, N>) {
+        *self = *self & other;
+    }
+}
+
+impl<T, const N: usize> BitOrAssign for Mask<T, N> {
+    fn bitor_assign(&mut self, other: Mask<T, N>) {
+        *self = *self | other;
+    }
+}
+
+pub trait Select<T> {
+    fn select(self, true_values: T, false_values: T) -> T;
+}
+
+impl<const N: usize> Select<Simd<u32, N>> for Mask<i32, N> {
+    fn select(self, true_values: Simd<u32, N>, false_values: Simd<u32, N>) -> Simd<u32, N> {
+        Simd(std::array::from_fn(|lane| {
+            if self.lanes[lane] {
+                true_values.0[lane]
+            } else {
+                false_values.0[lane]
+            }
+        }))
+    }
+}
+
+impl<const N: usize> Select<Simd<f64, N>> for Mask<i64, N> {
+    fn select(self, true_values: Simd<f64, N>, false_values: Simd<f64, N>) -> Simd<f64, N> {
+        Simd(std::array::from_fn(|lane| {
+            if self.lanes[lane] {
+                true_values.0[lane]
+            } else {
+                false_values.0[lane]
+            }
+        }))
+    }
+}
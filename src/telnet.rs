@@ -0,0 +1,542 @@
+// The serve subcommand, which runs a viewer for each telnet client on a thread of its own.
+// SSH is left to sshd, as the README describes, rather than built in.
+
+use crate::app::{App, AppError, MAX_SIZE};
+use crate::terminal::{enter_terminal, write_restore, Input};
+
+use std::io::Write;
+
+pub(crate) const SERVE_ADDRESS: &str = "127.0.0.1:2323";
+// How long a new client gets to report its window size before 80x24 is assumed
+const WINDOW_SIZE_WAIT: std::time::Duration = std::time::Duration::from_secs(1);
+
+const TELNET_IAC: u8 = 255;
+const TELNET_SB: u8 = 250;
+const TELNET_SE: u8 = 240;
+const TELNET_NAWS: u8 = 31;
+// Asks the client to send each key as it is pressed without echoing it (the server will
+// echo and won't wait for go-aheads), and to report its window size (DO NAWS)
+const TELNET_SETUP: [u8; 9] = [255, 251, 1, 255, 251, 3, 255, 253, 31];
+// Telnet commands and terminal input still waiting for their end are dropped past these
+// lengths, so a client can't make them grow without limit
+const MAX_TELNET_COMMAND: usize = 64;
+const MAX_PENDING_INPUT: usize = 1 << 16;
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+// Clients past this many are told the server is full
+const MAX_CLIENTS: usize = 16;
+
+// Turns what a telnet client sends into events: telnet commands are taken out, window size
+// reports become resizes, and terminal escape sequences become keys, mouse events and pastes
+#[derive(Default)]
+struct TelnetDecoder {
+    // Terminal input that ends partway through a sequence or character
+    pending: Vec<u8>,
+    // A telnet command cut off at the end of the last read
+    command: Vec<u8>,
+    // Whether the rest of a paste too long to keep is being skipped
+    skipping_paste: bool,
+}
+
+impl TelnetDecoder {
+    fn decode(&mut self, bytes: &[u8]) -> Vec<crossterm::event::Event> {
+        let mut events = Vec::new();
+        for &byte in bytes {
+            if !self.command.is_empty() {
+                self.command.push(byte);
+                if let Some(event) = self.finish_command() {
+                    events.extend(event);
+                } else if self.command.len() > MAX_TELNET_COMMAND {
+                    // Only the start and the last two bytes are kept, enough to see it end
+                    self.command.drain(2..self.command.len() - 2);
+                }
+            } else if byte == TELNET_IAC {
+                self.command.push(byte);
+            } else {
+                self.pending.push(byte);
+            }
+        }
+
+        let mut start = 0;
+        while start < self.pending.len() {
+            if self.skipping_paste {
+                let rest = &self.pending[start..];
+                match rest.windows(PASTE_END.len()).position(|window| window == PASTE_END) {
+                    Some(end) => {
+                        start += end + PASTE_END.len();
+                        self.skipping_paste = false;
+                        continue;
+                    }
+                    // Keeping what could be the start of the end
+                    None => {
+                        start = self.pending.len().saturating_sub(PASTE_END.len() - 1).max(start);
+                        break;
+                    }
+                }
+            }
+            match parse_terminal_input(&self.pending[start..], bytes.is_empty()) {
+                Some((event, length)) => {
+                    events.extend(event);
+                    start += length;
+                }
+                None => break,
+            }
+        }
+        self.pending.drain(..start);
+        if self.pending.len() > MAX_PENDING_INPUT {
+            // A paste goes along with the rest of it still to come, so none of it is taken
+            // as keys
+            self.skipping_paste = self.pending.starts_with(PASTE_START);
+            self.pending.clear();
+        }
+        events
+    }
+
+    // Handles the telnet command so far, returning None until it is complete
+    fn finish_command(&mut self) -> Option<Option<crossterm::event::Event>> {
+        let command = &self.command;
+        let event = match command[1..] {
+            // A doubled IAC is a 255 byte of data, which can't be part of UTF-8
+            [TELNET_IAC] => None,
+            // Option negotiation takes one more byte, which isn't needed
+            [251..=254] => return None,
+            [251..=254, _] => None,
+            [TELNET_SB, ..] => {
+                if !command.ends_with(&[TELNET_IAC, TELNET_SE]) {
+                    return None;
+                }
+                // Sizes are sent as 16-bit numbers, with any 255 byte doubled
+                let data = &command[2..command.len() - 2];
+                let mut unescaped = Vec::new();
+                let mut bytes = data.iter();
+                while let Some(&byte) = bytes.next() {
+                    if byte == TELNET_IAC {
+                        bytes.next();
+                    }
+                    unescaped.push(byte);
+                }
+                // Clients pick their own size, so it is held to what --size takes
+                match unescaped[..] {
+                    [TELNET_NAWS, w1, w0, h1, h0] => Some(crossterm::event::Event::Resize(
+                        u16::from_be_bytes([w1, w0]).clamp(1, MAX_SIZE),
+                        u16::from_be_bytes([h1, h0]).clamp(1, MAX_SIZE),
+                    )),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        self.command.clear();
+        Some(event)
+    }
+}
+
+// The event at the start of `bytes` and how many bytes it took, or None if they end partway
+// through one. A lone escape is only taken as the Esc key at the end of a read
+fn parse_terminal_input(
+    bytes: &[u8],
+    end_of_read: bool,
+) -> Option<(Option<crossterm::event::Event>, usize)> {
+    use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+    let key = |code, modifiers| Some(Event::Key(KeyEvent::new(code, modifiers)));
+    match bytes {
+        // Telnet sends Enter as CR LF or CR NUL
+        [b'\r', b'\n' | b'\0', ..] => Some((key(KeyCode::Enter, KeyModifiers::NONE), 2)),
+        [b'\r' | b'\n', ..] => Some((key(KeyCode::Enter, KeyModifiers::NONE), 1)),
+        [b'\t', ..] => Some((key(KeyCode::Tab, KeyModifiers::NONE), 1)),
+        [0x7f | 0x08, ..] => Some((key(KeyCode::Backspace, KeyModifiers::NONE), 1)),
+        [0x1b] if end_of_read => Some((key(KeyCode::Esc, KeyModifiers::NONE), 1)),
+        [0x1b] | [0x1b, b'[' | b'O'] => None,
+        [0x1b, b'[', rest @ ..] => {
+            let end = rest.iter().position(|byte| (0x40..=0x7e).contains(byte))?;
+            let event = parse_csi(&rest[..end], rest[end], &rest[end + 1..]);
+            match event {
+                CsiEvent::Event(event) => Some((event, end + 3)),
+                CsiEvent::Paste(text, length) => Some((Some(Event::Paste(text)), end + 3 + length)),
+                CsiEvent::Incomplete => None,
+            }
+        }
+        [0x1b, b'O', code, ..] => {
+            let code = match code {
+                b'A' => KeyCode::Up,
+                b'B' => KeyCode::Down,
+                b'C' => KeyCode::Right,
+                b'D' => KeyCode::Left,
+                b'H' => KeyCode::Home,
+                b'F' => KeyCode::End,
+                b'P'..=b'S' => KeyCode::F(code - b'P' + 1),
+                _ => return Some((None, 3)),
+            };
+            Some((key(code, KeyModifiers::NONE), 3))
+        }
+        [0x1b, 0x1b, ..] => Some((key(KeyCode::Esc, KeyModifiers::NONE), 1)),
+        [0x1b, rest @ ..] => {
+            let (event, length) = parse_terminal_input(rest, end_of_read)?;
+            let event = match event {
+                Some(Event::Key(mut event)) => {
+                    event.modifiers |= KeyModifiers::ALT;
+                    Some(Event::Key(event))
+                }
+                event => event,
+            };
+            Some((event, length + 1))
+        }
+        [0, ..] => Some((None, 1)),
+        [byte @ 1..=26, ..] => Some((
+            key(
+                KeyCode::Char((b'a' + byte - 1) as char),
+                KeyModifiers::CONTROL,
+            ),
+            1,
+        )),
+        [byte, ..] if *byte < 0x20 => Some((None, 1)),
+        _ => {
+            // A character, which may still be arriving
+            let length = match bytes[0] {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => 1,
+            };
+            if bytes.len() < length {
+                return None;
+            }
+            match std::str::from_utf8(&bytes[..length]) {
+                Ok(text) => Some((
+                    key(KeyCode::Char(text.chars().next()?), KeyModifiers::NONE),
+                    length,
+                )),
+                // A byte that can't start a character is skipped on its own
+                Err(_) => Some((None, 1)),
+            }
+        }
+    }
+}
+
+enum CsiEvent {
+    Event(Option<crossterm::event::Event>),
+    // A bracketed paste, and how many bytes after the opening sequence it took
+    Paste(String, usize),
+    Incomplete,
+}
+
+fn parse_csi(parameters: &[u8], final_byte: u8, rest: &[u8]) -> CsiEvent {
+    use crossterm::event::{
+        Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    };
+    let text = String::from_utf8_lossy(parameters);
+    if final_byte == b'~' && text == "200" {
+        return match rest.windows(PASTE_END.len()).position(|window| window == PASTE_END) {
+            Some(end) => CsiEvent::Paste(
+                String::from_utf8_lossy(&rest[..end]).into_owned(),
+                end + PASTE_END.len(),
+            ),
+            None => CsiEvent::Incomplete,
+        };
+    }
+
+    if let Some(mouse) = text.strip_prefix('<') {
+        let numbers: Vec<u16> = mouse.split(';').filter_map(|n| n.parse().ok()).collect();
+        let [button, column, row] = numbers[..] else {
+            return CsiEvent::Event(None);
+        };
+        let pressed = match button & 3 {
+            0 => MouseButton::Left,
+            1 => MouseButton::Middle,
+            _ => MouseButton::Right,
+        };
+        let kind = match (button & 64 != 0, button & 32 != 0, final_byte) {
+            (true, _, _) if button & 1 == 0 => MouseEventKind::ScrollUp,
+            (true, _, _) => MouseEventKind::ScrollDown,
+            (false, true, _) if button & 3 == 3 => MouseEventKind::Moved,
+            (false, true, _) => MouseEventKind::Drag(pressed),
+            (false, false, b'm') => MouseEventKind::Up(pressed),
+            (false, false, _) => MouseEventKind::Down(pressed),
+        };
+        let mut modifiers = KeyModifiers::NONE;
+        for (bit, modifier) in [
+            (4, KeyModifiers::SHIFT),
+            (8, KeyModifiers::ALT),
+            (16, KeyModifiers::CONTROL),
+        ] {
+            if button & bit != 0 {
+                modifiers |= modifier;
+            }
+        }
+        return CsiEvent::Event(Some(Event::Mouse(MouseEvent {
+            kind,
+            column: column.saturating_sub(1),
+            row: row.saturating_sub(1),
+            modifiers,
+        })));
+    }
+
+    // Modifiers come as a second number, one more than a bit set of shift, alt and control
+    let mut numbers = text.split(';').map(|n| n.parse::<u8>().unwrap_or(1));
+    let first = numbers.next().unwrap_or(1);
+    let flags = numbers.next().unwrap_or(1).saturating_sub(1);
+    let mut modifiers = KeyModifiers::NONE;
+    for (bit, modifier) in [
+        (1, KeyModifiers::SHIFT),
+        (2, KeyModifiers::ALT),
+        (4, KeyModifiers::CONTROL),
+    ] {
+        if flags & bit != 0 {
+            modifiers |= modifier;
+        }
+    }
+    let code = match (final_byte, first) {
+        (b'A', _) => KeyCode::Up,
+        (b'B', _) => KeyCode::Down,
+        (b'C', _) => KeyCode::Right,
+        (b'D', _) => KeyCode::Left,
+        (b'H', _) | (b'~', 1 | 7) => KeyCode::Home,
+        (b'F', _) | (b'~', 4 | 8) => KeyCode::End,
+        (b'Z', _) => KeyCode::BackTab,
+        (b'P'..=b'S', _) => KeyCode::F(final_byte - b'P' + 1),
+        (b'~', 2) => KeyCode::Insert,
+        (b'~', 3) => KeyCode::Delete,
+        (b'~', 5) => KeyCode::PageUp,
+        (b'~', 6) => KeyCode::PageDown,
+        (b'~', 11..=15) => KeyCode::F(first - 10),
+        (b'~', 17..=21) => KeyCode::F(first - 11),
+        (b'~', 23 | 24) => KeyCode::F(first - 12),
+        _ => return CsiEvent::Event(None),
+    };
+    CsiEvent::Event(Some(Event::Key(KeyEvent::new(code, modifiers))))
+}
+
+// Events from a client's connection, which a thread reads and decodes
+pub(crate) struct ClientInput {
+    pub(crate) events: std::sync::mpsc::Receiver<crossterm::event::Event>,
+    pub(crate) size: (u16, u16),
+}
+
+impl Input for ClientInput {
+    fn next_event(
+        &mut self,
+        timeout: Option<std::time::Duration>,
+    ) -> std::io::Result<Option<crossterm::event::Event>> {
+        let disconnected = || std::io::Error::from(std::io::ErrorKind::ConnectionAborted);
+        let event = match timeout {
+            Some(timeout) => match self.events.recv_timeout(timeout) {
+                Ok(event) => event,
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => return Ok(None),
+                Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => return Err(disconnected()),
+            },
+            None => self.events.recv().map_err(|_| disconnected())?,
+        };
+        if let crossterm::event::Event::Resize(width, height) = event {
+            self.size = (width, height);
+        }
+        Ok(Some(event))
+    }
+
+    fn size(&self) -> std::io::Result<(u16, u16)> {
+        Ok(self.size)
+    }
+}
+
+fn read_client(
+    mut stream: std::net::TcpStream,
+    sender: std::sync::mpsc::Sender<crossterm::event::Event>,
+) {
+    use std::io::Read;
+    let mut decoder = TelnetDecoder::default();
+    let mut buffer = [0; 4096];
+    // Stops when the client disconnects or its session has ended
+    while let Ok(read @ 1..) = stream.read(&mut buffer) {
+        let events = decoder.decode(&buffer[..read]);
+        let events = events.into_iter().chain(decoder.decode(&[]));
+        if events
+            .map(|event| sender.send(event))
+            .any(|sent| sent.is_err())
+        {
+            break;
+        }
+    }
+}
+
+// Runs a viewer of its own for a client until it quits or disconnects
+fn serve_client(stream: std::net::TcpStream, app: &mut App) -> std::io::Result<()> {
+    stream.set_nodelay(true)?;
+    let mut writer = std::io::BufWriter::new(stream.try_clone()?);
+    writer.write_all(&TELNET_SETUP)?;
+    writer.flush()?;
+    let (sender, events) = std::sync::mpsc::channel();
+    let reader = stream.try_clone()?;
+    std::thread::spawn(move || read_client(reader, sender));
+
+    let mut input = ClientInput {
+        events,
+        size: (80, 24),
+    };
+    // Keys pressed before the size arrived are kept for the viewer
+    let mut early = Vec::new();
+    let waited = std::time::Instant::now();
+    while let Some(remaining) = WINDOW_SIZE_WAIT.checked_sub(waited.elapsed()) {
+        match input.next_event(Some(remaining))? {
+            Some(crossterm::event::Event::Resize(..)) => break,
+            Some(event) => early.push(event),
+            None => break,
+        }
+    }
+
+    enter_terminal(&mut writer)?;
+    for event in early {
+        if !app.handle_event(event) {
+            return write_restore(&mut writer);
+        }
+    }
+    let result = app.run(&mut input, &mut writer);
+    let _ = write_restore(&mut writer);
+    let _ = stream.shutdown(std::net::Shutdown::Both);
+    match result {
+        Err(AppError::Io(error)) if error.kind() == std::io::ErrorKind::ConnectionAborted => Ok(()),
+        Err(AppError::Io(error)) => Err(error),
+        Err(error) => Err(std::io::Error::other(error.to_string())),
+        Ok(()) => Ok(()),
+    }
+}
+
+// Gives every client that connects its own viewer, made by `new_app`, on a thread of its own,
+// up to MAX_CLIENTS at once
+pub(crate) fn serve(
+    address: &str,
+    new_app: impl Fn() -> App + Send + Sync + 'static,
+) -> Result<(), AppError> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    let listener = std::net::TcpListener::bind(address)
+        .map_err(|error| AppError::Options(format!("{}: {}", address, error)))?;
+    println!("listening on {}", listener.local_addr()?);
+    let new_app = std::sync::Arc::new(new_app);
+    let clients = std::sync::Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                eprintln!("{}", error);
+                continue;
+            }
+        };
+        let peer = stream
+            .peer_addr()
+            .map_or("client".to_string(), |peer| peer.to_string());
+        if clients.fetch_add(1, Ordering::SeqCst) >= MAX_CLIENTS {
+            clients.fetch_sub(1, Ordering::SeqCst);
+            eprintln!("{} refused, {} clients already connected", peer, MAX_CLIENTS);
+            let _ = stream.write_all(b"The server is full, try again later.\r\n");
+            continue;
+        }
+        let (new_app, clients) = (new_app.clone(), clients.clone());
+        std::thread::spawn(move || {
+            eprintln!("{} connected", peer);
+            let mut app = new_app();
+            match serve_client(stream, &mut app) {
+                Ok(()) => eprintln!("{} disconnected", peer),
+                Err(error) => eprintln!("{}: {}", peer, error),
+            }
+            clients.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_telnet_decoder() {
+        use crossterm::event::{
+            Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+        };
+        let key = |code, modifiers| Event::Key(KeyEvent::new(code, modifiers));
+        let mut decoder = TelnetDecoder::default();
+        // Negotiation is skipped, and a window size is read even when it is split up
+        assert_eq!(decoder.decode(&[255, 251, 31, 255, 250, 31, 0, 100]), []);
+        assert_eq!(
+            decoder.decode(&[0, 255, 255, 255, 240, b'z']),
+            [
+                Event::Resize(100, 255),
+                key(KeyCode::Char('z'), KeyModifiers::NONE)
+            ]
+        );
+
+        assert_eq!(
+            decoder.decode(b"a\r\0\r\n\x7f\x01\x1bx\x1b[A\x1b[1;5C\x1bOQ\x1b[15~\x1b[Z"),
+            [
+                key(KeyCode::Char('a'), KeyModifiers::NONE),
+                key(KeyCode::Enter, KeyModifiers::NONE),
+                key(KeyCode::Enter, KeyModifiers::NONE),
+                key(KeyCode::Backspace, KeyModifiers::NONE),
+                key(KeyCode::Char('a'), KeyModifiers::CONTROL),
+                key(KeyCode::Char('x'), KeyModifiers::ALT),
+                key(KeyCode::Up, KeyModifiers::NONE),
+                key(KeyCode::Right, KeyModifiers::CONTROL),
+                key(KeyCode::F(2), KeyModifiers::NONE),
+                key(KeyCode::F(5), KeyModifiers::NONE),
+                key(KeyCode::BackTab, KeyModifiers::NONE),
+            ]
+        );
+
+        // Sequences and characters cut off at the end of a read wait for the rest
+        assert_eq!(decoder.decode(b"\x1b[<0;5;3"), []);
+        assert_eq!(
+            decoder.decode("M\x1b[<64;1;1m\u{e9}".as_bytes().split_last().unwrap().1),
+            [
+                Event::Mouse(MouseEvent {
+                    kind: MouseEventKind::Down(MouseButton::Left),
+                    column: 4,
+                    row: 2,
+                    modifiers: KeyModifiers::NONE,
+                }),
+                Event::Mouse(MouseEvent {
+                    kind: MouseEventKind::ScrollUp,
+                    column: 0,
+                    row: 0,
+                    modifiers: KeyModifiers::NONE,
+                }),
+            ]
+        );
+        assert_eq!(
+            decoder.decode(&[0xa9]),
+            [key(KeyCode::Char('\u{e9}'), KeyModifiers::NONE)]
+        );
+        assert_eq!(decoder.decode(b"\x1b[200~-0.5,"), []);
+        assert_eq!(
+            decoder.decode(b"0.1\x1b[201~"),
+            [Event::Paste("-0.5,0.1".to_string())]
+        );
+
+        // A lone escape is only the Esc key once nothing follows it in the same read
+        assert_eq!(decoder.decode(b"\x1b"), []);
+        assert_eq!(decoder.decode(&[]), [key(KeyCode::Esc, KeyModifiers::NONE)]);
+
+        // Window sizes stay within the frames the viewer takes
+        assert_eq!(
+            decoder.decode(&[255, 250, 31, 255, 255, 255, 255, 0, 0, 255, 240]),
+            [Event::Resize(MAX_SIZE, 1)]
+        );
+
+        // A subnegotiation that never ends is kept short, and dropped once it does
+        assert_eq!(decoder.decode(&[255, 250, 31]), []);
+        assert_eq!(decoder.decode(&[1; 100000]), []);
+        assert!(decoder.command.len() <= MAX_TELNET_COMMAND);
+        assert_eq!(
+            decoder.decode(&[255, 240, b'z']),
+            [key(KeyCode::Char('z'), KeyModifiers::NONE)]
+        );
+
+        // And so is a sequence, or a paste along with the rest of it
+        assert_eq!(decoder.decode(&[b"\x1b[".as_slice(), &[b'1'; 70000]].concat()), []);
+        assert!(decoder.pending.is_empty());
+        assert_eq!(decoder.decode(&[b"\x1b[200~".as_slice(), &[b'x'; 70000]].concat()), []);
+        assert!(decoder.pending.is_empty());
+        assert_eq!(decoder.decode(b"xx\x1b[20"), []);
+        assert_eq!(
+            decoder.decode(b"1~q"),
+            [key(KeyCode::Char('q'), KeyModifiers::NONE)]
+        );
+    }
+}
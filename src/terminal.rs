@@ -0,0 +1,661 @@
+// Setting the terminal up and putting it back, recording it, and drawing on consoles with
+// fewer colors and characters.
+
+use crate::app::TITLE;
+
+use std::io::Write;
+
+fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut output = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let group = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+
+        for index in 0..4 {
+            if index <= chunk.len() {
+                output.push(ALPHABET[(group >> (18 - index * 6)) as usize & 63] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+pub(crate) fn copy_to_clipboard(text: &str) -> String {
+    // OSC 52 asks the terminal to set the clipboard, which also works over SSH
+    format!("\x1b]52;c;{}\x07", base64_encode(text.as_bytes()))
+}
+
+// The escape sequences write_restore produces, for signal handlers that can't use crossterm
+#[cfg(unix)]
+const RESTORE_SEQUENCE: &[u8] = b"\x1b[2J\x1b[?25h\x1b[?12h\x1b[?2004l\x1b[?1006l\x1b[?1015l\x1b[?1003l\x1b[?1002l\x1b[?1000l\x1b[?1049l\x1b[0m";
+
+// The terminal settings from before raw mode was enabled
+#[cfg(unix)]
+static ORIGINAL_TERMIOS: std::sync::OnceLock<libc::termios> = std::sync::OnceLock::new();
+
+pub(crate) fn write_restore(writer: &mut impl Write) -> std::io::Result<()> {
+    crossterm::execute!(
+        writer,
+        crossterm::terminal::Clear(crossterm::terminal::ClearType::All),
+        crossterm::cursor::Show,
+        crossterm::cursor::EnableBlinking,
+        crossterm::event::DisableBracketedPaste,
+        crossterm::event::DisableMouseCapture,
+        crossterm::terminal::LeaveAlternateScreen,
+        crossterm::style::ResetColor,
+    )
+}
+
+pub(crate) fn enter_terminal(writer: &mut impl Write) -> std::io::Result<()> {
+    crossterm::execute!(
+        writer,
+        crossterm::terminal::SetTitle(TITLE),
+        crossterm::terminal::EnterAlternateScreen,
+        crossterm::event::EnableMouseCapture,
+        crossterm::event::EnableBracketedPaste,
+        crossterm::cursor::DisableBlinking,
+        crossterm::cursor::Hide,
+        crossterm::terminal::Clear(crossterm::terminal::ClearType::All),
+        crossterm::cursor::MoveTo(0, 0)
+    )
+}
+
+pub(crate) fn restore_terminal(writer: &mut impl Write) -> std::io::Result<()> {
+    write_restore(writer)?;
+    crossterm::terminal::disable_raw_mode()
+}
+
+// An asciinema v2 recording of what is written to the terminal
+pub(crate) struct Cast {
+    file: std::io::BufWriter<std::fs::File>,
+    started: std::time::Instant,
+    size: (u16, u16),
+    // Output since the last event, which may end partway through a character
+    pending: Vec<u8>,
+}
+
+impl Cast {
+    pub(crate) fn create(path: &std::path::Path, size: (u16, u16)) -> std::io::Result<Cast> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let header = serde_json::json!({
+            "version": 2,
+            "width": size.0,
+            "height": size.1,
+            "timestamp": timestamp,
+            "env": {"TERM": std::env::var("TERM").unwrap_or_default()},
+        });
+        writeln!(file, "{}", header)?;
+        Ok(Cast {
+            file,
+            started: std::time::Instant::now(),
+            size,
+            pending: Vec::new(),
+        })
+    }
+
+    // Writes the output so far as one event, after a resize event if the terminal changed
+    fn event(&mut self, size: Option<(u16, u16)>) -> std::io::Result<()> {
+        let time = self.started.elapsed().as_micros() as f64 / 1e6;
+        if let Some(size) = size.filter(|size| *size != self.size) {
+            let resize = format!("{}x{}", size.0, size.1);
+            writeln!(self.file, "{}", serde_json::json!([time, "r", resize]))?;
+            self.size = size;
+        }
+        // A character cut off at the end waits for the rest of it
+        let end = match std::str::from_utf8(&self.pending) {
+            Err(error) if error.error_len().is_none() => error.valid_up_to(),
+            _ => self.pending.len(),
+        };
+        if end > 0 {
+            let text = String::from_utf8_lossy(&self.pending[..end]);
+            writeln!(self.file, "{}", serde_json::json!([time, "o", text]))?;
+            self.pending.drain(..end);
+        }
+        self.file.flush()
+    }
+}
+
+// Passes output on to the terminal, recording each flush of it, which is one frame, when
+// a cast is being made
+pub(crate) struct Recorder<W: Write> {
+    pub(crate) terminal: W,
+    pub(crate) cast: Option<Cast>,
+}
+
+impl<W: Write> Write for Recorder<W> {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        let written = self.terminal.write(bytes)?;
+        if let Some(cast) = &mut self.cast {
+            cast.pending.extend_from_slice(&bytes[..written]);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.terminal.flush()?;
+        match &mut self.cast {
+            Some(cast) => cast.event(crossterm::terminal::size().ok()),
+            None => Ok(()),
+        }
+    }
+}
+
+// What the terminal can show: everything, or for old consoles 16 colors and either the
+// characters of code page 437 or only ASCII
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Console {
+    Modern,
+    Legacy,
+    Ascii,
+}
+
+pub(crate) const CONSOLE_NAMES: [&str; 4] = ["Auto", "Modern", "Legacy", "ASCII"];
+
+// The console named by an index into CONSOLE_NAMES, with Auto worked out from where the
+// viewer is running
+pub(crate) fn console_for(index: usize) -> Console {
+    match index {
+        1 => Console::Modern,
+        2 => Console::Legacy,
+        3 => Console::Ascii,
+        _ => detect_console(cfg!(windows), |name| std::env::var(name).ok()),
+    }
+}
+
+fn detect_console(windows: bool, variable: impl Fn(&str) -> Option<String>) -> Console {
+    // Windows Terminal and the other terminals for Windows announce themselves; the console
+    // host that opens otherwise may not have the colors or the font for the frame
+    let terminal = ["WT_SESSION", "TERM_PROGRAM", "ConEmuANSI"]
+        .iter()
+        .any(|name| variable(name).is_some());
+    // The Linux virtual console also has 16 colors and lacks the quadrant blocks
+    if windows && !terminal || variable("TERM").as_deref() == Some("linux") {
+        Console::Legacy
+    } else {
+        Console::Modern
+    }
+}
+
+// The colors of the classic console palette, in the order of their escape codes
+const CONSOLE_COLORS: [[u8; 3]; 16] = [
+    [0, 0, 0],
+    [128, 0, 0],
+    [0, 128, 0],
+    [128, 128, 0],
+    [0, 0, 128],
+    [128, 0, 128],
+    [0, 128, 128],
+    [192, 192, 192],
+    [128, 128, 128],
+    [255, 0, 0],
+    [0, 255, 0],
+    [255, 255, 0],
+    [0, 0, 255],
+    [255, 0, 255],
+    [0, 255, 255],
+    [255, 255, 255],
+];
+
+fn nearest_console_color(color: [u8; 3]) -> u8 {
+    let distance = |other: &[u8; 3]| -> u32 {
+        (0..3)
+            .map(|channel| (color[channel] as i32 - other[channel] as i32).pow(2) as u32)
+            .sum()
+    };
+    (0..16)
+        .min_by_key(|index| distance(&CONSOLE_COLORS[*index as usize]))
+        .unwrap()
+}
+
+// The escape code selecting one of the 16 colors
+fn console_color_code(index: u8, background: bool) -> u8 {
+    let base = if index < 8 { 30 } else { 82 };
+    base + index + if background { 10 } else { 0 }
+}
+
+// The stand-in a console without the character has for it
+fn console_char(character: char, console: Console) -> char {
+    if console == Console::Modern {
+        return character;
+    }
+    let legacy = match character {
+        '▖' | '▘' | '▝' | '▗' => '░',
+        '▚' | '▞' => '▒',
+        '▙' | '▟' | '▛' | '▜' => '▓',
+        '┊' => '│',
+        '┈' => '─',
+        '▶' => '►',
+        '◀' => '◄',
+        // Braille dots are shaded by how many of the eight are on
+        '\u{2801}'..='\u{28ff}' => match (character as u32 - 0x2800).count_ones() {
+            1 | 2 => '░',
+            3..=5 => '▒',
+            _ => '▓',
+        },
+        _ => character,
+    };
+    if console == Console::Legacy || legacy.is_ascii() {
+        return legacy;
+    }
+    match legacy {
+        '░' => '.',
+        '▒' | '▀' | '▄' | '▌' | '▐' => ':',
+        '▓' => '%',
+        '█' => '#',
+        '─' => '-',
+        '│' => '|',
+        '┌' | '┐' | '└' | '┘' | '┼' => '+',
+        '►' => '>',
+        '◄' => '<',
+        '•' => '*',
+        _ => '?',
+    }
+}
+
+// Passes output on to a terminal, turning the colors in it into the nearest of 16 and the
+// characters into ones the console has, unless it is modern
+pub(crate) struct ConsoleOutput<W: Write> {
+    terminal: W,
+    console: Console,
+    // The end of the output so far when it stops partway through a character or escape
+    // sequence
+    pending: Vec<u8>,
+    // The colors selected, as indices into CONSOLE_COLORS
+    foreground: Option<u8>,
+    background: Option<u8>,
+}
+
+impl<W: Write> ConsoleOutput<W> {
+    pub(crate) fn new(terminal: W, console: Console) -> ConsoleOutput<W> {
+        ConsoleOutput {
+            terminal,
+            console,
+            pending: Vec::new(),
+            foreground: None,
+            background: None,
+        }
+    }
+
+    // Rewrites the parameters of a color escape sequence with 16 colors
+    fn translate_colors(&mut self, parameters: &str, output: &mut Vec<u8>) {
+        let previous = (self.foreground, self.background);
+        let mut only_colors = true;
+        let mut codes = Vec::new();
+        let mut parameters = parameters.split(';');
+        while let Some(parameter) = parameters.next() {
+            let code = parameter.parse::<u8>().unwrap_or(0);
+            let color = match code {
+                38 | 48 => {
+                    let color = match parameters.next() {
+                        Some("2") => {
+                            let mut channel = || parameters.next()?.parse::<u8>().ok();
+                            match (channel(), channel(), channel()) {
+                                (Some(r), Some(g), Some(b)) => Some([r, g, b]),
+                                _ => None,
+                            }
+                        }
+                        Some("5") => parameters
+                            .next()
+                            .and_then(|index| index.parse::<u8>().ok())
+                            .map(|index| match index {
+                                0..=15 => CONSOLE_COLORS[index as usize],
+                                16..=231 => {
+                                    let level =
+                                        |value: u8| if value == 0 { 0 } else { value * 40 + 55 };
+                                    let index = index - 16;
+                                    [level(index / 36), level(index / 6 % 6), level(index % 6)]
+                                }
+                                _ => [(index - 232) * 10 + 8; 3],
+                            }),
+                        _ => None,
+                    };
+                    match color {
+                        Some(color) => Some((code == 48, nearest_console_color(color))),
+                        None => continue,
+                    }
+                }
+                _ => None,
+            };
+            match (color, code) {
+                (Some((background, index)), _) => {
+                    if background {
+                        self.background = Some(index);
+                    } else {
+                        self.foreground = Some(index);
+                    }
+                    codes.push(console_color_code(index, background).to_string());
+                }
+                (None, 0) => {
+                    only_colors = false;
+                    (self.foreground, self.background) = (None, None);
+                    codes.push(parameter.to_string());
+                }
+                (None, code) => {
+                    only_colors = false;
+                    match code {
+                        30..=37 => self.foreground = Some(code - 30),
+                        90..=97 => self.foreground = Some(code - 82),
+                        39 => self.foreground = None,
+                        40..=47 => self.background = Some(code - 40),
+                        100..=107 => self.background = Some(code - 92),
+                        49 => self.background = None,
+                        _ => {}
+                    }
+                    codes.push(parameter.to_string());
+                }
+            }
+        }
+        // Colors that come out the same as the last ones needn't be sent again
+        if only_colors && (self.foreground, self.background) == previous {
+            return;
+        }
+        output.extend_from_slice(format!("\x1b[{}m", codes.join(";")).as_bytes());
+    }
+
+    // Translates what it can of `bytes`, returning how much of it was used; the rest is the
+    // start of a character or escape sequence that hasn't all arrived
+    fn translate(&mut self, bytes: &[u8], output: &mut Vec<u8>) -> usize {
+        let mut index = 0;
+        while index < bytes.len() {
+            let byte = bytes[index];
+            if byte == 0x1b {
+                let rest = &bytes[index + 1..];
+                let length = match rest.first() {
+                    None => return index,
+                    // Control sequences run up to a final byte from @ to ~
+                    Some(b'[') => match rest[1..]
+                        .iter()
+                        .position(|byte| (0x40..=0x7e).contains(byte))
+                    {
+                        Some(end) => {
+                            let parameters = &rest[1..end + 1];
+                            if rest[end + 1] == b'm' {
+                                let parameters = String::from_utf8_lossy(parameters).into_owned();
+                                self.translate_colors(&parameters, output);
+                            } else {
+                                output.extend_from_slice(&bytes[index..index + end + 3]);
+                            }
+                            index += end + 3;
+                            continue;
+                        }
+                        None => return index,
+                    },
+                    // Titles and other commands end with a bell or ESC \
+                    Some(b']') => match rest.iter().enumerate().position(|(at, byte)| {
+                        *byte == 0x07 || *byte == b'\\' && rest[at - 1] == 0x1b
+                    }) {
+                        Some(end) => end + 2,
+                        None => return index,
+                    },
+                    Some(_) => 2,
+                };
+                output.extend_from_slice(&bytes[index..index + length]);
+                index += length;
+            } else if byte.is_ascii() {
+                output.push(byte);
+                index += 1;
+            } else {
+                let length = match byte {
+                    0xc0..=0xdf => 2,
+                    0xe0..=0xef => 3,
+                    0xf0..=0xf7 => 4,
+                    _ => 1,
+                };
+                if index + length > bytes.len() {
+                    return index;
+                }
+                match std::str::from_utf8(&bytes[index..index + length]) {
+                    Ok(text) => {
+                        for character in text.chars() {
+                            match (character, self.console, self.foreground) {
+                                // A full block of ASCII can't be drawn in its color, but a
+                                // space on it as the background can
+                                ('█', Console::Ascii, Some(foreground)) => {
+                                    let restore = match self.background {
+                                        Some(background) => console_color_code(background, true),
+                                        None => 49,
+                                    };
+                                    let block = format!(
+                                        "\x1b[{}m \x1b[{}m",
+                                        console_color_code(foreground, true),
+                                        restore
+                                    );
+                                    output.extend_from_slice(block.as_bytes());
+                                }
+                                (character, console, _) => {
+                                    let character = console_char(character, console);
+                                    let mut buffer = [0; 4];
+                                    output.extend_from_slice(
+                                        character.encode_utf8(&mut buffer).as_bytes(),
+                                    );
+                                }
+                            }
+                        }
+                        index += length;
+                    }
+                    Err(_) => {
+                        output.push(byte);
+                        index += 1;
+                    }
+                }
+            }
+        }
+        index
+    }
+}
+
+impl<W: Write> Write for ConsoleOutput<W> {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        if self.console == Console::Modern {
+            return self.terminal.write(bytes);
+        }
+        let mut input = std::mem::take(&mut self.pending);
+        input.extend_from_slice(bytes);
+        let mut output = Vec::with_capacity(input.len());
+        let used = self.translate(&input, &mut output);
+        self.pending = input.split_off(used);
+        self.terminal.write_all(&output)?;
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        // Anything still pending was cut short, so goes on as it is
+        self.terminal
+            .write_all(&std::mem::take(&mut self.pending))?;
+        self.terminal.flush()
+    }
+}
+
+// Where the viewer's events and terminal size come from: the terminal it runs in, or a client
+// of `serve`
+pub(crate) trait Input {
+    // The next event, once one comes or `timeout` runs out, waiting for as long as it takes
+    // without one
+    fn next_event(
+        &mut self,
+        timeout: Option<std::time::Duration>,
+    ) -> std::io::Result<Option<crossterm::event::Event>>;
+    fn size(&self) -> std::io::Result<(u16, u16)>;
+}
+
+pub(crate) struct TerminalInput;
+
+impl Input for TerminalInput {
+    fn next_event(
+        &mut self,
+        timeout: Option<std::time::Duration>,
+    ) -> std::io::Result<Option<crossterm::event::Event>> {
+        match timeout {
+            Some(timeout) if !crossterm::event::poll(timeout)? => Ok(None),
+            _ => crossterm::event::read().map(Some),
+        }
+    }
+
+    fn size(&self) -> std::io::Result<(u16, u16)> {
+        crossterm::terminal::size()
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn handle_signal(signal: libc::c_int) {
+    // Only async-signal-safe calls are allowed here, so the terminal is restored by hand
+    // Safety: write, tcsetattr, signal and raise are all async-signal-safe
+    unsafe {
+        libc::write(
+            libc::STDOUT_FILENO,
+            RESTORE_SEQUENCE.as_ptr().cast(),
+            RESTORE_SEQUENCE.len(),
+        );
+        if let Some(termios) = ORIGINAL_TERMIOS.get() {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, termios);
+        }
+        // Die from the signal itself so the parent sees why
+        libc::signal(signal, libc::SIG_DFL);
+        libc::raise(signal);
+    }
+}
+
+// Put the terminal back if the program panics or is killed while the viewer is up
+pub(crate) fn install_terminal_guards() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal(&mut std::io::stdout());
+        default_hook(info);
+    }));
+
+    #[cfg(unix)]
+    // Safety: tcgetattr only fills in the termios it is given, and handle_signal only
+    // makes async-signal-safe calls
+    unsafe {
+        let mut termios = std::mem::zeroed();
+        if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) == 0 {
+            let _ = ORIGINAL_TERMIOS.set(termios);
+        }
+        let handler = handle_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+            libc::signal(signal, handler);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_console() {
+        let variables = |names: &'static [&'static str]| {
+            move |name: &str| names.contains(&name).then(|| "linux".to_string())
+        };
+        assert_eq!(detect_console(true, variables(&[])), Console::Legacy);
+        assert_eq!(detect_console(true, variables(&["WT_SESSION"])), Console::Modern);
+        assert_eq!(detect_console(false, variables(&[])), Console::Modern);
+        assert_eq!(detect_console(false, variables(&["TERM"])), Console::Legacy);
+    }
+
+    #[test]
+    fn test_console_output() {
+        let translate = |console: Console, chunks: &[&[u8]]| {
+            let mut output = ConsoleOutput::new(Vec::new(), console);
+            for chunk in chunks {
+                output.write_all(chunk).unwrap();
+            }
+            output.flush().unwrap();
+            String::from_utf8(output.terminal).unwrap()
+        };
+
+        let text = "\x1b[38;2;250;10;10m\x1b[48;2;0;0;90m▚\x1b[38;2;255;0;0m█\x1b[0m┈";
+        assert_eq!(
+            translate(Console::Modern, &[text.as_bytes()]),
+            text.to_string()
+        );
+        // The second red is the same of the 16 colors, so isn't sent again
+        assert_eq!(
+            translate(Console::Legacy, &[text.as_bytes()]),
+            "\x1b[91m\x1b[44m▒█\x1b[0m─"
+        );
+        assert_eq!(
+            translate(Console::Ascii, &[text.as_bytes()]),
+            "\x1b[91m\x1b[44m:\x1b[101m \x1b[44m\x1b[0m-"
+        );
+
+        // Characters and escape sequences split between writes come out whole
+        let bytes = text.as_bytes();
+        let split: Vec<&[u8]> = bytes.chunks(3).collect();
+        assert_eq!(
+            translate(Console::Legacy, &split),
+            "\x1b[91m\x1b[44m▒█\x1b[0m─"
+        );
+        // Other sequences pass through
+        assert_eq!(
+            translate(Console::Legacy, &[b"\x1b]0;title\x07\x1b[2J\x1b[1;4H\x1b[1;38;5;196m"]),
+            "\x1b]0;title\x07\x1b[2J\x1b[1;4H\x1b[1;91m"
+        );
+        assert_eq!(translate(Console::Legacy, &["⠁⠛⣿".as_bytes()]), "░▒▓");
+    }
+
+    #[test]
+    fn test_recorder() {
+        let path = std::env::temp_dir().join(format!("session-{}.cast", std::process::id()));
+        let mut recorder = Recorder {
+            terminal: Vec::new(),
+            cast: Some(Cast::create(&path, (80, 24)).unwrap()),
+        };
+        // The second character is split across two frames
+        let text = "frame \u{2580}\u{1b}[0m";
+        let split = text.len() - 5;
+        // Events are made by hand, since flushing reads the size of the real terminal
+        let frame = |recorder: &mut Recorder<Vec<u8>>, bytes, size| {
+            recorder.write_all(bytes).unwrap();
+            recorder.cast.as_mut().unwrap().event(size).unwrap();
+        };
+        frame(&mut recorder, &text.as_bytes()[..split], None);
+        frame(&mut recorder, &text.as_bytes()[split..], Some((100, 30)));
+        assert_eq!(recorder.terminal, text.as_bytes());
+        drop(recorder);
+
+        let cast = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<serde_json::Value> = cast
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["version"], 2);
+        assert_eq!(
+            (&lines[0]["width"], &lines[0]["height"]),
+            (&80.into(), &24.into())
+        );
+        assert_eq!(lines[1][1], "o");
+        assert_eq!(lines[1][2], "frame ");
+        assert_eq!(lines[2][1], "r");
+        assert_eq!(lines[2][2], "100x30");
+        assert_eq!(lines[3][2], "\u{2580}\u{1b}[0m");
+        assert!(lines[3][0].as_f64() >= lines[1][0].as_f64());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_restore_sequence() {
+        let mut output = Vec::new();
+        write_restore(&mut output).unwrap();
+        assert_eq!(output, RESTORE_SEQUENCE);
+    }
+
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+    }
+}
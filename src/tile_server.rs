@@ -0,0 +1,323 @@
+// The tiles subcommand, an HTTP server of map tiles for slippy-map viewers.
+
+use crate::app::AppError;
+use crate::cli::parse_name;
+use crate::explore::iterations_for_zoom;
+use crate::{
+    fractal_names, image, iteration_parameters, palette_names, u32x1, ColorTable,
+    FractalParameters, FrameBuffer, Position, TileCache, COLORING_NAMES,
+};
+use std::io::Write;
+
+pub(crate) const TILES_ADDRESS: &str = "127.0.0.1:8080";
+const MAP_TILE_SIZE: u16 = 256;
+// Deeper tiles have edges an f64 can't place exactly
+const MAX_TILE_ZOOM: u32 = 48;
+// Enough map tiles for a screenful
+const MAP_TILE_CACHE_CAPACITY: usize = 64;
+// Request and header lines longer than this are refused, as are requests with more headers
+const MAX_REQUEST_LINE: usize = 8192;
+const MAX_REQUEST_HEADERS: usize = 100;
+
+// A page showing the tiles with Leaflet, which passes its query on to them
+const MAP_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Mandelbrot Term</title>
+<link rel="stylesheet" href="https://unpkg.com/leaflet@1.9.4/dist/leaflet.css">
+<script src="https://unpkg.com/leaflet@1.9.4/dist/leaflet.js"></script>
+<style>html, body, #map { height: 100%; margin: 0; background: #000; }</style>
+</head>
+<body>
+<div id="map"></div>
+<script>
+const map = L.map('map', { crs: L.CRS.Simple, minZoom: 0, maxZoom: 48 }).setView([-128, 128], 1);
+L.tileLayer('/{z}/{x}/{y}.png' + location.search, { noWrap: true, maxZoom: 48 }).addTo(map);
+</script>
+</body>
+</html>
+"#;
+
+// The area of the plane a map tile covers. Zoom 0 is one tile around the whole set, and each
+// zoom splits every tile of the one before into four
+fn map_tile_position(zoom: u32, x: u64, y: u64) -> Position {
+    let size = 4.0 / 2f64.powi(zoom as i32);
+    let (left, top) = (-2.5 + x as f64 * size, -2.0 + y as f64 * size);
+    Position::new(top, top + size, left, left + size)
+}
+
+// The tile and parameters a request's target asks for, or the HTTP status to refuse it with
+fn parse_tile_target(
+    target: &str,
+    defaults: &FractalParameters,
+) -> Result<(u32, u64, u64, FractalParameters), (u16, String)> {
+    let not_found = || (404, format!("no such tile: {}", target));
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let numbers: Vec<&str> = path
+        .strip_prefix('/')
+        .and_then(|path| path.strip_suffix(".png"))
+        .ok_or_else(not_found)?
+        .split('/')
+        .collect();
+    let [zoom, x, y] = numbers[..] else {
+        return Err(not_found());
+    };
+    let zoom: u32 = zoom.parse().map_err(|_| not_found())?;
+    let (x, y): (u64, u64) = (
+        x.parse().map_err(|_| not_found())?,
+        y.parse().map_err(|_| not_found())?,
+    );
+    if zoom > MAX_TILE_ZOOM || x >> zoom != 0 || y >> zoom != 0 {
+        return Err(not_found());
+    }
+
+    let mut parameters = *defaults;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let index = match key {
+            "fractal" => &mut parameters.fractal_index,
+            "palette" => &mut parameters.palette_index,
+            "coloring" => &mut parameters.coloring_index,
+            _ => continue,
+        };
+        let names = match key {
+            "fractal" => fractal_names(),
+            "palette" => palette_names(),
+            _ => COLORING_NAMES.to_vec(),
+        };
+        *index = parse_name(&names, key, value).map_err(|error| (400, error))?;
+    }
+    Ok((zoom, x, y, parameters))
+}
+
+// A map tile's zoom and position, with the parameters its iterations depend on
+type MapTileKey = (u32, u64, u64, FractalParameters);
+
+// The cells of map tiles rendered before, least recently used first, so tiles asked for in
+// another palette or coloring are only colored again
+#[derive(Default)]
+struct MapTiles {
+    tiles: std::collections::VecDeque<(MapTileKey, std::sync::Arc<FrameBuffer>)>,
+}
+
+impl MapTiles {
+    fn get(&mut self, key: &MapTileKey) -> Option<std::sync::Arc<FrameBuffer>> {
+        let index = self.tiles.iter().position(|(cached, _)| cached == key)?;
+        let tile = self.tiles.remove(index)?;
+        self.tiles.push_back(tile.clone());
+        Some(tile.1)
+    }
+
+    fn insert(&mut self, key: MapTileKey, frame: std::sync::Arc<FrameBuffer>) {
+        self.tiles.retain(|(cached, _)| *cached != key);
+        self.tiles.push_back((key, frame));
+        while self.tiles.len() > MAP_TILE_CACHE_CAPACITY {
+            self.tiles.pop_front();
+        }
+    }
+}
+
+// Reads a request's line and skips its headers, which aren't needed but are read so the
+// client isn't cut off sending them
+fn read_request(reader: &mut impl std::io::BufRead) -> Result<String, (u16, String)> {
+    use std::io::{BufRead, Read};
+    let mut read_line = |line: &mut String| {
+        let read = reader
+            .by_ref()
+            .take(MAX_REQUEST_LINE as u64)
+            .read_line(line)
+            .map_err(|_| (400, "bad request".to_string()))?;
+        if read == MAX_REQUEST_LINE && !line.ends_with('\n') {
+            return Err((414, "request line too long".to_string()));
+        }
+        Ok(read)
+    };
+    let mut request_line = String::new();
+    read_line(&mut request_line)?;
+    let mut header = String::new();
+    for _ in 0..MAX_REQUEST_HEADERS {
+        match read_line(&mut header) {
+            Ok(0..=2) => return Ok(request_line),
+            Ok(_) => header.clear(),
+            Err(_) => break,
+        }
+    }
+    Err((400, "headers too long".to_string()))
+}
+
+// Answers one request on a connection, which is then closed
+fn handle_tile_request(
+    stream: std::net::TcpStream,
+    defaults: &FractalParameters,
+    default_position: &Position,
+    cache: &std::sync::Mutex<MapTiles>,
+) -> std::io::Result<()> {
+    let mut reader = std::io::BufReader::new(stream.try_clone()?);
+    let response = read_request(&mut reader).and_then(|request_line| {
+        let mut words = request_line.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("GET"), Some("/")) => Ok(("text/html", MAP_PAGE.as_bytes().to_vec())),
+            (Some("GET"), Some(target)) => {
+                let (zoom, x, y, parameters) = parse_tile_target(target, defaults)?;
+                let key = (zoom, x, y, iteration_parameters(&parameters));
+                let lock = || cache.lock().unwrap_or_else(|error| error.into_inner());
+                // The cache is only locked to look the tile up and to add it, so other
+                // requests aren't held up while it renders
+                let cached = lock().get(&key);
+                let frame = cached.unwrap_or_else(|| {
+                    let position = map_tile_position(zoom, x, y);
+                    let zoom = default_position.width() / position.width();
+                    let frame = std::sync::Arc::new(image::Image::frame(
+                        MAP_TILE_SIZE,
+                        MAP_TILE_SIZE,
+                        &position,
+                        u32x1::splat(iterations_for_zoom(zoom)),
+                        &parameters,
+                        &mut TileCache::new(0),
+                    ));
+                    lock().insert(key, frame.clone());
+                    frame
+                });
+                let colors = ColorTable::new(frame.max_iterations, &parameters);
+                let image = image::Image::from_frame(&frame, MAP_TILE_SIZE, MAP_TILE_SIZE, &colors);
+                Ok(("image/png", image.png()))
+            }
+            (Some(_), Some(_)) => Err((405, "only GET is supported".to_string())),
+            _ => Err((400, "bad request".to_string())),
+        }
+    });
+
+    let (status, content_type, body) = match response {
+        Ok((content_type, body)) => (200, content_type, body),
+        Err((status, message)) => (status, "text/plain", message.into_bytes()),
+    };
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        414 => "URI Too Long",
+        _ => "Method Not Allowed",
+    };
+    let mut writer = std::io::BufWriter::new(stream);
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\nCache-Control: max-age=86400\r\n\
+         Connection: close\r\n\r\n",
+        status,
+        reason,
+        content_type,
+        body.len()
+    )?;
+    writer.write_all(&body)?;
+    writer.flush()
+}
+
+// Serves map tiles of the fractal as PNGs, each request on a thread of its own
+pub(crate) fn serve_tiles(
+    address: &str,
+    parameters: FractalParameters,
+    default_position: Position,
+) -> Result<(), AppError> {
+    let listener = std::net::TcpListener::bind(address)
+        .map_err(|error| AppError::Options(format!("{}: {}", address, error)))?;
+    println!("serving tiles on http://{}/", listener.local_addr()?);
+    let cache = std::sync::Arc::new(std::sync::Mutex::new(MapTiles::default()));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                eprintln!("{}", error);
+                continue;
+            }
+        };
+        let cache = cache.clone();
+        let default_position = default_position.clone();
+        std::thread::spawn(move || {
+            if let Err(error) = handle_tile_request(stream, &parameters, &default_position, &cache)
+            {
+                eprintln!("{}", error);
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_PARAMETERS;
+
+    #[test]
+    fn test_map_tiles() {
+        assert_eq!(
+            map_tile_position(0, 0, 0),
+            Position::new(-2.0, 2.0, -2.5, 1.5)
+        );
+        // The four tiles of the next zoom cover their parent between them
+        let quarter = map_tile_position(1, 1, 0);
+        assert_eq!((quarter.left.to_f64(), quarter.right.to_f64()), (-0.5, 1.5));
+        assert_eq!((quarter.top.to_f64(), quarter.bottom.to_f64()), (-2.0, 0.0));
+
+        let fire = parse_tile_target(
+            "/3/7/2.png?palette=fire&coloring=cyclic&x=1",
+            &DEFAULT_PARAMETERS,
+        );
+        let (zoom, x, y, parameters) = fire.unwrap();
+        assert_eq!((zoom, x, y), (3, 7, 2));
+        assert_eq!(
+            (parameters.palette_index, parameters.coloring_index),
+            (2, 2)
+        );
+        assert_eq!(parameters.fractal_index, DEFAULT_PARAMETERS.fractal_index);
+
+        let status = |target| {
+            parse_tile_target(target, &DEFAULT_PARAMETERS)
+                .err()
+                .map(|(status, _)| status)
+        };
+        assert_eq!(status("/0/0/0.png"), None);
+        assert_eq!(status("/1/2/0.png"), Some(404));
+        assert_eq!(status("/49/0/0.png"), Some(404));
+        assert_eq!(status("/1/0.png"), Some(404));
+        assert_eq!(status("/1/0/0.jpg"), Some(404));
+        assert_eq!(status("/1/0/0.png?fractal=nope"), Some(400));
+
+        // Request lines and headers that run on are refused rather than read in whole
+        let request = |text: &str| read_request(&mut std::io::Cursor::new(text.as_bytes()));
+        assert_eq!(
+            request("GET /0/0/0.png HTTP/1.1\r\nHost: x\r\n\r\n"),
+            Ok("GET /0/0/0.png HTTP/1.1\r\n".to_string())
+        );
+        let long = format!("GET /{} HTTP/1.1\r\n\r\n", "0".repeat(MAX_REQUEST_LINE));
+        assert_eq!(request(&long).map_err(|(status, _)| status), Err(414));
+        let headers = "GET / HTTP/1.1\r\n".to_string() + "A: b\r\n".repeat(1000).as_str();
+        assert_eq!(request(&headers).map_err(|(status, _)| status), Err(400));
+
+        // Tiles are kept apart from the palette, and the oldest go once there are too many
+        let mut tiles = MapTiles::default();
+        let frame = |zoom, parameters: &FractalParameters| {
+            let position = map_tile_position(zoom, 0, 0);
+            let max_iterations = u32x1::splat(20);
+            let cache = &mut TileCache::new(0);
+            std::sync::Arc::new(image::Image::frame(
+                2,
+                2,
+                &position,
+                max_iterations,
+                parameters,
+                cache,
+            ))
+        };
+        let key = |zoom| (zoom, 0, 0, iteration_parameters(&parameters));
+        tiles.insert(key(0), frame(0, &parameters));
+        assert!(tiles.get(&key(0)).is_some());
+        assert!(tiles.get(&(0, 0, 0, DEFAULT_PARAMETERS)).is_some());
+        for zoom in 1..=MAP_TILE_CACHE_CAPACITY as u32 {
+            tiles.insert(key(zoom), frame(1, &parameters));
+        }
+        assert!(tiles.get(&key(0)).is_none());
+        assert!(tiles.get(&key(1)).is_some());
+    }
+}
@@ -0,0 +1,92 @@
+import { loadRenderer } from './renderer.js';
+
+const ZOOM_STEP = 1.5;
+const PAN_STEP = 0.1;
+const WORKERS = Math.min(navigator.hardwareConcurrency || 4, 16);
+const HELP = 'arrows or wasd move, +/- zoom, f fractal, p palette';
+
+const terminal = new Terminal({ convertEol: true });
+const fit = new FitAddon.FitAddon();
+terminal.loadAddon(fit);
+terminal.open(document.getElementById('terminal'));
+fit.fit();
+terminal.write('\x1b[?25l');
+terminal.focus();
+
+const renderer = await loadRenderer();
+const workers = Array.from(
+  { length: WORKERS },
+  () => new Worker(new URL('worker.js', import.meta.url), { type: 'module' }),
+);
+const view = { center: '-0.5,0', zoom: 1, fractal: 0, palette: 0 };
+// Bands from frames that have been replaced are thrown away
+let frame = 0;
+
+// The same limit the viewer follows the zoom with
+const iterations = (zoom) => Math.floor(100 + 100 * Math.log10(Math.max(zoom, 1)) ** 1.5);
+
+// The frame leaves the bottom row for the status line
+const frameSize = () => [terminal.cols, Math.max(terminal.rows - 1, 1)];
+
+function status(text) {
+  terminal.write(`\x1b[${terminal.rows};1H\x1b[0m\x1b[2K${text}`);
+}
+
+// Shares the frame out between the workers as bands of rows, drawing each as it arrives
+function draw() {
+  frame += 1;
+  const [width, height] = frameSize();
+  const rows = Math.ceil(height / workers.length);
+  const request = { ...view, iterations: iterations(view.zoom), width, height, frame, rows };
+  workers.forEach((worker, index) => {
+    if (index * rows < height) {
+      worker.postMessage({ ...request, firstRow: index * rows });
+    }
+  });
+  status(`${view.zoom.toPrecision(3)}x  ${HELP}`);
+}
+
+for (const worker of workers) {
+  worker.onmessage = ({ data }) => {
+    if (data.frame !== frame) {
+      return;
+    }
+    if (data.error !== undefined) {
+      status(data.error);
+    } else {
+      terminal.write(`\x1b[${data.firstRow + 1};1H${data.text}`);
+    }
+  };
+}
+
+function pan(x, y) {
+  const [width, height] = frameSize();
+  view.center = renderer.move(view, width, height, x * width * PAN_STEP, y * height * PAN_STEP);
+}
+
+terminal.onKey(({ domEvent }) => {
+  const actions = {
+    ArrowUp: () => pan(0, -1),
+    ArrowDown: () => pan(0, 1),
+    ArrowLeft: () => pan(-1, 0),
+    ArrowRight: () => pan(1, 0),
+    w: () => pan(0, -1),
+    s: () => pan(0, 1),
+    a: () => pan(-1, 0),
+    d: () => pan(1, 0),
+    '+': () => { view.zoom *= ZOOM_STEP; },
+    '=': () => { view.zoom *= ZOOM_STEP; },
+    '-': () => { view.zoom /= ZOOM_STEP; },
+    f: () => { view.fractal = (view.fractal + 1) % renderer.fractals; },
+    p: () => { view.palette = (view.palette + 1) % renderer.palettes; },
+  };
+  const action = actions[domEvent.key];
+  if (action !== undefined) {
+    action();
+    draw();
+  }
+});
+
+window.addEventListener('resize', () => fit.fit());
+terminal.onResize(draw);
+draw();
@@ -0,0 +1,44 @@
+// Calls into the renderer compiled to WebAssembly, passing text through its memory
+export async function loadRenderer(bytes) {
+  if (bytes === undefined) {
+    const response = await fetch(new URL('mandelbrot_term_web.wasm', import.meta.url));
+    bytes = await response.arrayBuffer();
+  }
+  const { instance } = await WebAssembly.instantiate(bytes);
+  const exports = instance.exports;
+  const encoder = new TextEncoder();
+  const decoder = new TextDecoder();
+
+  // Runs an export on some text, returning the text it leaves as output
+  const call = (text, run) => {
+    const input = encoder.encode(text);
+    const pointer = exports.mandelbrot_term_alloc(input.length);
+    new Uint8Array(exports.memory.buffer, pointer, input.length).set(input);
+    const length = run(pointer, input.length);
+    exports.mandelbrot_term_free(pointer, input.length);
+    const output = new Uint8Array(
+      exports.memory.buffer,
+      exports.mandelbrot_term_output(),
+      Math.abs(length),
+    );
+    const result = decoder.decode(output);
+    if (length < 0) {
+      throw new Error(result);
+    }
+    return result;
+  };
+
+  return {
+    fractals: exports.mandelbrot_term_fractals(),
+    palettes: exports.mandelbrot_term_palettes(),
+    render: (view, width, height, firstRow, rows) =>
+      call(view.center, (pointer, length) =>
+        exports.mandelbrot_term_render(
+          pointer, length, view.zoom, view.iterations, view.fractal, view.palette,
+          width, height, firstRow, rows,
+        )),
+    move: (view, width, height, columns, rows) =>
+      call(view.center, (pointer, length) =>
+        exports.mandelbrot_term_move(pointer, length, view.zoom, width, height, columns, rows)),
+  };
+}
@@ -0,0 +1,284 @@
+//! The renderer built for the browser, where `main.js` draws its frames into xterm.js.
+//!
+//! The functions are exported with the C ABI so the page needs no generated bindings. Text
+//! goes in through memory from `mandelbrot_term_alloc`, and the text a call produces is left
+//! at `mandelbrot_term_output`, with its length returned, or negated if it is an error.
+
+use mandelbrot_set::*;
+use std::sync::Mutex;
+
+static OUTPUT: Mutex<String> = Mutex::new(String::new());
+
+fn default_position() -> Position {
+    Position::new(-1.0, 1.0, -2.0, 1.0)
+}
+
+// The view of a `width` by `height` frame around a center given as "X,Y", which keeps every
+// digit of deep views
+fn view_position(center: &str, zoom: f64, width: u16, height: u16) -> Result<Position, String> {
+    let (x, y) = center
+        .split_once(',')
+        .ok_or(format!("invalid center: {}", center))?;
+    if !(zoom.is_finite() && zoom > 0.0) {
+        return Err(format!("invalid zoom: {}", zoom));
+    }
+    let default_position = default_position();
+    let position = default_position
+        .with_zoom(&default_position, zoom)
+        .centered_at(&(Fixed::parse(x.trim())?, Fixed::parse(y.trim())?));
+    Ok(fit_aspect(&position, width, height))
+}
+
+// Renders `rows` rows of the frame from `first_row` down, so the page can share a frame out
+// between workers
+#[allow(clippy::too_many_arguments)]
+fn render_band(
+    center: &str,
+    zoom: f64,
+    max_iterations: u32,
+    parameters: &FractalParameters,
+    width: u16,
+    height: u16,
+    first_row: u16,
+    rows: u16,
+) -> Result<String, String> {
+    let position = view_position(center, zoom, width, height)?;
+    let rows = rows.min(height.saturating_sub(first_row));
+    let row_height = position.height() / height.max(1) as f64;
+    let band = Position {
+        top: &position.top + first_row as f64 * row_height,
+        bottom: &position.top + (first_row + rows) as f64 * row_height,
+        ..position
+    };
+    let (buffer, _) = render_buffer(
+        width,
+        rows,
+        &band,
+        u32x1::splat(max_iterations),
+        parameters,
+        DEFAULT_TILE_SIZE,
+        None,
+        &mut TileCache::new(0),
+    );
+    Ok(buffer.text())
+}
+
+// The center after the view moves by a number of cells, which may be fractions of one
+fn moved_center(
+    center: &str,
+    zoom: f64,
+    width: u16,
+    height: u16,
+    columns: f64,
+    rows: f64,
+) -> Result<String, String> {
+    let position = view_position(center, zoom, width, height)?;
+    let moved = position.translated(
+        columns / width.max(1) as f64 * position.width(),
+        rows / height.max(1) as f64 * position.height(),
+    );
+    let places = (zoom.log10().max(0.0) as usize) + 6;
+    let (x, y) = moved.center();
+    Ok(format!("{},{}", x.to_decimal(places), y.to_decimal(places)))
+}
+
+fn finish(result: Result<String, String>) -> isize {
+    let (text, failed) = match result {
+        Ok(text) => (text, false),
+        Err(error) => (error, true),
+    };
+    let length = text.len() as isize;
+    *OUTPUT.lock().unwrap() = text;
+    if failed {
+        -length
+    } else {
+        length
+    }
+}
+
+// Safety: `text` must point to `length` readable bytes
+unsafe fn input<'a>(text: *const u8, length: usize) -> Result<&'a str, String> {
+    std::str::from_utf8(std::slice::from_raw_parts(text, length)).map_err(|error| error.to_string())
+}
+
+/// Reserves `length` bytes for passing text in.
+#[no_mangle]
+pub extern "C" fn mandelbrot_term_alloc(length: usize) -> *mut u8 {
+    let mut buffer = Vec::<u8>::with_capacity(length);
+    let pointer = buffer.as_mut_ptr();
+    std::mem::forget(buffer);
+    pointer
+}
+
+/// Frees bytes from `mandelbrot_term_alloc`.
+///
+/// # Safety
+///
+/// `pointer` and `length` must come from one call to `mandelbrot_term_alloc`.
+#[no_mangle]
+pub unsafe extern "C" fn mandelbrot_term_free(pointer: *mut u8, length: usize) {
+    drop(Vec::from_raw_parts(pointer, 0, length));
+}
+
+/// The text the last call produced, which stays valid until the next one.
+#[no_mangle]
+pub extern "C" fn mandelbrot_term_output() -> *const u8 {
+    OUTPUT.lock().unwrap().as_ptr()
+}
+
+/// The number of fractals and palettes, which are picked by index.
+#[no_mangle]
+pub extern "C" fn mandelbrot_term_fractals() -> u32 {
+    fractal_names().len() as u32
+}
+
+#[no_mangle]
+pub extern "C" fn mandelbrot_term_palettes() -> u32 {
+    palette_names().len() as u32
+}
+
+/// Renders rows `first_row` to `first_row + rows` of a `width` by `height` frame around the
+/// `center` text as escape-sequence text, one line per row.
+///
+/// # Safety
+///
+/// `center` must point to `center_length` readable bytes.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn mandelbrot_term_render(
+    center: *const u8,
+    center_length: usize,
+    zoom: f64,
+    max_iterations: u32,
+    fractal_index: u32,
+    palette_index: u32,
+    width: u16,
+    height: u16,
+    first_row: u16,
+    rows: u16,
+) -> isize {
+    let parameters = FractalParameters {
+        fractal_index: (fractal_index as usize).min(fractal_names().len() - 1),
+        palette_index: (palette_index as usize).min(palette_names().len() - 1),
+        ..DEFAULT_PARAMETERS
+    };
+    finish(input(center, center_length).and_then(|center| {
+        render_band(
+            center,
+            zoom,
+            max_iterations,
+            &parameters,
+            width,
+            height,
+            first_row,
+            rows,
+        )
+    }))
+}
+
+/// Moves the view around the `center` text by `columns` and `rows` cells, leaving the new
+/// center as the output.
+///
+/// # Safety
+///
+/// `center` must point to `center_length` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mandelbrot_term_move(
+    center: *const u8,
+    center_length: usize,
+    zoom: f64,
+    width: u16,
+    height: u16,
+    columns: f64,
+    rows: f64,
+) -> isize {
+    finish(
+        input(center, center_length)
+            .and_then(|center| moved_center(center, zoom, width, height, columns, rows)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_band() {
+        let (width, height) = (30, 12);
+        let band = |first_row, rows| {
+            render_band(
+                "-0.5,0",
+                1.0,
+                100,
+                &DEFAULT_PARAMETERS,
+                width,
+                height,
+                first_row,
+                rows,
+            )
+            .unwrap()
+        };
+        // Bands put together make up the whole frame
+        let position = view_position("-0.5,0", 1.0, width, height).unwrap();
+        let (buffer, _) = render_buffer(
+            width,
+            height,
+            &position,
+            u32x1::splat(100),
+            &DEFAULT_PARAMETERS,
+            DEFAULT_TILE_SIZE,
+            None,
+            &mut TileCache::new(0),
+        );
+        assert_eq!(band(0, height), buffer.text());
+        let halves = [band(0, 6), band(6, 6)];
+        assert_eq!(halves.each_ref().map(|half| half.lines().count()), [6, 6]);
+        assert_eq!(
+            halves.join("\n").replace("\x1b[0m", ""),
+            buffer.text().replace("\x1b[0m", "")
+        );
+        // Rows past the bottom of the frame are left out
+        assert_eq!(band(10, 5).lines().count(), 2);
+
+        assert_eq!(
+            view_position("-0.5", 1.0, width, height).err(),
+            Some("invalid center: -0.5".to_string())
+        );
+        assert!(view_position("-0.5,0", 0.0, width, height).is_err());
+    }
+
+    #[test]
+    fn test_moved_center() {
+        // The full width is 3, so 10 of 30 columns is 1 across
+        assert_eq!(
+            moved_center("-0.5,0", 1.0, 30, 12, 10.0, 0.0).unwrap(),
+            "0.500000,0.000000"
+        );
+        // Deep centers keep their digits
+        let center = "-1.7499999999999999999999,0.0000000000000000000001";
+        let moved = moved_center(center, 1e20, 30, 12, 0.0, 0.0).unwrap();
+        assert_eq!(
+            moved,
+            "-1.74999999999999999999990000,0.00000000000000000000010000"
+        );
+    }
+
+    #[test]
+    fn test_exports() {
+        let center = b"-0.5,0";
+        let pointer = mandelbrot_term_alloc(center.len());
+        unsafe {
+            std::ptr::copy_nonoverlapping(center.as_ptr(), pointer, center.len());
+            let length = mandelbrot_term_render(pointer, center.len(), 1.0, 50, 0, 0, 20, 8, 0, 8);
+            assert!(length > 0);
+            let text = std::slice::from_raw_parts(mandelbrot_term_output(), length as usize);
+            assert_eq!(std::str::from_utf8(text).unwrap().lines().count(), 8);
+
+            // Errors are left as the output, with their length negated
+            let length = mandelbrot_term_move(pointer, 3, 1.0, 20, 8, 1.0, 0.0);
+            let text = std::slice::from_raw_parts(mandelbrot_term_output(), -length as usize);
+            assert_eq!(text, b"invalid center: -0.");
+            mandelbrot_term_free(pointer, center.len());
+        }
+    }
+}
@@ -0,0 +1,15 @@
+// Renders bands of rows for the page, each worker with its own copy of the renderer, since
+// rayon has no threads to spread the work over in the browser
+import { loadRenderer } from './renderer.js';
+
+const renderer = loadRenderer();
+
+onmessage = async ({ data }) => {
+  const { render } = await renderer;
+  try {
+    const text = render(data, data.width, data.height, data.firstRow, data.rows);
+    postMessage({ frame: data.frame, firstRow: data.firstRow, text });
+  } catch (error) {
+    postMessage({ frame: data.frame, error: error.message });
+  }
+};